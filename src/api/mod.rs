@@ -0,0 +1,345 @@
+use chrono::{DateTime, Utc};
+use failure::{err_msg, Error};
+use log::*;
+use serde::Serialize;
+use tiny_http::{Header, Method, Response, Server};
+use url::percent_encoding::percent_decode;
+
+use std::net::SocketAddr;
+use std::thread;
+
+use crate::db::DbConnection;
+use crate::model::{GameServerState, Nation, TurnRecord};
+use crate::model::enums::{NationStatus, Nations, SubmissionStatus};
+use crate::server::ServerConnection;
+
+#[cfg(test)]
+mod tests;
+
+// Discord ids don't fit in a javascript number, so like discord's own API they're strings
+
+#[derive(Debug, Serialize)]
+struct GameSummary {
+    alias: String,
+    address: String,
+    /// -1 while pretenders are still being uploaded
+    last_seen_turn: i32,
+}
+
+#[derive(Debug, Serialize)]
+struct GameStatus {
+    alias: String,
+    address: String,
+    game_name: String,
+    turn: i32,
+    /// 0 or less when there's no timer
+    turn_timer_ms: i32,
+    nations: Vec<NationStatusJson>,
+    players: Vec<RegisteredPlayer>,
+}
+
+#[derive(Debug, Serialize)]
+struct NationStatusJson {
+    id: usize,
+    name: String,
+    era: String,
+    status: &'static str,
+    submitted: &'static str,
+    connected: bool,
+    discord_user_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct RegisteredPlayer {
+    discord_user_id: String,
+    nation_id: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct LobbySummary {
+    alias: String,
+    owner_discord_user_id: String,
+    era: String,
+    player_count: i32,
+    registered_player_count: i32,
+    description: Option<String>,
+    status: String,
+    scheduled_start: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct TurnJson {
+    turn: i32,
+    seen_at: String,
+    /// What was left when the turn was first seen, 0 or less when there's no timer
+    turn_timer_ms: i32,
+    defeated: Vec<NationRef>,
+    ai: Vec<NationRef>,
+    possible_stalls: Vec<NationRef>,
+}
+
+#[derive(Debug, Serialize)]
+struct NationRef {
+    id: usize,
+    name: &'static str,
+    era: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorJson {
+    error: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl ApiResponse {
+    fn json<T: Serialize>(status: u16, value: &T) -> ApiResponse {
+        match serde_json::to_string(value) {
+            Ok(body) => ApiResponse { status, body },
+            Err(err) => ApiResponse::error(500, &err.to_string()),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> ApiResponse {
+        let error = ErrorJson {
+            error: message.to_owned(),
+        };
+        ApiResponse {
+            status,
+            body: serde_json::to_string(&error).unwrap_or_else(|_| "{}".to_owned()),
+        }
+    }
+}
+
+fn show_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339()
+}
+
+fn nation_status_name(status: NationStatus) -> &'static str {
+    match status {
+        NationStatus::Empty => "empty",
+        NationStatus::Human => "human",
+        NationStatus::AI => "ai",
+        NationStatus::Independent => "independent",
+        NationStatus::Closed => "closed",
+        NationStatus::DefeatedThisTurn => "defeated_this_turn",
+        NationStatus::Defeated => "defeated",
+    }
+}
+
+fn submission_status_name(submitted: SubmissionStatus) -> &'static str {
+    match submitted {
+        SubmissionStatus::NotSubmitted => "not_submitted",
+        SubmissionStatus::PartiallySubmitted => "partially_submitted",
+        SubmissionStatus::Submitted => "submitted",
+        SubmissionStatus::Unknown(_) => "unknown",
+    }
+}
+
+fn nation_refs(nation_ids: &[usize]) -> Vec<NationRef> {
+    nation_ids
+        .iter()
+        .map(|&id| {
+            let &(name, era) = Nations::get_nation_desc(id);
+            NationRef {
+                id,
+                name,
+                era: era.to_string(),
+            }
+        })
+        .collect()
+}
+
+fn games(db_conn: &DbConnection) -> Result<Vec<GameSummary>, Error> {
+    let mut games = db_conn
+        .retrieve_all_servers()?
+        .into_iter()
+        .filter_map(|server| match server.state {
+            GameServerState::StartedState(started_state, _) => Some(GameSummary {
+                alias: server.alias,
+                address: started_state.address,
+                last_seen_turn: started_state.last_seen_turn,
+            }),
+            GameServerState::Lobby(_) => None,
+        })
+        .collect::<Vec<_>>();
+    games.sort_by(|a, b| a.alias.cmp(&b.alias));
+    Ok(games)
+}
+
+/// Goes through the same one minute cache as the bot's commands, so a busy website
+/// can't make the bot hammer a game server
+fn game_status<C: ServerConnection>(db_conn: &DbConnection, alias: &str) -> ApiResponse {
+    let server = match db_conn.game_for_alias(alias) {
+        Ok(server) => server,
+        Err(_) => return ApiResponse::error(404, &format!("no game called {}", alias)),
+    };
+    let started_state = match server.state {
+        GameServerState::StartedState(started_state, _) => started_state,
+        GameServerState::Lobby(_) => {
+            return ApiResponse::error(404, &format!("{} is a lobby that hasn't started yet", alias))
+        }
+    };
+    let players_nations = match db_conn.players_with_nations_for_game_alias(alias) {
+        Ok(players_nations) => players_nations,
+        Err(err) => return ApiResponse::error(500, &err.to_string()),
+    };
+    let mut game_data = match C::get_game_data(&started_state.address) {
+        Ok(game_data) => game_data,
+        Err(err) => {
+            return ApiResponse::error(
+                502,
+                &format!("could not get the status of {} from {}: {}", alias, started_state.address, err),
+            )
+        }
+    };
+    game_data.nations.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+    let player_for_nation = |nation: &Nation| {
+        players_nations
+            .iter()
+            .find(|&&(_, nation_id)| nation_id == nation.id)
+            .map(|&(ref player, _)| player.discord_user_id.0.to_string())
+    };
+    let nations = game_data
+        .nations
+        .iter()
+        .map(|nation| NationStatusJson {
+            id: nation.id,
+            name: nation.name.clone(),
+            era: nation.era.clone(),
+            status: nation_status_name(nation.status),
+            submitted: submission_status_name(nation.submitted),
+            connected: nation.connected,
+            discord_user_id: player_for_nation(nation),
+        })
+        .collect();
+    let players = players_nations
+        .iter()
+        .map(|&(ref player, nation_id)| RegisteredPlayer {
+            discord_user_id: player.discord_user_id.0.to_string(),
+            nation_id,
+        })
+        .collect();
+
+    ApiResponse::json(200, &GameStatus {
+        alias: server.alias,
+        address: started_state.address,
+        game_name: game_data.game_name,
+        turn: game_data.turn,
+        turn_timer_ms: game_data.turn_timer,
+        nations,
+        players,
+    })
+}
+
+fn lobbies(db_conn: &DbConnection) -> Result<Vec<LobbySummary>, Error> {
+    let lobbies = db_conn
+        .select_lobbies(false)?
+        .into_iter()
+        .filter_map(|(server, registered_player_count)| match server.state {
+            GameServerState::Lobby(lobby_state) => Some(LobbySummary {
+                alias: server.alias,
+                owner_discord_user_id: lobby_state.owner.0.to_string(),
+                era: lobby_state.era.to_string(),
+                player_count: lobby_state.player_count,
+                registered_player_count,
+                description: lobby_state.description,
+                status: lobby_state.status.to_string(),
+                scheduled_start: lobby_state.scheduled_start.map(show_time),
+            }),
+            GameServerState::StartedState(_, _) => None,
+        })
+        .collect();
+    Ok(lobbies)
+}
+
+fn turn_json(turn_record: TurnRecord) -> TurnJson {
+    TurnJson {
+        turn: turn_record.turn,
+        seen_at: show_time(turn_record.seen_at),
+        turn_timer_ms: turn_record.turn_timer,
+        defeated: nation_refs(&turn_record.defeated),
+        ai: nation_refs(&turn_record.ai),
+        possible_stalls: nation_refs(&turn_record.possible_stalls),
+    }
+}
+
+fn turns(db_conn: &DbConnection, alias: &str) -> ApiResponse {
+    if db_conn.game_for_alias(alias).is_err() {
+        return ApiResponse::error(404, &format!("no game called {}", alias));
+    }
+    match db_conn.turn_history_for_game_alias(alias) {
+        Ok(turn_records) => {
+            let turns = turn_records.into_iter().map(turn_json).collect::<Vec<_>>();
+            ApiResponse::json(200, &turns)
+        }
+        Err(err) => ApiResponse::error(500, &err.to_string()),
+    }
+}
+
+fn from_result<T: Serialize>(result: Result<T, Error>) -> ApiResponse {
+    match result {
+        Ok(value) => ApiResponse::json(200, &value),
+        Err(err) => ApiResponse::error(500, &err.to_string()),
+    }
+}
+
+/// Answers a GET for `path`, which can include a query string
+pub fn get<C: ServerConnection>(db_conn: &DbConnection, path: &str) -> ApiResponse {
+    let path = path.split('?').next().unwrap_or("");
+    let segments = path
+        .trim_matches('/')
+        .split('/')
+        .map(|segment| percent_decode(segment.as_bytes()).decode_utf8_lossy().into_owned())
+        .collect::<Vec<_>>();
+    let segments = segments.iter().map(|segment| segment.as_str()).collect::<Vec<_>>();
+    match segments.as_slice() {
+        ["api", "games"] => from_result(games(db_conn)),
+        ["api", "games", alias] => game_status::<C>(db_conn, alias),
+        ["api", "games", alias, "turns"] => turns(db_conn, alias),
+        ["api", "lobbies"] => from_result(lobbies(db_conn)),
+        _ => ApiResponse::error(404, &format!("nothing at {}", path)),
+    }
+}
+
+/// Starts answering API requests on a background thread, with its own handle to the
+/// connection pool. Returns the address actually listened on.
+pub fn serve<C: ServerConnection + 'static>(address: &str, db_conn: DbConnection) -> Result<SocketAddr, Error> {
+    let server = Server::http(address)
+        .map_err(|e| err_msg(format!("Listening for API requests on {}: {}", address, e)))?;
+    let local_address = server
+        .server_addr()
+        .to_ip()
+        .ok_or_else(|| err_msg(format!("Listening for API requests on {}: not an IP address", address)))?;
+    info!("serving the API on http://{}/api", local_address);
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let api_response = if *request.method() == Method::Get {
+                get::<C>(&db_conn, request.url())
+            } else {
+                ApiResponse::error(405, "the API is read only")
+            };
+            debug!("{} {} -> {}", request.method(), request.url(), api_response.status);
+            let response = Response::from_string(api_response.body)
+                .with_status_code(api_response.status)
+                .with_header(
+                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                        .expect("Static header is valid"),
+                )
+                // so the community website can call it from the browser
+                .with_header(
+                    Header::from_bytes(&b"Access-Control-Allow-Origin"[..], &b"*"[..])
+                        .expect("Static header is valid"),
+                );
+            if let Err(err) = request.respond(response) {
+                debug!("could not answer API request: {}", err);
+            }
+        }
+    });
+    Ok(local_address)
+}
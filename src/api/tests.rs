@@ -0,0 +1,203 @@
+use super::*;
+
+use crate::model::*;
+use crate::model::enums::*;
+use crate::model::Nation;
+use chrono::TimeZone;
+use serde_json::Value;
+use serenity::model::id::UserId;
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+fn populate(db_conn: &DbConnection) {
+    db_conn
+        .insert_game_server(&GameServer {
+            alias: "started".to_owned(),
+            state: GameServerState::StartedState(
+                StartedState {
+                    address: "foo.bar:3000".to_owned(),
+                    last_seen_turn: 23,
+                },
+                None,
+            ),
+        })
+        .unwrap();
+    db_conn
+        .insert_game_server(&GameServer {
+            alias: "lobby".to_owned(),
+            state: GameServerState::Lobby(LobbyState {
+                owner: UserId(7),
+                era: Era::Late,
+                player_count: 5,
+                description: Some("come play".to_owned()),
+                status: LobbyStatus::Open,
+                scheduled_start: Some(Utc.ymd(2019, 6, 1).and_hms(18, 0, 0)),
+            }),
+        })
+        .unwrap();
+    // bigger than a javascript number can hold exactly
+    db_conn.insert_player(&Player { discord_user_id: UserId(283_112_390_147_342_337), turn_notifications: true }).unwrap();
+    db_conn.insert_player(&Player { discord_user_id: UserId(2), turn_notifications: true }).unwrap();
+    db_conn.insert_server_player("started", UserId(283_112_390_147_342_337), 5).unwrap();
+    db_conn.insert_server_player("started", UserId(2), 12).unwrap();
+    db_conn.insert_server_player("lobby", UserId(2), 80).unwrap();
+}
+
+fn nation(id: usize, status: NationStatus, submitted: SubmissionStatus) -> Nation {
+    let &(name, era) = Nations::get_nation_desc(id);
+    Nation {
+        id,
+        status,
+        submitted,
+        connected: false,
+        name: name.to_owned(),
+        era: era.to_string(),
+    }
+}
+
+fn game_data() -> GameData {
+    GameData {
+        game_name: "startedgame".to_owned(),
+        nations: vec![
+            nation(5, NationStatus::Human, SubmissionStatus::Submitted),
+            nation(12, NationStatus::Human, SubmissionStatus::PartiallySubmitted),
+            nation(20, NationStatus::AI, SubmissionStatus::NotSubmitted),
+        ],
+        turn: 23,
+        turn_timer: 90 * 60 * 1000,
+    }
+}
+
+fn get_json<C: ServerConnection>(db_conn: &DbConnection, path: &str) -> (u16, Value) {
+    let response = get::<C>(db_conn, path);
+    (response.status, serde_json::from_str(&response.body).unwrap())
+}
+
+#[test]
+fn should_list_started_games_but_not_lobbies() {
+    mock_server_connection!(Mock, Ok(game_data()));
+    let db_conn = &DbConnection::test();
+    populate(db_conn);
+
+    let (status, json) = get_json::<Mock>(db_conn, "/api/games");
+    assert_eq!(status, 200);
+    assert_eq!(json, serde_json::json!([
+        { "alias": "started", "address": "foo.bar:3000", "last_seen_turn": 23 }
+    ]));
+}
+
+#[test]
+fn should_join_game_data_with_registered_players() {
+    mock_server_connection!(Mock, Ok(game_data()));
+    let db_conn = &DbConnection::test();
+    populate(db_conn);
+
+    let (status, json) = get_json::<Mock>(db_conn, "/api/games/started");
+    assert_eq!(status, 200);
+    assert_eq!(json["game_name"], "startedgame");
+    assert_eq!(json["turn"], 23);
+    assert_eq!(json["turn_timer_ms"], 90 * 60 * 1000);
+    let nations = json["nations"].as_array().unwrap();
+    assert_eq!(nations.len(), 3);
+    let nation_5 = nations.iter().find(|n| n["id"] == 5).unwrap();
+    assert_eq!(nation_5["discord_user_id"], "283112390147342337");
+    assert_eq!(nation_5["status"], "human");
+    assert_eq!(nation_5["submitted"], "submitted");
+    let nation_12 = nations.iter().find(|n| n["id"] == 12).unwrap();
+    assert_eq!(nation_12["submitted"], "partially_submitted");
+    let nation_20 = nations.iter().find(|n| n["id"] == 20).unwrap();
+    assert_eq!(nation_20["status"], "ai");
+    assert_eq!(nation_20["discord_user_id"], Value::Null);
+    assert_eq!(json["players"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn should_404_unknown_games_and_lobbies() {
+    mock_server_connection!(Mock, Ok(game_data()));
+    let db_conn = &DbConnection::test();
+    populate(db_conn);
+
+    assert_eq!(get::<Mock>(db_conn, "/api/games/nope").status, 404);
+    assert_eq!(get::<Mock>(db_conn, "/api/games/lobby").status, 404);
+    assert_eq!(get::<Mock>(db_conn, "/api/games/nope/turns").status, 404);
+    assert_eq!(get::<Mock>(db_conn, "/api/nothing").status, 404);
+}
+
+#[test]
+fn should_502_when_the_game_server_is_down() {
+    mock_server_connection!(Mock, Err(io::Error::from_raw_os_error(-1)));
+    let db_conn = &DbConnection::test();
+    populate(db_conn);
+
+    let (status, json) = get_json::<Mock>(db_conn, "/api/games/started");
+    assert_eq!(status, 502);
+    assert!(json["error"].as_str().unwrap().contains("foo.bar:3000"));
+}
+
+#[test]
+fn should_list_lobbies() {
+    mock_server_connection!(Mock, Ok(game_data()));
+    let db_conn = &DbConnection::test();
+    populate(db_conn);
+
+    let (status, json) = get_json::<Mock>(db_conn, "/api/lobbies");
+    assert_eq!(status, 200);
+    assert_eq!(json, serde_json::json!([{
+        "alias": "lobby",
+        "owner_discord_user_id": "7",
+        "era": Era::Late.to_string(),
+        "player_count": 5,
+        "registered_player_count": 1,
+        "description": "come play",
+        "status": "open",
+        "scheduled_start": "2019-06-01T18:00:00+00:00",
+    }]));
+}
+
+#[test]
+fn should_return_turn_history_newest_first() {
+    mock_server_connection!(Mock, Ok(game_data()));
+    let db_conn = &DbConnection::test();
+    populate(db_conn);
+    for turn in 22..24 {
+        db_conn
+            .insert_turn_record("started", &TurnRecord {
+                turn,
+                seen_at: Utc.ymd(2019, 6, 1).and_hms(turn as u32 - 10, 0, 0),
+                turn_timer: 24 * 60 * 60 * 1000,
+                defeated: if turn == 23 { vec![20] } else { vec![] },
+                ai: vec![],
+                possible_stalls: if turn == 23 { vec![5, 12] } else { vec![] },
+            })
+            .unwrap();
+    }
+
+    let (status, json) = get_json::<Mock>(db_conn, "/api/games/started/turns");
+    assert_eq!(status, 200);
+    let turns = json.as_array().unwrap();
+    assert_eq!(turns.len(), 2);
+    assert_eq!(turns[0]["turn"], 23);
+    assert_eq!(turns[0]["seen_at"], "2019-06-01T13:00:00+00:00");
+    assert_eq!(turns[0]["defeated"][0]["id"], 20);
+    assert_eq!(turns[0]["possible_stalls"].as_array().unwrap().len(), 2);
+    assert_eq!(turns[1]["turn"], 22);
+    assert_eq!(turns[1]["defeated"], serde_json::json!([]));
+}
+
+#[test]
+fn should_serve_the_api_over_http() {
+    mock_server_connection!(Mock, Ok(game_data()));
+    let db_conn = DbConnection::test();
+    populate(&db_conn);
+    let address = serve::<Mock>("127.0.0.1:0", db_conn).unwrap();
+
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "GET /api/games?pretty HTTP/1.0\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.0 200") || response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("application/json"));
+    assert!(response.contains("Access-Control-Allow-Origin: *"));
+    assert!(response.contains("\"alias\":\"started\""));
+}
@@ -0,0 +1,286 @@
+use failure::{err_msg, Error, ResultExt};
+use serenity::model::id::UserId;
+use log::*;
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+
+use crate::commands::admin::Admins;
+use crate::config::Config;
+use crate::db::*;
+use crate::model::{GameData, GameServer, GameServerState, Player, StartedState};
+use crate::model::enums::Nations;
+use crate::server::{OfflineServerConnection, ServerConnection};
+
+mod repl;
+
+#[cfg(test)]
+mod tests;
+
+const USAGE: &str = "\
+USAGE:
+    dom5status                                  run the discord bot
+    dom5status export <file>                    write the database to a JSON file
+    dom5status import <file> [merge|replace]    read a JSON file into the database, merging by default
+    dom5status games                            list the games and lobbies that aren't archived
+    dom5status lobbies                          list every lobby, including ones not taking players
+    dom5status players <alias>                  list who is registered as which nation in a game
+    dom5status add <address:port> <alias>       add a started game, once its server answers
+    dom5status remove <alias>                   archive a game, like !delete
+    dom5status purge <alias>                    permanently delete a game and its history
+    dom5status register <alias> <discord user id> <nation id>
+                                                register a player as a nation, even in a full or locked lobby
+    dom5status unregister <alias> <discord user id>
+                                                remove a player's registrations in a game
+    dom5status migrate                          bring the database up to date and list its migrations
+    dom5status poll <address:port|alias>        fetch a game's status once and print it
+    dom5status repl [--offline] [<discord user id>]
+                                                type chat commands like !details into a terminal,
+                                                as the given user or else the first admin. With
+                                                --offline game servers aren't contacted, each one
+                                                answers with an empty game on turn 1";
+
+/// Runs a command given on the command line instead of the discord bot
+pub fn run<C: ServerConnection>(
+    args: &[String],
+    config: &Config,
+    db_conn: &DbConnection,
+    admins: &Admins,
+) -> Result<(), Error> {
+    let arg = |i: usize| args.get(i).map(String::as_str).ok_or_else(|| err_msg(USAGE));
+    let text = match args.first().map(|arg| arg.as_ref()) {
+        Some("export") => return export(db_conn, arg(1)?),
+        Some("import") => {
+            let path = arg(1)?;
+            let mode = match args.get(2) {
+                Some(mode_arg) => ImportMode::from_string(mode_arg)
+                    .ok_or_else(|| err_msg(format!("Unknown import mode {}\n{}", mode_arg, USAGE)))?,
+                None => ImportMode::Merge,
+            };
+            return import(db_conn, path, mode);
+        }
+        Some("games") => games(db_conn)?,
+        Some("lobbies") => lobbies(db_conn)?,
+        Some("players") => players(db_conn, &arg(1)?.to_lowercase())?,
+        Some("add") => add::<C>(db_conn, arg(1)?, &arg(2)?.to_lowercase())?,
+        Some("remove") => {
+            let alias = arg(1)?.to_lowercase();
+            db_conn.archive_game(&alias)?;
+            format!("Archived {}, restore it with !restore", alias)
+        }
+        Some("purge") => {
+            let alias = arg(1)?.to_lowercase();
+            db_conn.game_for_alias_including_archived(&alias)?;
+            db_conn.remove_server(&alias)?;
+            format!("Deleted {} and its history", alias)
+        }
+        Some("register") => register(db_conn, &arg(1)?.to_lowercase(), arg(2)?, arg(3)?)?,
+        Some("unregister") => unregister(db_conn, &arg(1)?.to_lowercase(), arg(2)?)?,
+        Some("migrate") => migrate(),
+        Some("poll") => poll::<C>(db_conn, arg(1)?)?,
+        Some("repl") => {
+            let offline = args.get(1).map(String::as_str) == Some("--offline");
+            let author = match args.get(if offline { 2 } else { 1 }) {
+                Some(user_id) => parse_user_id(user_id)?,
+                None => *admins
+                    .0
+                    .first()
+                    .ok_or_else(|| err_msg("There are no admins configured, give a discord user id to run commands as"))?,
+            };
+            let tools = repl::ReplTools {
+                inspector: config.inspector()?,
+                backups: config.backups()?,
+            };
+            let stdin = io::stdin();
+            return if offline {
+                repl::repl::<OfflineServerConnection, _, _>(db_conn, admins, &tools, author, stdin.lock(), io::stdout())
+            } else {
+                repl::repl::<C, _, _>(db_conn, admins, &tools, author, stdin.lock(), io::stdout())
+            };
+        }
+        _ => return Err(err_msg(USAGE)),
+    };
+    info!("{}", text);
+    println!("{}", text);
+    Ok(())
+}
+
+fn export(db_conn: &DbConnection, path: &str) -> Result<(), Error> {
+    let export = db_conn.export()?;
+    let json = serde_json::to_string_pretty(&export)?;
+    let mut file = File::create(path).context(format!("Creating file '{}'", path))?;
+    file.write_all(json.as_bytes()).context(format!("Writing file '{}'", path))?;
+    info!("exported {} games and {} players to {}", export.games.len(), export.players.len(), path);
+    println!("Exported {} games and {} players to {}", export.games.len(), export.players.len(), path);
+    Ok(())
+}
+
+fn import(db_conn: &DbConnection, path: &str, mode: ImportMode) -> Result<(), Error> {
+    let mut file = File::open(path).context(format!("Opening file '{}'", path))?;
+    let mut json = String::new();
+    file.read_to_string(&mut json).context(format!("Reading file '{}'", path))?;
+    let export: DbExport = serde_json::from_str(&json).context(format!("Parsing file '{}'", path))?;
+    let report = db_conn.import(&export, mode)?;
+    println!("{}", report);
+    Ok(())
+}
+
+fn games(db_conn: &DbConnection) -> Result<String, Error> {
+    let servers = db_conn.retrieve_all_servers()?;
+    if servers.is_empty() {
+        return Ok("No games".to_owned());
+    }
+    let lines = servers
+        .iter()
+        .map(|server| match server.state {
+            GameServerState::StartedState(ref started_state, _) => format!(
+                "{:<20} {:<30} turn {}",
+                server.alias, started_state.address, started_state.last_seen_turn
+            ),
+            GameServerState::Lobby(ref lobby_state) => format!(
+                "{:<20} {:<30} {} lobby",
+                server.alias, "-", lobby_state.status
+            ),
+        })
+        .collect::<Vec<_>>();
+    Ok(lines.join("\n"))
+}
+
+fn lobbies(db_conn: &DbConnection) -> Result<String, Error> {
+    let lobbies = db_conn.select_lobbies(true)?;
+    if lobbies.is_empty() {
+        return Ok("No lobbies".to_owned());
+    }
+    let lines = lobbies
+        .iter()
+        .filter_map(|&(ref server, registered)| match server.state {
+            GameServerState::Lobby(ref lobby_state) => Some(format!(
+                "{:<20} {} {}/{} players, {}, owned by {}",
+                server.alias,
+                lobby_state.era,
+                registered,
+                lobby_state.player_count,
+                lobby_state.status,
+                lobby_state.owner.0,
+            )),
+            GameServerState::StartedState(..) => None,
+        })
+        .collect::<Vec<_>>();
+    Ok(lines.join("\n"))
+}
+
+fn players(db_conn: &DbConnection, alias: &str) -> Result<String, Error> {
+    db_conn.game_for_alias(alias)?;
+    let mut players_nations = db_conn.players_with_nations_for_game_alias(alias)?;
+    if players_nations.is_empty() {
+        return Ok(format!("Nobody is registered in {}", alias));
+    }
+    players_nations.sort_by_key(|&(_, nation_id)| nation_id);
+    let lines = players_nations
+        .iter()
+        .map(|&(ref player, nation_id)| {
+            let &(name, era) = Nations::get_nation_desc(nation_id);
+            format!("{:>4} {} {:<20} {}", nation_id, era, name, player.discord_user_id.0)
+        })
+        .collect::<Vec<_>>();
+    Ok(lines.join("\n"))
+}
+
+fn add<C: ServerConnection>(db_conn: &DbConnection, address: &str, alias: &str) -> Result<String, Error> {
+    let game_data = C::get_game_data(address).context(format!("Connecting to '{}'", address))?;
+    db_conn.insert_game_server(&GameServer {
+        alias: alias.to_owned(),
+        state: GameServerState::StartedState(
+            StartedState {
+                address: address.to_owned(),
+                last_seen_turn: game_data.turn,
+            },
+            None,
+        ),
+    })?;
+    Ok(format!("Added {} at {} on turn {}", alias, address, game_data.turn))
+}
+
+fn register(db_conn: &DbConnection, alias: &str, user_id: &str, nation_id: &str) -> Result<String, Error> {
+    db_conn.game_for_alias(alias)?;
+    let user_id = parse_user_id(user_id)?;
+    let nation_id = nation_id
+        .parse::<u32>()
+        .map_err(|_| err_msg(format!("'{}' isn't a nation id", nation_id)))?;
+    let nation = Nations::from_id(nation_id).ok_or_else(|| err_msg(format!("There's no nation {}", nation_id)))?;
+    if let Some((player, _)) = db_conn
+        .players_with_nations_for_game_alias(alias)?
+        .into_iter()
+        .find(|&(_, registered_nation_id)| registered_nation_id == nation_id as usize)
+    {
+        return Err(err_msg(format!(
+            "{} {} ({}) is already registered to {} in {}, unregister them first",
+            nation.era, nation.name, nation_id, player.discord_user_id.0, alias
+        )));
+    }
+    db_conn.insert_player(&Player {
+        discord_user_id: user_id,
+        turn_notifications: true,
+    })?;
+    db_conn.insert_server_player(alias, user_id, nation_id)?;
+    Ok(format!("Registered {} as {} {} ({}) in {}", user_id.0, nation.era, nation.name, nation_id, alias))
+}
+
+/// Unlike !unregister this doesn't promote anyone from the waitlist
+fn unregister(db_conn: &DbConnection, alias: &str, user_id: &str) -> Result<String, Error> {
+    db_conn.game_for_alias(alias)?;
+    let user_id = parse_user_id(user_id)?;
+    db_conn.remove_player_from_game(alias, user_id)?;
+    db_conn.remove_open_substitution_requests(alias, user_id)?;
+    Ok(format!("Unregistered {} from {}", user_id.0, alias))
+}
+
+fn parse_user_id(user_id: &str) -> Result<UserId, Error> {
+    user_id
+        .parse::<u64>()
+        .map(UserId)
+        .map_err(|_| err_msg(format!("'{}' isn't a discord user id", user_id)))
+}
+
+/// Opening the database has already applied anything that was missing
+fn migrate() -> String {
+    let tags = DbConnection::migration_tags();
+    format!("The database is up to date, with {} migrations:\n{}", tags.len(), tags.join("\n"))
+}
+
+fn poll<C: ServerConnection>(db_conn: &DbConnection, address_or_alias: &str) -> Result<String, Error> {
+    let address = if address_or_alias.contains(':') {
+        address_or_alias.to_owned()
+    } else {
+        match db_conn.game_for_alias(&address_or_alias.to_lowercase())?.state {
+            GameServerState::StartedState(started_state, _) => started_state.address,
+            GameServerState::Lobby(_) => {
+                return Err(err_msg(format!("{} is a lobby and has no server yet", address_or_alias)))
+            }
+        }
+    };
+    let game_data = C::get_game_data(&address).context(format!("Connecting to '{}'", address))?;
+    Ok(game_data_text(&game_data))
+}
+
+fn game_data_text(game_data: &GameData) -> String {
+    let timer = if game_data.turn_timer > 0 {
+        let mins_remaining = game_data.turn_timer / 1000 / 60;
+        format!("{}h {}m remaining", mins_remaining / 60, mins_remaining % 60)
+    } else {
+        "no timer".to_owned()
+    };
+    let mut text = format!("{}: turn {}, {}", game_data.game_name, game_data.turn, timer);
+    for nation in &game_data.nations {
+        text.push_str(&format!(
+            "\n{:>4} {} {:<20} {:<18} {:<3} {}",
+            nation.id,
+            nation.era,
+            nation.name,
+            nation.status.show(),
+            nation.submitted.show(),
+            if nation.connected { "connected" } else { "" },
+        ));
+    }
+    text
+}
@@ -0,0 +1,163 @@
+//! The chat commands, typed into a terminal instead of sent on discord
+
+use failure::Error;
+use serenity::model::id::{ChannelId, MessageId, UserId};
+
+use std::cell::RefCell;
+use std::io::{BufRead, Write};
+
+use crate::commands::admin::{run_admin_command, Admins, ADMIN_COMMANDS};
+use crate::commands::core::{run_command, split_args, CommandContext, Embed, Request};
+use crate::commands::servers::CORE_COMMANDS;
+use crate::commands::{run_search_command, Inspector, SEARCH_COMMANDS};
+use crate::db::{Backups, DbConnection};
+use crate::discord::{ChannelMessages, DirectMessages, PinnedMessages, UserLookup};
+use crate::server::ServerConnection;
+
+const PROMPT: &str = "> ";
+
+/// Prints what would have been sent on discord instead of sending it
+#[derive(Debug, Default)]
+struct TerminalDiscord {
+    sent: RefCell<Vec<String>>,
+}
+
+impl TerminalDiscord {
+    fn take_sent(&self) -> Vec<String> {
+        self.sent.borrow_mut().drain(..).collect()
+    }
+}
+
+impl UserLookup for TerminalDiscord {
+    fn user_name(&self, user_id: UserId) -> Result<String, Error> {
+        Ok(format!("@{}", user_id.0))
+    }
+
+    fn cached_user_name(&self, _user_id: UserId) -> Option<String> {
+        None
+    }
+}
+
+impl DirectMessages for TerminalDiscord {
+    fn send_dm(&self, user_id: UserId, text: &str) -> Result<(), Error> {
+        self.sent.borrow_mut().push(format!("[DM to @{}] {}", user_id.0, text));
+        Ok(())
+    }
+}
+
+impl ChannelMessages for TerminalDiscord {
+    fn say(&self, channel_id: ChannelId, text: &str) -> Result<(), Error> {
+        self.sent.borrow_mut().push(format!("[#{}] {}", channel_id.0, text));
+        Ok(())
+    }
+
+    fn send_embed(&self, channel_id: ChannelId, embed: &Embed) -> Result<(), Error> {
+        self.say(channel_id, &embed.to_text())
+    }
+
+    fn delete(&self, channel_id: ChannelId, message_id: MessageId) -> Result<(), Error> {
+        self.sent
+            .borrow_mut()
+            .push(format!("[#{}] (deleted message {})", channel_id.0, message_id.0));
+        Ok(())
+    }
+}
+
+impl PinnedMessages for TerminalDiscord {
+    fn pin_embed(&self, channel_id: ChannelId, embed: &Embed) -> Result<MessageId, Error> {
+        self.send_embed(channel_id, embed)?;
+        Ok(MessageId(self.sent.borrow().len() as u64))
+    }
+
+    fn edit_embed(&self, channel_id: ChannelId, message_id: MessageId, embed: &Embed) -> Result<(), Error> {
+        let text = format!("(edited message {}) {}", message_id.0, embed.to_text());
+        self.say(channel_id, &text)
+    }
+
+    fn unpin(&self, _channel_id: ChannelId, _message_id: MessageId) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn was_deleted(&self, _channel_id: ChannelId, _message_id: MessageId) -> bool {
+        false
+    }
+}
+
+/// What the search and admin commands need besides a `CommandContext`
+pub struct ReplTools {
+    pub inspector: Inspector,
+    pub backups: Backups,
+}
+
+/// Every command runs as `author`, until the input ends or says quit
+pub fn repl<C: ServerConnection, R: BufRead, W: Write>(
+    db_conn: &DbConnection,
+    admins: &Admins,
+    tools: &ReplTools,
+    author: UserId,
+    input: R,
+    mut output: W,
+) -> Result<(), Error> {
+    let discord = TerminalDiscord::default();
+    let context = CommandContext {
+        db_conn,
+        admins,
+        discord: &discord,
+    };
+    write!(output, "{}", PROMPT)?;
+    output.flush()?;
+    for line in input.lines() {
+        let text = match repl_line::<C>(&context, tools, author, &line?) {
+            Some(text) => text,
+            None => break,
+        };
+        for sent in discord.take_sent() {
+            writeln!(output, "{}", sent)?;
+        }
+        if !text.is_empty() {
+            writeln!(output, "{}", text)?;
+        }
+        write!(output, "{}", PROMPT)?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// `None` to quit
+fn repl_line<C: ServerConnection>(
+    context: &CommandContext,
+    tools: &ReplTools,
+    author: UserId,
+    line: &str,
+) -> Option<String> {
+    let line = line.trim();
+    // so commands can be pasted straight from discord
+    let line = if line.starts_with('!') { &line[1..] } else { line };
+    let mut args = split_args(line);
+    if args.is_empty() {
+        return Some(String::new());
+    }
+    let command = args.remove(0);
+    match command.as_str() {
+        "quit" | "exit" => None,
+        "help" => Some(format!(
+            "Commands: {}\nSearches: {}\nAdmin commands: {}\n\
+             There's no channel here, so give every command its game's alias. \
+             !export and !import move files, so use `dom5status export` and `dom5status import` instead",
+            CORE_COMMANDS.join(", "),
+            SEARCH_COMMANDS.join(", "),
+            ADMIN_COMMANDS.join(", "),
+        )),
+        _ => {
+            let mut request = Request::new(author, None, args);
+            let result = run_command::<C>(context, &command, &mut request)
+                .or_else(|| run_search_command(&tools.inspector, &command, &mut request))
+                .or_else(|| run_admin_command(context, &tools.backups, &command, &mut request));
+            Some(match result {
+                Some(Ok(response)) => response.to_text(),
+                Some(Err(e)) => format!("ERROR: {}", e.0),
+                None => format!("{} only works on discord, try help", command),
+            })
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use super::*;
+
+use crate::commands::Inspector;
+use crate::model::Nation;
+use crate::model::enums::*;
+use std::io;
+
+fn game_data(turn: i32) -> GameData {
+    GameData {
+        game_name: "foo".to_owned(),
+        nations: vec![Nation {
+            id: 5,
+            status: NationStatus::Human,
+            submitted: SubmissionStatus::Submitted,
+            connected: true,
+            name: "Arcoscephale".to_owned(),
+            era: "EA".to_owned(),
+        }],
+        turn,
+        turn_timer: (2 * 60 + 5) * 60 * 1000,
+    }
+}
+
+/// Links searches to the inspector, and backs up nowhere in particular
+fn repl_tools() -> repl::ReplTools {
+    repl::ReplTools {
+        inspector: Inspector::load("https://larzm42.github.io/dom5inspector/", None),
+        backups: Backups {
+            dir: std::env::temp_dir().join("dom5status-test-repl-backups"),
+            keep: 1,
+        },
+    }
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| (*arg).to_owned()).collect()
+}
+
+#[test]
+fn should_list_games_and_lobbies() {
+    let db_conn = &DbConnection::test();
+    assert_eq!(games(db_conn).unwrap(), "No games");
+    db_conn.insert_test_game("started", "started.cli:3000", 3);
+    db_conn.insert_test_lobby("open", UserId(7), 4);
+    db_conn.insert_test_lobby("cancelled", UserId(7), 4);
+    db_conn.set_lobby_status("cancelled", LobbyStatus::Cancelled).unwrap();
+
+    let games = games(db_conn).unwrap();
+    assert!(games.contains("started.cli:3000"));
+    assert!(games.contains("turn 3"));
+    assert!(games.contains("open"));
+
+    let lobbies = lobbies(db_conn).unwrap();
+    assert_eq!(lobbies.lines().count(), 2);
+    assert!(lobbies.contains("0/4 players"));
+    assert!(lobbies.contains("owned by 7"));
+}
+
+#[test]
+fn should_add_a_game_on_the_servers_turn() {
+    mock_server_connection!(Mock, Ok(game_data(12)));
+    let db_conn = &DbConnection::test();
+    let config = Config::default();
+
+    run::<Mock>(&args(&["add", "new.cli:3000", "NewGame"]), &config, db_conn, &Admins(vec![])).unwrap();
+
+    match db_conn.game_for_alias("newgame").unwrap().state {
+        GameServerState::StartedState(started_state, None) => {
+            assert_eq!(started_state.address, "new.cli:3000");
+            assert_eq!(started_state.last_seen_turn, 12);
+        }
+        state => panic!("expected a started game but got {:?}", state),
+    }
+}
+
+#[test]
+fn should_not_add_a_game_whose_server_is_down() {
+    mock_server_connection!(Mock, Err(io::Error::new(io::ErrorKind::Other, "down")));
+    let db_conn = &DbConnection::test();
+    let config = Config::default();
+
+    assert!(run::<Mock>(&args(&["add", "down.cli:3000", "down"]), &config, db_conn, &Admins(vec![])).is_err());
+    assert!(db_conn.game_for_alias("down").is_err());
+}
+
+#[test]
+fn should_archive_and_purge_games() {
+    mock_server_connection!(Mock, Ok(game_data(1)));
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("archived", "archived.cli:3000", 3);
+    db_conn.insert_test_game("purged", "purged.cli:3000", 3);
+    let config = Config::default();
+
+    run::<Mock>(&args(&["remove", "archived"]), &config, db_conn, &Admins(vec![])).unwrap();
+    run::<Mock>(&args(&["purge", "purged"]), &config, db_conn, &Admins(vec![])).unwrap();
+
+    assert!(db_conn.retrieve_all_servers().unwrap().is_empty());
+    assert_eq!(db_conn.archived_games().unwrap().len(), 1);
+    assert!(run::<Mock>(&args(&["purge", "purged"]), &config, db_conn, &Admins(vec![])).is_err());
+}
+
+#[test]
+fn should_fix_registrations() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.cli:3000", 3);
+
+    register(db_conn, "foo", "1234", "5").unwrap();
+    // someone else already has the nation
+    assert!(register(db_conn, "foo", "99", "5").is_err());
+    assert!(register(db_conn, "foo", "99", "not a nation").is_err());
+    assert!(register(db_conn, "foo", "a name", "6").is_err());
+    assert!(register(db_conn, "missing", "99", "6").is_err());
+
+    let registered = db_conn.players_with_nations_for_game_alias("foo").unwrap();
+    assert_eq!(registered.len(), 1);
+    assert_eq!(registered[0].0.discord_user_id, UserId(1234));
+    assert_eq!(registered[0].1, 5);
+    let &(name, _) = Nations::get_nation_desc(5);
+    assert!(players(db_conn, "foo").unwrap().contains(name));
+
+    unregister(db_conn, "foo", "1234").unwrap();
+    assert!(db_conn.players_with_nations_for_game_alias("foo").unwrap().is_empty());
+    assert_eq!(players(db_conn, "foo").unwrap(), "Nobody is registered in foo");
+}
+
+#[test]
+fn should_poll_a_game_by_alias_or_address() {
+    mock_conditional_server_connection!(Mock, |address: &str| if address == "foo.cli:3000" {
+        Ok(game_data(4))
+    } else {
+        Err(io::Error::new(io::ErrorKind::Other, "wrong address"))
+    });
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.cli:3000", 3);
+    db_conn.insert_test_lobby("lobby", UserId(7), 4);
+
+    let by_alias = poll::<Mock>(db_conn, "foo").unwrap();
+    assert_eq!(poll::<Mock>(db_conn, "foo.cli:3000").unwrap(), by_alias);
+    assert!(by_alias.starts_with("foo: turn 4, 2h 5m remaining\n"));
+    assert!(by_alias.contains("Arcoscephale"));
+    assert!(by_alias.contains("connected"));
+    assert!(poll::<Mock>(db_conn, "lobby").is_err());
+}
+
+#[test]
+fn should_list_every_migration() {
+    let text = migrate();
+    assert!(text.contains("001-baseline"));
+    assert!(text.contains(DbConnection::migration_tags().last().unwrap()));
+}
+
+#[test]
+fn should_show_usage_for_unknown_commands() {
+    mock_server_connection!(Mock, Ok(game_data(1)));
+    let db_conn = &DbConnection::test();
+    let config = Config::default();
+    let err = run::<Mock>(&args(&["frobnicate"]), &config, db_conn, &Admins(vec![])).unwrap_err();
+    assert!(err.to_string().starts_with("USAGE"));
+    assert!(run::<Mock>(&args(&["players"]), &config, db_conn, &Admins(vec![])).is_err());
+}
+
+#[test]
+fn should_run_chat_commands_in_the_repl() {
+    mock_server_connection!(Mock, Ok(game_data(3)));
+    let db_conn = &DbConnection::test();
+    let input = "!add foo.cli:3000 Foo\n\nregister-id 5 foo\ndetails foo\nturns\nstalls foo\nnotify-channel foo\nbackup\nitem fire sword\nquit\nlist\n";
+    let mut output = vec![];
+
+    repl::repl::<Mock, _, _>(db_conn, &Admins(vec![]), &repl_tools(), UserId(1234), input.as_bytes(), &mut output).unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("Successfully inserted with alias foo"));
+    assert!(output.contains("registering nation Arcoscephale (5) for user @1234"));
+    assert!(output.contains("foo (foo.cli:3000): turn 3, 2h 5m remaining"));
+    // DMs are shown rather than sent
+    assert!(output.contains("[DM to @1234] Your turns:\nfoo turn 3"));
+    assert!(output.contains("foo stalls\nNo stalls recorded"));
+    assert!(output.contains("ERROR: That only works in a discord channel"));
+    assert!(output.contains("ERROR: only bot admins can do that"));
+    assert!(output.contains("https://larzm42.github.io/dom5inspector/?page=item&itemq=fire%20sword"));
+    // nothing runs after quit
+    assert!(!output.contains("Servers:"));
+    assert_eq!(db_conn.players_with_nations_for_game_alias("foo").unwrap().len(), 1);
+}
+
+#[test]
+fn should_show_repl_errors_and_carry_on() {
+    mock_server_connection!(Mock, Ok(game_data(3)));
+    let db_conn = &DbConnection::test();
+    let mut output = vec![];
+
+    repl::repl::<Mock, _, _>(db_conn, &Admins(vec![]), &repl_tools(), UserId(1234), "details\ndetails missing\nhelp\n".as_bytes(), &mut output).unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("ERROR: Could not game alias from command argument or channel name"));
+    assert_eq!(output.matches("ERROR").count(), 2);
+    assert!(output.contains("Commands: add, list"));
+}
+
+#[test]
+fn should_run_a_lobby_through_the_offline_repl() {
+    let db_conn = &DbConnection::test();
+    let input = "lobby ea 2 bar\nregister-id 5 bar\nstart bar.offline:3000 bar\nmove bar.elsewhere:4000 bar\n";
+    let mut output = vec![];
+
+    repl::repl::<OfflineServerConnection, _, _>(db_conn, &Admins(vec![]), &repl_tools(), UserId(1234), input.as_bytes(), &mut output)
+        .unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("Creating game lobby with name bar"));
+    assert!(output.contains("[DM to @1234] Pretender upload has started in bar.\nServer address is bar.offline:3000."));
+    assert!(output.contains("started!"));
+    assert!(output.contains("Moved bar to bar.elsewhere:4000 (turn 1)"));
+    assert!(!output.contains("ERROR"));
+}
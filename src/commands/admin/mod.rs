@@ -0,0 +1,202 @@
+use serenity::framework::standard::{Args, CommandError, StandardFramework};
+use serenity::client::Context;
+use serenity::model::channel::Message;
+use serenity::model::id::UserId;
+use serenity::http;
+use chrono::{DateTime, Utc};
+use failure::{err_msg, Error};
+use log::*;
+
+use crate::commands::core::{respond_on_discord, CommandContext, Embed, Request, Response};
+use crate::db::*;
+use crate::discord::SerenityDiscord;
+
+#[cfg(test)]
+mod tests;
+
+/// The users in the config's `admin_user_ids`, plus whoever owns the bot's Discord
+/// application. The owner is looked up once at startup (see `with_bot_owner`) so checking
+/// never has to ask discord, and the CLI and tests can run without a token.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Admins(pub Vec<UserId>);
+
+impl Admins {
+    pub fn with_bot_owner(mut self) -> Result<Admins, Error> {
+        let bot_owner = http::get_current_application_info()
+            .map_err(|e| err_msg(format!("Looking up the bot's owner: {:?}", e)))?
+            .owner
+            .id;
+        if !self.0.contains(&bot_owner) {
+            self.0.push(bot_owner);
+        }
+        Ok(self)
+    }
+
+    pub fn check(&self, user_id: UserId) -> Result<(), CommandError> {
+        if self.0.contains(&user_id) {
+            Ok(())
+        } else {
+            Err(CommandError::from("only bot admins can do that"))
+        }
+    }
+}
+
+fn export_helper(db_conn: &DbConnection) -> Result<String, CommandError> {
+    let export = db_conn.export()?;
+    let json = serde_json::to_string_pretty(&export)?;
+    Ok(json)
+}
+
+fn import_helper(db_conn: &DbConnection, json: &str, mode: ImportMode) -> Result<ImportReport, CommandError> {
+    let export: DbExport = serde_json::from_str(json)
+        .map_err(|e| CommandError(format!("Could not read the export: {}", e)))?;
+    let report = db_conn.import(&export, mode)?;
+    Ok(report)
+}
+
+fn export(db_conn: &DbConnection, admins: &Admins, message: &Message) -> Result<(), CommandError> {
+    admins.check(message.author.id)?;
+    let json = export_helper(db_conn)?;
+    let file_name = format!("dom5status-{}.json", Utc::now().format("%Y-%m-%d-%H%M%S"));
+    info!("sending export {} to {}", file_name, message.author.id);
+    // the export has everyone's registrations in it, so it shouldn't go to a public channel
+    let private_channel = message.author.id.create_dm_channel()?;
+    private_channel.send_files(vec![(json.as_bytes(), file_name.as_str())], |m| {
+        m.content("Database export")
+    })?;
+    message.reply(&"sent you the export")?;
+    Ok(())
+}
+
+fn import(db_conn: &DbConnection, admins: &Admins, message: &Message, mut args: Args) -> Result<(), CommandError> {
+    admins.check(message.author.id)?;
+    let mode_arg = args.single_quoted::<String>()
+        .map_err(|_| CommandError::from("Need to say whether to \"merge\" or \"replace\""))?;
+    let mode = ImportMode::from_string(&mode_arg)
+        .ok_or_else(|| CommandError(format!("Unknown import mode {}, need \"merge\" or \"replace\"", mode_arg)))?;
+    if !args.is_empty() {
+        return Err(CommandError::from(
+            "Too many arguments. TIP: spaces in arguments need to be quoted \"like this\"",
+        ));
+    }
+    let attachment = message.attachments.first()
+        .ok_or("Need to attach an export file to the message")?;
+    let bytes = attachment.download()?;
+    let json = String::from_utf8(bytes)?;
+
+    let report = import_helper(db_conn, &json, mode)?;
+    info!("imported {}: {}", attachment.filename, report);
+    message.reply(&report.to_string())?;
+    Ok(())
+}
+
+fn backup_helper(db_conn: &DbConnection, backups: &Backups, now: DateTime<Utc>) -> Result<String, CommandError> {
+    let backup = backups.create(db_conn, now)?;
+    Ok(format!(
+        "backed up the database to {} ({} KB)",
+        backup.file_name(),
+        backup.size_bytes / 1024,
+    ))
+}
+
+fn backups_helper(backups: &Backups) -> Result<Option<Embed>, CommandError> {
+    let backup_files = backups.list()?;
+    if backup_files.is_empty() {
+        return Ok(None);
+    }
+
+    let mut file_names = String::new();
+    let mut taken_ats = String::new();
+    let mut sizes = String::new();
+    for backup_file in backup_files {
+        file_names.push_str(&format!("{}\n", backup_file.file_name()));
+        taken_ats.push_str(&format!("{}\n", backup_file.taken_at.format("%Y-%m-%d %H:%M:%S")));
+        sizes.push_str(&format!("{} KB\n", backup_file.size_bytes / 1024));
+    }
+
+    let embed = Embed::default()
+        .title(format!("Backups (keeping the newest {})", backups.keep))
+        .field("File", file_names, true)
+        .field("Taken (UTC)", taken_ats, true)
+        .field("Size", sizes, true);
+    Ok(Some(embed))
+}
+
+fn backup(context: &CommandContext, backups: &Backups, request: &mut Request) -> Result<Response, CommandError> {
+    context.admins.check(request.author)?;
+    request.no_more_args()?;
+    let text = backup_helper(context.db_conn, backups, Utc::now())?;
+    Ok(Response::Reply(text))
+}
+
+fn list_backups(context: &CommandContext, backups: &Backups, request: &mut Request) -> Result<Response, CommandError> {
+    context.admins.check(request.author)?;
+    request.no_more_args()?;
+    match backups_helper(backups)? {
+        Some(embed) => Ok(Response::Embed(embed)),
+        None => Ok(Response::Reply("No backups yet".to_owned())),
+    }
+}
+
+/// The admin commands that run through `run_admin_command`. `export` and `import` send and
+/// receive files, so they only work on discord. The command line has its own for those.
+pub const ADMIN_COMMANDS: &[&str] = &["backup", "backups"];
+
+/// `None` if `command` isn't one of `ADMIN_COMMANDS`
+pub fn run_admin_command(
+    context: &CommandContext,
+    backups: &Backups,
+    command: &str,
+    request: &mut Request,
+) -> Option<Result<Response, CommandError>> {
+    let run: fn(&CommandContext, &Backups, &mut Request) -> Result<Response, CommandError> = match command {
+        "backup" => backup,
+        "backups" => list_backups,
+        _ => return None,
+    };
+    Some(run(context, backups, request))
+}
+
+pub trait WithAdminCommands: Sized {
+    fn get_standard_framework(self) -> StandardFramework;
+    fn with_admin_commands(
+        self,
+        bucket: &str,
+        db_conn: &DbConnection,
+        backups: &Backups,
+        admins: &Admins,
+    ) -> StandardFramework {
+        let framework = self
+            .get_standard_framework()
+            .command("export", |c| {
+                let db_conn = db_conn.clone();
+                let admins = admins.clone();
+                c.bucket(bucket).cmd(move |_: &mut Context, m: &Message, _: Args| export(&db_conn, &admins, m))
+            })
+            .command("import", |c| {
+                let db_conn = db_conn.clone();
+                let admins = admins.clone();
+                c.bucket(bucket).cmd(move |_: &mut Context, m: &Message, a: Args| import(&db_conn, &admins, m, a))
+            });
+        ADMIN_COMMANDS.iter().fold(framework, |framework, &command| {
+            framework.command(command, |c| {
+                let db_conn = db_conn.clone();
+                let backups = backups.clone();
+                let admins = admins.clone();
+                c.bucket(bucket).cmd(move |_: &mut Context, m: &Message, a: Args| {
+                    let context = CommandContext {
+                        db_conn: &db_conn,
+                        admins: &admins,
+                        discord: &SerenityDiscord,
+                    };
+                    respond_on_discord(command, m, a, |request| run_admin_command(&context, &backups, command, request))
+                })
+            })
+        })
+    }
+}
+impl WithAdminCommands for StandardFramework {
+    fn get_standard_framework(self) -> StandardFramework {
+        self
+    }
+}
@@ -0,0 +1,380 @@
+use super::*;
+
+use crate::discord::FakeDiscord;
+use crate::model::*;
+use crate::model::enums::*;
+use serenity::model::id::{ChannelId, MessageId, UserId};
+
+fn populate(db_conn: &DbConnection) {
+    db_conn
+        .insert_game_server(&GameServer {
+            alias: "started".to_owned(),
+            state: GameServerState::StartedState(
+                StartedState {
+                    address: "foo.bar:3000".to_owned(),
+                    last_seen_turn: 23,
+                },
+                None,
+            ),
+        })
+        .unwrap();
+    db_conn
+        .insert_game_server(&GameServer {
+            alias: "lobby".to_owned(),
+            state: GameServerState::Lobby(LobbyState {
+                owner: UserId(7),
+                era: Era::Late,
+                player_count: 5,
+                description: Some("come play".to_owned()),
+                status: LobbyStatus::Locked,
+                scheduled_start: None,
+            }),
+        })
+        .unwrap();
+    db_conn.insert_player(&Player { discord_user_id: UserId(1), turn_notifications: false }).unwrap();
+    db_conn.set_reminder_thresholds(UserId(1), Some(&[60, 10])).unwrap();
+    db_conn.insert_player(&Player { discord_user_id: UserId(2), turn_notifications: true }).unwrap();
+    db_conn.insert_server_player("started", UserId(1), 5).unwrap();
+    db_conn.insert_server_player("started", UserId(2), 12).unwrap();
+    db_conn.insert_server_player("lobby", UserId(1), 80).unwrap();
+}
+
+#[test]
+fn should_round_trip_export_through_json() {
+    let db_conn = &DbConnection::test();
+    populate(db_conn);
+    db_conn.archive_game("started").unwrap();
+    let json = export_helper(db_conn).unwrap();
+
+    let fresh_db_conn = &DbConnection::test();
+    let report = import_helper(fresh_db_conn, &json, ImportMode::Merge).unwrap();
+    assert_eq!(report.games, 2);
+    assert_eq!(report.players, 3);
+    assert_eq!(report.registrations, 3);
+    assert!(report.conflicts.is_empty());
+
+    assert_eq!(fresh_db_conn.export().unwrap(), db_conn.export().unwrap());
+    assert_eq!(fresh_db_conn.archived_games().unwrap().len(), 1);
+    assert_eq!(
+        fresh_db_conn.reminder_thresholds(UserId(1)).unwrap(),
+        Some(vec![60, 10])
+    );
+}
+
+#[test]
+fn should_round_trip_everything_a_replace_deletes() {
+    let db_conn = &DbConnection::test();
+    populate(db_conn);
+    let webhook = Webhook {
+        url: "https://example.com/hook".to_owned(),
+        secret: Some("shh".to_owned()),
+    };
+    db_conn.insert_player(&Player { discord_user_id: UserId(3), turn_notifications: true }).unwrap();
+    db_conn.set_player_discord_dms(UserId(2), false).unwrap();
+    db_conn.set_player_webhook(UserId(2), Some(&webhook)).unwrap();
+    db_conn.set_notification_channel("started", Some(ChannelId(100))).unwrap();
+    db_conn.set_substitute_channel("started", Some(ChannelId(101))).unwrap();
+    db_conn.set_game_webhook("started", Some(&webhook)).unwrap();
+    db_conn.set_pinned_status("started", ChannelId(100), MessageId(200)).unwrap();
+    db_conn.set_game_reminder_thresholds("started", UserId(1), Some(&[30])).unwrap();
+    db_conn.insert_substitution_requests("started", UserId(2), &[12], 23).unwrap();
+    db_conn.insert_sent_reminder("started", UserId(1), 5, 23, 60).unwrap();
+    db_conn.insert_stall("started", 5, 22).unwrap();
+    db_conn
+        .update_game_with_possibly_new_turn(
+            "started",
+            &TurnRecord {
+                turn: 24,
+                seen_at: at("2019-06-02T18:00:00Z"),
+                turn_timer: 3_600_000,
+                defeated: vec![7],
+                ai: vec![],
+                possible_stalls: vec![5, 12],
+            },
+            &[OutboxMessage {
+                game: "started".to_owned(),
+                turn: 24,
+                kind: "new_turn".to_owned(),
+                recipient: None,
+                backend: "discord".to_owned(),
+                text: "turn 24".to_owned(),
+            }],
+        )
+        .unwrap();
+    db_conn.set_stall_warning_threshold("lobby", Some(3)).unwrap();
+    db_conn.insert_waitlist_entry("lobby", UserId(3), Some(10)).unwrap();
+    db_conn.insert_lobby_start_reminder("lobby", 60).unwrap();
+    let export = db_conn.export().unwrap();
+    let json = export_helper(db_conn).unwrap();
+
+    let other_db_conn = &DbConnection::test();
+    populate(other_db_conn);
+    let report = import_helper(other_db_conn, &json, ImportMode::Replace).unwrap();
+    assert!(report.conflicts.is_empty());
+    assert_eq!(other_db_conn.export().unwrap(), export);
+
+    let started = &export.games[1];
+    assert_eq!(started.notification_channel_id, Some(100));
+    assert!(started.webhook.is_some() && started.pinned_status.is_some());
+    for &(table, row_count) in &[
+        ("player_settings", started.player_settings.len()),
+        ("substitutions", started.substitutions.len()),
+        ("sent_reminders", started.sent_reminders.len()),
+        ("stalls", started.stalls.len()),
+        ("turn_history", started.turn_history.len()),
+        ("outbox", started.outbox.len()),
+        ("waitlist", export.games[0].waitlist.len()),
+        ("lobby_start_reminders", export.games[0].lobby_start_reminders_mins.len()),
+    ] {
+        assert_eq!(row_count, 1, "{}", table);
+    }
+    assert_eq!(export.games[0].lobby.as_ref().unwrap().stall_warning_threshold, Some(3));
+    assert_eq!(
+        other_db_conn.player_notifiers(UserId(2)).unwrap(),
+        PlayerNotifiers { discord_dms: false, webhook: Some(webhook) }
+    );
+}
+
+#[test]
+fn should_report_conflicts_when_merging() {
+    let db_conn = &DbConnection::test();
+    populate(db_conn);
+    let mut export = db_conn.export().unwrap();
+    export.players[0].turn_notifications = !export.players[0].turn_notifications;
+    export.games.push(GameExport {
+        alias: "same_address".to_owned(),
+        archived_at: None,
+        started: Some(StartedExport {
+            address: "foo.bar:3000".to_owned(),
+            last_seen_turn: 1,
+        }),
+        lobby: None,
+        registrations: vec![],
+        ..GameExport::default()
+    });
+    export.games.push(GameExport {
+        alias: "new".to_owned(),
+        archived_at: None,
+        started: Some(StartedExport {
+            address: "new.bar:3000".to_owned(),
+            last_seen_turn: 1,
+        }),
+        lobby: None,
+        registrations: vec![RegistrationExport { discord_user_id: 3, nation_id: 5 }],
+        ..GameExport::default()
+    });
+
+    let report = db_conn.import(&export, ImportMode::Merge).unwrap();
+    assert_eq!(report.games, 1);
+    assert_eq!(report.players, 0);
+    assert_eq!(report.registrations, 1);
+    // one changed player, two existing aliases and one existing address
+    assert_eq!(report.conflicts.len(), 4);
+    assert_eq!(db_conn.count_servers(), 3);
+    assert_eq!(db_conn.players_with_nations_for_game_alias("started").unwrap().len(), 2);
+}
+
+#[test]
+fn should_replace_everything() {
+    let db_conn = &DbConnection::test();
+    populate(db_conn);
+    let export = DbExport {
+        version: DB_EXPORT_VERSION,
+        players: vec![],
+        games: vec![GameExport {
+            alias: "lobby".to_owned(),
+            archived_at: None,
+            started: None,
+            lobby: Some(LobbyExport {
+                owner_discord_user_id: 9,
+                era: "EA".to_owned(),
+                player_count: 3,
+                description: None,
+                status: "open".to_owned(),
+                scheduled_start: Some("2030-01-02 03:04:05".to_owned()),
+                ..LobbyExport::default()
+            }),
+            registrations: vec![],
+            ..GameExport::default()
+        }],
+    };
+
+    let report = db_conn.import(&export, ImportMode::Replace).unwrap();
+    assert!(report.conflicts.is_empty());
+    assert_eq!(db_conn.count_servers(), 1);
+    match db_conn.game_for_alias("lobby").unwrap().state {
+        GameServerState::Lobby(lobby_state) => {
+            assert_eq!(lobby_state.owner, UserId(9));
+            assert_eq!(lobby_state.era, Era::Early);
+            assert!(lobby_state.scheduled_start.is_some());
+        }
+        _ => panic!("expected a lobby"),
+    }
+    assert!(db_conn.players_with_nations_for_game_alias("lobby").unwrap().is_empty());
+}
+
+#[test]
+fn should_skip_invalid_games() {
+    let db_conn = &DbConnection::test();
+    let export = DbExport {
+        version: DB_EXPORT_VERSION,
+        players: vec![],
+        games: vec![
+            GameExport {
+                alias: "nothing".to_owned(),
+                archived_at: None,
+                started: None,
+                lobby: None,
+                registrations: vec![],
+                ..GameExport::default()
+            },
+            GameExport {
+                alias: "twice".to_owned(),
+                archived_at: None,
+                started: Some(StartedExport {
+                    address: "foo.bar:3000".to_owned(),
+                    last_seen_turn: 1,
+                }),
+                lobby: None,
+                registrations: vec![
+                    RegistrationExport { discord_user_id: 1, nation_id: 5 },
+                    RegistrationExport { discord_user_id: 2, nation_id: 5 },
+                ],
+                ..GameExport::default()
+            },
+        ],
+    };
+
+    let report = db_conn.import(&export, ImportMode::Merge).unwrap();
+    assert_eq!(report.games, 0);
+    assert_eq!(report.conflicts.len(), 2);
+    assert_eq!(db_conn.count_servers(), 0);
+}
+
+#[test]
+fn should_refuse_newer_versions() {
+    let db_conn = &DbConnection::test();
+    let json = format!(r#"{{"version": {}, "players": [], "games": []}}"#, DB_EXPORT_VERSION + 1);
+    assert!(import_helper(db_conn, &json, ImportMode::Replace).is_err());
+}
+
+#[test]
+fn should_only_accept_listed_admins() {
+    let admins = Admins(vec![UserId(9)]);
+    assert!(admins.check(UserId(9)).is_ok());
+    assert_eq!(admins.check(UserId(2)).unwrap_err().0, "only bot admins can do that");
+    assert!(Admins::default().check(UserId(9)).is_err());
+}
+
+fn test_backups(name: &str, keep: usize) -> Backups {
+    let dir = std::env::temp_dir().join(format!("dom5status-test-backups-{}", name));
+    let _ = std::fs::remove_dir_all(&dir);
+    Backups { dir, keep }
+}
+
+fn at(time: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc)
+}
+
+#[test]
+fn should_back_up_a_usable_copy() {
+    let db_conn = &DbConnection::test();
+    populate(db_conn);
+    let backups = test_backups("usable", 3);
+
+    backup_helper(db_conn, &backups, at("2019-06-01T18:00:00Z")).unwrap();
+
+    let backup_files = backups.list().unwrap();
+    assert_eq!(backup_files.len(), 1);
+    assert_eq!(backup_files[0].file_name(), "dom5bot-2019-06-01-180000.db");
+    // the test database doesn't have migrant's bookkeeping, so open the copy directly
+    let copy = rusqlite::Connection::open(&backup_files[0].path).unwrap();
+    let game_count: i32 = copy
+        .query_row("SELECT COUNT(*) FROM game_servers", &[], |r| r.get(0))
+        .unwrap();
+    assert_eq!(game_count, 2);
+    std::fs::remove_dir_all(&backups.dir).unwrap();
+}
+
+#[test]
+fn should_rotate_old_backups() {
+    let db_conn = &DbConnection::test();
+    let backups = test_backups("rotate", 2);
+    std::fs::create_dir_all(&backups.dir).unwrap();
+    std::fs::write(backups.dir.join("notes.txt"), "not a backup").unwrap();
+
+    for time in &["2019-06-01T18:00:00Z", "2019-06-03T18:00:00Z", "2019-06-02T18:00:00Z"] {
+        backups.create(db_conn, at(time)).unwrap();
+    }
+
+    let file_names = backups
+        .list()
+        .unwrap()
+        .iter()
+        .map(|backup_file| backup_file.file_name())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        file_names,
+        vec!["dom5bot-2019-06-03-180000.db", "dom5bot-2019-06-02-180000.db"]
+    );
+    assert!(backups.dir.join("notes.txt").exists());
+    assert!(backups_helper(&backups).unwrap().is_some());
+    std::fs::remove_dir_all(&backups.dir).unwrap();
+}
+
+#[test]
+fn should_delete_partial_backups() {
+    let db_conn = &DbConnection::test();
+    let backups = test_backups("partial", 2);
+    assert!(backups.remove_partials().unwrap().is_empty());
+    std::fs::create_dir_all(&backups.dir).unwrap();
+    let partial = backups.dir.join("dom5bot-2019-06-01-180000.db.partial");
+    std::fs::write(&partial, "half a backup").unwrap();
+    std::fs::write(backups.dir.join("notes.partial"), "not a backup").unwrap();
+    backups.create(db_conn, at("2019-06-02T18:00:00Z")).unwrap();
+
+    assert_eq!(backups.remove_partials().unwrap(), vec![partial.clone()]);
+    assert!(!partial.exists());
+    assert!(backups.dir.join("notes.partial").exists());
+    assert_eq!(backups.list().unwrap().len(), 1);
+    std::fs::remove_dir_all(&backups.dir).unwrap();
+}
+
+#[test]
+fn should_be_due_once_the_interval_has_passed() {
+    let db_conn = &DbConnection::test();
+    let backups = test_backups("due", 2);
+    let day = std::time::Duration::from_secs(24 * 60 * 60);
+    assert!(backups.is_due(day, at("2019-06-01T18:00:00Z")).unwrap());
+    assert!(backups_helper(&backups).unwrap().is_none());
+
+    backups.create(db_conn, at("2019-06-01T18:00:00Z")).unwrap();
+    assert!(!backups.is_due(day, at("2019-06-02T17:59:59Z")).unwrap());
+    assert!(backups.is_due(day, at("2019-06-02T18:00:00Z")).unwrap());
+    std::fs::remove_dir_all(&backups.dir).unwrap();
+}
+
+#[test]
+fn should_only_list_backups_for_admins() {
+    let db_conn = &DbConnection::test();
+    let admins = Admins(vec![UserId(9)]);
+    let discord = FakeDiscord::new();
+    let context = CommandContext {
+        db_conn,
+        admins: &admins,
+        discord: &discord,
+    };
+    let backups = test_backups("request", 2);
+
+    let mut request = Request::new(UserId(2), None, vec![]);
+    let err = run_admin_command(&context, &backups, "backups", &mut request).unwrap().unwrap_err();
+    assert_eq!(err.0, "only bot admins can do that");
+
+    let mut request = Request::new(UserId(9), None, vec![]);
+    assert_eq!(
+        run_admin_command(&context, &backups, "backups", &mut request).unwrap().unwrap(),
+        Response::Reply("No backups yet".to_owned())
+    );
+    let mut request = Request::new(UserId(9), None, vec![]);
+    assert!(run_admin_command(&context, &backups, "export", &mut request).is_none());
+}
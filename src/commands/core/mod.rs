@@ -0,0 +1,356 @@
+//! Commands as plain requests and responses, so the same command logic can run from
+//! discord or from a terminal
+
+use serenity::builder::CreateEmbed;
+use serenity::framework::standard::{Args, CommandError};
+use serenity::model::channel::Message;
+use serenity::model::id::{ChannelId, MessageId, UserId};
+use serenity::utils::parse_username;
+
+use std::collections::VecDeque;
+use std::fmt::Display;
+
+use crate::commands::admin::Admins;
+use crate::commands::servers::run_servers_command;
+use crate::db::DbConnection;
+use crate::discord::{ChannelMessages, Discord, SerenityDiscord};
+use crate::server::ServerConnection;
+
+#[cfg(test)]
+mod tests;
+
+/// A command's arguments, along with who sent it and from where
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub author: UserId,
+    /// The channel the command came from. Commands use it as the game's alias when
+    /// they aren't given one.
+    pub channel_name: Option<String>,
+    /// Where to post anything that belongs in the command's channel. `None` away from discord.
+    pub channel_id: Option<ChannelId>,
+    /// The message the command was sent in, for commands that need to delete it
+    pub message_id: Option<MessageId>,
+    /// Sent in a DM rather than a channel everyone can read
+    pub is_private: bool,
+    args: VecDeque<String>,
+}
+
+impl Request {
+    pub fn new(author: UserId, channel_name: Option<String>, args: Vec<String>) -> Request {
+        Request {
+            author,
+            channel_name,
+            channel_id: None,
+            message_id: None,
+            is_private: false,
+            args: args.into_iter().collect(),
+        }
+    }
+
+    /// Quoted arguments are already one argument each
+    pub fn from_discord(message: &Message, mut args: Args) -> Result<Request, CommandError> {
+        let mut split_args = vec![];
+        while !args.is_empty() {
+            split_args.push(args.single_quoted::<String>()?);
+        }
+        let mut request = Request::new(message.author.id, message.channel_id.name(), split_args);
+        request.channel_id = Some(message.channel_id);
+        request.message_id = Some(message.id);
+        request.is_private = message.is_private();
+        Ok(request)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+
+    pub fn arg(&mut self) -> Option<String> {
+        self.args.pop_front()
+    }
+
+    /// `what` finishes the sentence "Expected ..."
+    pub fn required_arg(&mut self, what: &str) -> Result<String, CommandError> {
+        self.arg()
+            .ok_or_else(|| CommandError::from(format!("Expected {}", what)))
+    }
+
+    /// Every argument that's left
+    pub fn rest(&mut self) -> Vec<String> {
+        self.args.drain(..).collect()
+    }
+
+    /// The next argument, or the channel's name if there are no more
+    pub fn alias(&mut self) -> Result<String, CommandError> {
+        let result_alias = if !self.args.is_empty() {
+            self.arg()
+        } else {
+            self.channel_name.clone()
+        };
+        result_alias
+            .clone()
+            .map(|s| s.to_lowercase())
+            .and_then(|s| if !s.is_empty() { Some(s) } else { None })
+            .ok_or_else(|| {
+                CommandError::from(format!(
+                    "Could not game alias from command argument or channel name \"{}\"",
+                    result_alias.unwrap_or_default()
+                ))
+            })
+    }
+
+    /// A mention, or a discord user id
+    pub fn user(&mut self) -> Result<UserId, CommandError> {
+        let arg = self.required_arg("a user mention")?;
+        parse_username(&arg)
+            .or_else(|| arg.parse::<u64>().ok())
+            .map(UserId)
+            .ok_or_else(|| CommandError::from(format!("Expected a user mention but got \"{}\"", arg)))
+    }
+
+    /// For commands that only make sense in a channel
+    pub fn channel(&self) -> Result<ChannelId, CommandError> {
+        self.channel_id
+            .ok_or_else(|| CommandError::from("That only works in a discord channel"))
+    }
+
+    pub fn no_more_args(&self) -> Result<(), CommandError> {
+        if self.args.is_empty() {
+            Ok(())
+        } else {
+            Err(CommandError::from(
+                "Too many arguments. TIP: spaces in arguments need to be quoted \"like this\"",
+            ))
+        }
+    }
+}
+
+/// Splits a line the way discord commands are split, keeping "quoted text" together
+pub fn split_args(line: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_arg = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_arg {
+                    args.push(current.clone());
+                    current.clear();
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
+            }
+        }
+    }
+    if has_arg {
+        args.push(current);
+    }
+    args
+}
+
+/// Titled columns of text, like a discord embed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Embed {
+    pub title: String,
+    /// Where the title links to
+    pub url: Option<String>,
+    /// Shown under the title, before the fields
+    pub description: Option<String>,
+    pub fields: Vec<EmbedField>,
+    pub footer: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbedField {
+    pub name: String,
+    pub value: String,
+    /// Inline fields sit side by side
+    pub inline: bool,
+}
+
+impl Embed {
+    pub fn title<D: Display>(mut self, title: D) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn url<D: Display>(mut self, url: D) -> Self {
+        self.url = Some(url.to_string());
+        self
+    }
+
+    pub fn description<D: Display>(mut self, description: D) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    pub fn field<T: Display, U: Display>(mut self, name: T, value: U, inline: bool) -> Self {
+        self.fields.push(EmbedField {
+            name: name.to_string(),
+            value: value.to_string(),
+            inline,
+        });
+        self
+    }
+
+    pub fn footer<D: Display>(mut self, footer: D) -> Self {
+        self.footer = Some(footer.to_string());
+        self
+    }
+
+    pub fn to_discord(&self) -> CreateEmbed {
+        let mut embed = self.fields.iter().fold(
+            CreateEmbed::default().title(&self.title),
+            |embed, field| embed.field(&field.name, &field.value, field.inline),
+        );
+        if let Some(ref url) = self.url {
+            embed = embed.url(url);
+        }
+        if let Some(ref description) = self.description {
+            embed = embed.description(description);
+        }
+        match self.footer {
+            Some(ref footer) => embed.footer(|f| f.text(footer)),
+            None => embed,
+        }
+    }
+
+    /// Inline fields become columns, the rest are listed under them
+    pub fn to_text(&self) -> String {
+        let mut text = self.title.clone();
+        if let Some(ref description) = self.description {
+            text.push_str(&format!("\n{}", description));
+        }
+        let mut i = 0;
+        while i < self.fields.len() {
+            if !self.fields[i].inline {
+                let field = &self.fields[i];
+                text.push_str(&format!("\n{}: {}", field.name, field.value.trim_end()));
+                i += 1;
+                continue;
+            }
+            let columns = self.fields[i..]
+                .iter()
+                .take_while(|field| field.inline)
+                .collect::<Vec<_>>();
+            text.push_str(&columns_text(&columns));
+            i += columns.len();
+        }
+        if let Some(ref footer) = self.footer {
+            text.push_str(&format!("\n{}", footer));
+        }
+        if let Some(ref url) = self.url {
+            text.push_str(&format!("\n{}", url));
+        }
+        text
+    }
+}
+
+fn columns_text(columns: &[&EmbedField]) -> String {
+    let cells = columns
+        .iter()
+        .map(|field| {
+            let mut cells = vec![field.name.clone()];
+            cells.extend(field.value.lines().map(|line| line.trim_end().to_owned()));
+            cells
+        })
+        .collect::<Vec<_>>();
+    let widths = cells
+        .iter()
+        .map(|column| column.iter().map(|cell| cell.chars().count()).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+    let rows = cells.iter().map(|column| column.len()).max().unwrap_or(0);
+    let mut text = String::new();
+    for row in 0..rows {
+        let mut line = String::new();
+        for (column, width) in cells.iter().zip(&widths) {
+            let cell = column.get(row).map(|cell| cell.as_str()).unwrap_or("");
+            line.push_str(cell);
+            line.push_str(&" ".repeat(width - cell.chars().count() + 2));
+        }
+        text.push('\n');
+        text.push_str(line.trim_end());
+    }
+    text
+}
+
+/// What a command has to say back
+#[derive(Debug, Clone, PartialEq)]
+pub enum Response {
+    /// Sent as a reply to whoever sent the command
+    Reply(String),
+    /// Sent to the channel the command came from
+    Embed(Embed),
+    /// The command already said everything it had to, e.g. in DMs
+    Nothing,
+}
+
+impl Response {
+    pub fn to_text(&self) -> String {
+        match *self {
+            Response::Reply(ref text) => text.clone(),
+            Response::Embed(ref embed) => embed.to_text(),
+            Response::Nothing => String::new(),
+        }
+    }
+}
+
+/// Everything a command can use besides its request
+pub struct CommandContext<'a> {
+    pub db_conn: &'a DbConnection,
+    pub admins: &'a Admins,
+    /// For looking people up and messaging anyone besides whoever sent the command
+    pub discord: &'a dyn Discord,
+}
+
+/// `None` if it isn't a game or lobby command. The search and admin commands need more than a
+/// context, see `run_search_command` and `run_admin_command`.
+pub fn run_command<C: ServerConnection>(
+    context: &CommandContext,
+    command: &str,
+    request: &mut Request,
+) -> Option<Result<Response, CommandError>> {
+    run_servers_command::<C>(context, command, request)
+}
+
+/// The discord adapter: runs the command and sends its response
+pub fn run_on_discord<C: ServerConnection>(
+    db_conn: &DbConnection,
+    admins: &Admins,
+    command: &str,
+    message: &Message,
+    args: Args,
+) -> Result<(), CommandError> {
+    let discord = SerenityDiscord;
+    let context = CommandContext {
+        db_conn,
+        admins,
+        discord: &discord,
+    };
+    respond_on_discord(command, message, args, |request| run_command::<C>(&context, command, request))
+}
+
+/// Turns the message into a request for `run`, and sends back whatever it answers.
+/// For commands that need more than a `CommandContext`.
+pub fn respond_on_discord<F>(command: &str, message: &Message, args: Args, run: F) -> Result<(), CommandError>
+where
+    F: FnOnce(&mut Request) -> Option<Result<Response, CommandError>>,
+{
+    let mut request = Request::from_discord(message, args)?;
+    let response = run(&mut request).ok_or_else(|| CommandError::from(format!("Unknown command {}", command)))??;
+    match response {
+        Response::Reply(text) => {
+            message.reply(&text)?;
+        }
+        Response::Embed(embed) => SerenityDiscord.send_embed(message.channel_id, &embed)?,
+        Response::Nothing => (),
+    }
+    Ok(())
+}
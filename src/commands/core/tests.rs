@@ -0,0 +1,163 @@
+use super::*;
+
+use crate::discord::{FakeDiscord, Sent};
+use crate::model::{GameData, GameServer, GameServerState, Nation, StartedState};
+use crate::model::enums::*;
+use std::io;
+
+fn request(channel_name: Option<&str>, args: &[&str]) -> Request {
+    Request::new(
+        UserId(1),
+        channel_name.map(|name| name.to_owned()),
+        args.iter().map(|arg| (*arg).to_owned()).collect(),
+    )
+}
+
+fn game_data() -> GameData {
+    GameData {
+        game_name: "foo".to_owned(),
+        nations: vec![Nation {
+            id: 5,
+            status: NationStatus::Human,
+            submitted: SubmissionStatus::Submitted,
+            connected: true,
+            name: "Arcoscephale".to_owned(),
+            era: "EA".to_owned(),
+        }],
+        turn: 3,
+        turn_timer: 3 * 60 * 60 * 1000,
+    }
+}
+
+#[test]
+fn should_split_args_like_discord() {
+    assert_eq!(split_args("  details  foo "), vec!["details", "foo"]);
+    assert_eq!(split_args("describe foo \"a long game\""), vec!["describe", "foo", "a long game"]);
+    assert_eq!(split_args("register \"\" foo"), vec!["register", "", "foo"]);
+    assert!(split_args("   ").is_empty());
+}
+
+#[test]
+fn should_take_the_alias_from_the_args_or_the_channel() {
+    assert_eq!(request(Some("chan"), &["FOO"]).alias().unwrap(), "foo");
+    assert_eq!(request(Some("Chan"), &[]).alias().unwrap(), "chan");
+    assert!(request(None, &[]).alias().is_err());
+    assert!(request(Some(""), &[]).alias().is_err());
+}
+
+#[test]
+fn should_complain_about_missing_and_extra_args() {
+    let mut request = request(None, &["foo", "bar"]);
+    assert_eq!(request.required_arg("an address").unwrap(), "foo");
+    assert!(request.no_more_args().is_err());
+    assert_eq!(request.arg(), Some("bar".to_owned()));
+    assert!(request.no_more_args().is_ok());
+    assert_eq!(request.required_arg("an address").unwrap_err().0, "Expected an address");
+}
+
+#[test]
+fn should_line_up_inline_fields_as_columns() {
+    let embed = Embed::default()
+        .title("foo: turn 3")
+        .field("Nation", "EA Arcoscephale\nEA Ulm\n", true)
+        .field("Player", "@1\n", true)
+        .field("Description", "a game", false)
+        .footer("Last updated");
+    assert_eq!(
+        embed.to_text(),
+        "foo: turn 3\n\
+         Nation           Player\n\
+         EA Arcoscephale  @1\n\
+         EA Ulm\n\
+         Description: a game\n\
+         Last updated"
+    );
+}
+
+#[test]
+fn should_run_core_commands_without_discord() {
+    mock_server_connection!(Mock, Ok(game_data()));
+    let db_conn = &DbConnection::test();
+    db_conn
+        .insert_game_server(&GameServer {
+            alias: "foo".to_owned(),
+            state: GameServerState::StartedState(
+                StartedState {
+                    address: "foo.core:3000".to_owned(),
+                    last_seen_turn: 3,
+                },
+                None,
+            ),
+        })
+        .unwrap();
+    let admins = Admins(vec![]);
+    let discord = FakeDiscord::new().with_user(UserId(1), "one");
+    let context = CommandContext {
+        db_conn,
+        admins: &admins,
+        discord: &discord,
+    };
+
+    let registered = run_command::<Mock>(&context, "register-id", &mut request(None, &["5", "foo"]))
+        .unwrap()
+        .unwrap();
+    assert_eq!(registered, Response::Reply("registering nation Arcoscephale (5) for user @one".to_owned()));
+
+    match run_command::<Mock>(&context, "deets", &mut request(Some("foo"), &[])) {
+        Some(Ok(Response::Embed(embed))) => {
+            assert!(embed.title.starts_with("foo (foo.core:3000): turn 3"));
+            assert!(embed.to_text().contains("**@one**"));
+        }
+        response => panic!("expected details but got {:?}", response),
+    }
+
+    assert!(run_command::<Mock>(&context, "details", &mut request(None, &["missing"])).unwrap().is_err());
+    assert!(run_command::<Mock>(&context, "backup", &mut request(None, &[])).is_none());
+
+    let turns = run_command::<Mock>(&context, "turns", &mut request(None, &[])).unwrap().unwrap();
+    assert_eq!(turns, Response::Nothing);
+    match discord.sent().last() {
+        Some(&Sent::Dm(UserId(1), ref text)) => {
+            assert!(text.starts_with("Your turns:\nfoo turn 3 (3h 0m): Arcoscephale (5)"))
+        }
+        sent => panic!("expected a DM but got {:?}", sent),
+    }
+    // nothing reached discord besides the DM, the rest were responses
+    assert_eq!(discord.sent().len(), 1);
+}
+
+#[test]
+fn should_dm_whoever_is_promoted_from_the_waitlist() {
+    mock_server_connection!(Mock, Ok(game_data()));
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("lobby", UserId(1), 1);
+    let admins = Admins(vec![]);
+    let discord = FakeDiscord::new()
+        .with_user(UserId(1), "one")
+        .with_user(UserId(2), "two");
+    let context = CommandContext {
+        db_conn,
+        admins: &admins,
+        discord: &discord,
+    };
+    let second = |args: &[&str]| {
+        Request::new(UserId(2), None, args.iter().map(|arg| (*arg).to_owned()).collect())
+    };
+
+    run_command::<Mock>(&context, "register-id", &mut request(None, &["5", "lobby"])).unwrap().unwrap();
+    let waitlisted = run_command::<Mock>(&context, "register-id", &mut second(&["6", "lobby"]))
+        .unwrap()
+        .unwrap()
+        .to_text();
+    assert!(waitlisted.starts_with("lobby already full, adding @two to the waitlist at number 1"));
+    assert!(discord.sent().is_empty());
+
+    run_command::<Mock>(&context, "unregister", &mut request(None, &["lobby"])).unwrap().unwrap();
+
+    let dms = discord.dms_to(UserId(2));
+    assert_eq!(dms.len(), 1);
+    assert!(dms[0].starts_with("A slot opened up in lobby. You have been moved off the waitlist"));
+    let registered = db_conn.players_with_nations_for_game_alias("lobby").unwrap();
+    assert_eq!(registered.len(), 1);
+    assert_eq!(registered[0].0.discord_user_id, UserId(2));
+}
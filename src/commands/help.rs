@@ -8,19 +8,52 @@ pub fn help(message: &Message) -> Result<(), CommandError> {
         "Commands (server alias is optional, defaults to channel name): \n\
         - !add <address:port> <alias>: save the dom5 server address\n\
         - !list: return a list of the saved server addresses and aliases\n\
-        - !delete <alias>: remove the server address from the list\n\
+        - !delete <alias>: archive the game so it's no longer listed or checked for turns\n\
+        - !archived: list archived games\n\
+        - !restore <alias>: bring back an archived game\n\
+        - !purge <alias>: permanently delete a game and its history (owner or bot admins only)\n\
+        - !rename <old alias> <new alias>: rename a game, keeping its players and history\n\
+        - !move <address:port> <alias>: point a started game at a new server address\n\
         - !details <alias>: return a list of the nations and their statuses in the game\n\
         - !register nation_prefix <alias>: register yourself as a nation in a game\n\
         - !register-id nation_id <alias>: register yourself as a nation in a game using the id\n\
         - !unregister <alias>: unregister yourself in a game\n\
         - !turns: show all of the games you're in and their turn status\n\
-        - !notifications {true, false}: enable/disable turn notifications\n\
+        - !notifications {true, false} [<alias>]: enable/disable turn notifications, for one game if an alias is given\n\
+        - !notification-format {summary, detailed} <alias>: choose how much detail your turn notifications for a game have\n\
+        - !pin-status <alias>: post and pin the game's details, which the bot then keeps up to date\n\
+        - !unpin-status <alias>: stop updating the game's pinned details\n\
+        - !sub-needed <alias>: ask for someone to take over your nation\n\
+        - !sub-take <alias>: take over a nation that needs a substitute\n\
+        - !sub-cancel <alias>: stop looking for a substitute\n\
+        - !sub-channel <alias>: post substitute requests for the game in this channel (owner or bot admins only)\n\
+        - !subs <alias>: show open substitute requests and past handovers\n\
+        - !waitlist <alias>: join the waitlist for a full lobby\n\
+        - !waitlist-leave <alias>: leave the waitlist for a lobby\n\
         - !lobby {EA/MA/LA} <num_players> <alias>: create a lobby with no server\n\
-        - !lobbies: list available lobbies\n\
+        - !lobbies [all]: list available lobbies, or all of them including cancelled and expired ones\n\
+        - !lobby-lock <alias>: stop new players joining a lobby (owner only)\n\
+        - !lobby-open <alias>: reopen a locked, cancelled or expired lobby (owner only)\n\
+        - !lobby-cancel <alias>: cancel a lobby (owner only)\n\
+        - !kick @user <alias>: remove a player from your lobby or game (owner only)\n\
+        - !transfer-owner @user <alias>: make someone else the owner of your lobby or game (owner only)\n\
+        - !lobby-schedule <when> <alias>: set when a lobby will start, e.g. 2d12h or \"2019-06-01 18:00\" (UTC), or off\n\
         - !start <address:port> <alias>: register a started server for a lobby game\n\
         - !{item, spell, unit, site, merc, event} <text>: get dom5inspector search url\n\
         - !help: display this text\n\
+        - !export: DM you the whole database as JSON (bot admins only)\n\
+        - !import {merge, replace}: load an attached JSON export into the database (bot admins only)\n\
+        - !backup: back up the database now (bot admins only)\n\
+        - !backups: list the database backups (bot admins only)\n\
         - !describe \"text\" <alias>: add a description to a lobby. Quotes required.\n\
+        - !stalls <alias>: show how often each player has stalled and the most recent stalls\n\
+        - !stall-warning {<count>, off} <alias>: DM the lobby owner when a player reaches that many stalls\n\
+        - !notify-channel <alias>: announce new turns for the game in this channel (owner or bot admins only)\n\
+        - !notify-channel-remove <alias>: stop announcing new turns for the game in a channel (owner or bot admins only)\n\
+        - !reminders [<thresholds> | off | default] [<alias>]: DM you when the timer drops below e.g. 12h 2h 30m and you haven't submitted\n\
+        - !webhook [<url> [<secret>] | off]: also send your notifications as a JSON POST to this url. DM it to me, the url is its password.\n\
+        - !dms {on, off}: turn your notification DMs on or off\n\
+        - !game-webhook <alias> {<url> [<secret>], off}: also send the game's new turn announcements to this url (owner or bot admins only)\n\
         Source is located at www.github.com/djmcgill/dominions-5-status Contributions welcome!"
     )?;
     Ok(())
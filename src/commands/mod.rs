@@ -4,4 +4,6 @@ pub use self::search::*;
 mod help;
 pub use self::help::*;
 
+pub mod admin;
+pub mod core;
 pub mod servers;
@@ -0,0 +1,240 @@
+//! Local copies of the inspector's gamedata tables, so searches can answer with stats
+//! instead of only a link
+
+use failure::{err_msg, Error, ResultExt};
+use log::*;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Discord won't take a field longer than 1024 characters
+const MAX_TEXT_LENGTH: usize = 1000;
+
+/// Indexed by the number in the gamedata
+const PATHS: [&str; 9] = ["F", "A", "W", "E", "S", "D", "N", "B", "H"];
+const SCHOOLS: [&str; 8] = [
+    "Conjuration",
+    "Alteration",
+    "Evocation",
+    "Construction",
+    "Enchantment",
+    "Thaumaturgy",
+    "Blood",
+    "Divine",
+];
+
+/// A column to show in the embed and what to call it
+pub enum Stat {
+    Plain(&'static str, &'static str),
+    /// A magic path and the column with its level, shown like F2.
+    /// An empty level column shows just the path.
+    Path(&'static str, &'static str, &'static str),
+    School(&'static str, &'static str),
+    /// Too long to sit next to the other stats
+    Text(&'static str, &'static str),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TableMatch {
+    One(usize),
+    /// In table order
+    Several(Vec<usize>),
+    None,
+}
+
+impl TableMatch {
+    fn from_rows(mut rows: Vec<usize>) -> TableMatch {
+        match rows.len() {
+            0 => TableMatch::None,
+            1 => TableMatch::One(rows.remove(0)),
+            _ => TableMatch::Several(rows),
+        }
+    }
+}
+
+/// One of the inspector's tab separated tables, rows are referred to by index
+#[derive(Debug)]
+pub struct InspectorTable {
+    columns: HashMap<String, usize>,
+    rows: Vec<Vec<String>>,
+}
+
+impl InspectorTable {
+    /// Needs a header row with at least `id` and `name`
+    pub fn parse(contents: &str) -> Result<InspectorTable, Error> {
+        let mut lines = contents.lines();
+        let header = lines.next().ok_or_else(|| err_msg("Empty table"))?;
+        let columns = header
+            .split('\t')
+            .enumerate()
+            // some tables call it "id#"
+            .map(|(i, column)| (column.trim().trim_end_matches('#').to_lowercase(), i))
+            .collect::<HashMap<_, _>>();
+        for required in &["id", "name"] {
+            if !columns.contains_key(*required) {
+                return Err(err_msg(format!("No {} column", required)));
+            }
+        }
+        let rows = lines
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.split('\t').map(|cell| cell.trim().to_owned()).collect())
+            .collect();
+        Ok(InspectorTable { columns, rows })
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// `None` for a missing column or an empty cell
+    fn cell(&self, row: usize, column: &str) -> Option<&str> {
+        self.columns
+            .get(column)
+            .and_then(|&i| self.rows[row].get(i))
+            .map(|cell| cell.as_str())
+            .filter(|cell| !cell.is_empty())
+    }
+
+    pub fn name(&self, row: usize) -> &str {
+        self.cell(row, "name").unwrap_or("")
+    }
+
+    pub fn id(&self, row: usize) -> &str {
+        self.cell(row, "id").unwrap_or("")
+    }
+
+    /// A number is an id, otherwise the best of an exact name, the start of a name
+    /// or anywhere in a name
+    pub fn search(&self, search_term: &str) -> TableMatch {
+        let term = normalise(search_term);
+        if term.is_empty() {
+            return TableMatch::None;
+        }
+        let all_rows = 0..self.rows.len();
+        if let Ok(id) = term.parse::<i64>() {
+            let by_id = all_rows
+                .clone()
+                .filter(|&row| self.id(row).parse::<i64>().ok() == Some(id))
+                .collect::<Vec<_>>();
+            if !by_id.is_empty() {
+                return TableMatch::from_rows(by_id);
+            }
+        }
+        let names = all_rows.map(|row| (row, normalise(self.name(row)))).collect::<Vec<_>>();
+        let matching = |matches: &dyn Fn(&str) -> bool| {
+            names
+                .iter()
+                .filter(|&&(_, ref name)| matches(name))
+                .map(|&(row, _)| row)
+                .collect::<Vec<_>>()
+        };
+        let exact = matching(&|name| name == term);
+        if !exact.is_empty() {
+            return TableMatch::from_rows(exact);
+        }
+        let prefix = matching(&|name| name.starts_with(&term));
+        if !prefix.is_empty() {
+            return TableMatch::from_rows(prefix);
+        }
+        TableMatch::from_rows(matching(&|name| name.contains(&term)))
+    }
+
+    /// Label, value and whether it's inline, for the stats the row has
+    pub fn stats(&self, row: usize, stats: &[Stat]) -> Vec<(&'static str, String, bool)> {
+        stats
+            .iter()
+            .filter_map(|stat| match *stat {
+                Stat::Plain(column, label) => self.cell(row, column).map(|value| (label, value.to_owned(), true)),
+                Stat::Path(column, level_column, label) => self
+                    .cell(row, column)
+                    .and_then(|path| show_path(path, self.cell(row, level_column)))
+                    .map(|value| (label, value, true)),
+                Stat::School(column, label) => self
+                    .cell(row, column)
+                    .map(show_school)
+                    .map(|value| (label, value, true)),
+                Stat::Text(column, label) => self
+                    .cell(row, column)
+                    .map(|text| (label, truncate(text, MAX_TEXT_LENGTH), false)),
+            })
+            .collect()
+    }
+}
+
+/// Lowercase, no apostrophes, single spaces
+fn normalise(name: &str) -> String {
+    name.to_lowercase()
+        .replace('\'', "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Negative means no path
+fn show_path(path: &str, level: Option<&str>) -> Option<String> {
+    let path = match path.parse::<i64>() {
+        Ok(number) if number < 0 => return None,
+        Ok(number) => PATHS
+            .get(number as usize)
+            .map(|path| (*path).to_owned())
+            .unwrap_or_else(|| path.to_owned()),
+        Err(_) => path.to_owned(),
+    };
+    Some(format!("{}{}", path, level.unwrap_or("")))
+}
+
+/// Negative means it can't be researched
+fn show_school(school: &str) -> String {
+    match school.parse::<i64>() {
+        Ok(number) if number < 0 => "Unresearchable".to_owned(),
+        Ok(number) => SCHOOLS
+            .get(number as usize)
+            .map(|school| (*school).to_owned())
+            .unwrap_or_else(|| school.to_owned()),
+        Err(_) => school.to_owned(),
+    }
+}
+
+pub fn truncate(text: &str, max_length: usize) -> String {
+    if text.chars().count() > max_length {
+        format!("{}...", text.chars().take(max_length).collect::<String>())
+    } else {
+        text.to_owned()
+    }
+}
+
+/// The tables that loaded, by category
+#[derive(Debug, Default)]
+pub struct InspectorData {
+    tables: HashMap<String, InspectorTable>,
+}
+
+impl InspectorData {
+    /// A missing or broken table is logged and left out, its category's searches
+    /// fall back to the inspector link
+    pub fn load(dir: &Path) -> InspectorData {
+        let mut tables = HashMap::new();
+        for &(category, data_file) in super::data_files().iter() {
+            let path = dir.join(data_file);
+            match load_table(&path) {
+                Ok(table) => {
+                    info!("Loaded {} {}s from '{}'", table.row_count(), category, path.display());
+                    tables.insert(category.to_owned(), table);
+                }
+                Err(e) => warn!("Not searching {} data: {}", category, e),
+            }
+        }
+        InspectorData { tables }
+    }
+
+    pub fn table(&self, category: &str) -> Option<&InspectorTable> {
+        self.tables.get(category)
+    }
+}
+
+fn load_table(path: &Path) -> Result<InspectorTable, Error> {
+    let contents = fs::read_to_string(path).context(format!("Reading '{}'", path.display()))?;
+    let table = InspectorTable::parse(&contents).context(format!("Parsing '{}'", path.display()))?;
+    Ok(table)
+}
@@ -1,9 +1,29 @@
 use url::percent_encoding::{utf8_percent_encode, QUERY_ENCODE_SET};
 
 use serenity::framework::standard::{Args, CommandError};
+use serenity::client::Context;
 use serenity::model::channel::Message;
 use log::*;
 
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::commands::core::{respond_on_discord, Embed, Request, Response};
+
+mod inspector_data;
+pub use self::inspector_data::InspectorData;
+use self::inspector_data::{truncate, InspectorTable, Stat, TableMatch};
+
+#[cfg(test)]
+mod tests;
+
+/// More than this and the list is too long to be any help
+const MAX_DISAMBIGUATION_MATCHES: usize = 10;
+/// Discord won't take a title longer than 256 characters
+const MAX_TITLE_SEARCH_TERM_LENGTH: usize = 100;
+/// Small enough that every row sharing an id fits in one embed
+const MAX_SAME_ID_STATS_LENGTH: usize = 500;
+
 // enum InspectorCategoryV {Item, Spell, Unit, ...}
 
 // TODO: implement some kind of static enum macro or library
@@ -11,6 +31,10 @@ use log::*;
 trait InspectorCategory: Copy {
     fn show() -> &'static str;
     fn event_append() -> &'static str;
+    /// The inspector's gamedata table for this category
+    fn data_file() -> &'static str;
+    /// What goes in the embed, when the table has those columns
+    fn stats() -> &'static [Stat];
     // fn reify() -> InspectorCategoryV;
 }
 
@@ -23,6 +47,17 @@ impl InspectorCategory for Item {
     fn event_append() -> &'static str {
         ""
     }
+    fn data_file() -> &'static str {
+        "BaseI.csv"
+    }
+    fn stats() -> &'static [Stat] {
+        &[
+            Stat::Path("mainpath", "mainlevel", "Path"),
+            Stat::Path("secondarypath", "secondarylevel", "Second path"),
+            Stat::Plain("constlevel", "Construction"),
+            Stat::Plain("type", "Type"),
+        ]
+    }
 }
 #[derive(Clone, Copy)]
 struct Spell;
@@ -33,6 +68,21 @@ impl InspectorCategory for Spell {
     fn event_append() -> &'static str {
         ""
     }
+    fn data_file() -> &'static str {
+        "Spells.csv"
+    }
+    fn stats() -> &'static [Stat] {
+        &[
+            Stat::School("school", "School"),
+            Stat::Plain("researchlevel", "Research"),
+            Stat::Path("path1", "pathlevel1", "Path"),
+            Stat::Path("path2", "pathlevel2", "Second path"),
+            Stat::Plain("fatiguecost", "Fatigue"),
+            Stat::Plain("range", "Range"),
+            Stat::Plain("aoe", "Area"),
+            Stat::Plain("nreff", "Effects"),
+        ]
+    }
 }
 #[derive(Clone, Copy)]
 struct Unit;
@@ -43,6 +93,27 @@ impl InspectorCategory for Unit {
     fn event_append() -> &'static str {
         ""
     }
+    fn data_file() -> &'static str {
+        "BaseU.csv"
+    }
+    fn stats() -> &'static [Stat] {
+        &[
+            Stat::Plain("hp", "HP"),
+            Stat::Plain("prot", "Prot"),
+            Stat::Plain("mr", "MR"),
+            Stat::Plain("mor", "Morale"),
+            Stat::Plain("str", "Str"),
+            Stat::Plain("att", "Att"),
+            Stat::Plain("def", "Def"),
+            Stat::Plain("prec", "Prec"),
+            Stat::Plain("enc", "Enc"),
+            Stat::Plain("size", "Size"),
+            Stat::Plain("mapmove", "Map move"),
+            Stat::Plain("ap", "AP"),
+            Stat::Plain("gcost", "Gold"),
+            Stat::Plain("rcost", "Resources"),
+        ]
+    }
 }
 #[derive(Clone, Copy)]
 struct Site;
@@ -53,6 +124,18 @@ impl InspectorCategory for Site {
     fn event_append() -> &'static str {
         ""
     }
+    fn data_file() -> &'static str {
+        "MagicSites.csv"
+    }
+    fn stats() -> &'static [Stat] {
+        &[
+            Stat::Path("path", "", "Path"),
+            Stat::Plain("level", "Level"),
+            Stat::Plain("rarity", "Rarity"),
+            Stat::Plain("gold", "Gold"),
+            Stat::Plain("res", "Resources"),
+        ]
+    }
 }
 #[derive(Clone, Copy)]
 struct Merc;
@@ -63,6 +146,20 @@ impl InspectorCategory for Merc {
     fn event_append() -> &'static str {
         ""
     }
+    fn data_file() -> &'static str {
+        "Mercenary.csv"
+    }
+    fn stats() -> &'static [Stat] {
+        &[
+            Stat::Plain("bossname", "Commander"),
+            Stat::Plain("com", "Commander id"),
+            Stat::Plain("unit", "Unit id"),
+            Stat::Plain("nrunits", "Units"),
+            Stat::Plain("minmen", "Min men"),
+            Stat::Plain("minpay", "Min pay"),
+            Stat::Plain("xp", "XP"),
+        ]
+    }
 }
 #[derive(Clone, Copy)]
 struct Event;
@@ -73,40 +170,188 @@ impl InspectorCategory for Event {
     fn event_append() -> &'static str {
         "&loadEvents=1"
     }
+    fn data_file() -> &'static str {
+        "events.csv"
+    }
+    fn stats() -> &'static [Stat] {
+        &[
+            Stat::Plain("rarity", "Rarity"),
+            Stat::Text("description", "Description"),
+        ]
+    }
+}
+
+/// Every category's name and its table in the inspector's gamedata
+fn data_files() -> [(&'static str, &'static str); 6] {
+    [
+        (Item::show(), Item::data_file()),
+        (Spell::show(), Spell::data_file()),
+        (Unit::show(), Unit::data_file()),
+        (Site::show(), Site::data_file()),
+        (Merc::show(), Merc::data_file()),
+        (Event::show(), Event::data_file()),
+    ]
+}
+
+fn inspector_url<I: InspectorCategory>(inspector_base_url: &str, search_term: &str) -> String {
+    let search_term = utf8_percent_encode(search_term, QUERY_ENCODE_SET).to_string();
+    format!(
+        "{}?page={}&{}q={}&showmodcmds=1&showmoddinginfo=1&showids=1{}",
+    inspector_base_url, I::show(), I::show(), search_term, I::event_append())
+}
+
+/// Only the inspector link without the data, or if the data doesn't have this category
+fn search_helper<I: InspectorCategory>(
+    inspector_base_url: &str,
+    inspector_data: Option<&InspectorData>,
+    search_term: &str,
+) -> Response {
+    let table = match inspector_data.and_then(|data| data.table(I::show())) {
+        Some(table) => table,
+        None => return Response::Reply(inspector_url::<I>(inspector_base_url, search_term)),
+    };
+    match table.search(search_term) {
+        TableMatch::One(row) => {
+            let name = table.name(row);
+            let embed = table.stats(row, I::stats()).into_iter().fold(
+                Embed::default()
+                    .title(format!("{} ({} {})", name, I::show(), table.id(row)))
+                    .url(inspector_url::<I>(inspector_base_url, name)),
+                |embed, (label, value, inline)| embed.field(label, value, inline),
+            );
+            Response::Embed(embed)
+        }
+        TableMatch::Several(rows) => {
+            let title_term = truncate(search_term, MAX_TITLE_SEARCH_TERM_LENGTH);
+            if rows.iter().all(|&row| table.id(row) == table.id(rows[0])) {
+                // searching by id again would only find the same rows, so show them all
+                return Response::Embed(same_id_embed::<I>(inspector_base_url, table, &rows, &title_term));
+            }
+            let names = rows
+                .iter()
+                .take(MAX_DISAMBIGUATION_MATCHES)
+                .map(|&row| format!("{} ({})", table.name(row), table.id(row)))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let more = if rows.len() > MAX_DISAMBIGUATION_MATCHES {
+                format!("\n...and {} more", rows.len() - MAX_DISAMBIGUATION_MATCHES)
+            } else {
+                String::new()
+            };
+            let embed = Embed::default()
+                .title(format!("{} {}s match \"{}\"", rows.len(), I::show(), title_term))
+                .url(inspector_url::<I>(inspector_base_url, search_term))
+                .field("Name (id)", format!("{}{}", names, more), false);
+            // only suggest an id that finds a single row
+            let unique_id = rows
+                .iter()
+                .map(|&row| table.id(row))
+                .find(|id| is_unique_id(table, id));
+            Response::Embed(match unique_id {
+                Some(id) => embed.footer(format!("Search by id to pick one, e.g. !{} {}", I::show(), id)),
+                None => embed,
+            })
+        }
+        TableMatch::None => Response::Reply(format!(
+            "No {} matching \"{}\". {}",
+            I::show(),
+            search_term,
+            inspector_url::<I>(inspector_base_url, search_term)
+        )),
+    }
 }
 
-fn search<I: InspectorCategory>(message: &Message, args: &Args) -> Result<(), CommandError> {
-    let search_term = utf8_percent_encode(&args.full(), QUERY_ENCODE_SET).to_string();
-    let response = format!(
-        "https://larzm42.github.io/dom5inspector/?page={}&{}q={}&showmodcmds=1&showmoddinginfo=1&showids=1{}",
-    I::show(), I::show(), search_term, I::event_append());
-    info!("responding with {}", response);
-    let _ = message.reply(&response);
-    Ok(())
+fn is_unique_id(table: &InspectorTable, id: &str) -> bool {
+    match table.search(id) {
+        TableMatch::One(_) => true,
+        _ => false,
+    }
+}
+
+/// Every row with the id and its stats, up to `MAX_DISAMBIGUATION_MATCHES`
+fn same_id_embed<I: InspectorCategory>(
+    inspector_base_url: &str,
+    table: &InspectorTable,
+    rows: &[usize],
+    title_term: &str,
+) -> Embed {
+    let embed = Embed::default()
+        .title(format!("{} {}s match \"{}\"", rows.len(), I::show(), title_term))
+        .url(inspector_url::<I>(inspector_base_url, table.id(rows[0])));
+    let embed = rows.iter().take(MAX_DISAMBIGUATION_MATCHES).fold(embed, |embed, &row| {
+        let stats = table
+            .stats(row, I::stats())
+            .into_iter()
+            .map(|(label, value, _)| format!("{}: {}", label, value))
+            .collect::<Vec<_>>();
+        let stats = if stats.is_empty() {
+            "No stats".to_owned()
+        } else {
+            truncate(&stats.join("\n"), MAX_SAME_ID_STATS_LENGTH)
+        };
+        embed.field(format!("{} ({} {})", table.name(row), I::show(), table.id(row)), stats, false)
+    });
+    if rows.len() > MAX_DISAMBIGUATION_MATCHES {
+        embed.footer(format!("...and {} more", rows.len() - MAX_DISAMBIGUATION_MATCHES))
+    } else {
+        embed
+    }
+}
+
+/// Every search command. They all run through `run_search_command`, so they also work away from discord.
+pub const SEARCH_COMMANDS: &[&str] = &["item", "spell", "unit", "site", "merc", "event"];
+
+/// Where searches link to, and the inspector's data to answer them with if there is some
+#[derive(Clone, Default)]
+pub struct Inspector {
+    pub base_url: String,
+    pub data: Option<Arc<InspectorData>>,
+}
+
+impl Inspector {
+    /// Searches only link to the inspector without a `data_dir`
+    pub fn load(base_url: &str, data_dir: Option<&Path>) -> Inspector {
+        Inspector {
+            base_url: base_url.to_owned(),
+            data: data_dir.map(|dir| Arc::new(InspectorData::load(dir))),
+        }
+    }
+}
+
+/// `None` if `command` isn't one of `SEARCH_COMMANDS`. Everything after the command is the search term.
+pub fn run_search_command(
+    inspector: &Inspector,
+    command: &str,
+    request: &mut Request,
+) -> Option<Result<Response, CommandError>> {
+    let search: fn(&str, Option<&InspectorData>, &str) -> Response = match command {
+        "item" => search_helper::<Item>,
+        "spell" => search_helper::<Spell>,
+        "unit" => search_helper::<Unit>,
+        "site" => search_helper::<Site>,
+        "merc" => search_helper::<Merc>,
+        "event" => search_helper::<Event>,
+        _ => return None,
+    };
+    let search_term = request.rest().join(" ");
+    let response = search(&inspector.base_url, inspector.data.as_ref().map(|data| &**data), &search_term);
+    info!("responding with {:?}", response);
+    Some(Ok(response))
 }
 
 use serenity::framework::standard::StandardFramework;
 pub trait WithSearchCommands: Sized {
     fn get_standard_framework(self) -> StandardFramework;
-    fn with_search_commands(self, bucket: &str) -> StandardFramework {
-        self.get_standard_framework()
-            .command(Item::show(), |c| {
-                c.bucket(bucket).exec(|_, m, a| search::<Item>(m, &a))
-            })
-            .command(Spell::show(), |c| {
-                c.bucket(bucket).exec(|_, m, a| search::<Spell>(m, &a))
-            })
-            .command(Unit::show(), |c| {
-                c.bucket(bucket).exec(|_, m, a| search::<Unit>(m, &a))
-            })
-            .command(Site::show(), |c| {
-                c.bucket(bucket).exec(|_, m, a| search::<Site>(m, &a))
-            })
-            .command(Merc::show(), |c| {
-                c.bucket(bucket).exec(|_, m, a| search::<Merc>(m, &a))
-            })
-            .command(Event::show(), |c| {
-                c.bucket(bucket).exec(|_, m, a| search::<Event>(m, &a))
+    fn with_search_commands(self, bucket: &str, inspector: &Inspector) -> StandardFramework {
+        SEARCH_COMMANDS
+            .iter()
+            .fold(self.get_standard_framework(), |framework, &command| {
+                framework.command(command, |c| {
+                    let inspector = inspector.clone();
+                    c.bucket(bucket).cmd(move |_: &mut Context, m: &Message, a: Args| {
+                        respond_on_discord(command, m, a, |request| run_search_command(&inspector, command, request))
+                    })
+                })
             })
     }
 }
@@ -0,0 +1,182 @@
+use super::*;
+
+use super::inspector_data::InspectorTable;
+use serenity::model::id::UserId;
+use std::fs;
+
+const BASE_URL: &str = "https://larzm42.github.io/dom5inspector/";
+
+const ITEMS: &str = "id#\tname\tconstlevel\tmainpath\tmainlevel\tsecondarypath\tsecondarylevel\ttype\n\
+                     1\tFire Sword\t0\t0\t1\t-1\t0\t1-h wpn\n\
+                     2\tFire Brand\t2\t0\t2\t-1\t0\t1-h wpn\n\
+                     3\tAshen Fire Brand\t4\t0\t3\t5\t1\t1-h wpn\n\
+                     4\tThe Ring of Warding\t4\t4\t1\t-1\t0\tmisc\n";
+
+// a mod can add a unit with an id that's already taken
+const DUPLICATE_UNITS: &str = "id\tname\thp\tprot\n\
+                               7\tLongdead\t5\t0\n\
+                               7\tSoulless\t11\t1\n";
+
+const UNITS: &str = "id\tname\thp\tprot\n\
+                     1\tMilitia\t10\t3\n\
+                     2\tMilitia\t11\t5\n";
+
+fn test_data(name: &str, tables: &[(&str, &str)]) -> InspectorData {
+    let dir = std::env::temp_dir().join(format!("dom5status-test-inspector-data-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for &(file, contents) in tables {
+        fs::write(dir.join(file), contents).unwrap();
+    }
+    InspectorData::load(&dir)
+}
+
+fn expect_embed(response: Response) -> Embed {
+    match response {
+        Response::Embed(embed) => embed,
+        response => panic!("expected an embed but got {:?}", response),
+    }
+}
+
+#[test]
+fn should_need_id_and_name_columns() {
+    assert!(InspectorTable::parse("").is_err());
+    assert!(InspectorTable::parse("id\thp\n1\t10\n").is_err());
+    assert_eq!(InspectorTable::parse(ITEMS).unwrap().row_count(), 4);
+}
+
+#[test]
+fn should_prefer_exact_then_prefix_then_anywhere_matches() {
+    let table = InspectorTable::parse(ITEMS).unwrap();
+    assert_eq!(table.search("fire brand"), TableMatch::One(1));
+    assert_eq!(table.search("  Fire   SWORD "), TableMatch::One(0));
+    assert_eq!(table.search("fire"), TableMatch::Several(vec![0, 1]));
+    assert_eq!(table.search("brand"), TableMatch::Several(vec![1, 2]));
+    assert_eq!(table.search("rings of"), TableMatch::None);
+    assert_eq!(table.search("the ring of warding"), TableMatch::One(3));
+    assert_eq!(table.search(""), TableMatch::None);
+}
+
+#[test]
+fn should_search_numbers_by_id() {
+    let table = InspectorTable::parse(ITEMS).unwrap();
+    assert_eq!(table.search("3"), TableMatch::One(2));
+    assert_eq!(table.search("99"), TableMatch::None);
+}
+
+#[test]
+fn should_show_paths_and_skip_missing_stats() {
+    let table = InspectorTable::parse(ITEMS).unwrap();
+    assert_eq!(
+        table.stats(2, Item::stats()),
+        vec![
+            ("Path", "F3".to_owned(), true),
+            ("Second path", "D1".to_owned(), true),
+            ("Construction", "4".to_owned(), true),
+            ("Type", "1-h wpn".to_owned(), true),
+        ]
+    );
+    // no second path, and no column for spells' school at all
+    assert_eq!(table.stats(0, Item::stats()).len(), 3);
+    assert!(table.stats(0, &[Stat::School("school", "School")]).is_empty());
+}
+
+#[test]
+fn should_answer_with_the_stats_of_a_single_match() {
+    let data = test_data("single", &[("BaseI.csv", ITEMS)]);
+    let embed = expect_embed(search_helper::<Item>(BASE_URL, Some(&data), "ashen"));
+    assert_eq!(embed.title, "Ashen Fire Brand (item 3)");
+    assert_eq!(
+        embed.url,
+        Some(inspector_url::<Item>(BASE_URL, "Ashen Fire Brand"))
+    );
+    assert!(embed.to_text().contains("F3"));
+}
+
+#[test]
+fn should_list_matches_to_pick_from() {
+    let data = test_data("several", &[("BaseU.csv", UNITS)]);
+    let embed = expect_embed(search_helper::<Unit>(BASE_URL, Some(&data), "militia"));
+    assert_eq!(embed.title, "2 units match \"militia\"");
+    let text = embed.to_text();
+    assert!(text.contains("Militia (1)\nMilitia (2)"));
+    assert!(text.contains("Search by id to pick one, e.g. !unit 1"));
+
+    let embed = expect_embed(search_helper::<Unit>(BASE_URL, Some(&data), "2"));
+    assert_eq!(embed.title, "Militia (unit 2)");
+}
+
+#[test]
+fn should_show_every_row_sharing_an_id() {
+    let data = test_data("duplicate-ids", &[("BaseU.csv", DUPLICATE_UNITS)]);
+    let embed = expect_embed(search_helper::<Unit>(BASE_URL, Some(&data), "7"));
+    assert_eq!(embed.title, "2 units match \"7\"");
+    let text = embed.to_text();
+    assert!(text.contains("Longdead (unit 7)"));
+    assert!(text.contains("HP: 11\nProt: 1"));
+    assert!(!text.contains("Search by id"));
+
+    // searching by name finds the same rows, and the id wouldn't pick one of them either
+    let embed = expect_embed(search_helper::<Unit>(BASE_URL, Some(&data), "e"));
+    assert_eq!(embed.title, "2 units match \"e\"");
+    assert!(embed.to_text().contains("Soulless (unit 7)"));
+}
+
+#[test]
+fn should_shorten_long_search_terms_in_the_title() {
+    let long_name = "militia".repeat(50);
+    let units = format!("id\tname\n1\t{}a\n2\t{}b\n", long_name, long_name);
+    let data = test_data("long-search-term", &[("BaseU.csv", units.as_str())]);
+    let embed = expect_embed(search_helper::<Unit>(BASE_URL, Some(&data), &long_name));
+    assert!(embed.title.starts_with("2 units match \"militia"));
+    assert!(embed.title.chars().count() <= 256);
+}
+
+#[test]
+fn should_link_the_inspector_without_a_match_or_data() {
+    let data = test_data("fallback", &[("BaseI.csv", ITEMS), ("Spells.csv", "no header here\n")]);
+    assert_eq!(
+        search_helper::<Item>(BASE_URL, Some(&data), "excalibur"),
+        Response::Reply(format!(
+            "No item matching \"excalibur\". {}",
+            inspector_url::<Item>(BASE_URL, "excalibur")
+        ))
+    );
+    // a broken or missing table is left out
+    assert!(data.table("spell").is_none());
+    assert!(data.table("unit").is_none());
+    assert_eq!(
+        search_helper::<Spell>(BASE_URL, Some(&data), "fireball"),
+        Response::Reply(inspector_url::<Spell>(BASE_URL, "fireball"))
+    );
+    assert_eq!(
+        search_helper::<Item>(BASE_URL, None, "fire sword"),
+        Response::Reply(
+            "https://larzm42.github.io/dom5inspector/?page=item&itemq=fire%20sword\
+             &showmodcmds=1&showmoddinginfo=1&showids=1"
+                .to_owned()
+        )
+    );
+}
+
+#[test]
+fn should_run_every_search_command_from_a_request() {
+    let inspector = Inspector::load(BASE_URL, None);
+    for &(category, _) in data_files().iter() {
+        assert!(SEARCH_COMMANDS.contains(&category));
+    }
+    for command in SEARCH_COMMANDS {
+        let mut request = Request::new(UserId(1), None, vec![]);
+        assert!(run_search_command(&inspector, command, &mut request).unwrap().is_ok());
+    }
+    let mut request = Request::new(UserId(1), None, vec![]);
+    assert!(run_search_command(&inspector, "details", &mut request).is_none());
+
+    // the search term is everything after the command
+    let args = vec!["fire".to_owned(), "sword".to_owned()];
+    let mut request = Request::new(UserId(1), None, args);
+    assert_eq!(
+        run_search_command(&inspector, "item", &mut request).unwrap().unwrap(),
+        search_helper::<Item>(BASE_URL, None, "fire sword")
+    );
+}
@@ -1,11 +1,9 @@
-use serenity::framework::standard::{Args, CommandError};
-use serenity::prelude::Context;
-use serenity::model::channel::Message;
+use serenity::framework::standard::CommandError;
 
+use crate::commands::core::{CommandContext, Request, Response};
 use crate::server::ServerConnection;
 use crate::model::{GameServer, GameServerState, StartedState};
-use crate::db::{DbConnection, DbConnectionKey};
-use super::alias_from_arg_or_channel_name;
+use crate::db::DbConnection;
 use log::*;
 
 #[cfg(test)]
@@ -14,7 +12,7 @@ mod tests;
 fn add_server_helper<C: ServerConnection>(
     server_address: &str,
     game_alias: &str,
-    db_connection: &DbConnection,
+    db_conn: &DbConnection,
 ) -> Result<(), CommandError> {
     let game_data = C::get_game_data(server_address)?;
 
@@ -29,31 +27,20 @@ fn add_server_helper<C: ServerConnection>(
         ),
     };
 
-    db_connection.insert_game_server(&server)?;
+    db_conn.insert_game_server(&server)?;
     Ok(())
 }
 
 pub fn add_server<C: ServerConnection>(
-    context: &mut Context,
-    message: &Message,
-    mut args: Args,
-) -> Result<(), CommandError> {
-    let server_address = args.single_quoted::<String>()?;
-
-    let alias = alias_from_arg_or_channel_name(&mut args, &message)?;
-
-    if !args.is_empty() {
-        return Err(CommandError::from(
-            "Too many arguments. TIP: spaces in arguments need to be quoted \"like this\"",
-        ));
-    }
-
-    let data = context.data.lock();
-    let db_connection = data.get::<DbConnectionKey>()
-        .ok_or("No DbConnection was created on startup. This is a bug.")?;
-    add_server_helper::<C>(&server_address, &alias, db_connection)?;
+    context: &CommandContext,
+    request: &mut Request,
+) -> Result<Response, CommandError> {
+    let server_address = request.required_arg("the server's address:port")?;
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    add_server_helper::<C>(&server_address, &alias, context.db_conn)?;
     let text = format!("Successfully inserted with alias {}", alias);
-    let _ = message.reply(&text);
     info!("{}", text);
-    Ok(())
+    Ok(Response::Reply(text))
 }
@@ -0,0 +1,53 @@
+use serenity::framework::standard::CommandError;
+
+use crate::commands::core::{CommandContext, Embed, Request, Response};
+use crate::db::*;
+use crate::model::GameServerState;
+use super::check_is_lobby_owner_or_admin;
+
+#[cfg(test)]
+mod tests;
+
+fn archived_helper(db_conn: &DbConnection) -> Result<Option<Embed>, CommandError> {
+    let archived_games = db_conn.archived_games()?;
+    if archived_games.is_empty() {
+        return Ok(None);
+    }
+
+    let mut aliases = String::new();
+    let mut addresses = String::new();
+    let mut archived_ats = String::new();
+    for (server, archived_at) in archived_games {
+        aliases.push_str(&format!("{}\n", server.alias));
+        match server.state {
+            GameServerState::Lobby(_) => addresses.push_str(&"-\n"),
+            GameServerState::StartedState(ref started_state, _) => {
+                addresses.push_str(&format!("{}\n", started_state.address))
+            }
+        }
+        archived_ats.push_str(&format!("{}\n", archived_at));
+    }
+
+    let embed = Embed::default()
+        .title("Archived games")
+        .field("Alias", aliases, true)
+        .field("Address", addresses, true)
+        .field("Archived (UTC)", archived_ats, true);
+    Ok(Some(embed))
+}
+
+pub fn archived(context: &CommandContext, _: &mut Request) -> Result<Response, CommandError> {
+    match archived_helper(context.db_conn)? {
+        Some(embed) => Ok(Response::Embed(embed)),
+        None => Ok(Response::Reply("No archived games".to_owned())),
+    }
+}
+
+pub fn restore(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    request.no_more_args()?;
+    let server = context.db_conn.game_for_alias_including_archived(&alias)?;
+    check_is_lobby_owner_or_admin(&server, request.author, context.admins)?;
+    context.db_conn.restore_game(&alias)?;
+    Ok(Response::Reply(format!("restored {}", alias)))
+}
@@ -0,0 +1,50 @@
+use super::*;
+
+use crate::commands::admin::Admins;
+use crate::discord::FakeDiscord;
+use serenity::model::id::UserId;
+
+fn restore_as(context: &CommandContext, author: UserId, alias: &str) -> Result<Response, CommandError> {
+    restore(context, &mut Request::new(author, None, vec![alias.to_owned()]))
+}
+
+#[test]
+fn should_only_find_archived_games_for_restore_and_purge() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+    db_conn.archive_game("foo").unwrap();
+
+    let err = db_conn.game_for_alias("foo").unwrap_err();
+    assert_eq!(err.to_string(), "foo is archived, bring it back with !restore foo");
+    assert_eq!(db_conn.game_for_alias_including_archived("foo").unwrap().alias, "foo");
+    assert!(db_conn.game_for_alias_including_archived("bar").is_err());
+}
+
+#[test]
+fn should_only_let_the_owner_or_an_admin_restore() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+    db_conn.archive_game("foo").unwrap();
+    db_conn.insert_test_lobby("bar", UserId(1), 4);
+    db_conn.archive_game("bar").unwrap();
+    let admins = Admins(vec![UserId(9)]);
+    let discord = FakeDiscord::new();
+    let context = CommandContext {
+        db_conn,
+        admins: &admins,
+        discord: &discord,
+    };
+
+    let err = restore_as(&context, UserId(2), "foo").unwrap_err();
+    assert_eq!(err.0, "only the owner of foo or a bot admin can do that");
+    assert!(db_conn.game_for_alias("foo").is_err());
+
+    assert_eq!(
+        restore_as(&context, UserId(1), "foo").unwrap(),
+        Response::Reply("restored foo".to_owned())
+    );
+    assert!(db_conn.game_for_alias("foo").is_ok());
+
+    restore_as(&context, UserId(9), "bar").unwrap();
+    assert!(db_conn.game_for_alias("bar").is_ok());
+}
@@ -1,29 +1,16 @@
-use super::alias_from_arg_or_channel_name;
+use serenity::framework::standard::CommandError;
 
-use serenity::framework::standard::{Args, CommandError};
-use serenity::prelude::Context;
-use serenity::model::channel::Message;
+use crate::commands::core::{CommandContext, Request, Response};
 
-use crate::db::DbConnectionKey;
-
-pub fn describe(
-    context: &mut Context,
-    message: &Message,
-    mut args: Args,
-) -> Result<(), CommandError> {
-    let data = context.data.lock();
-    let db_conn = data.get::<DbConnectionKey>()
-        .ok_or("No DbConnection was created on startup. This is a bug.")?;
-
-    let description = args.single_quoted::<String>()?;
-    let alias = alias_from_arg_or_channel_name(&mut args, &message)?;
-    if !args.is_empty() {
+pub fn describe(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let description = request.required_arg("a description")?;
+    let alias = request.alias()?;
+    if !request.is_empty() {
         return Err(CommandError::from(
             "Too many arguments. TIP: the description needs to be in quotes",
         ));
     }
 
-    db_conn.update_lobby_with_description(&alias, &description)?;
-    message.reply(&format!("added description to {}", alias))?;
-    Ok(())
+    context.db_conn.update_lobby_with_description(&alias, &description)?;
+    Ok(Response::Reply(format!("added description to {}", alias)))
 }
\ No newline at end of file
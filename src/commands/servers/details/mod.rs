@@ -1,14 +1,13 @@
 use crate::server::ServerConnection;
-use super::alias_from_arg_or_channel_name;
+use super::lobby_lifecycle::show_scheduled_start;
 
-use serenity::framework::standard::{Args, CommandError};
-use serenity::prelude::Context;
-use serenity::model::channel::Message;
-use serenity::builder::CreateEmbed;
+use serenity::framework::standard::CommandError;
 
+use crate::commands::core::{CommandContext, Embed, Request, Response};
+use crate::discord::Discord;
 use crate::model::{GameServerState, LobbyState, StartedState};
 use crate::model::enums::{NationStatus, Nations, SubmissionStatus};
-use crate::db::{DbConnection, DbConnectionKey};
+use crate::db::DbConnection;
 use std::collections::HashMap;
 use log::*;
 
@@ -17,54 +16,42 @@ mod tests;
 
 pub fn details_helper<C: ServerConnection>(
     db_conn: &DbConnection,
+    discord: &dyn Discord,
     alias: &str,
-) -> Result<CreateEmbed, CommandError> {
+) -> Result<Embed, CommandError> {
     let server = db_conn.game_for_alias(&alias)?;
     info!("got server details");
 
     let embed_response = match server.state {
-        GameServerState::Lobby(lobby_state) => lobby_details(db_conn, &lobby_state, &alias)?,
+        GameServerState::Lobby(lobby_state) => lobby_details(db_conn, discord, &lobby_state, &alias)?,
         GameServerState::StartedState(started_state, None) => {
-            started_details::<C>(db_conn, &started_state, &alias)?
+            started_details::<C>(db_conn, discord, &started_state, &alias)?
         }
         GameServerState::StartedState(started_state, Some(lobby_state)) => {
             if started_state.last_seen_turn == -1 {
-                uploading_from_lobby_details::<C>(db_conn, &started_state, &lobby_state, &alias)?
+                uploading_from_lobby_details::<C>(db_conn, discord, &started_state, &lobby_state, &alias)?
             } else {
-                started_from_lobby_details::<C>(db_conn, &started_state, &lobby_state, &alias)?
+                started_from_lobby_details::<C>(db_conn, discord, &started_state, &lobby_state, &alias)?
             }
         }
     };
     Ok(embed_response)
 }
 
-pub fn details<C: ServerConnection>(
-    context: &mut Context,
-    message: &Message,
-    mut args: Args,
-) -> Result<(), CommandError> {
-    let data = context.data.lock();
-    let db_conn = data.get::<DbConnectionKey>()
-        .ok_or("No DbConnection was created on startup. This is a bug.")?;
-    let alias = alias_from_arg_or_channel_name(&mut args, &message)?;
-    if !args.is_empty() {
-        return Err(CommandError::from(
-            "Too many arguments. TIP: spaces in arguments need to be quoted \"like this\"",
-        ));
-    }
+pub fn details<C: ServerConnection>(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    request.no_more_args()?;
 
-    let embed_response = details_helper::<C>(db_conn, &alias)?;
-    message
-        .channel_id
-        .send_message(|m| m.embed(|_| embed_response))?;
-    Ok(())
+    let embed_response = details_helper::<C>(context.db_conn, context.discord, &alias)?;
+    Ok(Response::Embed(embed_response))
 }
 
 fn lobby_details(
     db_conn: &DbConnection,
+    discord: &dyn Discord,
     lobby_state: &LobbyState,
     alias: &str,
-) -> Result<CreateEmbed, CommandError> {
+) -> Result<Embed, CommandError> {
     let embed_title = format!("{} ({} Lobby)", alias, lobby_state.era);
     let players_nations = db_conn.players_with_nations_for_game_alias(&alias)?;
     let registered_player_count = players_nations.len() as i32;
@@ -72,21 +59,47 @@ fn lobby_details(
     let mut player_names = String::new();
     let mut nation_names = String::new();
 
+    let waitlist = db_conn.waitlist_for_game_alias(&alias)?;
+    let mut reserved_player_count = 0;
+    let mut waitlist_names = String::new();
+
     for (player, nation_id) in players_nations {
         let &(nation_name, era) = Nations::get_nation_desc(nation_id);
-        player_names.push_str(&format!("{} \n", player.discord_user_id.to_user()?));
+        player_names.push_str(&format!("{} \n", discord.user_name(player.discord_user_id)?));
         nation_names.push_str(&format!("{} {} ({})\n", era, nation_name, nation_id));
     }
-    for _ in 0..(lobby_state.player_count - registered_player_count) {
+    for entry in &waitlist {
+        if entry.promoted {
+            reserved_player_count += 1;
+            player_names.push_str(&format!("{} \n", discord.user_name(entry.player)?));
+            nation_names.push_str(&"RESERVED\n");
+        } else {
+            waitlist_names.push_str(&format!("{}\n", discord.user_name(entry.player)?));
+        }
+    }
+    for _ in 0..(lobby_state.player_count - registered_player_count - reserved_player_count) {
         player_names.push_str(&".\n");
         nation_names.push_str(&"OPEN\n");
     }
-    let owner = lobby_state.owner.to_user()?;
-    let e_temp = CreateEmbed::default()
+    let owner = discord.user_name(lobby_state.owner)?;
+    let e_temp = Embed::default()
         .title(embed_title)
         .field("Nation", nation_names, true)
         .field("Player", player_names, true)
         .field("Owner", format!("{}", owner), false);
+    let e_temp = match lobby_state.scheduled_start {
+        Some(scheduled_start) => e_temp.field(
+            "Status",
+            format!("{}, scheduled to start {}", lobby_state.status, show_scheduled_start(scheduled_start)),
+            false,
+        ),
+        None => e_temp.field("Status", lobby_state.status, false),
+    };
+    let e_temp = if !waitlist_names.is_empty() {
+        e_temp.field("Waitlist", waitlist_names, false)
+    } else {
+        e_temp
+    };
     let e = match lobby_state.description {
         Some(ref description) if !description.is_empty() => e_temp.field("Description", description, false),
         _ => e_temp,
@@ -97,10 +110,11 @@ fn lobby_details(
 
 fn uploading_from_lobby_details<C: ServerConnection>(
     db_conn: &DbConnection,
+    discord: &dyn Discord,
     started_state: &StartedState,
     lobby_state: &LobbyState,
     alias: &str,
-) -> Result<CreateEmbed, CommandError> {
+) -> Result<Embed, CommandError> {
     let server_address = &started_state.address;
     let game_data = C::get_game_data(&server_address)?;
 
@@ -124,11 +138,11 @@ fn uploading_from_lobby_details<C: ServerConnection>(
     let mut submitted_status = String::new();
 
     for (&nation_id, _) in players_uploaded_by_nation_id.iter() {
-        let player_name = id_player_registered_nations.iter()
-            .find(|&&(_, found_nation_id)| nation_id == found_nation_id)
-            .map(|&(ref p, _)|
-                     format!("**{}**\n", p.discord_user_id.to_user().unwrap()))
-            .unwrap_or_else(|| format!("{}\n", NationStatus::Human.show()));
+        let player_name = match id_player_registered_nations.iter()
+            .find(|&&(_, found_nation_id)| nation_id == found_nation_id) {
+            Some(&(ref p, _)) => format!("**{}**\n", discord.user_name(p.discord_user_id)?),
+            None => format!("{}\n", NationStatus::Human.show()),
+        };
         let &(nation_name, era) = Nations::get_nation_desc(nation_id);
         nation_names.push_str(&format!("{} {} ({})\n", era, nation_name, nation_id));
         player_names.push_str(&player_name);
@@ -138,7 +152,7 @@ fn uploading_from_lobby_details<C: ServerConnection>(
     for &(ref player, nation_id) in players_not_uploaded {
         let &(nation_name, era) = Nations::get_nation_desc(nation_id);
         nation_names.push_str(&format!("{} {}\n", era, nation_name));
-        player_names.push_str(&format!("**{}**\n", player.discord_user_id.to_user()?));
+        player_names.push_str(&format!("**{}**\n", discord.user_name(player.discord_user_id)?));
         submitted_status.push_str(&format!("{}\n", SubmissionStatus::NotSubmitted.show()));
     }
 
@@ -148,8 +162,8 @@ fn uploading_from_lobby_details<C: ServerConnection>(
         started_state.address,
     );
 
-    let owner = lobby_state.owner.to_user()?;
-    let e_temp = CreateEmbed::default()
+    let owner = discord.user_name(lobby_state.owner)?;
+    let e_temp = Embed::default()
         .title(embed_title)
         .field("Nation", nation_names, true)
         .field("Player", player_names, true)
@@ -164,10 +178,11 @@ fn uploading_from_lobby_details<C: ServerConnection>(
 
 fn started_from_lobby_details<C: ServerConnection>(
     db_conn: &DbConnection,
+    discord: &dyn Discord,
     started_state: &StartedState,
     lobby_state: &LobbyState,
     alias: &str,
-) -> Result<CreateEmbed, CommandError> {
+) -> Result<Embed, CommandError> {
     let server_address = &started_state.address;
     let mut game_data = C::get_game_data(&server_address)?;
     game_data
@@ -189,7 +204,7 @@ fn started_from_lobby_details<C: ServerConnection>(
                 .iter()
                 .find(|&&(_, nation_id)| nation_id == nation.id)
             {
-                format!("**{}**", player.discord_user_id.to_user()?)
+                format!("**{}**", discord.user_name(player.discord_user_id)?)
             } else {
                 nation.status.show().to_string()
             }
@@ -219,7 +234,7 @@ fn started_from_lobby_details<C: ServerConnection>(
     for &(ref player, nation_id) in &not_uploaded_players {
         let &(nation_name, era) = Nations::get_nation_desc(nation_id);
         nation_names.push_str(&format!("{} {} ({})\n", era, nation_name, nation_id));
-        player_names.push_str(&format!("**{}**\n", player.discord_user_id.to_user()?));
+        player_names.push_str(&format!("**{}**\n", discord.user_name(player.discord_user_id)?));
         submitted_status.push_str(&format!("{}\n", SubmissionStatus::NotSubmitted.show()));
     }
 
@@ -246,8 +261,8 @@ fn started_from_lobby_details<C: ServerConnection>(
         submitted_status
     );
 
-    let owner = lobby_state.owner.to_user()?;
-    let e_temp = CreateEmbed::default()
+    let owner = discord.user_name(lobby_state.owner)?;
+    let e_temp = Embed::default()
         .title(embed_title)
         .field("Nation", nation_names, true)
         .field("Player", player_names, true)
@@ -262,9 +277,10 @@ fn started_from_lobby_details<C: ServerConnection>(
 
 fn started_details<C: ServerConnection>(
     db_conn: &DbConnection,
+    discord: &dyn Discord,
     started_state: &StartedState,
     alias: &str,
-) -> Result<CreateEmbed, CommandError> {
+) -> Result<Embed, CommandError> {
     let server_address = &started_state.address;
     let mut game_data = C::get_game_data(&server_address)?;
     game_data
@@ -286,7 +302,7 @@ fn started_details<C: ServerConnection>(
                 .iter()
                 .find(|&&(_, nation_id)| nation_id == nation.id)
             {
-                format!("**{}**", player.discord_user_id.to_user()?)
+                format!("**{}**", discord.user_name(player.discord_user_id)?)
             } else {
                 nation.status.show().to_string()
             }
@@ -329,7 +345,7 @@ fn started_details<C: ServerConnection>(
         submitted_status
     );
 
-    let e = CreateEmbed::default()
+    let e = Embed::default()
         .title(embed_title)
         .field("Nation", nation_names, true)
         .field("Player", player_names, true)
@@ -1,5 +1,7 @@
 use super::*;
 
+use crate::discord::FakeDiscord;
+
 use std::io;
 use crate::model::*;
 use crate::model::enums::*;
@@ -9,7 +11,7 @@ use serenity::model::*;
 fn should_return_error_on_no_connection() {
     mock_server_connection!(Mock, Err(io::Error::from_raw_os_error(-1)));
 
-    let result = details_helper::<Mock>(&DbConnection::noop(), "");
+    let result = details_helper::<Mock>(&DbConnection::noop(), &FakeDiscord::new(), "");
     assert!(result.is_err());
 }
 
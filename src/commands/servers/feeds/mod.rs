@@ -0,0 +1,190 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use failure::{Error, ResultExt};
+use url::percent_encoding::{utf8_percent_encode, PATH_SEGMENT_ENCODE_SET};
+use log::*;
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::db::DbConnection;
+use crate::model::{GameServerState, TurnRecord};
+use crate::model::enums::Nations;
+use super::status_pages::{escape, safe_file_stem, write_file};
+use super::turns::hours_mins_remaining;
+
+#[cfg(test)]
+mod tests;
+
+const GAMES_DIR: &str = "games";
+const COMBINED_FEED_FILE_NAME: &str = "all.atom";
+/// Per feed, feed readers only ever look at the newest few anyway
+const MAX_ENTRIES: usize = 100;
+/// Every tag URI needs a date the authority owned the name on, it never changes
+const TAG_PREFIX: &str = "tag:dom5status,2019:";
+
+/// Where the atom feeds go. `all.atom` has every game's events, and each started game
+/// gets its own feed in `games/`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feeds {
+    pub dir: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct FeedEntry {
+    id: String,
+    title: String,
+    updated: DateTime<Utc>,
+    content: String,
+}
+
+fn nation_text(nation_id: usize) -> String {
+    let &(name, era) = Nations::get_nation_desc(nation_id);
+    format!("{} {} ({})", era, name, nation_id)
+}
+
+fn tag(alias: &str, rest: &str) -> String {
+    format!(
+        "{}games/{}{}",
+        TAG_PREFIX,
+        utf8_percent_encode(alias, PATH_SEGMENT_ENCODE_SET),
+        rest
+    )
+}
+
+/// The new turn comes first, then what happened in the turn that just ended
+fn entries_for_turn(alias: &str, turn_record: &TurnRecord) -> Vec<FeedEntry> {
+    let turn = turn_record.turn;
+    let timer_text = if turn_record.turn_timer > 0 {
+        let (hours_remaining, mins_remaining) = hours_mins_remaining(turn_record.turn_timer);
+        format!(", {}h {}m remaining", hours_remaining, mins_remaining)
+    } else {
+        String::new()
+    };
+    let mut entries = vec![FeedEntry {
+        id: tag(alias, &format!("/turn/{}", turn)),
+        title: format!("{}: turn {}", alias, turn),
+        updated: turn_record.seen_at,
+        content: format!("{} has a new turn ({}){}", alias, turn, timer_text),
+    }];
+    for &nation_id in &turn_record.defeated {
+        entries.push(FeedEntry {
+            id: tag(alias, &format!("/turn/{}/defeated/{}", turn, nation_id)),
+            title: format!("{}: {} was defeated", alias, nation_text(nation_id)),
+            updated: turn_record.seen_at,
+            content: format!("{} was defeated before turn {} of {}", nation_text(nation_id), turn, alias),
+        });
+    }
+    for &nation_id in &turn_record.ai {
+        entries.push(FeedEntry {
+            id: tag(alias, &format!("/turn/{}/ai/{}", turn, nation_id)),
+            title: format!("{}: {} went AI", alias, nation_text(nation_id)),
+            updated: turn_record.seen_at,
+            content: format!("{} was taken over by the AI before turn {} of {}", nation_text(nation_id), turn, alias),
+        });
+    }
+    for &nation_id in &turn_record.possible_stalls {
+        entries.push(FeedEntry {
+            id: tag(alias, &format!("/turn/{}/stall/{}", turn, nation_id)),
+            title: format!("{}: {} possibly stalled", alias, nation_text(nation_id)),
+            updated: turn_record.seen_at,
+            content: format!("{} possibly stalled turn {} of {}", nation_text(nation_id), turn - 1, alias),
+        });
+    }
+    entries
+}
+
+fn show_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+/// Only depends on the entries, so an unchanged feed is byte for byte the same and
+/// doesn't need rewriting
+fn feed_xml(id: &str, title: &str, entries: &[FeedEntry]) -> String {
+    // a feed has to say when it was updated even before it has anything in it
+    let updated = entries
+        .iter()
+        .map(|entry| entry.updated)
+        .max()
+        .unwrap_or_else(|| DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(0, 0), Utc));
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\">\n\
+         <id>{}</id>\n\
+         <title>{}</title>\n\
+         <updated>{}</updated>\n\
+         <author><name>dom5status</name></author>\n",
+        escape(id),
+        escape(title),
+        show_time(updated),
+    );
+    for entry in entries.iter().take(MAX_ENTRIES) {
+        xml.push_str(&format!(
+            "<entry>\n\
+             <id>{}</id>\n\
+             <title>{}</title>\n\
+             <updated>{}</updated>\n\
+             <content type=\"text\">{}</content>\n\
+             </entry>\n",
+            escape(&entry.id),
+            escape(&entry.title),
+            show_time(entry.updated),
+            escape(&entry.content),
+        ));
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// Returns whether the file changed
+fn write_if_changed(path: &Path, contents: &str) -> Result<bool, Error> {
+    if let Ok(existing) = fs::read_to_string(path) {
+        if existing == contents {
+            return Ok(false);
+        }
+    }
+    write_file(path, contents)?;
+    Ok(true)
+}
+
+impl Feeds {
+    /// Rewrites the feeds that have new entries, and deletes the feeds of games that have gone
+    pub fn write(&self, db_conn: &DbConnection) -> Result<(), Error> {
+        let games_dir = self.dir.join(GAMES_DIR);
+        fs::create_dir_all(&games_dir).context(format!("Creating feed directory '{}'", games_dir.display()))?;
+
+        let mut all_entries = vec![];
+        let mut written = HashSet::new();
+        for server in db_conn.retrieve_all_servers()? {
+            if let GameServerState::StartedState(_, _) = server.state {
+                let entries = db_conn
+                    .turn_history_for_game_alias(&server.alias)?
+                    .iter()
+                    .flat_map(|turn_record| entries_for_turn(&server.alias, turn_record))
+                    .collect::<Vec<_>>();
+                let file_name = format!("{}.atom", safe_file_stem(&server.alias));
+                let xml = feed_xml(&tag(&server.alias, ""), &format!("{} turns", server.alias), &entries);
+                if write_if_changed(&games_dir.join(&file_name), &xml)? {
+                    info!("updated the feed for {}", server.alias);
+                }
+                written.insert(file_name);
+                all_entries.extend(entries);
+            }
+        }
+
+        // stable, so each turn's events stay after the turn itself
+        all_entries.sort_by(|a, b| b.updated.cmp(&a.updated));
+        let xml = feed_xml(&format!("{}all", TAG_PREFIX), "All games", &all_entries);
+        write_if_changed(&self.dir.join(COMBINED_FEED_FILE_NAME), &xml)?;
+
+        for entry in fs::read_dir(&games_dir)? {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if file_name.ends_with(".atom") && !written.contains(&file_name) {
+                info!("deleting old feed {}", entry.path().display());
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use super::*;
+
+use chrono::TimeZone;
+
+fn test_feeds(name: &str) -> Feeds {
+    let dir = std::env::temp_dir().join(format!("dom5status-test-feeds-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    Feeds { dir }
+}
+
+fn read(path: PathBuf) -> String {
+    fs::read_to_string(&path).unwrap()
+}
+
+fn turn_record(turn: i32, hour: u32) -> TurnRecord {
+    TurnRecord {
+        turn,
+        seen_at: Utc.ymd(2019, 6, 1).and_hms(hour, 0, 0),
+        turn_timer: 0,
+        defeated: vec![],
+        ai: vec![],
+        possible_stalls: vec![],
+    }
+}
+
+#[test]
+fn should_have_an_entry_for_each_event() {
+    let turn_record = TurnRecord {
+        turn_timer: (24 * 60 + 5) * 60 * 1000,
+        defeated: vec![5],
+        ai: vec![12],
+        possible_stalls: vec![20, 21],
+        ..turn_record(8, 12)
+    };
+    let entries = entries_for_turn("foo bar", &turn_record);
+    assert_eq!(entries.len(), 5);
+    assert_eq!(entries[0].id, "tag:dom5status,2019:games/foo%20bar/turn/8");
+    assert_eq!(entries[0].content, "foo bar has a new turn (8), 24h 5m remaining");
+    assert_eq!(entries[1].id, "tag:dom5status,2019:games/foo%20bar/turn/8/defeated/5");
+    assert_eq!(entries[2].id, "tag:dom5status,2019:games/foo%20bar/turn/8/ai/12");
+    assert_eq!(entries[3].id, "tag:dom5status,2019:games/foo%20bar/turn/8/stall/20");
+    assert!(entries[3].content.ends_with("possibly stalled turn 7 of foo bar"));
+    assert!(entries.iter().all(|entry| entry.updated == turn_record.seen_at));
+}
+
+#[test]
+fn should_write_a_feed_per_game_and_a_combined_feed() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("first", "first.feeds:3000", 1);
+    db_conn.insert_test_game("<second>", "<second>.feeds:3000", 1);
+    db_conn.insert_turn_record("first", &turn_record(2, 10)).unwrap();
+    db_conn.insert_turn_record("<second>", &TurnRecord {
+        defeated: vec![5],
+        ..turn_record(2, 11)
+    }).unwrap();
+    db_conn.insert_turn_record("first", &turn_record(3, 12)).unwrap();
+    let feeds = test_feeds("write");
+
+    feeds.write(db_conn).unwrap();
+
+    let first = read(feeds.dir.join("games").join("first.atom"));
+    assert!(first.contains("<updated>2019-06-01T12:00:00Z</updated>"));
+    assert!(first.find("games/first/turn/3").unwrap() < first.find("games/first/turn/2").unwrap());
+    assert!(!first.contains("second"));
+
+    let second = read(feeds.dir.join("games").join("_3Csecond_3E.atom"));
+    assert!(second.contains("<title>&lt;second&gt;: turn 2</title>"));
+    assert!(second.contains("/turn/2/defeated/5"));
+
+    let all = read(feeds.dir.join("all.atom"));
+    let positions = ["first/turn/3", "%3Csecond%3E/turn/2<", "%3Csecond%3E/turn/2/defeated/5", "first/turn/2<"]
+        .iter()
+        .map(|id| all.find(id).unwrap())
+        .collect::<Vec<_>>();
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+
+    fs::remove_dir_all(&feeds.dir).unwrap();
+}
+
+#[test]
+fn should_only_rewrite_feeds_that_changed() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("quiet", "quiet.feeds:3000", 1);
+    db_conn.insert_test_game("busy", "busy.feeds:3000", 1);
+    let feeds = test_feeds("unchanged");
+    feeds.write(db_conn).unwrap();
+    let quiet_path = feeds.dir.join("games").join("quiet.atom");
+    let quiet_modified = fs::metadata(&quiet_path).unwrap().modified().unwrap();
+    // empty feeds still need to be valid
+    assert!(read(quiet_path.clone()).contains("<updated>1970-01-01T00:00:00Z</updated>"));
+
+    db_conn.insert_turn_record("busy", &turn_record(2, 10)).unwrap();
+    assert!(!write_if_changed(&quiet_path, &read(quiet_path.clone())).unwrap());
+    feeds.write(db_conn).unwrap();
+    assert_eq!(fs::metadata(&quiet_path).unwrap().modified().unwrap(), quiet_modified);
+    assert!(read(feeds.dir.join("games").join("busy.atom")).contains("busy/turn/2"));
+
+    db_conn.remove_server("busy").unwrap();
+    feeds.write(db_conn).unwrap();
+    assert!(!feeds.dir.join("games").join("busy.atom").exists());
+    assert!(!read(feeds.dir.join("all.atom")).contains("busy"));
+
+    fs::remove_dir_all(&feeds.dir).unwrap();
+}
+
+#[test]
+fn should_give_every_alias_its_own_feed() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("a.b", "a.b.feeds:3000", 1);
+    db_conn.insert_test_game("a_b", "a_b.feeds:3000", 1);
+    db_conn.insert_turn_record("a.b", &turn_record(2, 10)).unwrap();
+    db_conn.insert_turn_record("a_b", &turn_record(3, 11)).unwrap();
+    let feeds = test_feeds("collisions");
+
+    feeds.write(db_conn).unwrap();
+
+    assert!(read(feeds.dir.join("games").join("a_2Eb.atom")).contains("a.b/turn/2"));
+    assert!(read(feeds.dir.join("games").join("a_5Fb.atom")).contains("a_b/turn/3"));
+
+    fs::remove_dir_all(&feeds.dir).unwrap();
+}
@@ -1,12 +1,10 @@
 use serenity::framework::standard::CommandError;
-use serenity::prelude::Context;
-use serenity::model::channel::Message;
-use serenity::builder::CreateEmbed;
 
+use crate::commands::core::{CommandContext, Embed, Request, Response};
 use crate::db::*;
 use crate::model::GameServerState;
 
-fn list_servers_helper(db_conn: &DbConnection) -> Result<CreateEmbed, CommandError> {
+fn list_servers_helper(db_conn: &DbConnection) -> Result<Embed, CommandError> {
     let server_list = db_conn.retrieve_all_servers().map_err(CommandError::from)?;
     let embed_title = "Servers:";
     let mut server_aliases = String::new();
@@ -24,7 +22,7 @@ fn list_servers_helper(db_conn: &DbConnection) -> Result<CreateEmbed, CommandErr
         }
     }
 
-    let embed = CreateEmbed::default()
+    let embed = Embed::default()
         .title(embed_title)
         .field("Alias", server_aliases, true)
         .field("Address", server_addresses, true);
@@ -32,11 +30,7 @@ fn list_servers_helper(db_conn: &DbConnection) -> Result<CreateEmbed, CommandErr
     Ok(embed)
 }
 
-pub fn list_servers(context: &mut Context, message: &Message) -> Result<(), CommandError> {
-    let data = context.data.lock();
-    let db_conn = data.get::<DbConnectionKey>()
-        .ok_or_else(|| CommandError("No db connection".to_string()))?;
-    let embed = list_servers_helper(db_conn)?;
-    message.channel_id.send_message(|m| m.embed(|_| embed))?;
-    Ok(())
+pub fn list_servers(context: &CommandContext, _: &mut Request) -> Result<Response, CommandError> {
+    let embed = list_servers_helper(context.db_conn)?;
+    Ok(Response::Embed(embed))
 }
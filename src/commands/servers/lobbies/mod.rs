@@ -1,30 +1,28 @@
 use serenity::framework::standard::CommandError;
-use serenity::prelude::Context;
-use serenity::model::channel::Message;
-use serenity::builder::CreateEmbed;
-
-use crate::db::*;
 
+use crate::commands::core::{CommandContext, Embed, Request, Response};
 use crate::model::{GameServer, GameServerState};
+use super::lobby_lifecycle::show_scheduled_start;
 
-pub fn lobbies(context: &mut Context, message: &Message) -> Result<(), CommandError> {
-    let data = context.data.lock();
-    let db_conn = data.get::<DbConnectionKey>()
-        .ok_or_else(|| CommandError("No db connection".to_string()))?;
+pub fn lobbies(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let include_inactive = match request.arg() {
+        Some(ref arg) if arg.to_lowercase() == "all" => true,
+        Some(arg) => return Err(CommandError::from(format!("Expected \"all\" or nothing but got \"{}\"", arg))),
+        None => false,
+    };
 
-    let lobbies_and_player_count = db_conn.select_lobbies()?;
+    let lobbies_and_player_count = context.db_conn.select_lobbies(include_inactive)?;
     if lobbies_and_player_count.is_empty() {
-        message.reply(&"No available lobbies")?;
+        Ok(Response::Reply("No available lobbies".to_owned()))
     } else {
-        let embed = lobbies_helper(lobbies_and_player_count)?;
-        message.channel_id.send_message(|m| m.embed(|_| embed))?;
+        Ok(Response::Embed(lobbies_helper(lobbies_and_player_count)?))
     }
-    Ok(())
 }
 
-fn lobbies_helper(lobbies_and_player_count: Vec<(GameServer, i32)>) -> Result<CreateEmbed, CommandError> {
+fn lobbies_helper(lobbies_and_player_count: Vec<(GameServer, i32)>) -> Result<Embed, CommandError> {
     let mut aliases = String::new();
     let mut player_counts = String::new();
+    let mut statuses = String::new();
 
     for (lobby, registered_count) in lobbies_and_player_count {
         aliases.push_str(&format!("{}\n", lobby.alias));
@@ -34,15 +32,25 @@ fn lobbies_helper(lobbies_and_player_count: Vec<(GameServer, i32)>) -> Result<Cr
                 registered_count,
                 state.player_count
             ));
+            match state.scheduled_start {
+                Some(scheduled_start) => statuses.push_str(&format!(
+                    "{}, starts {}\n",
+                    state.status,
+                    show_scheduled_start(scheduled_start)
+                )),
+                None => statuses.push_str(&format!("{}\n", state.status)),
+            }
         } else {
             player_counts.push_str(&"ERROR");
+            statuses.push_str(&"ERROR");
         }
     }
 
-    let embed = CreateEmbed::default()
+    let embed = Embed::default()
         .title("Lobbies")
         .field("Alias", aliases, true)
-        .field("Players", player_counts, true);
+        .field("Players", player_counts, true)
+        .field("Status", statuses, true);
 
     Ok(embed)
 }
\ No newline at end of file
@@ -1,12 +1,10 @@
-use serenity::framework::standard::{Args, CommandError};
-use serenity::prelude::Context;
-use serenity::model::channel::Message;
+use serenity::framework::standard::CommandError;
 use serenity::model::id::UserId;
 
+use crate::commands::core::{CommandContext, Request, Response};
 use crate::model::{GameServer, GameServerState, LobbyState};
-use crate::model::enums::Era;
+use crate::model::enums::{Era, LobbyStatus};
 use crate::db::*;
-use super::alias_from_arg_or_channel_name;
 
 
 #[cfg(test)]
@@ -26,22 +24,23 @@ fn lobby_helper(
             owner: author_id,
             player_count,
             description: None,
+            status: LobbyStatus::Open,
+            scheduled_start: None,
         }),
     })?;
     Ok(())
 }
 
-pub fn lobby(context: &mut Context, message: &Message, mut args: Args) -> Result<(), CommandError> {
-    let era_str = args.single_quoted::<String>()?;
+pub fn lobby(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let era_str = request.required_arg("an era")?;
     let era = Era::from_string(&era_str).ok_or("unknown era")?;
-    let player_count = args.single_quoted::<i32>()?;
-    let alias = alias_from_arg_or_channel_name(&mut args, &message)?;
-    let data = context.data.lock();
-    let db_connection = data.get::<DbConnectionKey>()
-        .ok_or("No DbConnection was created on startup. This is a bug.")?;
+    let player_count_str = request.required_arg("a number of players")?;
+    let player_count = player_count_str
+        .parse::<i32>()
+        .map_err(|_| CommandError::from(format!("'{}' isn't a number of players", player_count_str)))?;
+    let alias = request.alias()?;
 
-    lobby_helper(db_connection, era, player_count, &alias, message.author.id)?;
+    lobby_helper(context.db_conn, era, player_count, &alias, request.author)?;
 
-    message.reply(&format!("Creating game lobby with name {}", alias))?;
-    Ok(())
+    Ok(Response::Reply(format!("Creating game lobby with name {}", alias)))
 }
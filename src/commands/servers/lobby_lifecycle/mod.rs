@@ -0,0 +1,244 @@
+use serenity::framework::standard::CommandError;
+use serenity::model::id::UserId;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use failure::Error;
+use log::*;
+
+use crate::commands::core::{CommandContext, Request, Response};
+use crate::db::DbConnection;
+use crate::discord::Discord;
+use crate::notifier::{notify_player, Notification};
+use crate::model::{GameServerState, LobbyState};
+use crate::model::enums::LobbyStatus;
+use super::check_is_lobby_owner;
+use super::reminders::{parse_duration_mins, show_duration_mins};
+use super::waitlist::{notify_promotions, promote_from_waitlist};
+
+#[cfg(test)]
+mod tests;
+
+/// Registered players are reminded this long before a scheduled start, and again when it's due
+pub(crate) const START_REMINDER_THRESHOLDS_MINS: [i32; 3] = [24 * 60, 60, 0];
+
+pub(crate) fn show_scheduled_start(scheduled_start: DateTime<Utc>) -> String {
+    scheduled_start.format("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+/// Accepts either a duration from now like "2d12h" or a UTC time like "2019-06-01 18:00"
+fn parse_scheduled_start(text: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if let Some(mins) = parse_duration_mins(text) {
+        return Some(now + Duration::minutes(i64::from(mins)));
+    }
+    ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .filter_map(|format| NaiveDateTime::parse_from_str(text.trim(), format).ok())
+        .map(|naive| DateTime::<Utc>::from_utc(naive, Utc))
+        .next()
+        .filter(|scheduled_start| *scheduled_start > now)
+}
+
+fn start_reminders_due(now: DateTime<Utc>, scheduled_start: DateTime<Utc>) -> Vec<i32> {
+    let mins_until_start = scheduled_start.signed_duration_since(now).num_minutes();
+    START_REMINDER_THRESHOLDS_MINS
+        .iter()
+        .cloned()
+        .filter(|&threshold_mins| mins_until_start <= i64::from(threshold_mins))
+        .collect()
+}
+
+fn owned_lobby_state(
+    db_conn: &DbConnection,
+    alias: &str,
+    user_id: UserId,
+) -> Result<LobbyState, CommandError> {
+    let server = db_conn.game_for_alias(alias)?;
+    check_is_lobby_owner(&server, user_id)?;
+    match server.state {
+        GameServerState::Lobby(lobby_state) => Ok(lobby_state),
+        GameServerState::StartedState(_, _) => {
+            Err(CommandError::from(format!("{} has already started", alias)))
+        }
+    }
+}
+
+fn set_lobby_status_helper(
+    db_conn: &DbConnection,
+    alias: &str,
+    user_id: UserId,
+    status: LobbyStatus,
+) -> Result<(), CommandError> {
+    let lobby_state = owned_lobby_state(db_conn, alias, user_id)?;
+    if lobby_state.status == status {
+        return Err(CommandError::from(format!("{} is already {}", alias, status)));
+    }
+    db_conn.set_lobby_status(alias, status)?;
+    Ok(())
+}
+
+fn lobby_status_command(
+    context: &CommandContext,
+    request: &mut Request,
+    status: LobbyStatus,
+) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    set_lobby_status_helper(context.db_conn, &alias, request.author, status)?;
+    if status == LobbyStatus::Open {
+        // slots may have opened up while it was closed
+        let promotions = promote_from_waitlist(context.db_conn, &alias)?;
+        notify_promotions(context.db_conn, context.discord, &alias, &promotions);
+    }
+    Ok(Response::Reply(format!("{} is now {}", alias, status)))
+}
+
+pub fn lobby_lock(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    lobby_status_command(context, request, LobbyStatus::Locked)
+}
+
+pub fn lobby_open(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    lobby_status_command(context, request, LobbyStatus::Open)
+}
+
+pub fn lobby_cancel(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    lobby_status_command(context, request, LobbyStatus::Cancelled)
+}
+
+fn lobby_schedule_helper(
+    db_conn: &DbConnection,
+    alias: &str,
+    user_id: UserId,
+    when: &str,
+    now: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, CommandError> {
+    let _ = owned_lobby_state(db_conn, alias, user_id)?;
+    let scheduled_start = if when.to_lowercase() == "off" {
+        None
+    } else {
+        Some(parse_scheduled_start(when, now).ok_or_else(|| {
+            CommandError::from(format!(
+                "Could not understand start time \"{}\". Use a duration like 2d12h or a future UTC time like \"2019-06-01 18:00\"",
+                when
+            ))
+        })?)
+    };
+    db_conn.set_lobby_scheduled_start(alias, scheduled_start)?;
+    Ok(scheduled_start)
+}
+
+pub fn lobby_schedule(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let when = request.required_arg("a start time, or \"off\"")?;
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    let text = match lobby_schedule_helper(context.db_conn, &alias, request.author, &when, Utc::now())? {
+        Some(scheduled_start) => format!(
+            "{} is scheduled to start at {}",
+            alias,
+            show_scheduled_start(scheduled_start)
+        ),
+        None => format!("{} no longer has a scheduled start", alias),
+    };
+    Ok(Response::Reply(text))
+}
+
+fn send_start_reminders(
+    db_conn: &DbConnection,
+    discord: &dyn Discord,
+    alias: &str,
+    lobby_state: &LobbyState,
+    scheduled_start: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<(), Error> {
+    let already_sent = db_conn.sent_lobby_start_reminder_thresholds(alias)?;
+    let newly_due = start_reminders_due(now, scheduled_start)
+        .into_iter()
+        .filter(|threshold_mins| !already_sent.contains(threshold_mins))
+        .collect::<Vec<_>>();
+    // only the closest reminder is worth sending if several became due at once
+    let threshold_mins = match newly_due.iter().cloned().min() {
+        Some(threshold_mins) => threshold_mins,
+        None => return Ok(()),
+    };
+
+    let text = if threshold_mins == 0 {
+        format!("{} is scheduled to start now ({})", alias, show_scheduled_start(scheduled_start))
+    } else {
+        let mins_until_start = scheduled_start.signed_duration_since(now).num_minutes().max(1);
+        format!(
+            "{} is scheduled to start in {} ({})",
+            alias,
+            show_duration_mins(mins_until_start as i32),
+            show_scheduled_start(scheduled_start)
+        )
+    };
+
+    let mut recipients = db_conn
+        .players_with_nations_for_game_alias(alias)?
+        .into_iter()
+        .map(|(player, _)| player.discord_user_id)
+        .collect::<Vec<_>>();
+    recipients.push(lobby_state.owner);
+    recipients.sort();
+    recipients.dedup();
+
+    for recipient in recipients {
+        let recipient_text = if recipient == lobby_state.owner && threshold_mins == 0 {
+            format!("{}\nUse !start <address:port> {} once the server is up", text, alias)
+        } else {
+            text.clone()
+        };
+        // one player's DMs being closed shouldn't stop everyone else's reminders
+        if let Err(err) = notify_player(
+            db_conn,
+            discord,
+            recipient,
+            &Notification::for_player(recipient, alias, None, "start_reminder", recipient_text),
+        ) {
+            error!("error reminding {} that {} is starting: {}", recipient, alias, err);
+        }
+    }
+    // recorded only once they've gone out, so a check that fails before sending tries again
+    for threshold_mins in newly_due {
+        db_conn.insert_lobby_start_reminder(alias, threshold_mins)?;
+    }
+    Ok(())
+}
+
+/// Expires lobbies with no activity for `lobby_expiry_days` and sends reminders for scheduled
+/// starts. Run with every turn check.
+pub(crate) fn check_lobbies(
+    db_conn: &DbConnection,
+    discord: &dyn Discord,
+    lobby_expiry_days: u32,
+) -> Result<(), Error> {
+    for (alias, owner) in db_conn.expire_inactive_lobbies(lobby_expiry_days)? {
+        info!("lobby {} expired", alias);
+        let text = format!(
+            "Your lobby {} expired after {} days without activity. Use !lobby-open {} to reopen it",
+            alias,
+            lobby_expiry_days,
+            alias
+        );
+        let notification = Notification::for_player(owner, &alias, None, "lobby_expired", text);
+        if let Err(err) = notify_player(db_conn, discord, owner, &notification) {
+            error!("could not tell the owner of {} that it expired: {}", alias, err);
+        }
+    }
+
+    let now = Utc::now();
+    for server in db_conn.retrieve_all_servers()? {
+        if let GameServerState::Lobby(ref lobby_state) = server.state {
+            if let (true, Some(scheduled_start)) =
+                (lobby_state.status.is_active(), lobby_state.scheduled_start)
+            {
+                if let Err(err) =
+                    send_start_reminders(db_conn, discord, &server.alias, lobby_state, scheduled_start, now)
+                {
+                    error!("error sending start reminders for {}: {}", server.alias, err);
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use super::*;
+
+use crate::discord::FakeDiscord;
+use chrono::TimeZone;
+
+fn lobby_state(db_conn: &DbConnection, alias: &str) -> LobbyState {
+    match db_conn.game_for_alias(alias).unwrap().state {
+        GameServerState::Lobby(lobby_state) => lobby_state,
+        state => panic!("expected a lobby but got {:?}", state),
+    }
+}
+
+#[test]
+fn should_hide_cancelled_lobbies_but_keep_them_queryable() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+    db_conn.insert_test_lobby("bar", UserId(1), 4);
+
+    set_lobby_status_helper(db_conn, "foo", UserId(1), LobbyStatus::Cancelled).unwrap();
+
+    let lobbies = db_conn.select_lobbies(false).unwrap();
+    assert_eq!(lobbies.len(), 1);
+    assert_eq!(lobbies[0].0.alias, "bar");
+    assert_eq!(db_conn.select_lobbies(true).unwrap().len(), 2);
+    assert_eq!(lobby_state(db_conn, "foo").status, LobbyStatus::Cancelled);
+}
+
+#[test]
+fn should_only_let_the_owner_change_status() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+
+    assert!(set_lobby_status_helper(db_conn, "foo", UserId(2), LobbyStatus::Locked).is_err());
+    assert!(set_lobby_status_helper(db_conn, "foo", UserId(1), LobbyStatus::Open).is_err());
+    set_lobby_status_helper(db_conn, "foo", UserId(1), LobbyStatus::Locked).unwrap();
+    assert_eq!(lobby_state(db_conn, "foo").status, LobbyStatus::Locked);
+}
+
+#[test]
+fn should_expire_inactive_lobbies() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+    db_conn.insert_test_lobby("bar", UserId(2), 4);
+    db_conn.insert_test_lobby("baz", UserId(3), 4);
+    db_conn.set_lobby_last_activity("foo", "2000-01-01 00:00:00");
+    db_conn.set_lobby_last_activity("baz", "2000-01-01 00:00:00");
+    set_lobby_status_helper(db_conn, "baz", UserId(3), LobbyStatus::Cancelled).unwrap();
+    db_conn.set_lobby_last_activity("baz", "2000-01-01 00:00:00");
+
+    let expired = db_conn.expire_inactive_lobbies(30).unwrap();
+
+    assert_eq!(expired, vec![("foo".to_owned(), UserId(1))]);
+    assert_eq!(lobby_state(db_conn, "foo").status, LobbyStatus::Expired);
+    assert_eq!(lobby_state(db_conn, "bar").status, LobbyStatus::Open);
+    assert_eq!(lobby_state(db_conn, "baz").status, LobbyStatus::Cancelled);
+    assert!(db_conn.expire_inactive_lobbies(30).unwrap().is_empty());
+}
+
+#[test]
+fn should_schedule_and_unschedule_start() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+    let now = Utc.ymd(2019, 5, 1).and_hms(12, 0, 0);
+
+    let scheduled_start = lobby_schedule_helper(db_conn, "foo", UserId(1), "2019-06-01 18:00", now).unwrap();
+    assert_eq!(scheduled_start, Some(Utc.ymd(2019, 6, 1).and_hms(18, 0, 0)));
+    assert_eq!(lobby_state(db_conn, "foo").scheduled_start, scheduled_start);
+
+    lobby_schedule_helper(db_conn, "foo", UserId(1), "off", now).unwrap();
+    assert_eq!(lobby_state(db_conn, "foo").scheduled_start, None);
+}
+
+#[test]
+fn should_parse_scheduled_start() {
+    let now = Utc.ymd(2019, 5, 1).and_hms(12, 0, 0);
+    assert_eq!(parse_scheduled_start("1d2h", now), Some(Utc.ymd(2019, 5, 2).and_hms(14, 0, 0)));
+    assert_eq!(parse_scheduled_start("2019-05-03T09:30", now), Some(Utc.ymd(2019, 5, 3).and_hms(9, 30, 0)));
+    assert_eq!(parse_scheduled_start("2019-04-01 09:30", now), None);
+    assert_eq!(parse_scheduled_start("tomorrow", now), None);
+}
+
+#[test]
+fn should_send_start_reminders_as_thresholds_pass() {
+    let scheduled_start = Utc.ymd(2019, 5, 2).and_hms(12, 0, 0);
+    assert!(start_reminders_due(Utc.ymd(2019, 5, 1).and_hms(11, 0, 0), scheduled_start).is_empty());
+    assert_eq!(start_reminders_due(Utc.ymd(2019, 5, 1).and_hms(12, 0, 0), scheduled_start), vec![24 * 60]);
+    assert_eq!(start_reminders_due(Utc.ymd(2019, 5, 2).and_hms(11, 30, 0), scheduled_start), vec![24 * 60, 60]);
+    assert_eq!(start_reminders_due(Utc.ymd(2019, 5, 2).and_hms(12, 5, 0), scheduled_start), vec![24 * 60, 60, 0]);
+}
+
+#[test]
+fn should_keep_reminding_the_others_when_one_dm_fails() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+    for (user_id, nation_id) in &[(2, 5), (3, 7)] {
+        db_conn.register_test_player("foo", UserId(*user_id), *nation_id);
+    }
+    let lobby_state = lobby_state(db_conn, "foo");
+    let scheduled_start = Utc.ymd(2019, 5, 2).and_hms(12, 0, 0);
+    let now = Utc.ymd(2019, 5, 2).and_hms(11, 30, 0);
+    // discord won't DM the first player
+    let discord = FakeDiscord::new()
+        .with_user(UserId(1), "owner")
+        .with_user(UserId(3), "three");
+
+    send_start_reminders(db_conn, &discord, "foo", &lobby_state, scheduled_start, now).unwrap();
+    assert_eq!(discord.dms_to(UserId(1)).len(), 1);
+    assert_eq!(discord.dms_to(UserId(3)), vec!["foo is scheduled to start in 30m (2019-05-02 12:00 UTC)"]);
+    let mut sent = db_conn.sent_lobby_start_reminder_thresholds("foo").unwrap();
+    sent.sort();
+    assert_eq!(sent, vec![60, 24 * 60]);
+
+    send_start_reminders(db_conn, &discord, "foo", &lobby_state, scheduled_start, now).unwrap();
+    assert_eq!(discord.dms_to(UserId(3)).len(), 1);
+}
@@ -37,83 +37,222 @@ pub use self::lobbies::*;
 mod describe;
 pub use self::describe::*;
 
-use serenity::framework::standard::{Args, StandardFramework};
+mod stalls;
+use self::stalls::*;
+
+mod notify_channel;
+use self::notify_channel::*;
+
+mod reminders;
+use self::reminders::*;
+
+mod pin_status;
+use self::pin_status::*;
+
+mod substitutes;
+use self::substitutes::*;
+
+mod waitlist;
+use self::waitlist::*;
+
+mod lobby_lifecycle;
+use self::lobby_lifecycle::*;
+
+mod owner;
+use self::owner::*;
+
+mod rename;
+use self::rename::*;
+
+mod move_server;
+use self::move_server::*;
+
+mod archive;
+use self::archive::*;
+
+mod status_pages;
+pub use self::status_pages::*;
+
+mod feeds;
+pub use self::feeds::*;
+
+mod notifiers;
+use self::notifiers::*;
+
+use serenity::framework::standard::{Args, CommandError, StandardFramework};
+use serenity::client::Context;
 use serenity::model::channel::Message;
+use serenity::model::id::UserId;
+use crate::model::{GameServer, GameServerState};
 use crate::server::ServerConnection;
+use crate::db::DbConnection;
+use crate::commands::admin::Admins;
+use crate::commands::core::{run_on_discord, CommandContext, Request, Response};
+
+/// Every game and lobby command. They all run through `run_servers_command`, so they also work away from discord.
+pub const CORE_COMMANDS: &[&str] = &[
+    "add",
+    "list",
+    "delete",
+    "purge",
+    "archived",
+    "restore",
+    "details",
+    "deets",
+    "register",
+    "register-id",
+    "unregister",
+    "lobbies",
+    "turns",
+    "start",
+    "stalls",
+    "stall-warning",
+    "sub-needed",
+    "sub-take",
+    "sub-cancel",
+    "sub-channel",
+    "subs",
+    "kick",
+    "transfer-owner",
+    "lobby",
+    "notifications",
+    "notification-format",
+    "describe",
+    "notify-channel",
+    "notify-channel-remove",
+    "reminders",
+    "webhook",
+    "dms",
+    "game-webhook",
+    "pin-status",
+    "unpin-status",
+    "waitlist",
+    "waitlist-leave",
+    "lobby-lock",
+    "lobby-open",
+    "lobby-cancel",
+    "lobby-schedule",
+    "rename",
+    "move",
+];
 
 pub trait WithServersCommands: Sized {
     fn get_standard_framework(self) -> StandardFramework;
-    fn with_servers_commands<C: ServerConnection>(self, bucket: &str) -> StandardFramework {
-        self.get_standard_framework()
-            .command("add", |c| {
-                c.bucket(bucket).exec(|cx, m, a| add_server::<C>(cx, m, a))
-            })
-            .command("list", |c| {
-                c.bucket(bucket).exec(|cx, m, _| list_servers(cx, m))
-            })
-            .command("delete", |c| {
-                c.bucket(bucket).exec(|cx, m, a| remove_server(cx, m, a))
-            })
-            .command("details", |c| {
-                c.bucket(bucket).exec(|cx, m, a| details::<C>(cx, m, a))
+    /// Every command gets its own handle to the connection pool, so none of them
+    /// need to lock the client's shared data
+    fn with_servers_commands<C: ServerConnection>(
+        self,
+        bucket: &str,
+        db_conn: &DbConnection,
+        admins: &Admins,
+    ) -> StandardFramework {
+        CORE_COMMANDS
+            .iter()
+            .fold(self.get_standard_framework(), |framework, &command| {
+                framework.command(command, |c| {
+                    let db_conn = db_conn.clone();
+                    let admins = admins.clone();
+                    c.bucket(bucket)
+                        .cmd(move |_: &mut Context, m: &Message, a: Args| run_on_discord::<C>(&db_conn, &admins, command, m, a))
+                })
             })
-            .command("deets", |c| {
-                c.bucket(bucket).exec(|cx, m, a| details::<C>(cx, m, a))
-            })
-            .command("register", |c| {
-                c.bucket(bucket)
-                    .exec(|cx, m, a| register_player::<C>(cx, m, a))
-            })
-            .command("register-id", |c| {
-                c.bucket(bucket)
-                    .exec(|cx, m, a| register_player_id::<C>(cx, m, a))
-            })
-            .command("unregister", |c| {
-                c.bucket(bucket)
-                    .exec(|cx, m, a| unregister_player(cx, m, a))
-            })
-            .command("turns", |c| {
-                c.bucket(bucket).exec(|cx, m, _| turns::<C>(cx, m))
-            })
-            .command(
-                "lobby",
-                |c| c.bucket(bucket).exec(|cx, m, a| lobby(cx, m, a)),
-            )
-            .command("notifications", |c| {
-                c.bucket(bucket).exec(|cx, m, a| notifications(cx, m, a))
-            })
-            .command("start", |c| {
-                c.bucket(bucket).exec(|cx, m, a| start::<C>(cx, m, a))
-            })
-            .command("lobbies", |c| {
-                c.bucket(bucket).exec(|cx, m, _| lobbies(cx, m))
-            })
-            .command("describe", |c| {
-                c.bucket(bucket).exec(|cx, m, a| describe(cx, m, a))
-            }
-            )
     }
 }
 
+/// `None` if `command` isn't one of `CORE_COMMANDS`
+pub fn run_servers_command<C: ServerConnection>(
+    context: &CommandContext,
+    command: &str,
+    request: &mut Request,
+) -> Option<Result<Response, CommandError>> {
+    let run: fn(&CommandContext, &mut Request) -> Result<Response, CommandError> = match command {
+        "add" => add_server::<C>,
+        "list" => list_servers,
+        "delete" => remove_server,
+        "purge" => purge_server,
+        "archived" => archived,
+        "restore" => restore,
+        "details" | "deets" => details::<C>,
+        "register" => register_player::<C>,
+        "register-id" => register_player_id::<C>,
+        "unregister" => unregister_player,
+        "lobbies" => lobbies,
+        "turns" => turns::<C>,
+        "start" => start::<C>,
+        "stalls" => stalls,
+        "stall-warning" => stall_warning,
+        "sub-needed" => sub_needed,
+        "sub-take" => sub_take::<C>,
+        "sub-cancel" => sub_cancel,
+        "sub-channel" => sub_channel,
+        "subs" => subs,
+        "kick" => kick,
+        "transfer-owner" => transfer_owner,
+        "lobby" => lobby,
+        "notifications" => notifications,
+        "notification-format" => notification_format,
+        "describe" => describe,
+        "notify-channel" => notify_channel,
+        "notify-channel-remove" => notify_channel_remove,
+        "reminders" => reminders,
+        "webhook" => webhook,
+        "dms" => dms,
+        "game-webhook" => game_webhook,
+        "pin-status" => pin_status::<C>,
+        "unpin-status" => unpin_status,
+        "waitlist" => waitlist,
+        "waitlist-leave" => waitlist_leave,
+        "lobby-lock" => lobby_lock,
+        "lobby-open" => lobby_open,
+        "lobby-cancel" => lobby_cancel,
+        "lobby-schedule" => lobby_schedule,
+        "rename" => rename,
+        "move" => move_server::<C>,
+        _ => return None,
+    };
+    Some(run(context, request))
+}
+
 impl WithServersCommands for StandardFramework {
     fn get_standard_framework(self) -> StandardFramework {
         self
     }
 }
 
-fn alias_from_arg_or_channel_name(args: &mut Args, message: &Message) -> Result<String, String> {
-    let result_alias = if !args.is_empty() {
-        args.single_quoted::<String>().ok()
-    } else {
-        message.channel_id.name()
+fn check_is_lobby_owner(server: &GameServer, user_id: UserId) -> Result<(), CommandError> {
+    let lobby_state = match server.state {
+        GameServerState::Lobby(ref lobby_state) => lobby_state,
+        GameServerState::StartedState(_, Some(ref lobby_state)) => lobby_state,
+        GameServerState::StartedState(_, None) => {
+            return Err(CommandError::from(format!(
+                "{} was not created from a lobby so it has no owner",
+                server.alias
+            )))
+        }
     };
-    result_alias
-        .clone()
-        .map(|s| s.to_lowercase())
-        .and_then(|s| if !s.is_empty() {Some(s)} else {None})
-        .ok_or_else(||
-            format!(
-                "Could not game alias from command argument or channel name \"{}\"",
-                result_alias.unwrap_or_default())
-        )
+    if lobby_state.owner == user_id {
+        Ok(())
+    } else {
+        Err(CommandError::from(format!(
+            "only the owner of {} can do that",
+            server.alias
+        )))
+    }
+}
+
+/// The lobby owner, or a bot admin
+fn check_is_lobby_owner_or_admin(
+    server: &GameServer,
+    user_id: UserId,
+    admins: &Admins,
+) -> Result<(), CommandError> {
+    if check_is_lobby_owner(server, user_id).is_ok() {
+        return Ok(());
+    }
+    admins.check(user_id).map_err(|_| {
+        CommandError::from(format!(
+            "only the owner of {} or a bot admin can do that",
+            server.alias
+        ))
+    })
 }
@@ -0,0 +1,102 @@
+use serenity::framework::standard::CommandError;
+use serenity::model::id::UserId;
+use log::*;
+
+use crate::commands::core::{CommandContext, Request, Response};
+use crate::db::DbConnection;
+use crate::model::{GameData, GameServerState};
+use crate::server::{snapshot_get, snapshot_move, ServerConnection};
+use super::check_is_lobby_owner;
+
+#[cfg(test)]
+mod tests;
+
+fn move_server_helper<C: ServerConnection>(
+    db_conn: &DbConnection,
+    alias: &str,
+    new_address: &str,
+    user_id: UserId,
+) -> Result<GameData, CommandError> {
+    let server = db_conn.game_for_alias(alias)?;
+    let started_state = match server.state {
+        GameServerState::StartedState(ref started_state, ref maybe_lobby_state) => {
+            if maybe_lobby_state.is_some() {
+                check_is_lobby_owner(&server, user_id)?;
+            }
+            started_state.clone()
+        }
+        GameServerState::Lobby(_) => {
+            return Err(CommandError::from(format!(
+                "{} hasn't started yet, use !start instead",
+                alias
+            )))
+        }
+    };
+    if started_state.address == new_address {
+        return Err(CommandError::from(format!("{} is already at {}", alias, new_address)));
+    }
+    // archived games keep their address, so moving onto one would break the unique constraint
+    let archived_servers = db_conn.archived_games()?.into_iter().map(|(server, _)| server);
+    if db_conn
+        .retrieve_all_servers()?
+        .into_iter()
+        .chain(archived_servers)
+        .any(|other| match other.state {
+            GameServerState::StartedState(ref other_state, _) => other_state.address == new_address,
+            GameServerState::Lobby(_) => false,
+        })
+    {
+        return Err(CommandError::from(format!("Another game is already using {}", new_address)));
+    }
+
+    // the old server is often down by the time a game gets moved, so prefer what the last poll saw
+    let old_game_name = match snapshot_get(&started_state.address) {
+        Some(old_game_data) => old_game_data.game_name,
+        None => C::get_game_data(&started_state.address)
+            .map_err(|_| {
+                CommandError::from(format!(
+                    "Could not check the game name of {}: {} isn't responding and hasn't been polled since the bot started",
+                    alias,
+                    started_state.address
+                ))
+            })?
+            .game_name,
+    };
+    let new_game_data = C::get_game_data(new_address)?;
+    if new_game_data.game_name != old_game_name {
+        return Err(CommandError::from(format!(
+            "{} is running the game \"{}\" but {} is \"{}\"",
+            new_address,
+            new_game_data.game_name,
+            alias,
+            old_game_name
+        )));
+    }
+    if new_game_data.turn < started_state.last_seen_turn {
+        return Err(CommandError::from(format!(
+            "{} is on turn {} but {} was last seen on turn {}",
+            new_address,
+            new_game_data.turn,
+            alias,
+            started_state.last_seen_turn
+        )));
+    }
+
+    db_conn.update_started_server_address(alias, new_address)?;
+    snapshot_move(&started_state.address, new_address);
+    Ok(new_game_data)
+}
+
+pub fn move_server<C: ServerConnection>(
+    context: &CommandContext,
+    request: &mut Request,
+) -> Result<Response, CommandError> {
+    let new_address = request.required_arg("the game's new address")?;
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    let game_data = move_server_helper::<C>(context.db_conn, &alias, &new_address, request.author)?;
+    let text = format!("Moved {} to {} (turn {})", alias, new_address, game_data.turn);
+    info!("{}", text);
+    Ok(Response::Reply(text))
+}
@@ -0,0 +1,93 @@
+use super::*;
+
+use std::io;
+use crate::model::*;
+
+fn game_data(game_name: &str, turn: i32) -> GameData {
+    GameData {
+        game_name: game_name.to_owned(),
+        nations: Vec::new(),
+        turn,
+        turn_timer: 1000,
+    }
+}
+
+fn address(db_conn: &DbConnection, alias: &str) -> String {
+    match db_conn.game_for_alias(alias).unwrap().state {
+        GameServerState::StartedState(started_state, _) => started_state.address,
+        state => panic!("expected a started game but got {:?}", state),
+    }
+}
+
+#[test]
+fn should_move_to_server_running_the_same_game() {
+    mock_conditional_server_connection!(Mock, |server_address| match server_address {
+        "move.old:1" => Ok(game_data("foogame", 5)),
+        "move.new:1" => Ok(game_data("foogame", 6)),
+        _ => Err(io::Error::from_raw_os_error(-1)),
+    });
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "move.old:1", 5);
+
+    let moved_game_data = move_server_helper::<Mock>(db_conn, "foo", "move.new:1", UserId(1)).unwrap();
+
+    assert_eq!(moved_game_data.turn, 6);
+    assert_eq!(address(db_conn, "foo"), "move.new:1");
+}
+
+#[test]
+fn should_not_move_to_a_different_game() {
+    mock_conditional_server_connection!(Mock, |server_address| match server_address {
+        "move.old:2" => Ok(game_data("foogame", 5)),
+        "move.new:2" => Ok(game_data("bargame", 5)),
+        _ => Err(io::Error::from_raw_os_error(-1)),
+    });
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "move.old:2", 5);
+
+    assert!(move_server_helper::<Mock>(db_conn, "foo", "move.new:2", UserId(1)).is_err());
+    assert_eq!(address(db_conn, "foo"), "move.old:2");
+}
+
+#[test]
+fn should_not_move_to_an_older_turn() {
+    mock_conditional_server_connection!(Mock, |server_address| match server_address {
+        "move.old:3" => Ok(game_data("foogame", 5)),
+        "move.new:3" => Ok(game_data("foogame", 4)),
+        _ => Err(io::Error::from_raw_os_error(-1)),
+    });
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "move.old:3", 5);
+
+    assert!(move_server_helper::<Mock>(db_conn, "foo", "move.new:3", UserId(1)).is_err());
+    assert_eq!(address(db_conn, "foo"), "move.old:3");
+}
+
+#[test]
+fn should_not_move_without_knowing_the_old_game() {
+    mock_conditional_server_connection!(Mock, |server_address| match server_address {
+        "move.new:4" => Ok(game_data("foogame", 5)),
+        _ => Err(io::Error::from_raw_os_error(-1)),
+    });
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "move.old:4", 5);
+
+    assert!(move_server_helper::<Mock>(db_conn, "foo", "move.new:4", UserId(1)).is_err());
+}
+
+#[test]
+fn should_not_move_to_an_archived_games_address() {
+    mock_conditional_server_connection!(Mock, |server_address| match server_address {
+        "move.old:5" => Ok(game_data("foogame", 5)),
+        "move.new:5" => Ok(game_data("foogame", 6)),
+        _ => Err(io::Error::from_raw_os_error(-1)),
+    });
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "move.old:5", 5);
+    db_conn.insert_test_game("bar", "move.new:5", 5);
+    db_conn.archive_game("bar").unwrap();
+
+    let err = move_server_helper::<Mock>(db_conn, "foo", "move.new:5", UserId(1)).unwrap_err();
+    assert_eq!(err.0, "Another game is already using move.new:5");
+    assert_eq!(address(db_conn, "foo"), "move.old:5");
+}
@@ -1,32 +1,66 @@
-use serenity::framework::standard::{Args, CommandError};
-use serenity::prelude::Context;
-use serenity::model::channel::Message;
+use serenity::framework::standard::CommandError;
 use serenity::model::id::UserId;
 
+use crate::commands::core::{CommandContext, Request, Response};
 use crate::db::*;
 
+#[cfg(test)]
+mod tests;
+
 fn notifications_helper(
     db_conn: &DbConnection,
     player_id: UserId,
     desired_turn_notifications: bool,
+    alias: Option<&str>,
 ) -> Result<(), CommandError> {
-    db_conn.set_turn_notifications(player_id, desired_turn_notifications)?;
+    match alias {
+        Some(alias) => db_conn.set_game_turn_notifications(alias, player_id, desired_turn_notifications)?,
+        None => db_conn.set_turn_notifications(player_id, desired_turn_notifications)?,
+    }
     Ok(())
 }
 
-pub fn notifications(
-    context: &mut Context,
-    message: &Message,
-    mut args: Args,
-) -> Result<(), CommandError> {
-    let desired_turn_notifications = args.single_quoted::<bool>()?;
-    let data = context.data.lock();
-    let db_conn = data.get::<DbConnectionKey>().ok_or("no db connection")?;
+pub fn notifications(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let desired_turn_notifications_str = request.required_arg("true or false")?;
+    let desired_turn_notifications = desired_turn_notifications_str
+        .parse::<bool>()
+        .map_err(|_| CommandError::from(format!("Expected true or false but got '{}'", desired_turn_notifications_str)))?;
+    // unlike most commands this doesn't default to the channel name, no alias means every game
+    let alias = request.arg().map(|alias| alias.to_lowercase());
 
-    notifications_helper(db_conn, message.author.id, desired_turn_notifications)?;
-    message.reply(&format!(
-        "Set turn notifications to {}",
-        desired_turn_notifications
-    ))?;
-    Ok(())
+    notifications_helper(
+        context.db_conn,
+        request.author,
+        desired_turn_notifications,
+        alias.as_ref().map(|alias| alias.as_str()),
+    )?;
+    let text = match alias {
+        Some(alias) => format!(
+            "Set turn notifications for {} to {}",
+            alias,
+            desired_turn_notifications
+        ),
+        None => format!(
+            "Set turn notifications to {}",
+            desired_turn_notifications
+        ),
+    };
+    Ok(Response::Reply(text))
+}
+
+pub fn notification_format(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let format = request.required_arg("\"summary\" or \"detailed\"")?.to_lowercase();
+    let detailed = match format.as_ref() {
+        "detailed" => true,
+        "summary" => false,
+        _ => return Err(CommandError::from("expected \"summary\" or \"detailed\"")),
+    };
+    let alias = request.alias()?;
+
+    context.db_conn.set_game_detailed_notifications(&alias, request.author, detailed)?;
+    Ok(Response::Reply(format!(
+        "Your turn notifications for {} will now be {}",
+        alias,
+        format
+    )))
 }
@@ -0,0 +1,67 @@
+use super::*;
+
+use crate::model::*;
+
+fn settings_for(db_conn: &DbConnection, alias: &str) -> NotificationSettings {
+    let mut players_nations = db_conn.players_with_nations_and_settings_for_game_alias(alias).unwrap();
+    assert_eq!(players_nations.len(), 1);
+    players_nations.remove(0).2
+}
+
+#[test]
+fn should_fall_back_to_global_settings() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.bar:3000", 5);
+    db_conn.register_test_player("foo", UserId(3), 5);
+
+    notifications_helper(db_conn, UserId(3), false, None).unwrap();
+
+    assert_eq!(
+        settings_for(db_conn, "foo"),
+        NotificationSettings {
+            turn_notifications: false,
+            reminder_thresholds_mins: None,
+            detailed: true,
+        }
+    );
+}
+
+#[test]
+fn should_override_global_settings_for_one_game() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.bar:3000", 5);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    db_conn.insert_test_game("bar", "bar.bar:3000", 5);
+    db_conn.register_test_player("bar", UserId(3), 6);
+
+    notifications_helper(db_conn, UserId(3), false, Some("foo")).unwrap();
+    db_conn.set_game_detailed_notifications("foo", UserId(3), false).unwrap();
+    db_conn.set_game_reminder_thresholds("foo", UserId(3), Some(&[30])).unwrap();
+    db_conn.set_reminder_thresholds(UserId(3), Some(&[12 * 60])).unwrap();
+
+    assert_eq!(
+        settings_for(db_conn, "foo"),
+        NotificationSettings {
+            turn_notifications: false,
+            reminder_thresholds_mins: Some(vec![30]),
+            detailed: false,
+        }
+    );
+    assert_eq!(
+        settings_for(db_conn, "bar"),
+        NotificationSettings {
+            turn_notifications: true,
+            reminder_thresholds_mins: Some(vec![12 * 60]),
+            detailed: true,
+        }
+    );
+}
+
+#[test]
+fn should_not_set_game_settings_when_not_registered() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.bar:3000", 5);
+    db_conn.register_test_player("foo", UserId(3), 5);
+
+    assert!(notifications_helper(db_conn, UserId(4), false, Some("foo")).is_err());
+}
@@ -0,0 +1,186 @@
+use serenity::framework::standard::CommandError;
+use serenity::model::id::UserId;
+use log::*;
+use url::Url;
+
+use crate::commands::admin::Admins;
+use crate::commands::core::{CommandContext, Request, Response};
+use crate::db::DbConnection;
+use crate::model::{Player, Webhook};
+use super::check_is_lobby_owner_or_admin;
+
+#[cfg(test)]
+mod tests;
+
+fn parse_webhook(url: &str, secret: Option<String>) -> Result<Webhook, CommandError> {
+    // the url is the credential for most webhooks, so don't repeat it back
+    match Url::parse(url) {
+        Ok(ref parsed) if (parsed.scheme() == "http" || parsed.scheme() == "https") && parsed.has_host() => {
+            Ok(Webhook {
+                url: url.to_owned(),
+                secret,
+            })
+        }
+        _ => Err(CommandError::from(
+            "That doesn't look like a webhook url, it needs to start with http:// or https://",
+        )),
+    }
+}
+
+/// Just the scheme and host. Discord and slack put the webhook's key in the path.
+fn mask_url(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(parsed) => match (parsed.host_str(), parsed.port()) {
+            (Some(host), Some(port)) => format!("{}://{}:{}/...", parsed.scheme(), host, port),
+            (Some(host), None) => format!("{}://{}/...", parsed.scheme(), host),
+            (None, _) => "a webhook".to_owned(),
+        },
+        Err(_) => "a webhook".to_owned(),
+    }
+}
+
+fn describe_webhook(webhook: Option<&Webhook>) -> String {
+    match webhook {
+        Some(&Webhook { ref url, secret: Some(_) }) => format!("{} (with a secret)", mask_url(url)),
+        Some(&Webhook { ref url, secret: None }) => mask_url(url),
+        None => "not set".to_owned(),
+    }
+}
+
+/// `off` or `<url> [secret]`
+fn webhook_from_args(request: &mut Request) -> Result<Option<Webhook>, CommandError> {
+    let first = request
+        .arg()
+        .ok_or_else(|| CommandError::from("Need a webhook url, or \"off\""))?;
+    if first.to_lowercase() == "off" {
+        return Ok(None);
+    }
+    let secret = request.arg();
+    request.no_more_args()?;
+    parse_webhook(&first, secret).map(Some)
+}
+
+/// Webhook urls and secrets shouldn't sit around in a public channel
+fn hide_webhook(context: &CommandContext, request: &Request) -> &'static str {
+    match (request.channel_id, request.message_id) {
+        (Some(channel_id), Some(message_id)) if !request.is_private => {
+            if context.discord.delete(channel_id, message_id).is_ok() {
+                "\nI deleted your message so the webhook isn't left in the channel"
+            } else {
+                "\nTIP: DM me commands with webhooks in them, and delete your message"
+            }
+        }
+        _ => "",
+    }
+}
+
+fn webhook_helper(db_conn: &DbConnection, user_id: UserId, webhook: Option<&Webhook>) -> Result<(), CommandError> {
+    db_conn.insert_player(&Player {
+        discord_user_id: user_id,
+        turn_notifications: true,
+    })?;
+    db_conn.set_player_webhook(user_id, webhook)?;
+    Ok(())
+}
+
+fn dms_helper(db_conn: &DbConnection, user_id: UserId, discord_dms: bool) -> Result<(), CommandError> {
+    db_conn.insert_player(&Player {
+        discord_user_id: user_id,
+        turn_notifications: true,
+    })?;
+    db_conn.set_player_discord_dms(user_id, discord_dms)?;
+    Ok(())
+}
+
+fn game_webhook_helper(
+    db_conn: &DbConnection,
+    admins: &Admins,
+    user_id: UserId,
+    alias: &str,
+    webhook: Option<&Webhook>,
+) -> Result<(), CommandError> {
+    let server = db_conn.game_for_alias(alias)?;
+    check_is_lobby_owner_or_admin(&server, user_id, admins)?;
+    db_conn.set_game_webhook(alias, webhook)?;
+    Ok(())
+}
+
+pub fn webhook(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let db_conn = context.db_conn;
+    if request.is_empty() {
+        let notifiers = db_conn.player_notifiers(request.author)?;
+        return Ok(Response::Reply(format!(
+            "Your webhook is {}, and discord DMs are {}",
+            describe_webhook(notifiers.webhook.as_ref()),
+            if notifiers.discord_dms { "on" } else { "off" },
+        )));
+    }
+
+    let webhook = webhook_from_args(request);
+    // hide the url even when it's rejected, it may still be someone's real webhook
+    let hidden = match webhook {
+        Ok(None) => "",
+        _ => hide_webhook(context, request),
+    };
+    let webhook = webhook?;
+    webhook_helper(db_conn, request.author, webhook.as_ref())?;
+    info!("{} set their webhook to {}", request.author, describe_webhook(webhook.as_ref()));
+    let text = match webhook {
+        Some(ref webhook) => format!(
+            "Your notifications will also be sent to {}{}",
+            describe_webhook(Some(webhook)),
+            hidden,
+        ),
+        None => "Your notifications won't be sent to a webhook any more".to_owned(),
+    };
+    Ok(Response::Reply(text))
+}
+
+pub fn dms(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let db_conn = context.db_conn;
+    let discord_dms = match request.arg().map(|arg| arg.to_lowercase()) {
+        Some(ref arg) if arg == "on" => true,
+        Some(ref arg) if arg == "off" => false,
+        _ => return Err(CommandError::from("Need to say whether DMs should be \"on\" or \"off\"")),
+    };
+    dms_helper(db_conn, request.author, discord_dms)?;
+    let text = if discord_dms {
+        "You'll get your notifications as discord DMs".to_owned()
+    } else {
+        match db_conn.player_notifiers(request.author)?.webhook {
+            Some(ref webhook) => format!(
+                "You won't get discord DMs any more, only notifications to {}",
+                describe_webhook(Some(webhook))
+            ),
+            None => "You won't get discord DMs any more. You have no webhook either, so you won't hear \
+                     about new turns at all until you set one with !webhook"
+                .to_owned(),
+        }
+    };
+    Ok(Response::Reply(text))
+}
+
+pub fn game_webhook(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let alias = request
+        .arg()
+        .ok_or_else(|| CommandError::from("Need the game's alias, then a webhook url or \"off\""))?
+        .to_lowercase();
+    let webhook = webhook_from_args(request);
+    let hidden = match webhook {
+        Ok(None) => "",
+        _ => hide_webhook(context, request),
+    };
+    let webhook = webhook?;
+    game_webhook_helper(context.db_conn, context.admins, request.author, &alias, webhook.as_ref())?;
+    info!("{} set the webhook for {} to {}", request.author, alias, describe_webhook(webhook.as_ref()));
+    let text = match webhook {
+        Some(ref webhook) => format!(
+            "New turns in {} will also be sent to {}{}",
+            alias,
+            describe_webhook(Some(webhook)),
+            hidden,
+        ),
+        None => format!("New turns in {} won't be sent to a webhook any more", alias),
+    };
+    Ok(Response::Reply(text))
+}
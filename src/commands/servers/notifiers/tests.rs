@@ -0,0 +1,181 @@
+use super::*;
+
+use crate::discord::{FakeDiscord, Sent};
+use serenity::model::id::{ChannelId, MessageId};
+use crate::model::*;
+
+fn webhook(secret: Option<&str>) -> Webhook {
+    Webhook {
+        url: "https://example.com/hook".to_owned(),
+        secret: secret.map(|secret| secret.to_owned()),
+    }
+}
+
+#[test]
+fn should_only_accept_http_webhook_urls() {
+    assert!(parse_webhook("ftp://example.com", None).is_err());
+    assert!(parse_webhook("example.com", None).is_err());
+    assert_eq!(
+        parse_webhook("https://example.com/hook", Some("s3cret".to_owned())).unwrap(),
+        webhook(Some("s3cret"))
+    );
+    assert!(parse_webhook("http://localhost:8000", None).is_ok());
+}
+
+#[test]
+fn should_not_show_webhook_secrets() {
+    assert_eq!(describe_webhook(None), "not set");
+    assert_eq!(describe_webhook(Some(&webhook(None))), "https://example.com/...");
+    let described = describe_webhook(Some(&webhook(Some("s3cret"))));
+    assert_eq!(described, "https://example.com/... (with a secret)");
+    assert!(!described.contains("s3cret"));
+}
+
+#[test]
+fn should_only_show_the_scheme_and_host_of_a_webhook_url() {
+    let discord_hook = Webhook {
+        url: "https://discordapp.com/api/webhooks/1234/t0k3n?wait=true".to_owned(),
+        secret: None,
+    };
+    assert_eq!(describe_webhook(Some(&discord_hook)), "https://discordapp.com/...");
+    assert_eq!(mask_url("http://localhost:8000/hook/t0k3n"), "http://localhost:8000/...");
+    assert_eq!(mask_url("not a url"), "a webhook");
+}
+
+#[test]
+fn should_not_repeat_a_rejected_webhook_url() {
+    let err = parse_webhook("discordapp.com/api/webhooks/1234/t0k3n", None).unwrap_err();
+    assert!(!err.0.contains("t0k3n"));
+}
+
+#[test]
+fn should_set_and_clear_a_player_webhook() {
+    let db_conn = &DbConnection::test();
+    assert_eq!(db_conn.player_notifiers(UserId(1)).unwrap(), PlayerNotifiers::default());
+
+    webhook_helper(db_conn, UserId(1), Some(&webhook(Some("s3cret")))).unwrap();
+    let notifiers = db_conn.player_notifiers(UserId(1)).unwrap();
+    assert_eq!(notifiers.webhook, Some(webhook(Some("s3cret"))));
+    assert!(notifiers.discord_dms);
+
+    webhook_helper(db_conn, UserId(1), None).unwrap();
+    assert_eq!(db_conn.player_notifiers(UserId(1)).unwrap().webhook, None);
+}
+
+#[test]
+fn should_turn_discord_dms_off_and_on() {
+    let db_conn = &DbConnection::test();
+    webhook_helper(db_conn, UserId(1), Some(&webhook(None))).unwrap();
+
+    dms_helper(db_conn, UserId(1), false).unwrap();
+    let notifiers = db_conn.player_notifiers(UserId(1)).unwrap();
+    assert!(!notifiers.discord_dms);
+    assert_eq!(notifiers.webhook, Some(webhook(None)));
+
+    dms_helper(db_conn, UserId(1), true).unwrap();
+    assert!(db_conn.player_notifiers(UserId(1)).unwrap().discord_dms);
+}
+
+#[test]
+fn should_let_the_owner_set_a_game_webhook() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(7), 4);
+    let admins = Admins(vec![]);
+
+    game_webhook_helper(db_conn, &admins, UserId(7), "foo", Some(&webhook(None))).unwrap();
+    assert_eq!(db_conn.game_webhook("foo").unwrap(), Some(webhook(None)));
+
+    game_webhook_helper(db_conn, &admins, UserId(7), "foo", None).unwrap();
+    assert_eq!(db_conn.game_webhook("foo").unwrap(), None);
+}
+
+#[test]
+fn should_let_an_admin_set_a_game_webhook() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(7), 4);
+    let admins = Admins(vec![UserId(8)]);
+
+    game_webhook_helper(db_conn, &admins, UserId(8), "foo", Some(&webhook(Some("s3cret")))).unwrap();
+    assert_eq!(db_conn.game_webhook("foo").unwrap(), Some(webhook(Some("s3cret"))));
+}
+
+#[test]
+fn should_not_set_a_webhook_for_a_missing_game() {
+    let db_conn = &DbConnection::test();
+    let admins = Admins(vec![UserId(8)]);
+    assert!(game_webhook_helper(db_conn, &admins, UserId(8), "foo", Some(&webhook(None))).is_err());
+}
+
+#[test]
+fn should_delete_a_webhook_sent_in_a_channel() {
+    let db_conn = &DbConnection::test();
+    let admins = Admins(vec![]);
+    let discord = FakeDiscord::new();
+    let context = CommandContext {
+        db_conn,
+        admins: &admins,
+        discord: &discord,
+    };
+    let args = vec!["https://example.com/hook".to_owned()];
+    let mut request = Request::new(UserId(3), Some("general".to_owned()), args.clone());
+    request.channel_id = Some(ChannelId(1));
+    request.message_id = Some(MessageId(10));
+
+    let response = super::webhook(&context, &mut request).unwrap();
+
+    assert_eq!(
+        response,
+        Response::Reply(
+            "Your notifications will also be sent to https://example.com/...\n\
+             I deleted your message so the webhook isn't left in the channel"
+                .to_owned()
+        )
+    );
+    assert_eq!(discord.sent(), vec![Sent::Deleted(ChannelId(1), MessageId(10))]);
+
+    // nothing to hide in a DM
+    let mut request = Request::new(UserId(3), None, args);
+    request.channel_id = Some(ChannelId(2));
+    request.message_id = Some(MessageId(11));
+    request.is_private = true;
+    super::webhook(&context, &mut request).unwrap();
+    assert_eq!(discord.sent().len(), 1);
+}
+
+#[test]
+fn should_delete_a_rejected_webhook_sent_in_a_channel() {
+    let db_conn = &DbConnection::test();
+    let admins = Admins(vec![]);
+    let discord = FakeDiscord::new();
+    let context = CommandContext {
+        db_conn,
+        admins: &admins,
+        discord: &discord,
+    };
+    let args = vec!["discordapp.com/api/webhooks/1234/t0k3n".to_owned()];
+    let mut request = Request::new(UserId(3), Some("general".to_owned()), args);
+    request.channel_id = Some(ChannelId(1));
+    request.message_id = Some(MessageId(10));
+
+    assert!(super::webhook(&context, &mut request).is_err());
+    assert_eq!(discord.sent(), vec![Sent::Deleted(ChannelId(1), MessageId(10))]);
+    assert_eq!(db_conn.player_notifiers(UserId(3)).unwrap().webhook, None);
+}
+
+#[test]
+fn should_not_delete_a_message_turning_the_webhook_off() {
+    let db_conn = &DbConnection::test();
+    let admins = Admins(vec![]);
+    let discord = FakeDiscord::new();
+    let context = CommandContext {
+        db_conn,
+        admins: &admins,
+        discord: &discord,
+    };
+    let mut request = Request::new(UserId(3), Some("general".to_owned()), vec!["off".to_owned()]);
+    request.channel_id = Some(ChannelId(1));
+    request.message_id = Some(MessageId(10));
+
+    super::webhook(&context, &mut request).unwrap();
+    assert!(discord.sent().is_empty());
+}
@@ -0,0 +1,41 @@
+use serenity::framework::standard::CommandError;
+use serenity::model::id::{ChannelId, UserId};
+
+use crate::commands::admin::Admins;
+use crate::commands::core::{CommandContext, Request, Response};
+use crate::db::DbConnection;
+use super::check_is_lobby_owner_or_admin;
+
+#[cfg(test)]
+mod tests;
+
+fn notify_channel_helper(
+    db_conn: &DbConnection,
+    admins: &Admins,
+    user_id: UserId,
+    alias: &str,
+    channel_id: Option<ChannelId>,
+) -> Result<(), CommandError> {
+    // fail early with a nice message if the game doesn't exist
+    let server = db_conn.game_for_alias(&alias)?;
+    check_is_lobby_owner_or_admin(&server, user_id, admins)?;
+    db_conn.set_notification_channel(&alias, channel_id)?;
+    Ok(())
+}
+
+pub fn notify_channel(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    request.no_more_args()?;
+    let channel_id = request.channel()?;
+
+    notify_channel_helper(context.db_conn, context.admins, request.author, &alias, Some(channel_id))?;
+    Ok(Response::Reply(format!("New turns in {} will be announced in this channel", alias)))
+}
+
+pub fn notify_channel_remove(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    notify_channel_helper(context.db_conn, context.admins, request.author, &alias, None)?;
+    Ok(Response::Reply(format!("New turns in {} will no longer be announced in a channel", alias)))
+}
@@ -0,0 +1,58 @@
+use super::*;
+
+use crate::model::*;
+
+#[test]
+fn should_set_and_clear_the_notification_channel() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(7), 4);
+    let admins = Admins(vec![]);
+
+    notify_channel_helper(db_conn, &admins, UserId(7), "foo", Some(ChannelId(3))).unwrap();
+    assert_eq!(db_conn.notification_channel_for_game_alias("foo").unwrap(), Some(ChannelId(3)));
+
+    notify_channel_helper(db_conn, &admins, UserId(7), "foo", None).unwrap();
+    assert_eq!(db_conn.notification_channel_for_game_alias("foo").unwrap(), None);
+}
+
+#[test]
+fn should_only_let_the_owner_or_an_admin_set_the_notification_channel() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(7), 4);
+    let admins = Admins(vec![UserId(8)]);
+
+    let err = notify_channel_helper(db_conn, &admins, UserId(2), "foo", Some(ChannelId(3))).unwrap_err();
+    assert_eq!(err.0, "only the owner of foo or a bot admin can do that");
+    assert_eq!(db_conn.notification_channel_for_game_alias("foo").unwrap(), None);
+
+    notify_channel_helper(db_conn, &admins, UserId(8), "foo", Some(ChannelId(3))).unwrap();
+    assert_eq!(db_conn.notification_channel_for_game_alias("foo").unwrap(), Some(ChannelId(3)));
+    assert!(notify_channel_helper(db_conn, &admins, UserId(2), "foo", None).is_err());
+}
+
+#[test]
+fn should_keep_the_owner_once_the_game_has_started() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(7), 4);
+    db_conn
+        .insert_started_state(
+            "foo",
+            &StartedState {
+                address: "foo.bar:3000".to_owned(),
+                last_seen_turn: 1,
+            },
+        )
+        .unwrap();
+    let admins = Admins(vec![]);
+
+    notify_channel_helper(db_conn, &admins, UserId(7), "foo", Some(ChannelId(3))).unwrap();
+    assert_eq!(db_conn.notification_channel_for_game_alias("foo").unwrap(), Some(ChannelId(3)));
+    assert!(notify_channel_helper(db_conn, &admins, UserId(2), "foo", None).is_err());
+}
+
+#[test]
+fn should_not_set_the_notification_channel_for_a_missing_game() {
+    let db_conn = &DbConnection::test();
+    let admins = Admins(vec![UserId(8)]);
+    assert!(notify_channel_helper(db_conn, &admins, UserId(8), "foo", Some(ChannelId(3))).is_err());
+}
@@ -0,0 +1,106 @@
+use serenity::framework::standard::CommandError;
+use serenity::model::id::UserId;
+use serenity::model::misc::Mentionable;
+use log::*;
+
+use crate::commands::core::{CommandContext, Request, Response};
+use crate::db::DbConnection;
+use crate::notifier::{notify_player, Notification};
+use super::check_is_lobby_owner;
+use super::waitlist::{notify_promotions, promote_from_waitlist, Promotion};
+
+#[cfg(test)]
+mod tests;
+
+fn kick_helper(
+    db_conn: &DbConnection,
+    alias: &str,
+    owner: UserId,
+    kicked: UserId,
+) -> Result<Vec<Promotion>, CommandError> {
+    let server = db_conn.game_for_alias(alias)?;
+    check_is_lobby_owner(&server, owner)?;
+
+    let registered = db_conn
+        .players_with_nations_for_game_alias(alias)?
+        .iter()
+        .any(|&(ref player, _)| player.discord_user_id == kicked);
+    let waitlisted = db_conn.remove_waitlist_entry(alias, kicked)?;
+    if !registered && !waitlisted {
+        return Err(CommandError::from(format!("That user is not registered in {}", alias)));
+    }
+
+    db_conn.remove_player_from_game(alias, kicked)?;
+    db_conn.remove_open_substitution_requests(alias, kicked)?;
+    db_conn.touch_lobby(alias)?;
+    promote_from_waitlist(db_conn, alias)
+}
+
+fn transfer_owner_helper(
+    db_conn: &DbConnection,
+    alias: &str,
+    owner: UserId,
+    new_owner: UserId,
+) -> Result<(), CommandError> {
+    let server = db_conn.game_for_alias(alias)?;
+    check_is_lobby_owner(&server, owner)?;
+    if new_owner == owner {
+        return Err(CommandError::from(format!("You already own {}", alias)));
+    }
+    db_conn.set_lobby_owner(alias, new_owner)?;
+    Ok(())
+}
+
+// Only for messages sent after the change is saved, which shouldn't fail because of a name
+fn user_name_or_mention(context: &CommandContext, user_id: UserId) -> String {
+    context.discord.user_name(user_id).unwrap_or_else(|_| user_id.mention())
+}
+
+pub fn kick(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let kicked = request.user()?;
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    let promotions = kick_helper(context.db_conn, &alias, request.author, kicked)?;
+    info!("{} kicked {} from {}", request.author, kicked, alias);
+
+    let text = format!("Removed {} from {}", user_name_or_mention(context, kicked), alias);
+    let notification = Notification::for_player(
+        kicked,
+        &alias,
+        None,
+        "kicked",
+        format!("You have been removed from {} by its owner", alias),
+    );
+    if let Err(err) = notify_player(context.db_conn, context.discord, kicked, &notification) {
+        error!("error telling {} they were kicked from {}: {}", kicked, alias, err);
+    }
+    notify_promotions(context.db_conn, context.discord, &alias, &promotions);
+    Ok(Response::Reply(text))
+}
+
+pub fn transfer_owner(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let new_owner = request.user()?;
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    transfer_owner_helper(context.db_conn, &alias, request.author, new_owner)?;
+    info!("{} transferred {} to {}", request.author, alias, new_owner);
+
+    let text = format!("{} is now the owner of {}", user_name_or_mention(context, new_owner), alias);
+    let notification = Notification::for_player(
+        new_owner,
+        &alias,
+        None,
+        "owner_transfer",
+        format!(
+            "{} has made you the owner of {}",
+            user_name_or_mention(context, request.author),
+            alias
+        ),
+    );
+    if let Err(err) = notify_player(context.db_conn, context.discord, new_owner, &notification) {
+        error!("error telling {} they now own {}: {}", new_owner, alias, err);
+    }
+    Ok(Response::Reply(text))
+}
@@ -0,0 +1,184 @@
+use super::*;
+
+use crate::commands::admin::Admins;
+use crate::discord::FakeDiscord;
+use crate::model::*;
+
+fn owner(db_conn: &DbConnection, alias: &str) -> UserId {
+    match db_conn.game_for_alias(alias).unwrap().state {
+        GameServerState::Lobby(lobby_state) => lobby_state.owner,
+        GameServerState::StartedState(_, lobby_state) => lobby_state.unwrap().owner,
+    }
+}
+
+#[test]
+fn should_create_two_lobbies_with_the_same_owner() {
+    let db_conn = &DbConnection::test();
+    // make sure the owner's player id and discord id differ
+    db_conn.insert_test_player(UserId(2));
+    db_conn.insert_test_lobby("foo", UserId(4), 5);
+    db_conn.insert_test_lobby("bar", UserId(4), 6);
+
+    let mut lobbies = db_conn
+        .select_lobbies(false)
+        .unwrap()
+        .into_iter()
+        .map(|(server, _)| server)
+        .collect::<Vec<_>>();
+    lobbies.sort_by(|a, b| a.alias.cmp(&b.alias));
+    assert_eq!(lobbies.len(), 2);
+    for (lobby, player_count) in lobbies.iter().zip([6, 5].iter()) {
+        match lobby.state {
+            GameServerState::Lobby(ref lobby_state) => {
+                assert_eq!(lobby_state.owner, UserId(4));
+                assert_eq!(lobby_state.player_count, *player_count);
+            }
+            ref state => panic!("expected a lobby but got {:?}", state),
+        }
+    }
+    assert!(db_conn
+        .retrieve_all_servers()
+        .unwrap()
+        .iter()
+        .all(|server| match server.state {
+            GameServerState::Lobby(ref lobby_state) => lobby_state.owner == UserId(4),
+            _ => false,
+        }));
+}
+
+#[test]
+fn should_kick_player_and_promote_waitlist() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 1);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    db_conn.insert_test_player(UserId(4));
+    db_conn.insert_waitlist_entry("foo", UserId(4), Some(6)).unwrap();
+
+    let promotions = kick_helper(db_conn, "foo", UserId(1), UserId(3)).unwrap();
+
+    assert_eq!(promotions, vec![Promotion { player: UserId(4), registered_nation_id: Some(6) }]);
+    let registrations = db_conn
+        .players_with_nations_for_game_alias("foo")
+        .unwrap()
+        .into_iter()
+        .map(|(player, nation_id)| (player.discord_user_id, nation_id))
+        .collect::<Vec<_>>();
+    assert_eq!(registrations, vec![(UserId(4), 6)]);
+}
+
+#[test]
+fn should_only_let_the_owner_kick() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    db_conn.register_test_player("foo", UserId(4), 6);
+
+    assert!(kick_helper(db_conn, "foo", UserId(4), UserId(3)).is_err());
+    assert!(kick_helper(db_conn, "foo", UserId(1), UserId(7)).is_err());
+    assert_eq!(db_conn.players_with_nations_for_game_alias("foo").unwrap().len(), 2);
+}
+
+#[test]
+fn should_transfer_ownership() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+
+    assert!(transfer_owner_helper(db_conn, "foo", UserId(2), UserId(2)).is_err());
+    assert!(transfer_owner_helper(db_conn, "foo", UserId(1), UserId(1)).is_err());
+    transfer_owner_helper(db_conn, "foo", UserId(1), UserId(2)).unwrap();
+
+    assert_eq!(owner(db_conn, "foo"), UserId(2));
+    assert!(transfer_owner_helper(db_conn, "foo", UserId(1), UserId(3)).is_err());
+}
+
+#[test]
+fn should_tell_the_kicked_player_and_whoever_was_promoted() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 1);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    db_conn.insert_test_player(UserId(4));
+    db_conn.insert_waitlist_entry("foo", UserId(4), None).unwrap();
+    let admins = Admins(vec![]);
+    let discord = FakeDiscord::new()
+        .with_user(UserId(3), "three")
+        .with_user(UserId(4), "four");
+    let context = CommandContext {
+        db_conn,
+        admins: &admins,
+        discord: &discord,
+    };
+    let mut request = Request::new(UserId(1), None, vec!["<@3>".to_owned(), "foo".to_owned()]);
+
+    assert_eq!(
+        kick(&context, &mut request).unwrap(),
+        Response::Reply("Removed @three from foo".to_owned())
+    );
+    assert_eq!(discord.dms_to(UserId(3)), vec!["You have been removed from foo by its owner"]);
+    assert_eq!(
+        discord.dms_to(UserId(4)),
+        vec!["A slot opened up in foo. It is reserved for you until you pick a nation with `!register <nation> foo`"]
+    );
+}
+
+#[test]
+fn should_not_message_anyone_when_a_non_owner_kicks() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    let admins = Admins(vec![]);
+    let discord = FakeDiscord::new().with_user(UserId(3), "three");
+    let context = CommandContext {
+        db_conn,
+        admins: &admins,
+        discord: &discord,
+    };
+    let mut request = Request::new(UserId(2), Some("foo".to_owned()), vec!["3".to_owned()]);
+
+    let err = kick(&context, &mut request).unwrap_err();
+    assert_eq!(err.0, "only the owner of foo can do that");
+    assert!(discord.sent().is_empty());
+}
+
+#[test]
+fn should_still_promote_and_reply_when_the_kicked_player_cant_be_messaged() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 1);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    db_conn.insert_test_player(UserId(4));
+    db_conn.insert_waitlist_entry("foo", UserId(4), None).unwrap();
+    let admins = Admins(vec![]);
+    // the fake can't look up or DM the kicked player
+    let discord = FakeDiscord::new().with_user(UserId(4), "four");
+    let context = CommandContext {
+        db_conn,
+        admins: &admins,
+        discord: &discord,
+    };
+    let mut request = Request::new(UserId(1), None, vec!["<@3>".to_owned(), "foo".to_owned()]);
+
+    assert_eq!(
+        kick(&context, &mut request).unwrap(),
+        Response::Reply("Removed <@3> from foo".to_owned())
+    );
+    assert_eq!(discord.dms_to(UserId(4)).len(), 1);
+}
+
+#[test]
+fn should_transfer_ownership_when_the_new_owner_cant_be_messaged() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+    let admins = Admins(vec![]);
+    let discord = FakeDiscord::new();
+    let context = CommandContext {
+        db_conn,
+        admins: &admins,
+        discord: &discord,
+    };
+    let mut request = Request::new(UserId(1), None, vec!["<@2>".to_owned(), "foo".to_owned()]);
+
+    assert_eq!(
+        transfer_owner(&context, &mut request).unwrap(),
+        Response::Reply("<@2> is now the owner of foo".to_owned())
+    );
+    assert_eq!(owner(db_conn, "foo"), UserId(2));
+}
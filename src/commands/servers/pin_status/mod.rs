@@ -0,0 +1,90 @@
+use serenity::framework::standard::CommandError;
+use serenity::model::id::{ChannelId, MessageId};
+use failure::{err_msg, Error};
+use chrono::Utc;
+use log::*;
+
+use crate::commands::core::{CommandContext, Embed, Request, Response};
+use crate::db::DbConnection;
+use crate::server::ServerConnection;
+use super::details::details_helper;
+use crate::discord::Discord;
+
+#[cfg(test)]
+mod tests;
+
+fn pinned_status_embed<C: ServerConnection>(
+    db_conn: &DbConnection,
+    discord: &dyn Discord,
+    alias: &str,
+) -> Result<Embed, CommandError> {
+    let embed = details_helper::<C>(db_conn, discord, alias)?;
+    let last_updated = Utc::now().format("%Y-%m-%d %H:%M UTC");
+    Ok(embed.footer(format!("Last updated {}", last_updated)))
+}
+
+pub(crate) fn update_pinned_statuses<C: ServerConnection>(
+    db_conn: &DbConnection,
+    discord: &dyn Discord,
+) -> Result<(), Error> {
+    for (alias, channel_id, message_id) in db_conn.pinned_statuses()? {
+        if let Err(err) = update_pinned_status::<C>(db_conn, discord, &alias, channel_id, message_id) {
+            error!("Updating pinned status for {} failed with: {}", alias, err);
+        }
+    }
+    Ok(())
+}
+
+fn update_pinned_status<C: ServerConnection>(
+    db_conn: &DbConnection,
+    discord: &dyn Discord,
+    alias: &str,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> Result<(), Error> {
+    let embed = pinned_status_embed::<C>(db_conn, discord, alias).map_err(|e| err_msg(e.0))?;
+    if let Err(edit_err) = discord.edit_embed(channel_id, message_id, &embed) {
+        // Either discord is having trouble or somebody deleted the message.
+        // Only repost in the second case, so that an outage doesn't spam the channel.
+        if discord.was_deleted(channel_id, message_id) {
+            info!("pinned status for {} was deleted, reposting", alias);
+            let new_message_id = discord.pin_embed(channel_id, &embed)?;
+            db_conn.set_pinned_status(alias, channel_id, new_message_id)?;
+        } else {
+            return Err(edit_err);
+        }
+    }
+    Ok(())
+}
+
+pub fn pin_status<C: ServerConnection>(
+    context: &CommandContext,
+    request: &mut Request,
+) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    request.no_more_args()?;
+    let channel_id = request.channel()?;
+
+    let db_conn = context.db_conn;
+    let embed = pinned_status_embed::<C>(db_conn, context.discord, &alias)?;
+    if let Some((old_channel_id, old_message_id)) = db_conn.pinned_status_for_game_alias(&alias)? {
+        // the old message won't be kept up to date any more
+        let _ = context.discord.unpin(old_channel_id, old_message_id);
+    }
+    let message_id = context.discord.pin_embed(channel_id, &embed)?;
+    db_conn.set_pinned_status(&alias, channel_id, message_id)?;
+    Ok(Response::Nothing)
+}
+
+pub fn unpin_status(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    let (channel_id, message_id) = context
+        .db_conn
+        .pinned_status_for_game_alias(&alias)?
+        .ok_or_else(|| CommandError::from(format!("{} has no pinned status", alias)))?;
+    let _ = context.discord.unpin(channel_id, message_id);
+    context.db_conn.remove_pinned_status(&alias)?;
+    Ok(Response::Reply(format!("The pinned status for {} will no longer be updated", alias)))
+}
@@ -0,0 +1,92 @@
+use super::*;
+
+use crate::discord::{FakeDiscord, PinnedMessages, Sent};
+use crate::model::GameData;
+use std::io;
+
+fn game_data() -> io::Result<GameData> {
+    Ok(GameData {
+        game_name: "foo".to_owned(),
+        nations: vec![],
+        turn: 5,
+        turn_timer: 60 * 60 * 1000,
+    })
+}
+
+#[test]
+fn should_insert_select_and_remove_pinned_statuses() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "pinned.status:3000", 5);
+    db_conn.insert_test_game("bar", "other.pinned.status:3000", 5);
+
+    db_conn.set_pinned_status("foo", ChannelId(1), MessageId(10)).unwrap();
+    db_conn.set_pinned_status("bar", ChannelId(2), MessageId(20)).unwrap();
+    // pinning again replaces the old one
+    db_conn.set_pinned_status("foo", ChannelId(1), MessageId(11)).unwrap();
+    assert!(db_conn.set_pinned_status("baz", ChannelId(1), MessageId(12)).is_err());
+
+    assert_eq!(
+        db_conn.pinned_status_for_game_alias("foo").unwrap(),
+        Some((ChannelId(1), MessageId(11)))
+    );
+    let mut pinned_statuses = db_conn.pinned_statuses().unwrap();
+    pinned_statuses.sort();
+    assert_eq!(
+        pinned_statuses,
+        vec![
+            ("bar".to_owned(), ChannelId(2), MessageId(20)),
+            ("foo".to_owned(), ChannelId(1), MessageId(11)),
+        ]
+    );
+
+    db_conn.remove_pinned_status("foo").unwrap();
+    assert_eq!(db_conn.pinned_status_for_game_alias("foo").unwrap(), None);
+    assert_eq!(db_conn.pinned_statuses().unwrap().len(), 1);
+}
+
+#[test]
+fn should_edit_the_pinned_status_in_place() {
+    mock_server_connection!(Mock, game_data());
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "pinned.status.edit:3000", 5);
+    let discord = FakeDiscord::new();
+    let message_id = discord.pin_embed(ChannelId(1), &Embed::default().title("old")).unwrap();
+    db_conn.set_pinned_status("foo", ChannelId(1), message_id).unwrap();
+
+    update_pinned_statuses::<Mock>(db_conn, &discord).unwrap();
+
+    match discord.sent().last() {
+        Some(&Sent::Edited(ChannelId(1), edited_id, ref embed)) => {
+            assert_eq!(edited_id, message_id);
+            assert!(embed.footer.as_ref().unwrap().starts_with("Last updated"));
+        }
+        sent => panic!("expected an edit but got {:?}", sent),
+    }
+    assert_eq!(
+        db_conn.pinned_status_for_game_alias("foo").unwrap(),
+        Some((ChannelId(1), message_id))
+    );
+}
+
+#[test]
+fn should_repost_a_deleted_pinned_status() {
+    mock_server_connection!(Mock, game_data());
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "pinned.status.repost:3000", 5);
+    let discord = FakeDiscord::new();
+    let message_id = discord.pin_embed(ChannelId(1), &Embed::default().title("old")).unwrap();
+    db_conn.set_pinned_status("foo", ChannelId(1), message_id).unwrap();
+    discord.delete_message(message_id);
+
+    update_pinned_statuses::<Mock>(db_conn, &discord).unwrap();
+
+    let new_message_id = match discord.sent().last() {
+        Some(&Sent::Pinned(ChannelId(1), new_message_id, _)) => new_message_id,
+        sent => panic!("expected a repost but got {:?}", sent),
+    };
+    assert_ne!(new_message_id, message_id);
+    assert_eq!(
+        db_conn.pinned_status_for_game_alias("foo").unwrap(),
+        Some((ChannelId(1), new_message_id))
+    );
+}
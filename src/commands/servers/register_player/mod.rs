@@ -1,15 +1,15 @@
-use serenity::framework::standard::{Args, CommandError};
-use serenity::prelude::Context;
-use serenity::model::channel::Message;
+use serenity::framework::standard::CommandError;
 use serenity::model::id::UserId;
 use log::*;
 
 use crate::server::ServerConnection;
 use crate::model::{GameServerState, Player};
 use crate::model::enums::*;
-use crate::db::{DbConnection, DbConnectionKey};
+use crate::db::DbConnection;
 use crate::model::Nation as StartedServerNation;
-use super::alias_from_arg_or_channel_name;
+use crate::commands::core::{CommandContext, Request, Response};
+use crate::discord::Discord;
+use super::waitlist::{join_waitlist_helper, lobby_slots_lock, taken_lobby_slots};
 use either::Either;
 
 fn get_nation_for_started_server(
@@ -131,19 +131,38 @@ fn register_player_helper<C: ServerConnection>(
     arg_nation: Either<&str, u32>,
     alias: &str,
     db_conn: &DbConnection,
-    message: &Message,
-) -> Result<(), CommandError> {
+    discord: &dyn Discord,
+) -> Result<String, CommandError> {
     let server = db_conn.game_for_alias(&alias).map_err(CommandError::from)?;
 
     match server.state {
         GameServerState::Lobby(lobby_state) => {
+            if lobby_state.status != LobbyStatus::Open {
+                return Err(CommandError::from(format!(
+                    "{} is {} and not taking new players",
+                    alias,
+                    lobby_state.status
+                )));
+            }
+            // held until the registration is in, so nobody else can take the slot meanwhile
+            let lock = lobby_slots_lock(&alias);
+            let _slots = lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
             let players_nations = db_conn.players_with_nations_for_game_alias(&alias)?;
-            if players_nations.len() as i32 >= lobby_state.player_count {
-                return Err(CommandError::from("lobby already full"));
-            };
-
+            let waitlist = db_conn.waitlist_for_game_alias(&alias)?;
             let nation = get_nation_for_lobby(arg_nation, lobby_state.era)?;
 
+            if taken_lobby_slots(players_nations.len(), &waitlist, Some(user_id)) >= lobby_state.player_count {
+                let position = join_waitlist_helper(db_conn, &alias, &lobby_state, user_id, Some(nation.id))?;
+                return Ok(format!(
+                    "lobby already full, adding {} to the waitlist at number {} with preferred nation {} {} ({})",
+                    discord.user_name(user_id)?,
+                    position,
+                    nation.era,
+                    nation.name,
+                    nation.id,
+                ));
+            };
+
            if players_nations
                 .iter()
                 .any(|&(_, player_nation_id)| {
@@ -163,14 +182,16 @@ fn register_player_helper<C: ServerConnection>(
             db_conn
                 .insert_server_player(&server.alias, user_id, nation.id)
                 .map_err(CommandError::from)?;
-            message.reply(&format!(
+            // uses up their reserved slot if they were promoted from the waitlist
+            db_conn.remove_waitlist_entry(&server.alias, user_id)?;
+            db_conn.touch_lobby(&server.alias)?;
+            Ok(format!(
                 "registering {} {} ({}) for {}",
                 nation.era,
                 nation.name,
                 nation.id,
-                user_id.to_user()?
-            ))?;
-            Ok(())
+                discord.user_name(user_id)?
+            ))
         }
         GameServerState::StartedState(started_state, _) => {
             let data = C::get_game_data(&started_state.address)?;
@@ -191,62 +212,51 @@ fn register_player_helper<C: ServerConnection>(
             db_conn
                 .insert_server_player(&server.alias, user_id, nation.id as u32)
                 .map_err(CommandError::from)?;
-            let text = format!(
+            Ok(format!(
                 "registering nation {} ({}) for user {}",
                 nation.name,
                 nation.id,
-                message.author
-            );
-            let _ = message.reply(&text);
-            Ok(())
+                discord.user_name(user_id)?
+            ))
         }
     }
 }
 
 pub fn register_player_id<C: ServerConnection>(
-    context: &mut Context,
-    message: &Message,
-    mut args: Args,
-) -> Result<(), CommandError> {
-    let arg_nation_id: u32 = args.single_quoted::<u32>()?;
-    let alias = alias_from_arg_or_channel_name(&mut args, &message)?;
+    context: &CommandContext,
+    request: &mut Request,
+) -> Result<Response, CommandError> {
+    let arg_nation_id = request.required_arg("a nation id")?;
+    let arg_nation_id = arg_nation_id
+        .parse::<u32>()
+        .map_err(|_| CommandError::from(format!("'{}' isn't a nation id", arg_nation_id)))?;
+    let alias = request.alias()?;
+    request.no_more_args()?;
 
-    let data = context.data.lock();
-    let db_conn = data.get::<DbConnectionKey>().ok_or("no db connection")?;
-
-    register_player_helper::<C>(
-        message.author.id,
+    let text = register_player_helper::<C>(
+        request.author,
         Either::Right(arg_nation_id),
         &alias,
-        db_conn,
-        message,
+        context.db_conn,
+        context.discord,
     )?;
-    Ok(())
+    Ok(Response::Reply(text))
 }
 
 pub fn register_player<C: ServerConnection>(
-    context: &mut Context,
-    message: &Message,
-    mut args: Args,
-) -> Result<(), CommandError> {
-    let arg_nation_name: String = args.single_quoted::<String>()?.to_lowercase();
-    let alias = alias_from_arg_or_channel_name(&mut args, &message)?;
-// FIXME: no idea why this isn't working
-//    if args.len() != 0 {
-//        return Err(CommandError::from(
-//            "Too many arguments. TIP: spaces in arguments need to be quoted \"like this\"",
-//        ));
-//    }
-
-    let data = context.data.lock();
-    let db_conn = data.get::<DbConnectionKey>().ok_or("no db connection")?;
+    context: &CommandContext,
+    request: &mut Request,
+) -> Result<Response, CommandError> {
+    let arg_nation_name = request.required_arg("a nation name")?.to_lowercase();
+    let alias = request.alias()?;
+    request.no_more_args()?;
 
-    register_player_helper::<C>(
-        message.author.id,
+    let text = register_player_helper::<C>(
+        request.author,
         Either::Left(&arg_nation_name),
         &alias,
-        db_conn,
-        message,
+        context.db_conn,
+        context.discord,
     )?;
-    Ok(())
+    Ok(Response::Reply(text))
 }
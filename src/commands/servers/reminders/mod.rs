@@ -0,0 +1,197 @@
+use serenity::framework::standard::CommandError;
+use serenity::model::id::UserId;
+
+use crate::commands::core::{CommandContext, Request, Response};
+use crate::db::DbConnection;
+use crate::model::Player;
+
+#[cfg(test)]
+mod tests;
+
+pub(crate) const DEFAULT_REMINDER_THRESHOLDS_MINS: [i32; 1] = [2 * 60];
+
+/// Parses durations like "12h", "30m", "1h30m" or "2d" into minutes
+pub(crate) fn parse_duration_mins(text: &str) -> Option<i32> {
+    let mut total_mins = 0;
+    let mut number = String::new();
+    let mut saw_unit = false;
+    for c in text.trim().to_lowercase().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+        } else {
+            let multiplier = match c {
+                'd' => 24 * 60,
+                'h' => 60,
+                'm' => 1,
+                _ => return None,
+            };
+            let amount = number.parse::<i32>().ok()?;
+            total_mins = total_mins.checked_add(amount.checked_mul(multiplier)?)?;
+            number.clear();
+            saw_unit = true;
+        }
+    }
+    if !number.is_empty() || !saw_unit || total_mins <= 0 {
+        None
+    } else {
+        Some(total_mins)
+    }
+}
+
+pub(crate) fn show_duration_mins(mins: i32) -> String {
+    let days = mins / (24 * 60);
+    let hours = (mins % (24 * 60)) / 60;
+    let remaining_mins = mins % 60;
+    let mut text = String::new();
+    if days > 0 {
+        text.push_str(&format!("{}d", days));
+    }
+    if hours > 0 {
+        text.push_str(&format!("{}h", hours));
+    }
+    if remaining_mins > 0 || text.is_empty() {
+        text.push_str(&format!("{}m", remaining_mins));
+    }
+    text
+}
+
+/// The thresholds that the turn timer (in ms) has already dropped below
+pub(crate) fn thresholds_crossed(turn_timer: i32, thresholds_mins: &[i32]) -> Vec<i32> {
+    thresholds_mins
+        .iter()
+        .cloned()
+        .filter(|&threshold_mins| i64::from(turn_timer) <= i64::from(threshold_mins) * 60 * 1000)
+        .collect()
+}
+
+pub(crate) fn describe_thresholds(thresholds_mins: Option<&[i32]>) -> String {
+    match thresholds_mins {
+        None => format!("the default ({})", describe_thresholds(Some(&DEFAULT_REMINDER_THRESHOLDS_MINS[..]))),
+        Some(thresholds_mins) if thresholds_mins.is_empty() => "off".to_owned(),
+        Some(thresholds_mins) => thresholds_mins
+            .iter()
+            .map(|&threshold_mins| show_duration_mins(threshold_mins))
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+/// "default" means use the default thresholds, "off" means no reminders
+pub(crate) fn parse_reminder_thresholds(words: &[&str]) -> Result<Option<Vec<i32>>, CommandError> {
+    match words {
+        ["default"] => Ok(None),
+        ["off"] => Ok(Some(vec![])),
+        _ => {
+            let mut thresholds_mins = words
+                .iter()
+                .map(|word| parse_duration_mins(word)
+                    .ok_or_else(|| CommandError::from(format!(
+                        "could not understand reminder threshold \"{}\", try something like 12h, 2h or 30m",
+                        word
+                    ))))
+                .collect::<Result<Vec<_>, _>>()?;
+            thresholds_mins.sort_unstable_by(|a, b| b.cmp(a));
+            thresholds_mins.dedup();
+            Ok(Some(thresholds_mins))
+        }
+    }
+}
+
+fn reminders_helper(
+    db_conn: &DbConnection,
+    user_id: UserId,
+    thresholds_mins: Option<&[i32]>,
+) -> Result<(), CommandError> {
+    db_conn.insert_player(&Player {
+        discord_user_id: user_id,
+        turn_notifications: true,
+    })?;
+    db_conn.set_reminder_thresholds(user_id, thresholds_mins)?;
+    Ok(())
+}
+
+fn game_reminders_helper(
+    db_conn: &DbConnection,
+    user_id: UserId,
+    alias: &str,
+    thresholds_mins: Option<&[i32]>,
+) -> Result<(), CommandError> {
+    db_conn.set_game_reminder_thresholds(alias, user_id, thresholds_mins)?;
+    Ok(())
+}
+
+fn game_reminder_thresholds(
+    db_conn: &DbConnection,
+    user_id: UserId,
+    alias: &str,
+) -> Result<Option<Vec<i32>>, CommandError> {
+    let players_nations = db_conn.players_with_nations_and_settings_for_game_alias(alias)?;
+    let settings = players_nations
+        .into_iter()
+        .find(|&(ref player, _, _)| player.discord_user_id == user_id)
+        .map(|(_, _, settings)| settings)
+        .ok_or_else(|| CommandError::from(format!("You are not registered in {}", alias)))?;
+    Ok(settings.reminder_thresholds_mins)
+}
+
+/// Digits followed by a unit letter, whether or not the unit is one we know
+fn looks_like_duration(word: &str) -> bool {
+    let unit = word.trim_start_matches(|c: char| c.is_ascii_digit());
+    unit.len() < word.len() && unit.starts_with(|c: char| c.is_ascii_alphabetic())
+}
+
+/// A trailing word that isn't a threshold is the game alias. Anything that looks like a duration
+/// counts as a threshold, so a typo like 5x is rejected instead of being taken for a game.
+fn split_alias<'a>(words: &[&'a str]) -> (Vec<&'a str>, Option<&'a str>) {
+    match words.split_last() {
+        Some((&last, rest)) if !looks_like_duration(last) && parse_reminder_thresholds(&[last]).is_err() => {
+            (rest.to_vec(), Some(last))
+        }
+        _ => (words.to_vec(), None),
+    }
+}
+
+pub fn reminders(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let db_conn = context.db_conn;
+    let full_args = request.rest().join(" ").to_lowercase();
+    let words = full_args.split_whitespace().collect::<Vec<_>>();
+    let (words, alias) = split_alias(&words);
+
+    let text = match (words.is_empty(), alias) {
+        (true, None) => {
+            let thresholds_mins = db_conn.reminder_thresholds(request.author)?;
+            format!(
+                "Your turn reminders are {}",
+                describe_thresholds(thresholds_mins.as_ref().map(|t| &t[..]))
+            )
+        }
+        (true, Some(alias)) => {
+            let thresholds_mins = game_reminder_thresholds(db_conn, request.author, alias)?;
+            format!(
+                "Your turn reminders for {} are {}",
+                alias,
+                describe_thresholds(thresholds_mins.as_ref().map(|t| &t[..]))
+            )
+        }
+        (false, None) => {
+            let thresholds_mins = parse_reminder_thresholds(&words)?;
+            let thresholds_mins = thresholds_mins.as_ref().map(|t| &t[..]);
+            reminders_helper(db_conn, request.author, thresholds_mins)?;
+            format!("Set your turn reminders to {}", describe_thresholds(thresholds_mins))
+        }
+        (false, Some(alias)) => {
+            let thresholds_mins = parse_reminder_thresholds(&words)?;
+            let thresholds_mins = thresholds_mins.as_ref().map(|t| &t[..]);
+            game_reminders_helper(db_conn, request.author, alias, thresholds_mins)?;
+            match thresholds_mins {
+                Some(_) => format!(
+                    "Set your turn reminders for {} to {}",
+                    alias,
+                    describe_thresholds(thresholds_mins)
+                ),
+                None => format!("Your turn reminders for {} will follow your global reminders", alias),
+            }
+        }
+    };
+    Ok(Response::Reply(text))
+}
@@ -0,0 +1,136 @@
+use super::*;
+
+use crate::commands::admin::Admins;
+use crate::discord::FakeDiscord;
+use crate::model::*;
+
+#[test]
+fn should_parse_durations() {
+    assert_eq!(parse_duration_mins("30m"), Some(30));
+    assert_eq!(parse_duration_mins("12h"), Some(12 * 60));
+    assert_eq!(parse_duration_mins("1h30m"), Some(90));
+    assert_eq!(parse_duration_mins("2D"), Some(2 * 24 * 60));
+}
+
+#[test]
+fn should_not_parse_invalid_durations() {
+    assert_eq!(parse_duration_mins(""), None);
+    assert_eq!(parse_duration_mins("12"), None);
+    assert_eq!(parse_duration_mins("h"), None);
+    assert_eq!(parse_duration_mins("0m"), None);
+    assert_eq!(parse_duration_mins("12 hours"), None);
+}
+
+#[test]
+fn should_not_parse_oversized_durations() {
+    assert_eq!(parse_duration_mins("99999999h99999999h"), None);
+    // each part fits on its own, the total doesn't
+    assert_eq!(parse_duration_mins("35000000h35000000h"), None);
+    assert_eq!(parse_duration_mins("99999999999m"), None);
+}
+
+#[test]
+fn should_show_durations() {
+    assert_eq!(show_duration_mins(30), "30m");
+    assert_eq!(show_duration_mins(12 * 60), "12h");
+    assert_eq!(show_duration_mins(24 * 60 + 90), "1d1h30m");
+    assert_eq!(show_duration_mins(0), "0m");
+}
+
+#[test]
+fn should_sort_and_dedup_thresholds() {
+    assert_eq!(
+        parse_reminder_thresholds(&["30m", "12h", "2h", "120m"]).unwrap(),
+        Some(vec![12 * 60, 2 * 60, 30])
+    );
+    assert_eq!(parse_reminder_thresholds(&["off"]).unwrap(), Some(vec![]));
+    assert_eq!(parse_reminder_thresholds(&["default"]).unwrap(), None);
+    assert!(parse_reminder_thresholds(&["soon"]).is_err());
+}
+
+#[test]
+fn should_split_trailing_alias() {
+    assert_eq!(split_alias(&["12h", "2h", "foo"]), (vec!["12h", "2h"], Some("foo")));
+    assert_eq!(split_alias(&["12h", "2h"]), (vec!["12h", "2h"], None));
+    assert_eq!(split_alias(&["off", "foo"]), (vec!["off"], Some("foo")));
+    assert_eq!(split_alias(&["foo"]), (vec![], Some("foo")));
+    assert_eq!(split_alias(&[]), (vec![], None));
+}
+
+#[test]
+fn should_not_take_a_bad_duration_for_an_alias() {
+    assert_eq!(split_alias(&["2h", "0m"]), (vec!["2h", "0m"], None));
+    assert_eq!(split_alias(&["5x"]), (vec!["5x"], None));
+    assert_eq!(split_alias(&["1h30"]), (vec!["1h30"], None));
+    assert_eq!(split_alias(&["2h", "4you"]), (vec!["2h", "4you"], None));
+    assert_eq!(split_alias(&["2h", "game2"]), (vec!["2h"], Some("game2")));
+    assert_eq!(split_alias(&["2h", "42"]), (vec!["2h"], Some("42")));
+}
+
+#[test]
+fn should_complain_about_a_bad_duration_instead_of_looking_for_a_game() {
+    let db_conn = &DbConnection::test();
+    let discord = FakeDiscord::new();
+    let admins = Admins(vec![]);
+    let context = CommandContext {
+        db_conn,
+        admins: &admins,
+        discord: &discord,
+    };
+
+    let mut request = Request::new(UserId(3), None, vec!["2h".to_owned(), "0m".to_owned()]);
+    let err = reminders(&context, &mut request).unwrap_err();
+    assert_eq!(
+        err.0,
+        "could not understand reminder threshold \"0m\", try something like 12h, 2h or 30m"
+    );
+    assert_eq!(db_conn.reminder_thresholds(UserId(3)).unwrap(), None);
+}
+
+#[test]
+fn should_find_crossed_thresholds() {
+    let thresholds = [12 * 60, 2 * 60, 30];
+    let hour = 60 * 60 * 1000;
+    assert_eq!(thresholds_crossed(13 * hour, &thresholds), Vec::<i32>::new());
+    assert_eq!(thresholds_crossed(5 * hour, &thresholds), vec![12 * 60]);
+    assert_eq!(thresholds_crossed(hour, &thresholds), vec![12 * 60, 2 * 60]);
+}
+
+#[test]
+fn should_store_reminder_thresholds() {
+    let db_conn = &DbConnection::test();
+
+    reminders_helper(db_conn, UserId(3), Some(&[12 * 60, 30])).unwrap();
+    assert_eq!(db_conn.reminder_thresholds(UserId(3)).unwrap(), Some(vec![12 * 60, 30]));
+
+    reminders_helper(db_conn, UserId(3), Some(&[])).unwrap();
+    assert_eq!(db_conn.reminder_thresholds(UserId(3)).unwrap(), Some(vec![]));
+
+    reminders_helper(db_conn, UserId(3), None).unwrap();
+    assert_eq!(db_conn.reminder_thresholds(UserId(3)).unwrap(), None);
+}
+
+#[test]
+fn should_only_send_a_reminder_once_per_turn() {
+    let db_conn = &DbConnection::test();
+    db_conn
+        .insert_game_server(&GameServer {
+            alias: "foo".to_owned(),
+            state: GameServerState::StartedState(
+                StartedState {
+                    address: "foo.bar:3000".to_owned(),
+                    last_seen_turn: 5,
+                },
+                None,
+            ),
+        })
+        .unwrap();
+    reminders_helper(db_conn, UserId(3), None).unwrap();
+
+    assert!(db_conn.insert_sent_reminder("foo", UserId(3), 5, 5, 120).unwrap());
+    assert!(!db_conn.insert_sent_reminder("foo", UserId(3), 5, 5, 120).unwrap());
+    assert!(db_conn.insert_sent_reminder("foo", UserId(3), 5, 6, 120).unwrap());
+
+    db_conn.remove_old_sent_reminders("foo", 6).unwrap();
+    assert!(db_conn.insert_sent_reminder("foo", UserId(3), 5, 5, 120).unwrap());
+}
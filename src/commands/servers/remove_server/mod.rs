@@ -1,33 +1,42 @@
-use serenity::framework::standard::{Args, CommandError};
-use serenity::prelude::Context;
-use serenity::model::channel::Message;
+use serenity::framework::standard::CommandError;
+use log::*;
 
+use crate::commands::core::{CommandContext, Request, Response};
 use crate::db::*;
-use super::alias_from_arg_or_channel_name;
+use super::check_is_lobby_owner_or_admin;
 
 #[cfg(test)]
 mod tests;
 
 fn remove_server_helper(db_conn: &DbConnection, alias: &str) -> Result<(), CommandError> {
-    db_conn.remove_server(&alias).map_err(CommandError::from)?;
+    db_conn.archive_game(&alias).map_err(CommandError::from)?;
     Ok(())
 }
 
-pub fn remove_server(
-    context: &mut Context,
-    message: &Message,
-    mut args: Args,
-) -> Result<(), CommandError> {
-    let alias = alias_from_arg_or_channel_name(&mut args, &message)?;
-    if !args.is_empty() {
-        return Err(CommandError::from(
-            "Too many arguments. TIP: spaces in arguments need to be quoted \"like this\"",
-        ));
-    }
-
-    let data = context.data.lock();
-    let db_conn = data.get::<DbConnectionKey>().ok_or("No DB connection")?;
-    remove_server_helper(db_conn, &alias)?;
-    let _ = message.reply(&format!("successfully removed server {}", alias));
+fn purge_server_helper(db_conn: &DbConnection, alias: &str) -> Result<(), CommandError> {
+    db_conn.remove_server(&alias).map_err(CommandError::from)?;
     Ok(())
 }
+
+pub fn remove_server(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    remove_server_helper(context.db_conn, &alias)?;
+    Ok(Response::Reply(format!(
+        "successfully archived server {}. Use !restore {} to bring it back",
+        alias,
+        alias
+    )))
+}
+
+pub fn purge_server(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    let server = context.db_conn.game_for_alias_including_archived(&alias)?;
+    check_is_lobby_owner_or_admin(&server, request.author, context.admins)?;
+    purge_server_helper(context.db_conn, &alias)?;
+    info!("{} purged {}", request.author, alias);
+    Ok(Response::Reply(format!("permanently deleted server {}", alias)))
+}
@@ -49,6 +49,8 @@ fn should_remove_lobby() {
                 era: Era::Early,
                 player_count: 8,
                 description: None,
+                status: LobbyStatus::Open,
+                scheduled_start: None,
             }),
         })
         .unwrap();
@@ -86,6 +88,8 @@ fn should_remove_started_server_with_lobby() {
                     era: Era::Early,
                     player_count: 8,
                     description: None,
+                    status: LobbyStatus::Open,
+                    scheduled_start: None,
                 }),
             ),
         })
@@ -105,3 +109,64 @@ fn should_remove_started_server_with_lobby() {
     let get_result_err = db_conn.game_for_alias(&alias);
     assert!(get_result_err.is_err());
 }
+
+#[test]
+fn should_archive_and_restore_server() {
+    let db_conn = &DbConnection::test();
+    db_conn
+        .insert_game_server(&GameServer {
+            alias: "foo".to_owned(),
+            state: GameServerState::StartedState(
+                StartedState {
+                    address: "foo.bar:3000".to_owned(),
+                    last_seen_turn: 23,
+                },
+                None,
+            ),
+        })
+        .unwrap();
+    db_conn.insert_player(&Player { discord_user_id: UserId(3), turn_notifications: true }).unwrap();
+    db_conn.insert_server_player("foo", UserId(3), 5).unwrap();
+
+    remove_server_helper(db_conn, "foo").unwrap();
+
+    assert_eq!(db_conn.count_servers(), 1);
+    assert!(db_conn.retrieve_all_servers().unwrap().is_empty());
+    assert!(db_conn.servers_for_player(UserId(3)).unwrap().is_empty());
+    let archived = db_conn.archived_games().unwrap();
+    assert_eq!(archived.len(), 1);
+    assert_eq!(archived[0].0.alias, "foo");
+    assert!(remove_server_helper(db_conn, "foo").is_err());
+
+    db_conn.restore_game("foo").unwrap();
+
+    assert_eq!(db_conn.retrieve_all_servers().unwrap().len(), 1);
+    assert!(db_conn.archived_games().unwrap().is_empty());
+    assert_eq!(db_conn.players_with_nations_for_game_alias("foo").unwrap().len(), 1);
+    assert!(db_conn.restore_game("foo").is_err());
+}
+
+#[test]
+fn should_purge_archived_server() {
+    let db_conn = &DbConnection::test();
+    db_conn
+        .insert_game_server(&GameServer {
+            alias: "foo".to_owned(),
+            state: GameServerState::Lobby(LobbyState {
+                owner: UserId(1),
+                era: Era::Early,
+                player_count: 8,
+                description: None,
+                status: LobbyStatus::Open,
+                scheduled_start: None,
+            }),
+        })
+        .unwrap();
+
+    remove_server_helper(db_conn, "foo").unwrap();
+    purge_server_helper(db_conn, "foo").unwrap();
+
+    assert_eq!(db_conn.count_servers(), 0);
+    assert_eq!(db_conn.count_lobby_state(), 0);
+    assert!(db_conn.archived_games().unwrap().is_empty());
+}
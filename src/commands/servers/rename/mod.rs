@@ -0,0 +1,45 @@
+use serenity::framework::standard::CommandError;
+use serenity::model::id::UserId;
+use log::*;
+
+use crate::commands::core::{CommandContext, Request, Response};
+use crate::db::DbConnection;
+use crate::model::GameServerState;
+use super::check_is_lobby_owner;
+
+#[cfg(test)]
+mod tests;
+
+fn rename_helper(
+    db_conn: &DbConnection,
+    old_alias: &str,
+    new_alias: &str,
+    user_id: UserId,
+) -> Result<(), CommandError> {
+    let server = db_conn.game_for_alias(old_alias)?;
+    match server.state {
+        GameServerState::Lobby(_) | GameServerState::StartedState(_, Some(_)) => {
+            check_is_lobby_owner(&server, user_id)?
+        }
+        GameServerState::StartedState(_, None) => (),
+    }
+    if db_conn.game_for_alias_including_archived(new_alias).is_ok() {
+        return Err(CommandError::from(format!("There is already a game called {}", new_alias)));
+    }
+    db_conn.rename_game(old_alias, new_alias)?;
+    Ok(())
+}
+
+pub fn rename(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let old_alias = request.required_arg("the game's alias")?.to_lowercase();
+    let new_alias = request.required_arg("a new alias")?.to_lowercase();
+    request.no_more_args()?;
+    if new_alias.is_empty() {
+        return Err(CommandError::from("The new name can't be empty"));
+    }
+
+    rename_helper(context.db_conn, &old_alias, &new_alias, request.author)?;
+    let text = format!("Renamed {} to {}", old_alias, new_alias);
+    info!("{}", text);
+    Ok(Response::Reply(text))
+}
@@ -0,0 +1,37 @@
+use super::*;
+
+#[test]
+fn should_keep_registrations_after_rename() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    db_conn.insert_stall("foo", 5, 2).unwrap();
+
+    rename_helper(db_conn, "foo", "bar", UserId(1)).unwrap();
+
+    assert!(db_conn.game_for_alias("foo").is_err());
+    assert!(db_conn.game_for_alias("bar").is_ok());
+    let players_nations = db_conn.players_with_nations_for_game_alias("bar").unwrap();
+    assert_eq!(players_nations.len(), 1);
+    assert_eq!(players_nations[0].0.discord_user_id, UserId(3));
+    assert_eq!(db_conn.stalls_for_game_alias("bar").unwrap().len(), 1);
+}
+
+#[test]
+fn should_not_rename_onto_existing_game() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+    db_conn.insert_test_lobby("bar", UserId(1), 4);
+
+    assert!(rename_helper(db_conn, "foo", "bar", UserId(1)).is_err());
+    assert!(db_conn.game_for_alias("foo").is_ok());
+}
+
+#[test]
+fn should_only_let_the_owner_rename_a_lobby() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+
+    assert!(rename_helper(db_conn, "foo", "bar", UserId(2)).is_err());
+    assert!(db_conn.game_for_alias("foo").is_ok());
+}
@@ -0,0 +1,157 @@
+use serenity::framework::standard::CommandError;
+use serenity::model::id::UserId;
+use std::collections::HashMap;
+
+use crate::commands::core::{CommandContext, Embed, Request, Response};
+use crate::db::DbConnection;
+use crate::discord::Discord;
+use crate::model::Stall;
+use crate::model::enums::Nations;
+use super::check_is_lobby_owner;
+
+#[cfg(test)]
+mod tests;
+
+const RECENT_STALLS_SHOWN: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Staller {
+    Player(UserId),
+    UnregisteredNation(usize),
+}
+
+fn stall_totals(stalls: &[Stall]) -> Vec<(Staller, usize)> {
+    let mut totals: HashMap<Staller, usize> = HashMap::new();
+    for stall in stalls {
+        let staller = match stall.player {
+            Some(user_id) => Staller::Player(user_id),
+            None => Staller::UnregisteredNation(stall.nation_id),
+        };
+        *totals.entry(staller).or_insert(0) += 1;
+    }
+    let mut totals = totals.into_iter().collect::<Vec<_>>();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    totals
+}
+
+/// For each player that stalled on `turn`: their stall count before that turn and including it
+pub(crate) fn player_stall_counts_for_turn(stalls: &[Stall], turn: i32) -> Vec<(UserId, usize, usize)> {
+    let mut counts: HashMap<UserId, (usize, usize)> = HashMap::new();
+    for stall in stalls {
+        if let Some(user_id) = stall.player {
+            let entry = counts.entry(user_id).or_insert((0, 0));
+            if stall.turn < turn {
+                entry.0 += 1;
+            }
+            if stall.turn <= turn {
+                entry.1 += 1;
+            }
+        }
+    }
+    let mut ret = counts
+        .into_iter()
+        .filter(|&(_, (before, after))| after > before)
+        .map(|(user_id, (before, after))| (user_id, before, after))
+        .collect::<Vec<_>>();
+    ret.sort_by_key(|&(user_id, _, _)| user_id);
+    ret
+}
+
+fn staller_name(discord: &dyn Discord, staller: Staller) -> Result<String, CommandError> {
+    match staller {
+        Staller::Player(user_id) => Ok(discord.user_name(user_id)?),
+        Staller::UnregisteredNation(nation_id) => {
+            let &(nation_name, era) = Nations::get_nation_desc(nation_id);
+            Ok(format!("{} {} (unregistered)", era, nation_name))
+        }
+    }
+}
+
+fn stalls_helper(db_conn: &DbConnection, discord: &dyn Discord, alias: &str) -> Result<Embed, CommandError> {
+    // make sure the game exists so we don't report "no stalls" for a typo
+    let _ = db_conn.game_for_alias(&alias)?;
+    let stalls = db_conn.stalls_for_game_alias(&alias)?;
+
+    let embed_title = format!("{} stalls", alias);
+    if stalls.is_empty() {
+        return Ok(Embed::default()
+            .title(embed_title)
+            .description("No stalls recorded"));
+    }
+
+    let mut staller_names = String::new();
+    let mut stall_counts = String::new();
+    for (staller, count) in stall_totals(&stalls) {
+        staller_names.push_str(&format!("{}\n", staller_name(discord, staller)?));
+        stall_counts.push_str(&format!("{}\n", count));
+    }
+
+    let mut recent_stalls = String::new();
+    for stall in stalls.iter().take(RECENT_STALLS_SHOWN) {
+        let &(nation_name, era) = Nations::get_nation_desc(stall.nation_id);
+        let player_name = match stall.player {
+            Some(user_id) => format!(" ({})", discord.user_name(user_id)?),
+            None => String::new(),
+        };
+        recent_stalls.push_str(&format!(
+            "turn {}: {} {}{}\n",
+            stall.turn,
+            era,
+            nation_name,
+            player_name
+        ));
+    }
+
+    let e = Embed::default()
+        .title(embed_title)
+        .field("Player", staller_names, true)
+        .field("Stalls", stall_counts, true)
+        .field("Recent", recent_stalls, false);
+    Ok(e)
+}
+
+pub fn stalls(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    let embed = stalls_helper(context.db_conn, context.discord, &alias)?;
+    Ok(Response::Embed(embed))
+}
+
+fn stall_warning_helper(
+    db_conn: &DbConnection,
+    threshold: Option<i32>,
+    alias: &str,
+    author_id: UserId,
+) -> Result<(), CommandError> {
+    let server = db_conn.game_for_alias(&alias)?;
+    check_is_lobby_owner(&server, author_id)?;
+    if let Some(threshold) = threshold {
+        if threshold < 1 {
+            return Err(CommandError::from("the stall warning threshold must be at least 1"));
+        }
+    }
+    db_conn.set_stall_warning_threshold(&alias, threshold)?;
+    Ok(())
+}
+
+pub fn stall_warning(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let threshold_str = request.required_arg("a number of stalls or \"off\"")?;
+    let threshold = if threshold_str.to_lowercase() == "off" {
+        None
+    } else {
+        Some(threshold_str
+            .parse::<i32>()
+            .map_err(|_| "expected a number of stalls or \"off\"")?)
+    };
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    stall_warning_helper(context.db_conn, threshold, &alias, request.author)?;
+
+    let text = match threshold {
+        Some(threshold) => format!("You will be warned when a player in {} reaches {} stalls", alias, threshold),
+        None => format!("Turned off stall warnings for {}", alias),
+    };
+    Ok(Response::Reply(text))
+}
@@ -0,0 +1,100 @@
+use super::*;
+
+use crate::model::*;
+
+#[test]
+fn should_record_stalls_against_registered_players() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.bar:3000", 5);
+    db_conn.register_test_player("foo", UserId(3), 5);
+
+    db_conn.insert_stall("foo", 5, 3).unwrap();
+    db_conn.insert_stall("foo", 6, 3).unwrap();
+    db_conn.insert_stall("foo", 5, 4).unwrap();
+
+    let stalls = db_conn.stalls_for_game_alias("foo").unwrap();
+    assert_eq!(
+        stalls,
+        vec![
+            Stall { nation_id: 5, player: Some(UserId(3)), turn: 4 },
+            Stall { nation_id: 5, player: Some(UserId(3)), turn: 3 },
+            Stall { nation_id: 6, player: None, turn: 3 },
+        ]
+    );
+}
+
+#[test]
+fn should_only_record_a_stall_once() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.bar:3000", 5);
+
+    db_conn.insert_stall("foo", 5, 3).unwrap();
+    db_conn.insert_stall("foo", 5, 3).unwrap();
+
+    assert_eq!(db_conn.stalls_for_game_alias("foo").unwrap().len(), 1);
+}
+
+#[test]
+fn should_only_return_stalls_for_the_game() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.bar:3000", 5);
+    db_conn.insert_test_game("bar", "bar.bar:3000", 5);
+
+    db_conn.insert_stall("foo", 5, 3).unwrap();
+    db_conn.insert_stall("bar", 5, 3).unwrap();
+
+    assert_eq!(db_conn.stalls_for_game_alias("foo").unwrap().len(), 1);
+}
+
+#[test]
+fn should_total_stalls_by_player_then_nation() {
+    let stalls = vec![
+        Stall { nation_id: 5, player: Some(UserId(3)), turn: 4 },
+        Stall { nation_id: 6, player: None, turn: 4 },
+        Stall { nation_id: 5, player: Some(UserId(3)), turn: 3 },
+    ];
+
+    assert_eq!(
+        stall_totals(&stalls),
+        vec![
+            (Staller::Player(UserId(3)), 2),
+            (Staller::UnregisteredNation(6), 1),
+        ]
+    );
+}
+
+#[test]
+fn should_count_stalls_before_and_after_turn() {
+    let stalls = vec![
+        Stall { nation_id: 5, player: Some(UserId(3)), turn: 4 },
+        Stall { nation_id: 6, player: Some(UserId(4)), turn: 4 },
+        Stall { nation_id: 5, player: Some(UserId(3)), turn: 3 },
+        Stall { nation_id: 7, player: Some(UserId(5)), turn: 3 },
+    ];
+
+    assert_eq!(
+        player_stall_counts_for_turn(&stalls, 4),
+        vec![(UserId(3), 1, 2), (UserId(4), 0, 1)]
+    );
+}
+
+#[test]
+fn should_set_stall_warning_threshold_for_owner() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_started_lobby("foo", UserId(1), "foo.bar:3000", 5);
+
+    stall_warning_helper(db_conn, Some(3), "foo", UserId(1)).unwrap();
+    assert_eq!(db_conn.stall_warning_threshold_for_game_alias("foo").unwrap(), Some(3));
+
+    stall_warning_helper(db_conn, None, "foo", UserId(1)).unwrap();
+    assert_eq!(db_conn.stall_warning_threshold_for_game_alias("foo").unwrap(), None);
+}
+
+#[test]
+fn should_not_set_stall_warning_threshold_for_non_owner() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_started_lobby("foo", UserId(1), "foo.bar:3000", 5);
+
+    assert!(stall_warning_helper(db_conn, Some(3), "foo", UserId(2)).is_err());
+    assert_eq!(db_conn.stall_warning_threshold_for_game_alias("foo").unwrap(), None);
+}
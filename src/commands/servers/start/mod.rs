@@ -1,13 +1,16 @@
 use crate::server::ServerConnection;
 
-use serenity::framework::standard::{Args, CommandError};
-use serenity::prelude::Context;
-use serenity::model::channel::Message;
+use serenity::framework::standard::CommandError;
+use log::*;
 
+use crate::commands::core::{CommandContext, Request, Response};
 use crate::model::*;
 use crate::model::enums::Nations;
 use crate::db::*;
-use super::alias_from_arg_or_channel_name;
+use crate::notifier::{notify_player, Notification};
+
+#[cfg(test)]
+mod tests;
 
 fn start_helper<C: ServerConnection>(
     db_conn: &DbConnection,
@@ -21,6 +24,14 @@ fn start_helper<C: ServerConnection>(
             return Err(CommandError::from("game already started"))
         }
         GameServerState::Lobby(lobby_state) => {
+            if !lobby_state.status.is_active() {
+                return Err(CommandError::from(format!(
+                    "{} is {}. Use !lobby-open {} first",
+                    alias,
+                    lobby_state.status,
+                    alias
+                )));
+            }
             let game_data = C::get_game_data(&address)?;
             if game_data.nations.len() as i32 > lobby_state.player_count {
                 return Err(CommandError::from("game has more players than the lobby"));
@@ -37,24 +48,21 @@ fn start_helper<C: ServerConnection>(
     Ok(())
 }
 
-pub fn start<C: ServerConnection>(
-    context: &mut Context,
-    message: &Message,
-    mut args: Args,
-) -> Result<(), CommandError> {
-    let data = context.data.lock();
-    let db_conn = data.get::<DbConnectionKey>()
-        .ok_or("No DbConnection was created on startup. This is a bug.")?;
-    let address = args.single_quoted::<String>()?;
-    let alias = alias_from_arg_or_channel_name(&mut args, &message)?;
-    if !args.is_empty() {
-        return Err(CommandError::from(
-            "Too many arguments. TIP: spaces in arguments need to be quoted \"like this\"",
-        ));
-    }
-    start_helper::<C>(db_conn, &address, &alias)?;
-    message.reply(&"started!")?;
-    for (player, nation_id) in db_conn.players_with_nations_for_game_alias(&alias)? {
+pub fn start<C: ServerConnection>(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let address = request.required_arg("a server address")?;
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    start_helper::<C>(context.db_conn, &address, &alias)?;
+    // the game has started now whether or not everyone hears about it
+    let players_nations = match context.db_conn.players_with_nations_for_game_alias(&alias) {
+        Ok(players_nations) => players_nations,
+        Err(err) => {
+            error!("error finding the players to tell that {} started: {}", alias, err);
+            vec![]
+        }
+    };
+    for (player, nation_id) in players_nations {
         let &(name, era) = Nations::get_nation_desc(nation_id);
         let text = format!(
             "Pretender upload has started in {}.\nServer address is {}.\nYou are registered as {} {}",
@@ -63,8 +71,11 @@ pub fn start<C: ServerConnection>(
             era,
             name,
         );
-        let private_channel = player.discord_user_id.create_dm_channel()?;
-        private_channel.say(&text)?;
+        let user_id = player.discord_user_id;
+        let notification = Notification::for_player(user_id, &alias, None, "game_started", text);
+        if let Err(err) = notify_player(context.db_conn, context.discord, user_id, &notification) {
+            error!("error telling {} that {} started: {}", user_id, alias, err);
+        }
     }
-    Ok(())
+    Ok(Response::Reply("started!".to_owned()))
 }
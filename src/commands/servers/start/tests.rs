@@ -0,0 +1,102 @@
+use super::*;
+
+use crate::commands::admin::Admins;
+use crate::discord::{FakeDiscord, Sent};
+use crate::model::enums::*;
+use serenity::model::id::UserId;
+use std::io;
+
+fn game_data() -> io::Result<GameData> {
+    Ok(GameData {
+        game_name: "foo".to_owned(),
+        nations: vec![],
+        turn: -1,
+        turn_timer: 0,
+    })
+}
+
+fn start_request(alias: &str) -> Request {
+    Request::new(UserId(1), None, vec!["foo.bar:3000".to_owned(), alias.to_owned()])
+}
+
+#[test]
+fn should_dm_every_registered_player_when_started() {
+    mock_server_connection!(Mock, game_data());
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    db_conn.register_test_player("foo", UserId(4), 6);
+    let admins = Admins(vec![]);
+    let discord = FakeDiscord::new()
+        .with_user(UserId(3), "three")
+        .with_user(UserId(4), "four");
+    let context = CommandContext {
+        db_conn,
+        admins: &admins,
+        discord: &discord,
+    };
+
+    assert_eq!(
+        start::<Mock>(&context, &mut start_request("foo")).unwrap(),
+        Response::Reply("started!".to_owned())
+    );
+
+    for &(user_id, nation_id) in &[(UserId(3), 5), (UserId(4), 6)] {
+        let &(name, era) = Nations::get_nation_desc(nation_id);
+        assert_eq!(
+            discord.dms_to(user_id),
+            vec![format!(
+                "Pretender upload has started in foo.\nServer address is foo.bar:3000.\nYou are registered as {} {}",
+                era,
+                name
+            )]
+        );
+    }
+    match db_conn.game_for_alias("foo").unwrap().state {
+        GameServerState::StartedState(started_state, _) => assert_eq!(started_state.address, "foo.bar:3000"),
+        state => panic!("expected a started game but got {:?}", state),
+    }
+}
+
+#[test]
+fn should_not_start_a_cancelled_lobby_or_message_anyone() {
+    mock_server_connection!(Mock, game_data());
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+    db_conn.set_lobby_status("foo", LobbyStatus::Cancelled).unwrap();
+    db_conn.register_test_player("foo", UserId(3), 5);
+    let admins = Admins(vec![]);
+    let discord = FakeDiscord::new().with_user(UserId(3), "three");
+    let context = CommandContext {
+        db_conn,
+        admins: &admins,
+        discord: &discord,
+    };
+
+    let err = start::<Mock>(&context, &mut start_request("foo")).unwrap_err();
+    assert_eq!(err.0, "foo is cancelled. Use !lobby-open foo first");
+    assert_eq!(discord.sent(), Vec::<Sent>::new());
+}
+
+#[test]
+fn should_say_started_and_message_the_rest_when_one_dm_fails() {
+    mock_server_connection!(Mock, game_data());
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 4);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    db_conn.register_test_player("foo", UserId(4), 6);
+    let admins = Admins(vec![]);
+    // the fake can't DM users it doesn't know
+    let discord = FakeDiscord::new().with_user(UserId(4), "four");
+    let context = CommandContext {
+        db_conn,
+        admins: &admins,
+        discord: &discord,
+    };
+
+    assert_eq!(
+        start::<Mock>(&context, &mut start_request("foo")).unwrap(),
+        Response::Reply("started!".to_owned())
+    );
+    assert_eq!(discord.dms_to(UserId(4)).len(), 1);
+}
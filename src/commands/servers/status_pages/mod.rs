@@ -0,0 +1,255 @@
+use chrono::{DateTime, Utc};
+use failure::{Error, ResultExt};
+use serenity::model::id::UserId;
+use log::*;
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::db::DbConnection;
+use crate::discord::Discord;
+use crate::model::{GameServer, GameServerState, StartedState};
+use crate::model::enums::NationStatus;
+use crate::server::ServerConnection;
+use super::turns::hours_mins_remaining;
+
+#[cfg(test)]
+mod tests;
+
+const GAMES_DIR: &str = "games";
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border-bottom: 1px solid #ccc; padding: 0.3em 1em; text-align: left; }
+.error { color: #a00; }
+footer { color: #777; font-size: 0.9em; }";
+
+/// Where the HTML status pages go. `index.html` lists every game and lobby, and each
+/// started game gets its own page in `games/`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusPages {
+    pub dir: PathBuf,
+}
+
+/// Only looks in the cache, so writing the pages never waits on a request to discord
+/// for each player. Falls back to the id for players that aren't cached yet.
+pub fn cached_player_name(discord: &dyn Discord, user_id: UserId) -> String {
+    discord
+        .cached_user_name(user_id)
+        .unwrap_or_else(|| user_id.0.to_string())
+}
+
+/// Also used for the atom feeds, XML needs the same characters escaped
+pub(super) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Aliases come from discord, so only ASCII letters, digits and '-' are kept as they are.
+/// Every other byte becomes '_' and its hex value, so that no two aliases share a file
+/// and the name needs no escaping in a link.
+pub(super) fn safe_file_stem(alias: &str) -> String {
+    let mut stem = String::with_capacity(alias.len());
+    for byte in alias.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' {
+            stem.push(byte as char);
+        } else {
+            stem.push_str(&format!("_{:02X}", byte));
+        }
+    }
+    stem
+}
+
+fn page_file_name(alias: &str) -> String {
+    format!("{}.html", safe_file_stem(alias))
+}
+
+fn page(title: &str, body: &str, now: DateTime<Utc>) -> String {
+    format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n\
+         <style>\n{style}\n</style>\n\
+         </head>\n\
+         <body>\n\
+         {body}\
+         <footer>Last updated {updated}</footer>\n\
+         </body>\n\
+         </html>\n",
+        title = escape(title),
+        style = STYLE,
+        body = body,
+        updated = now.format("%Y-%m-%d %H:%M UTC"),
+    )
+}
+
+fn timer_text(turn_timer: i32) -> String {
+    if turn_timer <= 0 {
+        "no timer".to_owned()
+    } else {
+        let (hours_remaining, mins_remaining) = hours_mins_remaining(turn_timer);
+        format!("{}h {}m remaining", hours_remaining, mins_remaining)
+    }
+}
+
+/// The same nation, player and submitted columns as `!details`
+fn game_page<C: ServerConnection, F: Fn(UserId) -> String>(
+    db_conn: &DbConnection,
+    alias: &str,
+    started_state: &StartedState,
+    player_name: &F,
+    now: DateTime<Utc>,
+) -> Result<String, Error> {
+    let mut body = "<p><a href=\"../index.html\">All games</a></p>\n".to_owned();
+    match C::get_game_data(&started_state.address) {
+        Ok(mut game_data) => {
+            game_data.nations.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+            let players_nations = db_conn.players_with_nations_for_game_alias(alias)?;
+            body.push_str(&format!(
+                "<h1>{} ({}): turn {}, {}</h1>\n",
+                escape(&game_data.game_name),
+                escape(&started_state.address),
+                game_data.turn,
+                timer_text(game_data.turn_timer),
+            ));
+            body.push_str("<table>\n<tr><th>Nation</th><th>Player</th><th>Submitted</th></tr>\n");
+            for nation in &game_data.nations {
+                let player = match nation.status {
+                    NationStatus::Human => players_nations
+                        .iter()
+                        .find(|&&(_, nation_id)| nation_id == nation.id)
+                        .map(|&(ref player, _)| format!("<b>{}</b>", escape(&player_name(player.discord_user_id))))
+                        .unwrap_or_else(|| nation.status.show().to_owned()),
+                    _ => nation.status.show().to_owned(),
+                };
+                let submitted = match nation.status {
+                    NationStatus::Human => nation.submitted.show().into_owned(),
+                    _ => ".".to_owned(),
+                };
+                body.push_str(&format!(
+                    "<tr><td>{} {} ({})</td><td>{}</td><td>{}</td></tr>\n",
+                    escape(&nation.era),
+                    escape(&nation.name),
+                    nation.id,
+                    player,
+                    escape(&submitted),
+                ));
+            }
+            if game_data.nations.is_empty() {
+                body.push_str("<tr><td>-</td><td>-</td><td>-</td></tr>\n");
+            }
+            body.push_str("</table>\n");
+        }
+        Err(err) => {
+            body.push_str(&format!(
+                "<h1>{} ({})</h1>\n<p class=\"error\">Could not reach the game server: {}</p>\n",
+                escape(alias),
+                escape(&started_state.address),
+                escape(&err.to_string()),
+            ));
+        }
+    }
+    Ok(page(alias, &body, now))
+}
+
+fn index_page<C: ServerConnection>(
+    servers: &[GameServer],
+    lobbies: &[(GameServer, i32)],
+    now: DateTime<Utc>,
+) -> String {
+    let mut body = "<h1>Games</h1>\n<table>\n<tr><th>Game</th><th>Turn</th><th>Timer</th></tr>\n".to_owned();
+    for server in servers {
+        if let GameServerState::StartedState(ref started_state, _) = server.state {
+            // the poll that just finished has already cached this
+            let (turn, timer) = match C::get_game_data(&started_state.address) {
+                Ok(game_data) => (game_data.turn.to_string(), timer_text(game_data.turn_timer)),
+                Err(_) => ("?".to_owned(), "server unreachable".to_owned()),
+            };
+            body.push_str(&format!(
+                "<tr><td><a href=\"{}/{}\">{}</a></td><td>{}</td><td>{}</td></tr>\n",
+                GAMES_DIR,
+                page_file_name(&server.alias),
+                escape(&server.alias),
+                escape(&turn),
+                escape(&timer),
+            ));
+        }
+    }
+    body.push_str("</table>\n<h1>Lobbies</h1>\n<table>\n<tr><th>Lobby</th><th>Era</th><th>Players</th><th>Status</th></tr>\n");
+    for &(ref server, registered) in lobbies {
+        if let GameServerState::Lobby(ref lobby_state) = server.state {
+            body.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}/{}</td><td>{}</td></tr>\n",
+                escape(&server.alias),
+                lobby_state.era,
+                registered,
+                lobby_state.player_count,
+                lobby_state.status,
+            ));
+        }
+    }
+    body.push_str("</table>\n");
+    page("Games", &body, now)
+}
+
+/// A half-written page shouldn't ever be served
+pub(super) fn write_file(path: &Path, contents: &str) -> Result<(), Error> {
+    let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let partial_path = path.with_file_name(format!("{}.partial", file_name));
+    fs::write(&partial_path, contents).context(format!("Writing '{}'", partial_path.display()))?;
+    fs::rename(&partial_path, path).context(format!("Moving page to '{}'", path.display()))?;
+    Ok(())
+}
+
+impl StatusPages {
+    /// Writes every page, then deletes the pages of games that have gone
+    pub fn write<C: ServerConnection, F: Fn(UserId) -> String>(
+        &self,
+        db_conn: &DbConnection,
+        player_name: F,
+        now: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let games_dir = self.dir.join(GAMES_DIR);
+        fs::create_dir_all(&games_dir).context(format!("Creating status page directory '{}'", games_dir.display()))?;
+
+        let servers = db_conn.retrieve_all_servers()?;
+        let mut written = HashSet::new();
+        for server in &servers {
+            if let GameServerState::StartedState(ref started_state, _) = server.state {
+                let file_name = page_file_name(&server.alias);
+                match game_page::<C, F>(db_conn, &server.alias, started_state, &player_name, now) {
+                    Ok(html) => write_file(&games_dir.join(&file_name), &html)?,
+                    Err(err) => error!("Writing the status page for {} failed with: {}", server.alias, err),
+                }
+                written.insert(file_name);
+            }
+        }
+
+        let lobbies = db_conn.select_lobbies(false)?;
+        write_file(&self.dir.join("index.html"), &index_page::<C>(&servers, &lobbies, now))?;
+
+        for entry in fs::read_dir(&games_dir)? {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if file_name.ends_with(".html") && !written.contains(&file_name) {
+                info!("deleting old status page {}", entry.path().display());
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+}
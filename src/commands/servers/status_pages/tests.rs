@@ -0,0 +1,189 @@
+use super::*;
+
+use crate::discord::FakeDiscord;
+use crate::model::*;
+use crate::model::enums::*;
+use crate::model::Nation;
+use chrono::TimeZone;
+
+use std::io;
+
+fn test_status_pages(name: &str) -> StatusPages {
+    let dir = std::env::temp_dir().join(format!("dom5status-test-status-pages-{}", name));
+    let _ = fs::remove_dir_all(&dir);
+    StatusPages { dir }
+}
+
+fn read(path: PathBuf) -> String {
+    fs::read_to_string(&path).unwrap()
+}
+
+fn player_name(user_id: UserId) -> String {
+    format!("player{}", user_id.0)
+}
+
+fn nation(id: usize, status: NationStatus, submitted: SubmissionStatus) -> Nation {
+    let &(name, era) = Nations::get_nation_desc(id);
+    Nation {
+        id,
+        status,
+        submitted,
+        connected: false,
+        name: name.to_owned(),
+        era: format!("{}", era),
+    }
+}
+
+fn populate(db_conn: &DbConnection) {
+    db_conn
+        .insert_game_server(&GameServer {
+            alias: "<started>".to_owned(),
+            state: GameServerState::StartedState(
+                StartedState {
+                    address: "status.pages:3000".to_owned(),
+                    last_seen_turn: 7,
+                },
+                None,
+            ),
+        })
+        .unwrap();
+    db_conn
+        .insert_game_server(&GameServer {
+            alias: "lobby".to_owned(),
+            state: GameServerState::Lobby(LobbyState {
+                owner: UserId(7),
+                era: Era::Middle,
+                player_count: 4,
+                description: None,
+                status: LobbyStatus::Open,
+                scheduled_start: None,
+            }),
+        })
+        .unwrap();
+    db_conn.insert_player(&Player { discord_user_id: UserId(1), turn_notifications: true }).unwrap();
+    db_conn.insert_server_player("<started>", UserId(1), 5).unwrap();
+}
+
+#[test]
+fn should_write_a_page_per_game_and_an_index() {
+    mock_server_connection!(Mock, Ok(GameData {
+        game_name: "startedgame".to_owned(),
+        nations: vec![
+            nation(5, NationStatus::Human, SubmissionStatus::Submitted),
+            nation(12, NationStatus::Human, SubmissionStatus::NotSubmitted),
+            nation(20, NationStatus::AI, SubmissionStatus::NotSubmitted),
+        ],
+        turn: 7,
+        turn_timer: (2 * 60 + 30) * 60 * 1000,
+    }));
+    let db_conn = &DbConnection::test();
+    populate(db_conn);
+    let status_pages = test_status_pages("write");
+    let now = Utc.ymd(2019, 6, 1).and_hms(18, 0, 0);
+
+    status_pages.write::<Mock, _>(db_conn, player_name, now).unwrap();
+
+    let index = read(status_pages.dir.join("index.html"));
+    assert!(index.contains("<a href=\"games/_3Cstarted_3E.html\">&lt;started&gt;</a>"));
+    assert!(index.contains("2h 30m remaining"));
+    assert!(index.contains("<td>lobby</td>"));
+    assert!(index.contains("Last updated 2019-06-01 18:00 UTC"));
+
+    let game = read(status_pages.dir.join("games").join("_3Cstarted_3E.html"));
+    assert!(game.contains("turn 7, 2h 30m remaining"));
+    let &(name_5, _) = Nations::get_nation_desc(5);
+    assert!(game.contains(&format!("{} (5)</td><td><b>player1</b></td><td>✓</td>", escape(name_5))));
+    // human but nobody registered
+    assert!(game.contains("(12)</td><td>Human</td><td>X</td>"));
+    assert!(game.contains("(20)</td><td>AI</td><td>.</td>"));
+    assert!(!game.contains("<started>"));
+
+    fs::remove_dir_all(&status_pages.dir).unwrap();
+}
+
+#[test]
+fn should_say_when_the_server_is_unreachable() {
+    mock_server_connection!(Mock, Err(io::Error::new(io::ErrorKind::TimedOut, "timed out")));
+    let db_conn = &DbConnection::test();
+    populate(db_conn);
+    let status_pages = test_status_pages("unreachable");
+
+    status_pages.write::<Mock, _>(db_conn, player_name, Utc::now()).unwrap();
+
+    let game = read(status_pages.dir.join("games").join("_3Cstarted_3E.html"));
+    assert!(game.contains("Could not reach the game server: timed out"));
+    let index = read(status_pages.dir.join("index.html"));
+    assert!(index.contains("server unreachable"));
+
+    fs::remove_dir_all(&status_pages.dir).unwrap();
+}
+
+#[test]
+fn should_delete_pages_of_removed_games() {
+    mock_server_connection!(Mock, Ok(GameData {
+        game_name: "startedgame".to_owned(),
+        nations: vec![],
+        turn: 7,
+        turn_timer: 0,
+    }));
+    let db_conn = &DbConnection::test();
+    populate(db_conn);
+    let status_pages = test_status_pages("removed");
+    status_pages.write::<Mock, _>(db_conn, player_name, Utc::now()).unwrap();
+    let old_page = status_pages.dir.join("games").join("_3Cstarted_3E.html");
+    assert!(old_page.exists());
+    assert!(read(old_page.clone()).contains("no timer"));
+
+    db_conn.remove_server("<started>").unwrap();
+    status_pages.write::<Mock, _>(db_conn, player_name, Utc::now()).unwrap();
+    assert!(!old_page.exists());
+    assert!(!read(status_pages.dir.join("index.html")).contains("started"));
+
+    fs::remove_dir_all(&status_pages.dir).unwrap();
+}
+
+#[test]
+fn should_give_every_alias_its_own_page() {
+    mock_server_connection!(Mock, Ok(GameData {
+        game_name: "startedgame".to_owned(),
+        nations: vec![],
+        turn: 7,
+        turn_timer: 0,
+    }));
+    let db_conn = &DbConnection::test();
+    for &(alias, address) in &[("a.b", "a.b:3000"), ("a_b", "a_b:3000"), ("a b", "a b:3000")] {
+        db_conn
+            .insert_game_server(&GameServer {
+                alias: alias.to_owned(),
+                state: GameServerState::StartedState(
+                    StartedState {
+                        address: address.to_owned(),
+                        last_seen_turn: 7,
+                    },
+                    None,
+                ),
+            })
+            .unwrap();
+    }
+    let status_pages = test_status_pages("collisions");
+
+    status_pages.write::<Mock, _>(db_conn, player_name, Utc::now()).unwrap();
+
+    assert_eq!(safe_file_stem("a.b"), "a_2Eb");
+    assert_eq!(safe_file_stem("a_b"), "a_5Fb");
+    assert_eq!(safe_file_stem("Ærø-1"), "_C3_86r_C3_B8-1");
+    let pages = fs::read_dir(status_pages.dir.join("games")).unwrap().count();
+    assert_eq!(pages, 3);
+    let index = read(status_pages.dir.join("index.html"));
+    assert!(index.contains("<a href=\"games/a_2Eb.html\">a.b</a>"));
+    assert!(index.contains("<a href=\"games/a_5Fb.html\">a_b</a>"));
+
+    fs::remove_dir_all(&status_pages.dir).unwrap();
+}
+
+#[test]
+fn should_fall_back_to_the_id_for_players_discord_hasnt_cached() {
+    let discord = FakeDiscord::new().with_user(UserId(3), "three");
+    assert_eq!(cached_player_name(&discord, UserId(3)), "three");
+    assert_eq!(cached_player_name(&discord, UserId(4)), "4");
+}
@@ -0,0 +1,295 @@
+use serenity::framework::standard::CommandError;
+use serenity::model::id::{ChannelId, UserId};
+use serenity::model::misc::Mentionable;
+use log::*;
+
+use crate::commands::admin::Admins;
+use crate::commands::core::{CommandContext, Embed, Request, Response};
+use crate::db::DbConnection;
+use crate::discord::Discord;
+use crate::notifier::{notify_player, Notification};
+use crate::model::{GameServer, GameServerState, Substitution};
+use crate::model::enums::Nations;
+use crate::server::ServerConnection;
+use super::check_is_lobby_owner_or_admin;
+use super::turns::hours_mins_remaining;
+
+#[cfg(test)]
+mod tests;
+
+fn current_turn(server: &GameServer) -> i32 {
+    match server.state {
+        GameServerState::StartedState(ref started_state, _) => started_state.last_seen_turn,
+        GameServerState::Lobby(_) => 0,
+    }
+}
+
+fn nation_desc(nation_id: usize) -> String {
+    let &(nation_name, era) = Nations::get_nation_desc(nation_id);
+    format!("{} {} ({})", era, nation_name, nation_id)
+}
+
+/// Posts go to the game's substitute channel, then its notification channel, then wherever the command was used
+fn substitute_channel(
+    db_conn: &DbConnection,
+    alias: &str,
+    fallback: Option<ChannelId>,
+) -> Result<ChannelId, CommandError> {
+    let channel_id = match db_conn.substitute_channel_for_game_alias(alias)? {
+        Some(channel_id) => Some(channel_id),
+        None => db_conn.notification_channel_for_game_alias(alias)?.or(fallback),
+    };
+    channel_id.ok_or_else(|| {
+        CommandError::from(format!(
+            "{} has no channel for substitutes, set one with !sub-channel {}",
+            alias,
+            alias
+        ))
+    })
+}
+
+// For messages sent after a handover is saved, which shouldn't fail because of a name
+fn user_name_or_mention(discord: &dyn Discord, user_id: UserId) -> String {
+    discord.user_name(user_id).unwrap_or_else(|_| user_id.mention())
+}
+
+fn sub_needed_helper(
+    db_conn: &DbConnection,
+    alias: &str,
+    user_id: UserId,
+) -> Result<Vec<usize>, CommandError> {
+    let server = db_conn.game_for_alias(alias)?;
+    let nation_ids = db_conn
+        .players_with_nations_for_game_alias(alias)?
+        .into_iter()
+        .filter(|&(ref player, _)| player.discord_user_id == user_id)
+        .map(|(_, nation_id)| nation_id)
+        .collect::<Vec<_>>();
+    if nation_ids.is_empty() {
+        return Err(CommandError::from(format!("You are not registered in {}", alias)));
+    }
+    db_conn.insert_substitution_requests(alias, user_id, &nation_ids, current_turn(&server))?;
+    Ok(nation_ids)
+}
+
+fn sub_take_helper(
+    db_conn: &DbConnection,
+    alias: &str,
+    incoming: UserId,
+) -> Result<(GameServer, Substitution), CommandError> {
+    let server = db_conn.game_for_alias(alias)?;
+    let substitution = db_conn
+        .substitutions_for_game_alias(alias)?
+        .into_iter()
+        .find(|substitution| substitution.incoming.is_none())
+        .ok_or_else(|| CommandError::from(format!("Nobody in {} is looking for a substitute", alias)))?;
+    if substitution.outgoing == incoming {
+        return Err(CommandError::from(
+            "You can't substitute for yourself. Use !sub-cancel if you want to keep playing",
+        ));
+    }
+
+    let taken_turn = current_turn(&server);
+    db_conn.take_substitution(alias, &substitution, incoming, taken_turn)?;
+    let substitution = Substitution {
+        incoming: Some(incoming),
+        taken_turn: Some(taken_turn),
+        ..substitution
+    };
+    Ok((server, substitution))
+}
+
+// The substitute missed the new turn notification, so tell them where the game is at now
+fn substitute_welcome_text<C: ServerConnection>(server: &GameServer, nation_id: usize) -> String {
+    match server.state {
+        GameServerState::Lobby(_) => format!(
+            "You are now registered as {} in the lobby {}",
+            nation_desc(nation_id),
+            server.alias
+        ),
+        GameServerState::StartedState(ref started_state, _) => {
+            let mut text = format!(
+                "You are now playing {} in {}.\nServer address is {}.",
+                nation_desc(nation_id),
+                server.alias,
+                started_state.address
+            );
+            match C::get_game_data(&started_state.address) {
+                Ok(game_data) => {
+                    let (hours_remaining, mins_remaining) = hours_mins_remaining(game_data.turn_timer);
+                    text.push_str(&format!(
+                        "\nIt is turn {} with {}h {}m remaining.",
+                        game_data.turn,
+                        hours_remaining,
+                        mins_remaining
+                    ));
+                    if let Some(nation) = game_data.nations.iter().find(|n| n.id == nation_id) {
+                        text.push_str(&format!(" Submitted: {}", nation.submitted.show()));
+                    }
+                }
+                Err(err) => info!("could not get game data for {}: {}", server.alias, err),
+            }
+            text
+        }
+    }
+}
+
+pub fn sub_needed(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    let nation_ids = sub_needed_helper(context.db_conn, &alias, request.author)?;
+    let channel_id = substitute_channel(context.db_conn, &alias, request.channel_id)?;
+    for nation_id in nation_ids {
+        context.discord.say(
+            channel_id,
+            &format!(
+                "{} is looking for a substitute to play {}. Use `!sub-take {}` to take over.",
+                alias,
+                nation_desc(nation_id),
+                alias
+            ),
+        )?;
+    }
+    if Some(channel_id) != request.channel_id {
+        Ok(Response::Reply(format!("Asked for a substitute in {}", channel_id.mention())))
+    } else {
+        Ok(Response::Nothing)
+    }
+}
+
+pub fn sub_take<C: ServerConnection>(
+    context: &CommandContext,
+    request: &mut Request,
+) -> Result<Response, CommandError> {
+    let alias = request.required_arg("a game alias")?.to_lowercase();
+    request.no_more_args()?;
+
+    let (server, substitution) = sub_take_helper(context.db_conn, &alias, request.author)?;
+    // the nation has been handed over now, so nothing below should turn that into an error
+    let nation_text = nation_desc(substitution.nation_id);
+    let author_name = user_name_or_mention(context.discord, request.author);
+
+    let turn = match server.state {
+        GameServerState::StartedState(_, _) => substitution.taken_turn,
+        GameServerState::Lobby(_) => None,
+    };
+    let welcome = Notification::for_player(
+        request.author,
+        &alias,
+        turn,
+        "substitute",
+        substitute_welcome_text::<C>(&server, substitution.nation_id),
+    );
+    if let Err(err) = notify_player(context.db_conn, context.discord, request.author, &welcome) {
+        error!("error welcoming {} as a substitute in {}: {}", request.author, alias, err);
+    }
+    let handover = Notification::for_player(
+        substitution.outgoing,
+        &alias,
+        turn,
+        "substitute",
+        format!("{} has taken over your nation {} in {}", author_name, nation_text, alias),
+    );
+    if let Err(err) = notify_player(context.db_conn, context.discord, substitution.outgoing, &handover) {
+        error!("error telling {} about their substitute in {}: {}", substitution.outgoing, alias, err);
+    }
+
+    let text = format!(
+        "{} has taken over {} in {} from {}",
+        author_name,
+        nation_text,
+        alias,
+        user_name_or_mention(context.discord, substitution.outgoing)
+    );
+    let announced = substitute_channel(context.db_conn, &alias, request.channel_id)
+        .and_then(|channel_id| context.discord.say(channel_id, &text).map_err(CommandError::from));
+    match announced {
+        Ok(()) => Ok(Response::Nothing),
+        Err(err) => {
+            error!("error announcing the substitute in {}: {}", alias, err.0);
+            Ok(Response::Reply(text))
+        }
+    }
+}
+
+pub fn sub_cancel(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    let removed = context.db_conn.remove_open_substitution_requests(&alias, request.author)?;
+    if removed == 0 {
+        return Err(CommandError::from(format!(
+            "You are not looking for a substitute in {}",
+            alias
+        )));
+    }
+    Ok(Response::Reply(format!("You are no longer looking for a substitute in {}", alias)))
+}
+
+fn sub_channel_helper(
+    db_conn: &DbConnection,
+    admins: &Admins,
+    user_id: UserId,
+    alias: &str,
+    channel_id: ChannelId,
+) -> Result<(), CommandError> {
+    // fail early with a nice message if the game doesn't exist
+    let server = db_conn.game_for_alias(alias)?;
+    check_is_lobby_owner_or_admin(&server, user_id, admins)?;
+    db_conn.set_substitute_channel(alias, Some(channel_id))?;
+    Ok(())
+}
+
+pub fn sub_channel(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    request.no_more_args()?;
+    let channel_id = request.channel()?;
+
+    sub_channel_helper(context.db_conn, context.admins, request.author, &alias, channel_id)?;
+    Ok(Response::Reply(format!("Substitute requests for {} will be posted in this channel", alias)))
+}
+
+fn subs_helper(db_conn: &DbConnection, discord: &dyn Discord, alias: &str) -> Result<Embed, CommandError> {
+    let _ = db_conn.game_for_alias(alias)?;
+    let substitutions = db_conn.substitutions_for_game_alias(alias)?;
+
+    let mut open_text = String::new();
+    let mut history_text = String::new();
+    for substitution in substitutions {
+        match (substitution.incoming, substitution.taken_turn) {
+            (Some(incoming), Some(taken_turn)) => history_text.push_str(&format!(
+                "turn {}: {} from {} to {}\n",
+                taken_turn,
+                nation_desc(substitution.nation_id),
+                discord.user_name(substitution.outgoing)?,
+                discord.user_name(incoming)?,
+            )),
+            _ => open_text.push_str(&format!(
+                "{} (since turn {})\n",
+                nation_desc(substitution.nation_id),
+                substitution.requested_turn,
+            )),
+        }
+    }
+    if open_text.is_empty() {
+        open_text.push_str("-");
+    }
+    if history_text.is_empty() {
+        history_text.push_str("-");
+    }
+
+    let e = Embed::default()
+        .title(format!("{} substitutes", alias))
+        .field("Looking for a substitute", open_text, false)
+        .field("Handovers", history_text, false);
+    Ok(e)
+}
+
+pub fn subs(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    let embed = subs_helper(context.db_conn, context.discord, &alias)?;
+    Ok(Response::Embed(embed))
+}
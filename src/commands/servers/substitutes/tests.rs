@@ -0,0 +1,170 @@
+use super::*;
+
+use std::io;
+
+use crate::discord::FakeDiscord;
+use crate::model::GameData;
+
+#[test]
+fn should_hand_nation_over_to_substitute() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.bar:3000", 5);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    db_conn.register_test_player("foo", UserId(4), 6);
+
+    let nation_ids = sub_needed_helper(db_conn, "foo", UserId(3)).unwrap();
+    assert_eq!(nation_ids, vec![5]);
+
+    let (_, substitution) = sub_take_helper(db_conn, "foo", UserId(7)).unwrap();
+    assert_eq!(substitution.nation_id, 5);
+    assert_eq!(substitution.outgoing, UserId(3));
+    assert_eq!(substitution.incoming, Some(UserId(7)));
+    assert_eq!(substitution.taken_turn, Some(5));
+
+    let mut registrations = db_conn
+        .players_with_nations_for_game_alias("foo")
+        .unwrap()
+        .into_iter()
+        .map(|(player, nation_id)| (player.discord_user_id, nation_id))
+        .collect::<Vec<_>>();
+    registrations.sort_by_key(|&(_, nation_id)| nation_id);
+    assert_eq!(registrations, vec![(UserId(7), 5), (UserId(4), 6)]);
+
+    assert_eq!(db_conn.substitutions_for_game_alias("foo").unwrap(), vec![substitution]);
+}
+
+#[test]
+fn should_not_take_nation_twice() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.bar:3000", 5);
+    db_conn.register_test_player("foo", UserId(3), 5);
+
+    sub_needed_helper(db_conn, "foo", UserId(3)).unwrap();
+    sub_take_helper(db_conn, "foo", UserId(7)).unwrap();
+    assert!(sub_take_helper(db_conn, "foo", UserId(8)).is_err());
+}
+
+#[test]
+fn should_not_ask_twice_for_the_same_nation() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.bar:3000", 5);
+    db_conn.register_test_player("foo", UserId(3), 5);
+
+    sub_needed_helper(db_conn, "foo", UserId(3)).unwrap();
+    assert!(sub_needed_helper(db_conn, "foo", UserId(3)).is_err());
+}
+
+#[test]
+fn should_not_ask_for_any_nation_when_one_is_already_asking() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.bar:3000", 5);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    db_conn.register_test_player("foo", UserId(3), 6);
+    db_conn.insert_substitution_requests("foo", UserId(3), &[6], 5).unwrap();
+
+    assert!(sub_needed_helper(db_conn, "foo", UserId(3)).is_err());
+
+    let nation_ids = db_conn
+        .substitutions_for_game_alias("foo")
+        .unwrap()
+        .into_iter()
+        .map(|substitution| substitution.nation_id)
+        .collect::<Vec<_>>();
+    assert_eq!(nation_ids, vec![6]);
+}
+
+#[test]
+fn should_not_ask_for_substitute_when_not_registered() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.bar:3000", 5);
+    db_conn.register_test_player("foo", UserId(3), 5);
+
+    assert!(sub_needed_helper(db_conn, "foo", UserId(4)).is_err());
+}
+
+#[test]
+fn should_not_substitute_for_yourself() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.bar:3000", 5);
+    db_conn.register_test_player("foo", UserId(3), 5);
+
+    sub_needed_helper(db_conn, "foo", UserId(3)).unwrap();
+    assert!(sub_take_helper(db_conn, "foo", UserId(3)).is_err());
+}
+
+#[test]
+fn should_cancel_open_requests() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.bar:3000", 5);
+    db_conn.register_test_player("foo", UserId(3), 5);
+
+    sub_needed_helper(db_conn, "foo", UserId(3)).unwrap();
+    assert_eq!(db_conn.remove_open_substitution_requests("foo", UserId(3)).unwrap(), 1);
+    assert!(sub_take_helper(db_conn, "foo", UserId(7)).is_err());
+}
+
+#[test]
+fn should_not_take_a_nation_nobody_is_registered_as() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.bar:3000", 5);
+    db_conn.register_test_player("foo", UserId(3), 5);
+
+    sub_needed_helper(db_conn, "foo", UserId(3)).unwrap();
+    // going around !unregister, which would cancel the request
+    db_conn.remove_player_from_game("foo", UserId(3)).unwrap();
+    assert!(sub_take_helper(db_conn, "foo", UserId(7)).is_err());
+
+    let substitutions = db_conn.substitutions_for_game_alias("foo").unwrap();
+    assert_eq!(substitutions.len(), 1);
+    assert_eq!(substitutions[0].incoming, None);
+    assert!(db_conn.players_with_nations_for_game_alias("foo").unwrap().is_empty());
+}
+
+#[test]
+fn should_only_let_the_owner_or_an_admin_set_the_substitute_channel() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_started_lobby("foo", UserId(7), "foo.bar:3000", 5);
+    let admins = Admins(vec![UserId(8)]);
+
+    let err = sub_channel_helper(db_conn, &admins, UserId(2), "foo", ChannelId(3)).unwrap_err();
+    assert_eq!(err.0, "only the owner of foo or a bot admin can do that");
+    assert_eq!(db_conn.substitute_channel_for_game_alias("foo").unwrap(), None);
+
+    sub_channel_helper(db_conn, &admins, UserId(7), "foo", ChannelId(3)).unwrap();
+    assert_eq!(db_conn.substitute_channel_for_game_alias("foo").unwrap(), Some(ChannelId(3)));
+
+    sub_channel_helper(db_conn, &admins, UserId(8), "foo", ChannelId(4)).unwrap();
+    assert_eq!(db_conn.substitute_channel_for_game_alias("foo").unwrap(), Some(ChannelId(4)));
+}
+
+#[test]
+fn should_not_set_the_substitute_channel_for_a_missing_game() {
+    let db_conn = &DbConnection::test();
+    let admins = Admins(vec![UserId(8)]);
+    assert!(sub_channel_helper(db_conn, &admins, UserId(8), "foo", ChannelId(3)).is_err());
+}
+
+#[test]
+fn should_keep_a_handover_when_nobody_can_be_told() {
+    mock_server_connection!(Mock, Err(io::Error::new(io::ErrorKind::Other, "offline")));
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.bar:3000", 5);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    sub_needed_helper(db_conn, "foo", UserId(3)).unwrap();
+    let admins = Admins(vec![]);
+    // the fake can't look anyone up or DM them, and there's no channel to post in
+    let discord = FakeDiscord::new();
+    let context = CommandContext {
+        db_conn,
+        admins: &admins,
+        discord: &discord,
+    };
+    let mut request = Request::new(UserId(7), None, vec!["foo".to_owned()]);
+
+    assert_eq!(
+        sub_take::<Mock>(&context, &mut request).unwrap(),
+        Response::Reply(format!("<@7> has taken over {} in foo from <@3>", nation_desc(5)))
+    );
+    let substitutions = db_conn.substitutions_for_game_alias("foo").unwrap();
+    assert_eq!(substitutions[0].incoming, Some(UserId(7)));
+}
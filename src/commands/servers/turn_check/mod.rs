@@ -1,53 +1,111 @@
-use typemap::ShareMap;
-
-use crate::db::{DbConnection, DbConnectionKey};
-use crate::model::{GameServer, GameServerState, Player, Nation};
+use crate::db::DbConnection;
+use crate::discord::Discord;
+use crate::metrics;
+use crate::notifier::{notify_player, outbox_messages, Notification};
+use crate::model::{GameServer, GameServerState, Player, Nation, NotificationSettings, TurnRecord};
 use crate::model::enums::{NationStatus, SubmissionStatus, Nations};
 use std::{thread, time};
-use serenity::prelude::Mutex;
 use failure::{err_msg, Error};
 use crate::server::ServerConnection;
-use std::error::Error as TraitError;
 use std::collections::HashMap;
 use log::*;
+use chrono::Utc;
+use serenity::model::id::UserId;
+use serenity::model::misc::Mentionable;
+
+#[cfg(test)]
+mod tests;
 
-pub fn check_for_new_turns_every_1_min<C: ServerConnection>(mutex: &Mutex<ShareMap>) {
+/// Has its own handle to the connection pool, so a command that's in the middle of
+/// talking to discord or a game server can't make it skip a check
+pub fn check_for_new_turns_every<C: ServerConnection>(
+    interval: time::Duration,
+    db_conn: &DbConnection,
+    discord: &dyn Discord,
+    status_pages: Option<&StatusPages>,
+    feeds: Option<&Feeds>,
+    lobby_expiry_days: u32,
+) {
+    let mut next_check = time::Instant::now() + interval;
     loop {
-        thread::sleep(time::Duration::from_secs(60));
+        // keep to the interval even when a check is slow, and run straight away if one overran
+        let now = time::Instant::now();
+        if next_check > now {
+            thread::sleep(next_check - now);
+        }
+        next_check = std::cmp::max(next_check, now) + interval;
         info!("checking for new turns!");
-        message_players_if_new_turn::<C>(&mutex).unwrap_or_else(|e| {
+        let started = time::Instant::now();
+        message_players_if_new_turn::<C>(db_conn, discord, lobby_expiry_days).unwrap_or_else(|e| {
             error!("Checking for new turns failed with: {}", e);
         });
+        metrics::observe_poll_cycle(started.elapsed());
+        if let Some(status_pages) = status_pages {
+            status_pages
+                .write::<C, _>(db_conn, |user_id| cached_player_name(discord, user_id), Utc::now())
+                .unwrap_or_else(|e| {
+                    error!("Writing status pages failed with: {}", e);
+                });
+        }
+        if let Some(feeds) = feeds {
+            feeds.write(db_conn).unwrap_or_else(|e| {
+                error!("Writing feeds failed with: {}", e);
+            });
+        }
     }
 }
 
 pub(crate) fn message_players_if_new_turn<C: ServerConnection>(
-    mutex: &Mutex<ShareMap>,
+    db_conn: &DbConnection,
+    discord: &dyn Discord,
+    lobby_expiry_days: u32,
 ) -> Result<(), Error> {
-        let data = mutex.try_lock().ok_or_else(|| err_msg("Could not obtain data mutex"))?;
-    let db_conn = data.get::<DbConnectionKey>().ok_or_else(|| err_msg("no db connection"))?;
     // TODO: transactions
     let servers = db_conn.retrieve_all_servers()?;
+    let lobby_count = servers
+        .iter()
+        .filter(|server| match server.state {
+            GameServerState::Lobby(_) => true,
+            _ => false,
+        })
+        .count();
+    metrics::set_tracked(servers.len() - lobby_count, lobby_count);
     for server in servers {
         let server_name = server.alias.clone();
-        if let Err(err) = check_server_for_new_turn::<C>(&server, db_conn) {
-            println!("error checking {} for turn: {:?}", server_name, err);
+        if let Err(err) = check_server_for_new_turn::<C>(&server, db_conn, discord) {
+            error!("error checking {} for turn: {:?}", server_name, err);
+        };
+        if let Err(err) = check_server_for_reminders::<C>(&server, db_conn, discord) {
+            error!("error sending reminders for {}: {:?}", server_name, err);
         };
     }
+    if let Err(err) = check_lobbies(db_conn, discord, lobby_expiry_days) {
+        error!("error checking lobbies: {:?}", err);
+    };
+    update_pinned_statuses::<C>(db_conn, discord)?;
     Ok(())
 }
 
 struct NewTurnNation {
     player: Player,
     nation_id: usize,
+    detailed: bool,
 }
 
-use crate::server::cache_get;
+use crate::server::snapshot_replace;
+use super::stalls::player_stall_counts_for_turn;
+use super::turns::hours_mins_remaining;
+use super::reminders::{thresholds_crossed, DEFAULT_REMINDER_THRESHOLDS_MINS};
+use super::pin_status::update_pinned_statuses;
+use super::lobby_lifecycle::check_lobbies;
+use super::status_pages::{cached_player_name, StatusPages};
+use super::feeds::Feeds;
 use crate::model::GameData;
 
 struct NewTurnResult {
     nations_to_notify: Vec<NewTurnNation>,
     new_turn_number: i32,
+    turn_timer: i32,
     ai_this_turn: Vec<usize>,
     defeated_this_turn: Vec<usize>,
     possible_stalls: Vec<usize>,
@@ -63,14 +121,12 @@ fn check_server_for_new_turn_helper<C: ServerConnection>(
 ) -> Result<Option<NewTurnResult>, Error> {
     if let GameServerState::StartedState(ref started_state, _) = server.state {
         info!("checking {} for new turn", server.alias);
-        let option_old_data: Option<GameData> = cache_get(&server.alias);
         let new_data = C::get_game_data(&started_state.address)?;
+        let option_old_data: Option<GameData> =
+            snapshot_replace(&started_state.address, new_data.clone());
         let new_turn = started_state.last_seen_turn < new_data.turn;
         if !new_turn { return Ok(None) }
-        let new_turn_no = new_data.turn;
-        let db_found_new_turn = db_conn.update_game_with_possibly_new_turn(&server.alias, new_turn_no)?;
-        if !db_found_new_turn { return Err(err_msg(format!("cache and db disagree game {}", server.alias))); }
-        let players_nations = db_conn.players_with_nations_for_game_alias(&server.alias)?;
+        let players_nations = db_conn.players_with_nations_and_settings_for_game_alias(&server.alias)?;
         if let Some(old_data) = option_old_data {
             Ok(Some(
                 new_turn_from_old(&old_data, &players_nations, new_data)
@@ -85,16 +141,21 @@ fn check_server_for_new_turn_helper<C: ServerConnection>(
     }
 }
 
-fn new_turn_from_old(old: &GameData, players_nations: &[(Player, usize)],  new: GameData) -> NewTurnResult {
+fn new_turn_from_old(
+    old: &GameData,
+    players_nations: &[(Player, usize, NotificationSettings)],
+    new: GameData,
+) -> NewTurnResult {
     let old_ai_nation_ids = old.nations.iter().filter(|&n| n.status == NationStatus::AI).map(|ref n| n.id).collect::<Vec<usize>>();
     let mut new_ai_nation_ids = new.nations.iter().filter(|&n| n.status == NationStatus::AI).map(|ref n| n.id).collect::<Vec<usize>>();
-    new_ai_nation_ids.retain(|ref n| old_ai_nation_ids.contains(n));
+    new_ai_nation_ids.retain(|ref n| !old_ai_nation_ids.contains(n));
 
     let not_submitted_nation_ids = if old.turn + 1 == new.turn && old.turn_timer <= 60 * 1000 {
         old.nations.iter()
             .filter(|&n|
-                n.submitted == SubmissionStatus::NotSubmitted
-                    || n.submitted == SubmissionStatus::PartiallySubmitted)
+                n.status == NationStatus::Human
+                    && (n.submitted == SubmissionStatus::NotSubmitted
+                        || n.submitted == SubmissionStatus::PartiallySubmitted))
             .map(|ref n| n.id).collect::<Vec<usize>>()
     } else {
         Vec::new()
@@ -105,9 +166,17 @@ fn new_turn_from_old(old: &GameData, players_nations: &[(Player, usize)],  new:
     new_turn_nations
 }
 
-fn new_turn_from(players_nations: &[(Player, usize)],  game_data: GameData) -> NewTurnResult {
+fn new_turn_from(
+    players_nations: &[(Player, usize, NotificationSettings)],
+    game_data: GameData,
+) -> NewTurnResult {
     let mut ret = Vec::new();
     let new_turn_number = game_data.turn;
+    let turn_timer = game_data.turn_timer;
+    let defeated_this_turn = game_data.nations.iter()
+        .filter(|&n| n.status == NationStatus::DefeatedThisTurn)
+        .map(|ref n| n.id)
+        .collect::<Vec<usize>>();
 
     let game_data_nations_by_id: HashMap<usize, Nation> = {
         let mut hm = HashMap::new();
@@ -121,11 +190,12 @@ fn new_turn_from(players_nations: &[(Player, usize)],  game_data: GameData) -> N
         }
         hm
     };
-    for &(ref player, nation_id) in players_nations {
-        if player.turn_notifications && game_data_nations_by_id.get(&nation_id).is_some() {
+    for &(ref player, nation_id, ref settings) in players_nations {
+        if settings.turn_notifications && game_data_nations_by_id.get(&nation_id).is_some() {
             ret.push(NewTurnNation{
                 player: player.clone(),
                 nation_id,
+                detailed: settings.detailed,
             });
 
         }
@@ -133,8 +203,9 @@ fn new_turn_from(players_nations: &[(Player, usize)],  game_data: GameData) -> N
     NewTurnResult {
         nations_to_notify: ret,
         new_turn_number,
+        turn_timer,
         ai_this_turn: Vec::new(),
-        defeated_this_turn: Vec::new(), // FIXME
+        defeated_this_turn,
         possible_stalls: Vec::new(),
     }
 }
@@ -142,48 +213,246 @@ fn new_turn_from(players_nations: &[(Player, usize)],  game_data: GameData) -> N
 fn check_server_for_new_turn<C: ServerConnection>(
     server: &GameServer,
     db_conn: &DbConnection,
+    discord: &dyn Discord,
 ) -> Result<(), Error> {
     if let Some(new_turn_result) = check_server_for_new_turn_helper::<C>(server, db_conn)? {
-        for new_turn_nation in new_turn_result.nations_to_notify {
-            let nation_id = new_turn_nation.nation_id;
-            let player = new_turn_nation.player;
-            let &(name, era) = Nations::get_nation_desc(nation_id);
-            let mut text = format!(
-                "your nation {} {} ({}) has a new turn ({}) in {}",
-                era,
-                name,
-                nation_id,
-                new_turn_result.new_turn_number,
-                server.alias
-            );
-            if !new_turn_result.defeated_this_turn.is_empty() {
-                let defeated_this_turn_text = nation_ids_to_comma_name_list(&new_turn_result.defeated_this_turn);
-                text.push_str(&format!(
-                    "\nDefeated nations this turn: {}",
-                    defeated_this_turn_text
-                ));
+        let mut notifications = vec![];
+        if !new_turn_result.possible_stalls.is_empty() {
+            notifications.extend(record_stalls(server, db_conn, discord, &new_turn_result)?);
+        }
+        notifications.extend(new_turn_notifications(server, &new_turn_result));
+
+        // every backend of every recipient gets its own message, so one failing
+        // can't stop the rest, and they're sent by the outbox worker
+        let mut outbox = vec![];
+        for notification in &notifications {
+            outbox.extend(outbox_messages(db_conn, notification)?);
+        }
+        let turn_record = TurnRecord {
+            turn: new_turn_result.new_turn_number,
+            seen_at: Utc::now(),
+            turn_timer: new_turn_result.turn_timer,
+            defeated: new_turn_result.defeated_this_turn.clone(),
+            ai: new_turn_result.ai_this_turn.clone(),
+            possible_stalls: new_turn_result.possible_stalls.clone(),
+        };
+        let db_found_new_turn = db_conn.update_game_with_possibly_new_turn(&server.alias, &turn_record, &outbox)?;
+        if !db_found_new_turn { return Err(err_msg(format!("cache and db disagree game {}", server.alias))); }
+    }
+    Ok(())
+}
+
+/// The game's announcement first, then one for each player. A player with more than
+/// one nation in the game gets a single message about all of them.
+fn new_turn_notifications(server: &GameServer, new_turn_result: &NewTurnResult) -> Vec<Notification> {
+    let new_turn_events_text = new_turn_events_text(new_turn_result);
+
+    let (hours_remaining, mins_remaining) = hours_mins_remaining(new_turn_result.turn_timer);
+    let text = format!(
+        "{} has a new turn ({}), {}h {}m remaining{}",
+        server.alias,
+        new_turn_result.new_turn_number,
+        hours_remaining,
+        mins_remaining,
+        new_turn_events_text
+    );
+    let mut notifications = vec![Notification::for_game(
+        &server.alias,
+        Some(new_turn_result.new_turn_number),
+        "new_turn",
+        text,
+    )];
+
+    for new_turn_nation in &new_turn_result.nations_to_notify {
+        let nation_id = new_turn_nation.nation_id;
+        let user_id = new_turn_nation.player.discord_user_id;
+        let &(name, era) = Nations::get_nation_desc(nation_id);
+        let mut text = format!(
+            "your nation {} {} ({}) has a new turn ({}) in {}",
+            era,
+            name,
+            nation_id,
+            new_turn_result.new_turn_number,
+            server.alias
+        );
+        if new_turn_nation.detailed {
+            text.push_str(&new_turn_events_text);
+        }
+        match notifications.iter_mut().find(|notification| notification.discord_user_id == Some(user_id)) {
+            Some(notification) => {
+                notification.text.push('\n');
+                notification.text.push_str(&text);
+            }
+            None => notifications.push(Notification::for_player(
+                user_id,
+                &server.alias,
+                Some(new_turn_result.new_turn_number),
+                "new_turn",
+                text,
+            )),
+        }
+    }
+    notifications
+}
+
+fn new_turn_events_text(new_turn_result: &NewTurnResult) -> String {
+    let mut text = String::new();
+    if !new_turn_result.defeated_this_turn.is_empty() {
+        let defeated_this_turn_text = nation_ids_to_comma_name_list(&new_turn_result.defeated_this_turn);
+        text.push_str(&format!(
+            "\nDefeated nations this turn: {}",
+            defeated_this_turn_text
+        ));
+    }
+    if !new_turn_result.ai_this_turn.is_empty() {
+        let ai_this_turn_text = nation_ids_to_comma_name_list(&new_turn_result.ai_this_turn);
+        text.push_str(&format!(
+            "\nAI nations this turn: {}",
+            ai_this_turn_text
+        ));
+    }
+    if !new_turn_result.possible_stalls.is_empty() {
+        let possible_stall_text = nation_ids_to_comma_name_list(&new_turn_result.possible_stalls);
+        text.push_str(&format!(
+            "\nPossible stalls this turn: {}",
+            possible_stall_text
+        ));
+    }
+    text
+}
+
+// The stalls happened on the turn that just ended. Returns a warning for the owner
+// if anyone crossed the game's stall threshold.
+fn record_stalls(
+    server: &GameServer,
+    db_conn: &DbConnection,
+    discord: &dyn Discord,
+    new_turn_result: &NewTurnResult,
+) -> Result<Option<Notification>, Error> {
+    let stalled_turn = new_turn_result.new_turn_number - 1;
+    for &nation_id in &new_turn_result.possible_stalls {
+        db_conn.insert_stall(&server.alias, nation_id, stalled_turn)?;
+    }
+
+    if let GameServerState::StartedState(_, Some(ref lobby_state)) = server.state {
+        if let Some(threshold) = db_conn.stall_warning_threshold_for_game_alias(&server.alias)? {
+            let stalls = db_conn.stalls_for_game_alias(&server.alias)?;
+            let mut warnings = vec![];
+            for (player, stalls_before, stalls_after) in player_stall_counts_for_turn(&stalls, stalled_turn) {
+                if stalls_before < threshold as usize && stalls_after >= threshold as usize {
+                    // a mention still reads as their name, and the turn mustn't go unrecorded over it
+                    let name = discord.user_name(player).unwrap_or_else(|e| {
+                        warn!("looking up {} for a stall warning failed with: {}", player, e);
+                        player.mention()
+                    });
+                    warnings.push(format!(
+                        "{} has now stalled {} times in {}",
+                        name,
+                        stalls_after,
+                        server.alias,
+                    ));
+                }
             }
-            if !new_turn_result.ai_this_turn.is_empty() {
-                let ai_this_turn_text = nation_ids_to_comma_name_list(&new_turn_result.ai_this_turn);
-                text.push_str(&format!(
-                    "\nAI nations this turn: {}",
-                    ai_this_turn_text
-                ));
+            if !warnings.is_empty() {
+                return Ok(Some(Notification::for_player(
+                    lobby_state.owner,
+                    &server.alias,
+                    Some(new_turn_result.new_turn_number),
+                    "stall_warning",
+                    warnings.join("\n"),
+                )));
             }
-            if !new_turn_result.possible_stalls.is_empty() {
-                let possible_stall_text = nation_ids_to_comma_name_list(&new_turn_result.possible_stalls);
-                text.push_str(&format!(
-                    "\nPossible stalls this turn: {}",
-                    possible_stall_text
-                ));
+        }
+    }
+    Ok(None)
+}
+
+// DM anyone who still hasn't submitted once the timer drops below one of their thresholds
+fn check_server_for_reminders<C: ServerConnection>(
+    server: &GameServer,
+    db_conn: &DbConnection,
+    discord: &dyn Discord,
+) -> Result<(), Error> {
+    if let GameServerState::StartedState(ref started_state, _) = server.state {
+        let game_data = C::get_game_data(&started_state.address)?;
+        // no timer, or still uploading pretenders
+        if game_data.turn_timer <= 0 || game_data.turn < 1 {
+            return Ok(());
+        }
+        db_conn.remove_old_sent_reminders(&server.alias, game_data.turn)?;
+
+        let players_nations = db_conn.players_with_nations_and_settings_for_game_alias(&server.alias)?;
+        for (player, nation_id, settings) in players_nations {
+            let user_id = player.discord_user_id;
+            let nation = match game_data.nations.iter().find(|n| n.id == nation_id) {
+                Some(nation) => nation,
+                None => continue,
+            };
+            if nation.status != NationStatus::Human
+                || nation.submitted == SubmissionStatus::Submitted {
+                continue;
+            }
+            let thresholds_mins = settings.reminder_thresholds_mins
+                .unwrap_or_else(|| DEFAULT_REMINDER_THRESHOLDS_MINS.to_vec());
+            // one player's DMs being closed shouldn't stop everyone else's reminders
+            if let Err(err) = remind_player(server, db_conn, discord, &game_data, user_id, nation, &thresholds_mins) {
+                error!("error reminding {} about {}: {}", user_id, server.alias, err);
             }
-            let private_channel = player.discord_user_id.create_dm_channel().map_err(|e| err_msg(e.description().to_owned()))?;
-            private_channel.say(&text).map_err(|e| err_msg(e.description().to_owned()))?;
         }
     }
     Ok(())
 }
 
+// Reminders are only recorded once they've been sent, so a failed one is tried again next check
+fn remind_player(
+    server: &GameServer,
+    db_conn: &DbConnection,
+    discord: &dyn Discord,
+    game_data: &GameData,
+    user_id: UserId,
+    nation: &Nation,
+    thresholds_mins: &[i32],
+) -> Result<(), Error> {
+    let already_sent = db_conn.sent_reminder_thresholds(&server.alias, user_id, nation.id, game_data.turn)?;
+    let newly_crossed = thresholds_crossed(game_data.turn_timer, thresholds_mins)
+        .into_iter()
+        .filter(|threshold_mins| !already_sent.contains(threshold_mins))
+        .collect::<Vec<_>>();
+    // only send one message even if several thresholds were passed since the last check
+    if newly_crossed.is_empty() {
+        return Ok(());
+    }
+
+    let (hours_remaining, mins_remaining) = hours_mins_remaining(game_data.turn_timer);
+    let submitted_text = if nation.submitted == SubmissionStatus::PartiallySubmitted {
+        "has only partially submitted"
+    } else {
+        "has not submitted"
+    };
+    let text = format!(
+        "Reminder: your nation {} {} ({}) {} turn {} in {}, {}h {}m remaining",
+        nation.era,
+        nation.name,
+        nation.id,
+        submitted_text,
+        game_data.turn,
+        server.alias,
+        hours_remaining,
+        mins_remaining,
+    );
+    notify_player(db_conn, discord, user_id, &Notification::for_player(
+        user_id,
+        &server.alias,
+        Some(game_data.turn),
+        "reminder",
+        text,
+    ))?;
+    for threshold_mins in newly_crossed {
+        db_conn.insert_sent_reminder(&server.alias, user_id, nation.id, game_data.turn, threshold_mins)?;
+    }
+    Ok(())
+}
+
 fn nation_ids_to_comma_name_list(ids: &[usize]) -> String {
     if ids.is_empty() {
         "<none>".to_owned()
@@ -0,0 +1,349 @@
+use super::*;
+
+use crate::discord::FakeDiscord;
+use crate::notifier::{deliver_outbox, outbox_notifier};
+use serenity::model::id::UserId;
+
+fn nation(id: usize, status: NationStatus, submitted: SubmissionStatus) -> Nation {
+    let &(name, era) = Nations::get_nation_desc(id);
+    Nation {
+        id,
+        status,
+        submitted,
+        connected: false,
+        name: name.to_owned(),
+        era: format!("{}", era),
+    }
+}
+
+fn game_data(turn: i32, turn_timer: i32, nations: Vec<Nation>) -> GameData {
+    GameData {
+        game_name: "foo".to_owned(),
+        nations,
+        turn,
+        turn_timer,
+    }
+}
+
+fn player(id: u64) -> Player {
+    Player {
+        discord_user_id: UserId(id),
+        turn_notifications: true,
+    }
+}
+
+fn settings(turn_notifications: bool, detailed: bool) -> NotificationSettings {
+    NotificationSettings {
+        turn_notifications,
+        reminder_thresholds_mins: None,
+        detailed,
+    }
+}
+
+#[test]
+fn should_only_report_nations_that_became_ai_this_turn() {
+    let old = game_data(3, 10 * 60 * 1000, vec![
+        nation(5, NationStatus::AI, SubmissionStatus::Submitted),
+        nation(6, NationStatus::Human, SubmissionStatus::Submitted),
+    ]);
+    let new = game_data(4, 10 * 60 * 1000, vec![
+        nation(5, NationStatus::AI, SubmissionStatus::Submitted),
+        nation(6, NationStatus::AI, SubmissionStatus::Submitted),
+    ]);
+
+    let result = new_turn_from_old(&old, &[], new);
+    assert_eq!(result.ai_this_turn, vec![6]);
+}
+
+#[test]
+fn should_report_defeated_nations() {
+    let new = game_data(4, 10 * 60 * 1000, vec![
+        nation(5, NationStatus::DefeatedThisTurn, SubmissionStatus::Submitted),
+        nation(6, NationStatus::Human, SubmissionStatus::NotSubmitted),
+    ]);
+
+    let result = new_turn_from(&[], new);
+    assert_eq!(result.defeated_this_turn, vec![5]);
+    assert_eq!(result.new_turn_number, 4);
+    assert_eq!(result.turn_timer, 10 * 60 * 1000);
+}
+
+#[test]
+fn should_report_unsubmitted_humans_as_stalls_when_timer_ran_out() {
+    let old = game_data(3, 30 * 1000, vec![
+        nation(5, NationStatus::Human, SubmissionStatus::NotSubmitted),
+        nation(6, NationStatus::Human, SubmissionStatus::PartiallySubmitted),
+        nation(7, NationStatus::Human, SubmissionStatus::Submitted),
+        nation(8, NationStatus::AI, SubmissionStatus::NotSubmitted),
+    ]);
+    let new = game_data(4, 10 * 60 * 1000, Vec::new());
+
+    let result = new_turn_from_old(&old, &[], new);
+    assert_eq!(result.possible_stalls, vec![5, 6]);
+}
+
+#[test]
+fn should_not_report_stalls_when_everyone_could_still_submit() {
+    let old = game_data(3, 10 * 60 * 1000, vec![
+        nation(5, NationStatus::Human, SubmissionStatus::NotSubmitted),
+    ]);
+    let new = game_data(4, 10 * 60 * 1000, Vec::new());
+
+    let result = new_turn_from_old(&old, &[], new);
+    assert!(result.possible_stalls.is_empty());
+}
+
+#[test]
+fn should_only_notify_players_with_notifications_on_for_the_game() {
+    let new = game_data(4, 10 * 60 * 1000, vec![
+        nation(5, NationStatus::Human, SubmissionStatus::NotSubmitted),
+        nation(6, NationStatus::Human, SubmissionStatus::NotSubmitted),
+    ]);
+    let players_nations = vec![
+        (player(1), 5, settings(true, false)),
+        (player(2), 6, settings(false, true)),
+    ];
+
+    let result = new_turn_from(&players_nations, new);
+    assert_eq!(result.nations_to_notify.len(), 1);
+    assert_eq!(result.nations_to_notify[0].nation_id, 5);
+    assert_eq!(result.nations_to_notify[0].player, player(1));
+    assert!(!result.nations_to_notify[0].detailed);
+}
+
+#[test]
+fn should_check_for_new_turns_while_commands_use_the_db() {
+    use std::io;
+    use crate::model::StartedState;
+    mock_server_connection!(Mock, Ok(game_data(5, 60 * 60 * 1000, vec![])));
+
+    let db_conn = DbConnection::test();
+    db_conn
+        .insert_game_server(&GameServer {
+            alias: "concurrent".to_owned(),
+            state: GameServerState::StartedState(
+                StartedState {
+                    address: "concurrent.turn.check:3000".to_owned(),
+                    last_seen_turn: 5,
+                },
+                None,
+            ),
+        })
+        .unwrap();
+
+    // the checker has its own handle rather than waiting on anything the commands hold
+    let checker_db_conn = db_conn.clone();
+    let checker = thread::spawn(move || message_players_if_new_turn::<Mock>(&checker_db_conn, &FakeDiscord::new(), 30));
+    assert_eq!(db_conn.retrieve_all_servers().unwrap().len(), 1);
+    checker.join().unwrap().unwrap();
+}
+
+#[test]
+fn should_record_new_turns_in_the_turn_history() {
+    use std::io;
+    use crate::model::StartedState;
+    mock_server_connection!(Mock, Ok(game_data(5, 60 * 60 * 1000, vec![
+        nation(3, NationStatus::DefeatedThisTurn, SubmissionStatus::NotSubmitted),
+    ])));
+
+    let db_conn = DbConnection::test();
+    db_conn
+        .insert_game_server(&GameServer {
+            alias: "history".to_owned(),
+            state: GameServerState::StartedState(
+                StartedState {
+                    address: "turn.history:3000".to_owned(),
+                    last_seen_turn: 4,
+                },
+                None,
+            ),
+        })
+        .unwrap();
+
+    message_players_if_new_turn::<Mock>(&db_conn, &FakeDiscord::new(), 30).unwrap();
+    // seeing the same turn again doesn't add it twice
+    message_players_if_new_turn::<Mock>(&db_conn, &FakeDiscord::new(), 30).unwrap();
+
+    let turn_history = db_conn.turn_history_for_game_alias("history").unwrap();
+    assert_eq!(turn_history.len(), 1);
+    assert_eq!(turn_history[0].turn, 5);
+    assert_eq!(turn_history[0].turn_timer, 60 * 60 * 1000);
+    assert_eq!(turn_history[0].defeated, vec![3]);
+}
+
+#[test]
+fn should_queue_new_turn_notifications_in_the_outbox() {
+    use std::io;
+    use crate::model::StartedState;
+    // too long left for a reminder
+    mock_server_connection!(Mock, Ok(game_data(5, 3 * 60 * 60 * 1000, vec![
+        nation(5, NationStatus::Human, SubmissionStatus::NotSubmitted),
+        nation(6, NationStatus::Human, SubmissionStatus::NotSubmitted),
+        nation(7, NationStatus::Human, SubmissionStatus::NotSubmitted),
+    ])));
+
+    let db_conn = DbConnection::test();
+    db_conn
+        .insert_game_server(&GameServer {
+            alias: "outbox".to_owned(),
+            state: GameServerState::StartedState(
+                StartedState {
+                    address: "turn.outbox:3000".to_owned(),
+                    last_seen_turn: 4,
+                },
+                None,
+            ),
+        })
+        .unwrap();
+    db_conn.insert_player(&player(1)).unwrap();
+    db_conn.insert_player(&player(2)).unwrap();
+    db_conn.insert_server_player("outbox", UserId(1), 5).unwrap();
+    db_conn.insert_server_player("outbox", UserId(1), 6).unwrap();
+    db_conn.insert_server_player("outbox", UserId(2), 7).unwrap();
+
+    message_players_if_new_turn::<Mock>(&db_conn, &FakeDiscord::new(), 30).unwrap();
+    message_players_if_new_turn::<Mock>(&db_conn, &FakeDiscord::new(), 30).unwrap();
+
+    let pending = db_conn
+        .pending_outbox_messages(Utc::now() + chrono::Duration::minutes(1), 1)
+        .unwrap();
+    // one DM each, even for the player with two nations, and nothing for the game
+    // since it has no notification channel
+    assert_eq!(pending.len(), 2);
+    assert_eq!(pending[0].message.recipient, Some(UserId(1)));
+    assert_eq!(pending[0].message.backend, "discord_dm");
+    assert_eq!(pending[0].message.turn, 5);
+    assert!(pending[0].message.text.contains("(5)"));
+    assert!(pending[0].message.text.contains("(6)"));
+    assert_eq!(pending[1].message.recipient, Some(UserId(2)));
+}
+
+#[test]
+fn should_dm_players_about_a_new_turn_once_the_outbox_is_delivered() {
+    use std::io;
+    mock_server_connection!(Mock, Ok(game_data(5, 3 * 60 * 60 * 1000, vec![
+        nation(5, NationStatus::Human, SubmissionStatus::NotSubmitted),
+        nation(7, NationStatus::Human, SubmissionStatus::NotSubmitted),
+    ])));
+    let db_conn = DbConnection::test();
+    db_conn.insert_test_game("dms", "dms.turn.check:3000", 4);
+    db_conn.insert_player(&player(1)).unwrap();
+    db_conn.insert_player(&player(2)).unwrap();
+    db_conn.insert_server_player("dms", UserId(1), 5).unwrap();
+    db_conn.insert_server_player("dms", UserId(2), 7).unwrap();
+    let discord = FakeDiscord::new()
+        .with_user(UserId(1), "one")
+        .with_user(UserId(2), "two");
+
+    message_players_if_new_turn::<Mock>(&db_conn, &discord, 30).unwrap();
+    // nothing is sent until the outbox worker runs
+    assert!(discord.sent().is_empty());
+    deliver_outbox(&db_conn, Utc::now() + chrono::Duration::minutes(1), |db_conn, message| {
+        outbox_notifier(db_conn, &discord, message)
+    }).unwrap();
+
+    let dms = discord.dms_to(UserId(1));
+    assert_eq!(dms.len(), 1);
+    assert!(dms[0].contains("dms"));
+    assert!(dms[0].contains("(5)"));
+    assert_eq!(discord.dms_to(UserId(2)).len(), 1);
+    assert_eq!(discord.sent().len(), 2);
+}
+
+#[test]
+fn should_dm_a_reminder_to_players_who_have_not_submitted() {
+    use std::io;
+    mock_server_connection!(Mock, Ok(game_data(5, 90 * 60 * 1000, vec![
+        nation(5, NationStatus::Human, SubmissionStatus::NotSubmitted),
+        nation(7, NationStatus::Human, SubmissionStatus::Submitted),
+    ])));
+    let db_conn = DbConnection::test();
+    db_conn.insert_test_game("remind", "remind.turn.check:3000", 5);
+    db_conn.insert_player(&player(1)).unwrap();
+    db_conn.insert_player(&player(2)).unwrap();
+    db_conn.insert_server_player("remind", UserId(1), 5).unwrap();
+    db_conn.insert_server_player("remind", UserId(2), 7).unwrap();
+    let discord = FakeDiscord::new()
+        .with_user(UserId(1), "one")
+        .with_user(UserId(2), "two");
+
+    message_players_if_new_turn::<Mock>(&db_conn, &discord, 30).unwrap();
+    // the same threshold isn't reminded about twice
+    message_players_if_new_turn::<Mock>(&db_conn, &discord, 30).unwrap();
+
+    let dms = discord.dms_to(UserId(1));
+    assert_eq!(dms.len(), 1);
+    assert!(dms[0].starts_with("Reminder: your nation"));
+    assert!(dms[0].contains("has not submitted turn 5 in remind, 1h 30m remaining"));
+    assert!(discord.dms_to(UserId(2)).is_empty());
+}
+
+#[test]
+fn should_retry_a_reminder_that_could_not_be_sent() {
+    use std::io;
+    mock_server_connection!(Mock, Ok(game_data(5, 90 * 60 * 1000, vec![
+        nation(5, NationStatus::Human, SubmissionStatus::NotSubmitted),
+        nation(7, NationStatus::Human, SubmissionStatus::NotSubmitted),
+    ])));
+    let db_conn = DbConnection::test();
+    db_conn.insert_test_game("remind", "remind.turn.check:3000", 5);
+    db_conn.insert_player(&player(1)).unwrap();
+    db_conn.insert_player(&player(2)).unwrap();
+    db_conn.insert_server_player("remind", UserId(1), 5).unwrap();
+    db_conn.insert_server_player("remind", UserId(2), 7).unwrap();
+    // discord won't DM the first player
+    let discord = FakeDiscord::new().with_user(UserId(2), "two");
+
+    message_players_if_new_turn::<Mock>(&db_conn, &discord, 30).unwrap();
+    assert_eq!(discord.dms_to(UserId(2)).len(), 1);
+    assert!(db_conn.sent_reminder_thresholds("remind", UserId(1), 5, 5).unwrap().is_empty());
+
+    let discord = FakeDiscord::new()
+        .with_user(UserId(1), "one")
+        .with_user(UserId(2), "two");
+    message_players_if_new_turn::<Mock>(&db_conn, &discord, 30).unwrap();
+    assert_eq!(discord.dms_to(UserId(1)).len(), 1);
+    assert!(discord.dms_to(UserId(2)).is_empty());
+}
+
+#[test]
+fn should_warn_about_stalls_even_when_discord_cannot_find_the_player() {
+    use crate::model::{LobbyState, StartedState};
+    use crate::model::enums::{Era, LobbyStatus};
+    let db_conn = DbConnection::test();
+    let server = GameServer {
+        alias: "stalled".to_owned(),
+        state: GameServerState::StartedState(
+            StartedState {
+                address: "stalled.turn.check:3000".to_owned(),
+                last_seen_turn: 4,
+            },
+            Some(LobbyState {
+                owner: UserId(1),
+                era: Era::Early,
+                player_count: 2,
+                description: None,
+                status: LobbyStatus::Open,
+                scheduled_start: None,
+            }),
+        ),
+    };
+    db_conn.insert_game_server(&server).unwrap();
+    db_conn.insert_player(&player(3)).unwrap();
+    db_conn.insert_server_player("stalled", UserId(3), 5).unwrap();
+    db_conn.set_stall_warning_threshold("stalled", Some(1)).unwrap();
+    let new_turn_result = NewTurnResult {
+        nations_to_notify: vec![],
+        new_turn_number: 5,
+        turn_timer: 60 * 60 * 1000,
+        ai_this_turn: vec![],
+        defeated_this_turn: vec![],
+        possible_stalls: vec![5],
+    };
+
+    let warning = record_stalls(&server, &db_conn, &FakeDiscord::new(), &new_turn_result)
+        .unwrap()
+        .unwrap();
+    assert_eq!(warning.discord_user_id, Some(UserId(1)));
+    assert_eq!(warning.text, "<@3> has now stalled 1 times in stalled");
+}
@@ -1,9 +1,8 @@
 use serenity::framework::standard::CommandError;
-use serenity::prelude::Context;
-use serenity::model::channel::Message;
 use serenity::model::id::UserId;
 use log::*;
 
+use crate::commands::core::{CommandContext, Request, Response};
 use crate::server::ServerConnection;
 use crate::db::*;
 use crate::model::{GameServerState, Nation};
@@ -56,18 +55,12 @@ fn turns_helper<C: ServerConnection>(
     Ok(text)
 }
 
-pub fn turns<C: ServerConnection>(
-    context: &mut Context,
-    message: &Message,
-) -> Result<(), CommandError> {
-    let data = context.data.lock();
-    let db_conn = data.get::<DbConnectionKey>()
-        .ok_or_else(|| CommandError("No db connection".to_string()))?;
-    let text = turns_helper::<C>(message.author.id, db_conn)?;
+/// Sent as a DM, so it doesn't clutter the channel
+pub fn turns<C: ServerConnection>(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let text = turns_helper::<C>(request.author, context.db_conn)?;
     info!("turns: replying with: {}", text);
-    let private_channel = message.author.id.create_dm_channel()?;
-    private_channel.say(&text)?;
-    Ok(())
+    context.discord.send_dm(request.author, &text)?;
+    Ok(Response::Nothing)
 }
 
 fn human_nations(nations: &[Nation]) -> i32 {
@@ -90,7 +83,7 @@ fn submitted_nations(nations: &[Nation]) -> i32 {
     })
 }
 
-fn hours_mins_remaining(turn_timer: i32) -> (i32, i32) {
+pub(crate) fn hours_mins_remaining(turn_timer: i32) -> (i32, i32) {
     let total_mins_remaining = turn_timer / (1000 * 60);
     let hours_remaining = total_mins_remaining / 60;
     let mins_remaining = total_mins_remaining - hours_remaining * 60;
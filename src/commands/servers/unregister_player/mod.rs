@@ -1,39 +1,38 @@
-use serenity::framework::standard::{Args, CommandError};
-use serenity::prelude::Context;
-use serenity::model::channel::Message;
+use serenity::framework::standard::CommandError;
 use serenity::model::id::UserId;
 use log::*;
 
-use super::alias_from_arg_or_channel_name;
-use crate::db::{DbConnection, DbConnectionKey};
+use crate::commands::core::{CommandContext, Request, Response};
+use super::waitlist::{notify_promotions, promote_from_waitlist, Promotion};
+use crate::db::DbConnection;
+
+#[cfg(test)]
+mod tests;
 
 fn unregister_player_helper(
     user_id: UserId,
     alias: &str,
     db_conn: &DbConnection,
-) -> Result<(), CommandError> {
+) -> Result<Vec<Promotion>, CommandError> {
     db_conn
         .remove_player_from_game(&alias, user_id)
         .map_err(CommandError::from)?;
-    Ok(())
+    db_conn.remove_waitlist_entry(&alias, user_id)?;
+    db_conn.remove_open_substitution_requests(&alias, user_id)?;
+    db_conn.touch_lobby(&alias)?;
+    promote_from_waitlist(db_conn, &alias)
 }
 
-pub fn unregister_player(
-    context: &mut Context,
-    message: &Message,
-    mut args: Args,
-) -> Result<(), CommandError> {
-    let alias = alias_from_arg_or_channel_name(&mut args, &message)?;
-    let data = context.data.lock();
-    let db_conn = data.get::<DbConnectionKey>().ok_or("No db connection")?;
-    unregister_player_helper(message.author.id, &alias, db_conn)?;
+pub fn unregister_player(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    let promotions = unregister_player_helper(request.author, &alias, context.db_conn)?;
+    notify_promotions(context.db_conn, context.discord, &alias, &promotions);
 
     let text = format!(
         "Removing user {} from all nations in game {}",
-        message.author,
+        context.discord.user_name(request.author)?,
         alias
     );
     info!("{}", text);
-    let _ = message.reply(&text);
-    Ok(())
+    Ok(Response::Reply(text))
 }
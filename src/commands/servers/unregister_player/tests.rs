@@ -0,0 +1,14 @@
+use super::*;
+
+#[test]
+fn should_cancel_open_substitute_requests() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.bar:3000", 5);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    db_conn.insert_substitution_requests("foo", UserId(3), &[5], 5).unwrap();
+
+    unregister_player_helper(UserId(3), "foo", db_conn).unwrap();
+
+    assert!(db_conn.players_with_nations_for_game_alias("foo").unwrap().is_empty());
+    assert_eq!(db_conn.remove_open_substitution_requests("foo", UserId(3)).unwrap(), 0);
+}
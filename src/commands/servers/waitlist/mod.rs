@@ -0,0 +1,210 @@
+use serenity::framework::standard::CommandError;
+use serenity::model::id::UserId;
+use lazy_static::lazy_static;
+use log::*;
+
+use crate::commands::core::{CommandContext, Request, Response};
+use crate::db::DbConnection;
+use crate::discord::Discord;
+use crate::notifier::{notify_player, Notification};
+use crate::model::{GameServerState, LobbyState, Player, WaitlistEntry};
+use crate::model::enums::{LobbyStatus, Nations};
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[cfg(test)]
+mod tests;
+
+/// Someone who was moved off the waitlist into the lobby
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Promotion {
+    pub player: UserId,
+    /// `None` if they still need to pick a nation before their reserved slot counts
+    pub registered_nation_id: Option<u32>,
+}
+
+lazy_static! {
+    // Commands run on serenity's thread pool, and checking for a free slot then taking it
+    // are separate queries. Anything that fills a lobby's slots holds its lock in between
+    // so two commands can't both take the last one.
+    static ref LOBBY_SLOT_LOCKS: Mutex<HashMap<String, Arc<Mutex<()>>>> = Mutex::new(HashMap::new());
+}
+
+/// Hold `.lock()` of the result while checking for and filling the lobby's slots
+pub(crate) fn lobby_slots_lock(alias: &str) -> Arc<Mutex<()>> {
+    let mut locks = LOBBY_SLOT_LOCKS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    locks.entry(alias.to_owned()).or_insert_with(|| Arc::new(Mutex::new(()))).clone()
+}
+
+/// Registered nations plus slots reserved for promoted players, not counting `ignoring`'s reservation
+pub(crate) fn taken_lobby_slots(
+    registered_count: usize,
+    waitlist: &[WaitlistEntry],
+    ignoring: Option<UserId>,
+) -> i32 {
+    let reserved_count = waitlist
+        .iter()
+        .filter(|entry| entry.promoted && Some(entry.player) != ignoring)
+        .count();
+    (registered_count + reserved_count) as i32
+}
+
+/// Adds the player to the back of the waitlist and returns their position in it.
+/// The caller holds the lobby's `lobby_slots_lock`.
+pub(crate) fn join_waitlist_helper(
+    db_conn: &DbConnection,
+    alias: &str,
+    lobby_state: &LobbyState,
+    user_id: UserId,
+    preferred_nation_id: Option<u32>,
+) -> Result<usize, CommandError> {
+    if lobby_state.status != LobbyStatus::Open {
+        return Err(CommandError::from(format!(
+            "{} is {} and not taking new players",
+            alias,
+            lobby_state.status
+        )));
+    }
+    let players_nations = db_conn.players_with_nations_for_game_alias(alias)?;
+    if players_nations.iter().any(|&(ref player, _)| player.discord_user_id == user_id) {
+        return Err(CommandError::from(format!("You are already registered in {}", alias)));
+    }
+    let waitlist = db_conn.waitlist_for_game_alias(alias)?;
+    if taken_lobby_slots(players_nations.len(), &waitlist, Some(user_id)) < lobby_state.player_count {
+        return Err(CommandError::from(format!(
+            "{} still has open slots, use !register instead",
+            alias
+        )));
+    }
+
+    db_conn.insert_player(&Player {
+        discord_user_id: user_id,
+        turn_notifications: true,
+    })?;
+    if !db_conn.insert_waitlist_entry(alias, user_id, preferred_nation_id)? {
+        return Err(CommandError::from(format!("You are already on the waitlist for {}", alias)));
+    }
+    db_conn.touch_lobby(alias)?;
+    Ok(waitlist.iter().filter(|entry| !entry.promoted).count() + 1)
+}
+
+/// Fills any free lobby slots from the front of the waitlist.
+/// Players whose preferred nation is still free are registered straight away,
+/// everyone else gets a slot reserved until they register a nation themselves.
+pub(crate) fn promote_from_waitlist(
+    db_conn: &DbConnection,
+    alias: &str,
+) -> Result<Vec<Promotion>, CommandError> {
+    let lock = lobby_slots_lock(alias);
+    let _slots = lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let server = db_conn.game_for_alias(alias)?;
+    let lobby_state = match server.state {
+        GameServerState::Lobby(ref lobby_state) if lobby_state.status == LobbyStatus::Open => {
+            lobby_state.clone()
+        }
+        _ => return Ok(vec![]),
+    };
+
+    let mut taken_nation_ids = db_conn
+        .players_with_nations_for_game_alias(alias)?
+        .into_iter()
+        .map(|(_, nation_id)| nation_id as u32)
+        .collect::<Vec<_>>();
+    let waitlist = db_conn.waitlist_for_game_alias(alias)?;
+    let mut free_slots = lobby_state.player_count
+        - taken_lobby_slots(taken_nation_ids.len(), &waitlist, None);
+
+    let mut promotions = vec![];
+    for entry in waitlist.into_iter().filter(|entry| !entry.promoted) {
+        if free_slots <= 0 {
+            break;
+        }
+        let registered_nation_id = match entry.preferred_nation_id {
+            Some(nation_id) if !taken_nation_ids.contains(&nation_id) => {
+                db_conn.insert_server_player(alias, entry.player, nation_id)?;
+                db_conn.remove_waitlist_entry(alias, entry.player)?;
+                taken_nation_ids.push(nation_id);
+                Some(nation_id)
+            }
+            _ => {
+                db_conn.promote_waitlist_entry(alias, entry.player)?;
+                None
+            }
+        };
+        info!("promoted {} from the {} waitlist", entry.player, alias);
+        free_slots -= 1;
+        promotions.push(Promotion {
+            player: entry.player,
+            registered_nation_id,
+        });
+    }
+    Ok(promotions)
+}
+
+/// The promotions have already happened, so one player not hearing about theirs doesn't
+/// stop the others
+pub(crate) fn notify_promotions(
+    db_conn: &DbConnection,
+    discord: &dyn Discord,
+    alias: &str,
+    promotions: &[Promotion],
+) {
+    for promotion in promotions {
+        let text = match promotion.registered_nation_id {
+            Some(nation_id) => {
+                let &(nation_name, era) = Nations::get_nation_desc(nation_id as usize);
+                format!(
+                    "A slot opened up in {}. You have been moved off the waitlist and registered as {} {}",
+                    alias,
+                    era,
+                    nation_name
+                )
+            }
+            None => format!(
+                "A slot opened up in {}. It is reserved for you until you pick a nation with `!register <nation> {}`",
+                alias,
+                alias
+            ),
+        };
+        let notification = Notification::for_player(promotion.player, alias, None, "waitlist_promotion", text);
+        if let Err(err) = notify_player(db_conn, discord, promotion.player, &notification) {
+            error!("error telling {} about their promotion in {}: {}", promotion.player, alias, err);
+        }
+    }
+}
+
+pub fn waitlist(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    let db_conn = context.db_conn;
+    let server = db_conn.game_for_alias(&alias)?;
+    let lobby_state = match server.state {
+        GameServerState::Lobby(lobby_state) => lobby_state,
+        GameServerState::StartedState(_, _) => {
+            return Err(CommandError::from(format!("{} has already started", alias)))
+        }
+    };
+    let lock = lobby_slots_lock(&alias);
+    let _slots = lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let position = join_waitlist_helper(db_conn, &alias, &lobby_state, request.author, None)?;
+    Ok(Response::Reply(format!(
+        "{} is full, you are number {} on the waitlist",
+        alias,
+        position
+    )))
+}
+
+pub fn waitlist_leave(context: &CommandContext, request: &mut Request) -> Result<Response, CommandError> {
+    let alias = request.alias()?;
+    request.no_more_args()?;
+
+    if !context.db_conn.remove_waitlist_entry(&alias, request.author)? {
+        return Err(CommandError::from(format!("You are not on the waitlist for {}", alias)));
+    }
+    // they may have been holding a reserved slot
+    let promotions = promote_from_waitlist(context.db_conn, &alias)?;
+    notify_promotions(context.db_conn, context.discord, &alias, &promotions);
+    Ok(Response::Reply(format!("Removed you from the waitlist for {}", alias)))
+}
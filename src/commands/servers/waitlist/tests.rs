@@ -0,0 +1,190 @@
+use super::*;
+
+use crate::discord::FakeDiscord;
+use crate::model::*;
+
+fn registrations(db_conn: &DbConnection, alias: &str) -> Vec<(UserId, usize)> {
+    let mut registrations = db_conn
+        .players_with_nations_for_game_alias(alias)
+        .unwrap()
+        .into_iter()
+        .map(|(player, nation_id)| (player.discord_user_id, nation_id))
+        .collect::<Vec<_>>();
+    registrations.sort_by_key(|&(_, nation_id)| nation_id);
+    registrations
+}
+
+#[test]
+fn should_not_join_waitlist_with_open_slots() {
+    let db_conn = &DbConnection::test();
+    let lobby_state = db_conn.insert_test_lobby("foo", UserId(1), 2);
+    db_conn.register_test_player("foo", UserId(3), 5);
+
+    assert!(join_waitlist_helper(db_conn, "foo", &lobby_state, UserId(4), None).is_err());
+}
+
+#[test]
+fn should_join_waitlist_in_order() {
+    let db_conn = &DbConnection::test();
+    let lobby_state = db_conn.insert_test_lobby("foo", UserId(1), 1);
+    db_conn.register_test_player("foo", UserId(3), 5);
+
+    assert_eq!(join_waitlist_helper(db_conn, "foo", &lobby_state, UserId(4), Some(6)).unwrap(), 1);
+    assert_eq!(join_waitlist_helper(db_conn, "foo", &lobby_state, UserId(5), None).unwrap(), 2);
+    assert!(join_waitlist_helper(db_conn, "foo", &lobby_state, UserId(4), None).is_err());
+    assert!(join_waitlist_helper(db_conn, "foo", &lobby_state, UserId(3), None).is_err());
+
+    assert_eq!(
+        db_conn.waitlist_for_game_alias("foo").unwrap(),
+        vec![
+            WaitlistEntry { player: UserId(4), preferred_nation_id: Some(6), promoted: false },
+            WaitlistEntry { player: UserId(5), preferred_nation_id: None, promoted: false },
+        ]
+    );
+}
+
+#[test]
+fn should_register_promoted_player_with_preferred_nation() {
+    let db_conn = &DbConnection::test();
+    let lobby_state = db_conn.insert_test_lobby("foo", UserId(1), 1);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    join_waitlist_helper(db_conn, "foo", &lobby_state, UserId(4), Some(6)).unwrap();
+    join_waitlist_helper(db_conn, "foo", &lobby_state, UserId(5), Some(7)).unwrap();
+
+    db_conn.remove_player_from_game("foo", UserId(3)).unwrap();
+    let promotions = promote_from_waitlist(db_conn, "foo").unwrap();
+
+    assert_eq!(
+        promotions,
+        vec![Promotion { player: UserId(4), registered_nation_id: Some(6) }]
+    );
+    assert_eq!(registrations(db_conn, "foo"), vec![(UserId(4), 6)]);
+    assert_eq!(
+        db_conn.waitlist_for_game_alias("foo").unwrap(),
+        vec![WaitlistEntry { player: UserId(5), preferred_nation_id: Some(7), promoted: false }]
+    );
+}
+
+#[test]
+fn should_reserve_slot_when_preferred_nation_taken() {
+    let db_conn = &DbConnection::test();
+    let lobby_state = db_conn.insert_test_lobby("foo", UserId(1), 2);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    db_conn.register_test_player("foo", UserId(4), 6);
+    join_waitlist_helper(db_conn, "foo", &lobby_state, UserId(5), Some(5)).unwrap();
+    join_waitlist_helper(db_conn, "foo", &lobby_state, UserId(6), None).unwrap();
+
+    db_conn.remove_player_from_game("foo", UserId(4)).unwrap();
+    let promotions = promote_from_waitlist(db_conn, "foo").unwrap();
+
+    assert_eq!(
+        promotions,
+        vec![Promotion { player: UserId(5), registered_nation_id: None }]
+    );
+    assert_eq!(registrations(db_conn, "foo"), vec![(UserId(3), 5)]);
+
+    // the reserved slot keeps the lobby full for everyone else
+    assert!(promote_from_waitlist(db_conn, "foo").unwrap().is_empty());
+    let waitlist = db_conn.waitlist_for_game_alias("foo").unwrap();
+    assert_eq!(taken_lobby_slots(1, &waitlist, Some(UserId(6))), 2);
+    assert_eq!(taken_lobby_slots(1, &waitlist, Some(UserId(5))), 1);
+}
+
+#[test]
+fn should_not_promote_after_start() {
+    let db_conn = &DbConnection::test();
+    let lobby_state = db_conn.insert_test_lobby("foo", UserId(1), 1);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    join_waitlist_helper(db_conn, "foo", &lobby_state, UserId(4), Some(6)).unwrap();
+
+    db_conn
+        .insert_started_state(
+            "foo",
+            &StartedState {
+                address: "foo.bar:3000".to_owned(),
+                last_seen_turn: -1,
+            },
+        )
+        .unwrap();
+    db_conn.remove_player_from_game("foo", UserId(3)).unwrap();
+
+    assert!(promote_from_waitlist(db_conn, "foo").unwrap().is_empty());
+    assert!(db_conn.waitlist_for_game_alias("foo").unwrap().is_empty());
+}
+
+#[test]
+fn should_only_fill_a_slot_once_when_promoting_concurrently() {
+    use std::thread;
+    let db_conn = DbConnection::test();
+    let lobby_state = db_conn.insert_test_lobby("crowded", UserId(1), 1);
+    db_conn.register_test_player("crowded", UserId(3), 5);
+    for user_id in 4..8 {
+        join_waitlist_helper(&db_conn, "crowded", &lobby_state, UserId(user_id), None).unwrap();
+    }
+    db_conn.remove_player_from_game("crowded", UserId(3)).unwrap();
+
+    let threads = (0..4)
+        .map(|_| {
+            let db_conn = db_conn.clone();
+            thread::spawn(move || promote_from_waitlist(&db_conn, "crowded").unwrap())
+        })
+        .collect::<Vec<_>>();
+    let promotions = threads
+        .into_iter()
+        .flat_map(|thread| thread.join().unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        promotions,
+        vec![Promotion { player: UserId(4), registered_nation_id: None }]
+    );
+}
+
+#[test]
+fn should_tell_promoted_players_the_way_they_chose() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 2);
+    db_conn.register_test_player("foo", UserId(3), 5);
+    db_conn.register_test_player("foo", UserId(4), 6);
+    db_conn.set_player_discord_dms(UserId(4), false).unwrap();
+    let discord = FakeDiscord::new()
+        .with_user(UserId(3), "three")
+        .with_user(UserId(4), "four");
+    let promotions = vec![
+        Promotion {
+            player: UserId(3),
+            registered_nation_id: Some(5),
+        },
+        Promotion {
+            player: UserId(4),
+            registered_nation_id: None,
+        },
+    ];
+
+    notify_promotions(db_conn, &discord, "foo", &promotions);
+
+    assert_eq!(discord.dms_to(UserId(3)).len(), 1);
+    assert!(discord.dms_to(UserId(4)).is_empty());
+}
+
+#[test]
+fn should_tell_every_promoted_player_even_when_one_dm_fails() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_lobby("foo", UserId(1), 2);
+    // the fake can't DM users it doesn't know
+    let discord = FakeDiscord::new().with_user(UserId(4), "four");
+    let promotions = vec![
+        Promotion {
+            player: UserId(3),
+            registered_nation_id: None,
+        },
+        Promotion {
+            player: UserId(4),
+            registered_nation_id: None,
+        },
+    ];
+
+    notify_promotions(db_conn, &discord, "foo", &promotions);
+
+    assert_eq!(discord.dms_to(UserId(4)).len(), 1);
+}
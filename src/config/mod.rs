@@ -0,0 +1,351 @@
+use failure::{err_msg, Error, ResultExt};
+use serde::Deserialize;
+use serenity::model::id::UserId;
+use simplelog::LogLevelFilter;
+
+use crate::commands::Inspector;
+use crate::db::Backups;
+
+use std::fs::File;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests;
+
+/// Used when `DOM5STATUS_CONFIG` isn't set. Unlike an explicitly given file, it's fine for
+/// this one not to exist, in which case everything is defaults plus environment variables.
+pub const DEFAULT_CONFIG_PATH: &str = "resources/config.toml";
+
+/// Ten years, which is as good as never
+const MAX_LOBBY_EXPIRY_DAYS: u32 = 3650;
+
+/// Everything is optional in the file. Each setting can be overridden by the environment
+/// variable named in its comment.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// DOM5STATUS_TOKEN. Takes precedence over `token_file`.
+    pub token: Option<String>,
+    /// DOM5STATUS_TOKEN_FILE
+    pub token_file: PathBuf,
+    /// DOM5STATUS_DB_PATH
+    pub db_path: PathBuf,
+    /// DOM5STATUS_PREFIX
+    pub prefix: String,
+    /// DOM5STATUS_POLL_INTERVAL_SECS
+    pub poll_interval_secs: u64,
+    /// DOM5STATUS_LOG_LEVEL, one of off, error, warn, info, debug or trace
+    pub log_level: String,
+    /// DOM5STATUS_INSPECTOR_BASE_URL
+    pub inspector_base_url: String,
+    /// DOM5STATUS_INSPECTOR_DATA_DIR, the inspector's gamedata tables for answering searches
+    /// with stats. Searches only link to the inspector when unset.
+    pub inspector_data_dir: Option<PathBuf>,
+    /// DOM5STATUS_CONNECT_TIMEOUT_SECS, for connecting to game servers
+    pub connect_timeout_secs: u64,
+    /// DOM5STATUS_READ_TIMEOUT_SECS, for each read from and write to a game server
+    pub read_timeout_secs: u64,
+    /// DOM5STATUS_ADMIN_USER_IDS, comma separated. The bot's owner is always an admin.
+    pub admin_user_ids: Vec<u64>,
+    /// DOM5STATUS_METRICS_ADDRESS, the ip:port to serve Prometheus metrics on. Off when unset.
+    pub metrics_address: Option<String>,
+    /// DOM5STATUS_API_ADDRESS, the ip:port to serve the read-only JSON API on. Off when unset.
+    pub api_address: Option<String>,
+    /// DOM5STATUS_STATUS_PAGE_DIR, where to write HTML status pages after every poll. Off when unset.
+    pub status_page_dir: Option<PathBuf>,
+    /// DOM5STATUS_FEED_DIR, where to write the atom feeds of turn events. Off when unset.
+    pub feed_dir: Option<PathBuf>,
+    /// DOM5STATUS_LOBBY_EXPIRY_DAYS, how long an open or locked lobby can go without activity
+    /// before it's marked as expired
+    pub lobby_expiry_days: u32,
+    pub backup: BackupConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackupConfig {
+    /// DOM5STATUS_BACKUP_DIR
+    pub dir: PathBuf,
+    /// DOM5STATUS_BACKUP_INTERVAL_HOURS. 0 turns scheduled backups off.
+    pub interval_hours: u64,
+    /// DOM5STATUS_BACKUP_KEEP
+    pub keep: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            token: None,
+            token_file: PathBuf::from("resources/token"),
+            db_path: PathBuf::from("resources/dom5bot.db"),
+            prefix: "!".to_owned(),
+            poll_interval_secs: 60,
+            log_level: "debug".to_owned(),
+            inspector_base_url: "https://larzm42.github.io/dom5inspector/".to_owned(),
+            inspector_data_dir: None,
+            connect_timeout_secs: 10,
+            read_timeout_secs: 30,
+            admin_user_ids: vec![],
+            metrics_address: None,
+            api_address: None,
+            status_page_dir: None,
+            feed_dir: None,
+            lobby_expiry_days: 30,
+            backup: BackupConfig::default(),
+        }
+    }
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        BackupConfig {
+            dir: PathBuf::from("resources/backups"),
+            interval_hours: 24,
+            keep: 7,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the file at `DOM5STATUS_CONFIG` (or the default path), applies the
+    /// environment variable overrides and checks the result
+    pub fn load() -> Result<Config, Error> {
+        let config = match std::env::var("DOM5STATUS_CONFIG") {
+            Ok(path) => Config::from_file(Path::new(&path))?,
+            Err(_) => {
+                let path = Path::new(DEFAULT_CONFIG_PATH);
+                if path.is_file() {
+                    Config::from_file(path)?
+                } else {
+                    Config::default()
+                }
+            }
+        };
+        let config = config.with_env_overrides(|name| std::env::var(name).ok())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config, Error> {
+        let mut file = File::open(path).context(format!("Opening config file '{}'", path.display()))?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)
+            .context(format!("Reading config file '{}'", path.display()))?;
+        let config = Config::from_toml(&contents)
+            .context(format!("Parsing config file '{}'", path.display()))?;
+        Ok(config)
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Config, Error> {
+        let config = toml::from_str(contents)?;
+        Ok(config)
+    }
+
+    /// `get_var` looks up an environment variable, and is a parameter so tests don't
+    /// have to touch the real environment
+    pub fn with_env_overrides<F: Fn(&str) -> Option<String>>(mut self, get_var: F) -> Result<Config, Error> {
+        if let Some(token) = get_var("DOM5STATUS_TOKEN") {
+            self.token = Some(token);
+        }
+        if let Some(token_file) = get_var("DOM5STATUS_TOKEN_FILE") {
+            self.token_file = PathBuf::from(token_file);
+        }
+        if let Some(db_path) = get_var("DOM5STATUS_DB_PATH") {
+            self.db_path = PathBuf::from(db_path);
+        }
+        if let Some(prefix) = get_var("DOM5STATUS_PREFIX") {
+            self.prefix = prefix;
+        }
+        if let Some(secs) = get_var("DOM5STATUS_POLL_INTERVAL_SECS") {
+            self.poll_interval_secs = parse_env_var("DOM5STATUS_POLL_INTERVAL_SECS", &secs)?;
+        }
+        if let Some(log_level) = get_var("DOM5STATUS_LOG_LEVEL") {
+            self.log_level = log_level;
+        }
+        if let Some(url) = get_var("DOM5STATUS_INSPECTOR_BASE_URL") {
+            self.inspector_base_url = url;
+        }
+        if let Some(dir) = get_var("DOM5STATUS_INSPECTOR_DATA_DIR") {
+            self.inspector_data_dir = if dir.trim().is_empty() { None } else { Some(PathBuf::from(dir)) };
+        }
+        if let Some(secs) = get_var("DOM5STATUS_CONNECT_TIMEOUT_SECS") {
+            self.connect_timeout_secs = parse_env_var("DOM5STATUS_CONNECT_TIMEOUT_SECS", &secs)?;
+        }
+        if let Some(secs) = get_var("DOM5STATUS_READ_TIMEOUT_SECS") {
+            self.read_timeout_secs = parse_env_var("DOM5STATUS_READ_TIMEOUT_SECS", &secs)?;
+        }
+        if let Some(ids) = get_var("DOM5STATUS_ADMIN_USER_IDS") {
+            self.admin_user_ids = ids
+                .split(',')
+                .map(|id| id.trim())
+                .filter(|id| !id.is_empty())
+                .map(|id| parse_env_var("DOM5STATUS_ADMIN_USER_IDS", id))
+                .collect::<Result<_, _>>()?;
+        }
+        // an empty value turns these back off, so a compose file can override the config file
+        if let Some(address) = get_var("DOM5STATUS_METRICS_ADDRESS") {
+            self.metrics_address = if address.trim().is_empty() { None } else { Some(address) };
+        }
+        if let Some(address) = get_var("DOM5STATUS_API_ADDRESS") {
+            self.api_address = if address.trim().is_empty() { None } else { Some(address) };
+        }
+        if let Some(dir) = get_var("DOM5STATUS_STATUS_PAGE_DIR") {
+            self.status_page_dir = if dir.trim().is_empty() { None } else { Some(PathBuf::from(dir)) };
+        }
+        if let Some(dir) = get_var("DOM5STATUS_FEED_DIR") {
+            self.feed_dir = if dir.trim().is_empty() { None } else { Some(PathBuf::from(dir)) };
+        }
+        if let Some(days) = get_var("DOM5STATUS_LOBBY_EXPIRY_DAYS") {
+            self.lobby_expiry_days = parse_env_var("DOM5STATUS_LOBBY_EXPIRY_DAYS", &days)?;
+        }
+        if let Some(dir) = get_var("DOM5STATUS_BACKUP_DIR") {
+            self.backup.dir = PathBuf::from(dir);
+        }
+        if let Some(hours) = get_var("DOM5STATUS_BACKUP_INTERVAL_HOURS") {
+            self.backup.interval_hours = parse_env_var("DOM5STATUS_BACKUP_INTERVAL_HOURS", &hours)?;
+        }
+        if let Some(keep) = get_var("DOM5STATUS_BACKUP_KEEP") {
+            self.backup.keep = parse_env_var("DOM5STATUS_BACKUP_KEEP", &keep)?;
+        }
+        Ok(self)
+    }
+
+    /// Reports every problem at once rather than one per restart
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut problems = vec![];
+        if let Some(ref token) = self.token {
+            if token.trim().is_empty() {
+                problems.push("token is empty".to_owned());
+            }
+        }
+        if self.prefix.is_empty() || self.prefix.contains(char::is_whitespace) {
+            problems.push(format!("prefix \"{}\" needs to be non-empty with no spaces", self.prefix));
+        }
+        if self.poll_interval_secs == 0 {
+            problems.push("poll_interval_secs needs to be at least 1".to_owned());
+        }
+        if parse_log_level(&self.log_level).is_none() {
+            problems.push(format!(
+                "log_level \"{}\" needs to be one of off, error, warn, info, debug or trace",
+                self.log_level
+            ));
+        }
+        if !self.inspector_base_url.starts_with("http://") && !self.inspector_base_url.starts_with("https://") {
+            problems.push(format!(
+                "inspector_base_url \"{}\" needs to start with http:// or https://",
+                self.inspector_base_url
+            ));
+        }
+        if self.connect_timeout_secs == 0 {
+            problems.push("connect_timeout_secs needs to be at least 1".to_owned());
+        }
+        if self.read_timeout_secs == 0 {
+            problems.push("read_timeout_secs needs to be at least 1".to_owned());
+        }
+        if self.admin_user_ids.contains(&0) {
+            problems.push("admin_user_ids can't contain 0".to_owned());
+        }
+        for &(name, ref address, example) in &[
+            ("metrics_address", &self.metrics_address, "127.0.0.1:9184"),
+            ("api_address", &self.api_address, "127.0.0.1:8080"),
+        ] {
+            if let Some(ref address) = address {
+                if address.parse::<SocketAddr>().is_err() {
+                    problems.push(format!(
+                        "{} \"{}\" needs to be an ip and port like {}",
+                        name, address, example
+                    ));
+                }
+            }
+        }
+        if self.lobby_expiry_days == 0 || self.lobby_expiry_days > MAX_LOBBY_EXPIRY_DAYS {
+            problems.push(format!(
+                "lobby_expiry_days needs to be between 1 and {}",
+                MAX_LOBBY_EXPIRY_DAYS
+            ));
+        }
+        if self.backup.keep == 0 {
+            problems.push("backup.keep needs to be at least 1".to_owned());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(err_msg(format!("Invalid config:\n- {}", problems.join("\n- "))))
+        }
+    }
+
+    pub fn read_token(&self) -> Result<String, Error> {
+        if let Some(ref token) = self.token {
+            return Ok(token.trim().to_owned());
+        }
+        let mut token_file = File::open(&self.token_file)
+            .context(format!("Opening token file '{}'", self.token_file.display()))?;
+        let mut token = String::new();
+        token_file.read_to_string(&mut token)
+            .context(format!("Reading token file '{}'", self.token_file.display()))?;
+        if token.trim().is_empty() {
+            return Err(err_msg(format!("Token file '{}' is empty", self.token_file.display())));
+        }
+        Ok(token.trim().to_owned())
+    }
+
+    pub fn log_level_filter(&self) -> LogLevelFilter {
+        parse_log_level(&self.log_level).unwrap_or(LogLevelFilter::Debug)
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval_secs)
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout_secs)
+    }
+
+    pub fn read_timeout(&self) -> Duration {
+        Duration::from_secs(self.read_timeout_secs)
+    }
+
+    pub fn admins(&self) -> Vec<UserId> {
+        self.admin_user_ids.iter().map(|&id| UserId(id)).collect()
+    }
+
+    /// Relative to the working directory, like the database
+    pub fn backups(&self) -> Result<Backups, Error> {
+        Ok(Backups {
+            dir: std::env::current_dir()?.join(&self.backup.dir),
+            keep: self.backup.keep,
+        })
+    }
+
+    /// Loads the inspector's data if there's a directory for it
+    pub fn inspector(&self) -> Result<Inspector, Error> {
+        let data_dir = match self.inspector_data_dir {
+            Some(ref dir) => Some(std::env::current_dir()?.join(dir)),
+            None => None,
+        };
+        Ok(Inspector::load(&self.inspector_base_url, data_dir.as_ref().map(PathBuf::as_path)))
+    }
+}
+
+fn parse_env_var<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, Error> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| err_msg(format!("Could not understand {}=\"{}\"", name, value)))
+}
+
+fn parse_log_level(log_level: &str) -> Option<LogLevelFilter> {
+    match log_level.to_lowercase().as_ref() {
+        "off" => Some(LogLevelFilter::Off),
+        "error" => Some(LogLevelFilter::Error),
+        "warn" => Some(LogLevelFilter::Warn),
+        "info" => Some(LogLevelFilter::Info),
+        "debug" => Some(LogLevelFilter::Debug),
+        "trace" => Some(LogLevelFilter::Trace),
+        _ => None,
+    }
+}
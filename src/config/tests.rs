@@ -0,0 +1,121 @@
+use super::*;
+
+use std::collections::HashMap;
+
+fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+        .collect();
+    move |name| vars.get(name).cloned()
+}
+
+#[test]
+fn should_default_to_the_old_hard_coded_settings() {
+    let config = Config::from_toml("").unwrap();
+    assert_eq!(config, Config::default());
+    assert_eq!(config.token_file, PathBuf::from("resources/token"));
+    assert_eq!(config.db_path, PathBuf::from("resources/dom5bot.db"));
+    assert_eq!(config.prefix, "!");
+    assert_eq!(config.poll_interval_secs, 60);
+    assert_eq!(config.lobby_expiry_days, 30);
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn should_read_toml() {
+    let config = Config::from_toml(
+        r#"
+        token = "abc"
+        db_path = "/data/bot.db"
+        prefix = "?"
+        poll_interval_secs = 120
+        log_level = "info"
+        inspector_base_url = "https://example.com/inspector/"
+        connect_timeout_secs = 5
+        read_timeout_secs = 15
+        admin_user_ids = [123, 456]
+        metrics_address = "0.0.0.0:9184"
+        api_address = "0.0.0.0:8080"
+
+        [backup]
+        dir = "/data/backups"
+        keep = 3
+        "#,
+    ).unwrap();
+    assert_eq!(config.token, Some("abc".to_owned()));
+    assert_eq!(config.db_path, PathBuf::from("/data/bot.db"));
+    assert_eq!(config.prefix, "?");
+    assert_eq!(config.poll_interval(), Duration::from_secs(120));
+    assert_eq!(config.log_level_filter(), LogLevelFilter::Info);
+    assert_eq!(config.connect_timeout(), Duration::from_secs(5));
+    assert_eq!(config.read_timeout(), Duration::from_secs(15));
+    assert_eq!(config.admins(), vec![UserId(123), UserId(456)]);
+    assert_eq!(config.metrics_address, Some("0.0.0.0:9184".to_owned()));
+    assert_eq!(config.api_address, Some("0.0.0.0:8080".to_owned()));
+    assert_eq!(config.backup.dir, PathBuf::from("/data/backups"));
+    assert_eq!(config.backup.interval_hours, 24);
+    assert_eq!(config.backup.keep, 3);
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn should_reject_unknown_settings() {
+    assert!(Config::from_toml("poll_interval = 60").is_err());
+}
+
+#[test]
+fn should_let_env_vars_override_the_file() {
+    let config = Config::from_toml("prefix = \"?\"\npoll_interval_secs = 120")
+        .unwrap()
+        .with_env_overrides(env(&[
+            ("DOM5STATUS_PREFIX", "$"),
+            ("DOM5STATUS_ADMIN_USER_IDS", "1, 2,3"),
+            ("DOM5STATUS_BACKUP_KEEP", "2"),
+            ("DOM5STATUS_METRICS_ADDRESS", "127.0.0.1:9184"),
+            ("DOM5STATUS_STATUS_PAGE_DIR", ""),
+            ("DOM5STATUS_FEED_DIR", "/data/feeds"),
+            ("DOM5STATUS_INSPECTOR_DATA_DIR", "/data/gamedata"),
+            ("DOM5STATUS_LOBBY_EXPIRY_DAYS", "14"),
+        ]))
+        .unwrap();
+    assert_eq!(config.inspector_data_dir, Some(PathBuf::from("/data/gamedata")));
+    assert_eq!(config.feed_dir, Some(PathBuf::from("/data/feeds")));
+    assert_eq!(config.status_page_dir, None);
+    assert_eq!(config.metrics_address, Some("127.0.0.1:9184".to_owned()));
+    assert_eq!(config.prefix, "$");
+    assert_eq!(config.poll_interval_secs, 120);
+    assert_eq!(config.admin_user_ids, vec![1, 2, 3]);
+    assert_eq!(config.backup.keep, 2);
+    assert_eq!(config.lobby_expiry_days, 14);
+}
+
+#[test]
+fn should_name_the_env_var_that_is_not_a_number() {
+    let err = Config::default()
+        .with_env_overrides(env(&[("DOM5STATUS_POLL_INTERVAL_SECS", "soon")]))
+        .unwrap_err();
+    assert!(err.to_string().contains("DOM5STATUS_POLL_INTERVAL_SECS"));
+}
+
+#[test]
+fn should_report_every_invalid_setting() {
+    let config = Config {
+        prefix: "".to_owned(),
+        poll_interval_secs: 0,
+        log_level: "loud".to_owned(),
+        inspector_base_url: "larzm42.github.io".to_owned(),
+        metrics_address: Some("localhost".to_owned()),
+        api_address: Some("8080".to_owned()),
+        lobby_expiry_days: 0,
+        ..Config::default()
+    };
+    let message = config.validate().unwrap_err().to_string();
+    assert!(message.contains("prefix"));
+    assert!(message.contains("poll_interval_secs"));
+    assert!(message.contains("log_level"));
+    assert!(message.contains("inspector_base_url"));
+    assert!(message.contains("metrics_address"));
+    assert!(message.contains("api_address"));
+    assert!(message.contains("lobby_expiry_days"));
+}
@@ -0,0 +1,171 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use failure::{Error, ResultExt};
+use rusqlite::DatabaseName;
+use log::*;
+
+use super::DbConnection;
+
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+const BACKUP_FILE_PREFIX: &str = "dom5bot-";
+const BACKUP_FILE_SUFFIX: &str = ".db";
+const PARTIAL_FILE_SUFFIX: &str = ".partial";
+const BACKUP_TIME_FORMAT: &str = "%Y-%m-%d-%H%M%S";
+
+/// Where backups go, and how many of them to keep
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backups {
+    pub dir: PathBuf,
+    pub keep: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupFile {
+    pub path: PathBuf,
+    pub taken_at: DateTime<Utc>,
+    pub size_bytes: u64,
+}
+
+impl BackupFile {
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+impl DbConnection {
+    /// Uses sqlite's online backup API, so this is safe while the bot is using the database
+    pub fn backup_to<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), Error> {
+        info!("db::backup_to {}", path.as_ref().display());
+        let conn = &*self.0.clone().get()?;
+        conn.backup(DatabaseName::Main, path, None)?;
+        Ok(())
+    }
+}
+
+impl Backups {
+    /// Takes a backup named after `now`, then deletes the oldest ones beyond `keep`
+    pub fn create(&self, db_conn: &DbConnection, now: DateTime<Utc>) -> Result<BackupFile, Error> {
+        fs::create_dir_all(&self.dir).context(format!("Creating backup directory '{}'", self.dir.display()))?;
+        let file_name = format!(
+            "{}{}{}",
+            BACKUP_FILE_PREFIX,
+            now.format(BACKUP_TIME_FORMAT),
+            BACKUP_FILE_SUFFIX,
+        );
+        let path = self.dir.join(&file_name);
+        // a half-written backup shouldn't ever look like a real one
+        let partial_path = self.dir.join(format!("{}{}", file_name, PARTIAL_FILE_SUFFIX));
+        db_conn.backup_to(&partial_path)?;
+        fs::rename(&partial_path, &path).context(format!("Moving backup to '{}'", path.display()))?;
+        let size_bytes = fs::metadata(&path)?.len();
+        info!("backed up the database to {}", path.display());
+
+        self.rotate()?;
+        Ok(BackupFile {
+            path,
+            taken_at: now,
+            size_bytes,
+        })
+    }
+
+    /// Newest first. Files in the directory that aren't backups are ignored.
+    pub fn list(&self) -> Result<Vec<BackupFile>, Error> {
+        if !self.dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut backups = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if let Some(taken_at) = taken_at_from_file_name(&file_name) {
+                backups.push(BackupFile {
+                    path: entry.path(),
+                    taken_at,
+                    size_bytes: entry.metadata()?.len(),
+                });
+            }
+        }
+        backups.sort_by(|a, b| b.taken_at.cmp(&a.taken_at));
+        Ok(backups)
+    }
+
+    /// Returns the backups that were deleted. The newest backup is always kept.
+    pub fn rotate(&self) -> Result<Vec<BackupFile>, Error> {
+        let keep = std::cmp::max(self.keep, 1);
+        let old_backups = self.list()?.into_iter().skip(keep).collect::<Vec<_>>();
+        for old_backup in &old_backups {
+            info!("deleting old backup {}", old_backup.path.display());
+            fs::remove_file(&old_backup.path)?;
+        }
+        Ok(old_backups)
+    }
+
+    /// Deletes backups left half-written by the bot stopping part way through one, and returns
+    /// their paths. Only call this when no backup is being taken, like at startup.
+    pub fn remove_partials(&self) -> Result<Vec<PathBuf>, Error> {
+        if !self.dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut partials = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if file_name.starts_with(BACKUP_FILE_PREFIX) && file_name.ends_with(PARTIAL_FILE_SUFFIX) {
+                info!("deleting partial backup {}", entry.path().display());
+                fs::remove_file(entry.path())?;
+                partials.push(entry.path());
+            }
+        }
+        Ok(partials)
+    }
+
+    /// Whether the newest backup is older than `interval`, or there isn't one
+    pub fn is_due(&self, interval: Duration, now: DateTime<Utc>) -> Result<bool, Error> {
+        let interval = chrono::Duration::from_std(interval)?;
+        Ok(match self.list()?.first() {
+            Some(newest) => newest.taken_at + interval <= now,
+            None => true,
+        })
+    }
+}
+
+fn taken_at_from_file_name(file_name: &str) -> Option<DateTime<Utc>> {
+    if !file_name.starts_with(BACKUP_FILE_PREFIX) || !file_name.ends_with(BACKUP_FILE_SUFFIX) {
+        return None;
+    }
+    let timestamp = &file_name[BACKUP_FILE_PREFIX.len()..file_name.len() - BACKUP_FILE_SUFFIX.len()];
+    NaiveDateTime::parse_from_str(timestamp, BACKUP_TIME_FORMAT)
+        .ok()
+        .map(|naive| DateTime::<Utc>::from_utc(naive, Utc))
+}
+
+/// Backs up whenever the newest backup is older than `interval`, so restarting the bot
+/// doesn't reset the schedule
+pub fn back_up_every(db_conn: &DbConnection, backups: &Backups, interval: Duration) {
+    if interval.as_secs() == 0 {
+        info!("scheduled backups are turned off");
+        return;
+    }
+    // check often enough that a short interval is still honoured
+    let check_interval = std::cmp::min(interval, Duration::from_secs(10 * 60));
+    loop {
+        let now = Utc::now();
+        let result = backups.is_due(interval, now).and_then(|is_due| {
+            if is_due {
+                backups.create(db_conn, now).map(|_| ())
+            } else {
+                Ok(())
+            }
+        });
+        result.unwrap_or_else(|e| {
+            error!("Scheduled backup failed with: {}", e);
+        });
+        thread::sleep(check_interval);
+    }
+}
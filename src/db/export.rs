@@ -0,0 +1,831 @@
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Bumped whenever the shape of `DbExport` changes. Imports of newer versions are refused.
+pub const DB_EXPORT_VERSION: u32 = 2;
+
+/// Everything `delete_everything.sql` clears, so a `Replace` import loses nothing the export had.
+/// Times are in sqlite's `YYYY-MM-DD HH:MM:SS` format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DbExport {
+    pub version: u32,
+    pub players: Vec<PlayerExport>,
+    pub games: Vec<GameExport>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerExport {
+    pub discord_user_id: u64,
+    pub turn_notifications: bool,
+    #[serde(default)]
+    pub reminder_thresholds_mins: Option<Vec<i32>>,
+    #[serde(default = "default_true")]
+    pub discord_dms: bool,
+    #[serde(default)]
+    pub webhook: Option<WebhookExport>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookExport {
+    pub url: String,
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GameExport {
+    pub alias: String,
+    /// When the game was archived
+    #[serde(default)]
+    pub archived_at: Option<String>,
+    #[serde(default)]
+    pub started: Option<StartedExport>,
+    #[serde(default)]
+    pub lobby: Option<LobbyExport>,
+    #[serde(default)]
+    pub registrations: Vec<RegistrationExport>,
+    #[serde(default)]
+    pub notification_channel_id: Option<u64>,
+    #[serde(default)]
+    pub substitute_channel_id: Option<u64>,
+    #[serde(default)]
+    pub webhook: Option<WebhookExport>,
+    #[serde(default)]
+    pub pinned_status: Option<PinnedStatusExport>,
+    #[serde(default)]
+    pub player_settings: Vec<PlayerGameSettingsExport>,
+    #[serde(default)]
+    pub substitutions: Vec<SubstitutionExport>,
+    #[serde(default)]
+    pub waitlist: Vec<WaitlistExport>,
+    #[serde(default)]
+    pub lobby_start_reminders_mins: Vec<i32>,
+    #[serde(default)]
+    pub sent_reminders: Vec<SentReminderExport>,
+    #[serde(default)]
+    pub stalls: Vec<StallExport>,
+    #[serde(default)]
+    pub turn_history: Vec<TurnRecordExport>,
+    #[serde(default)]
+    pub outbox: Vec<OutboxMessageExport>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StartedExport {
+    pub address: String,
+    pub last_seen_turn: i32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LobbyExport {
+    pub owner_discord_user_id: u64,
+    /// "EA", "MA" or "LA"
+    pub era: String,
+    pub player_count: i32,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// "open", "locked", "cancelled" or "expired"
+    pub status: String,
+    /// UTC
+    #[serde(default)]
+    pub scheduled_start: Option<String>,
+    #[serde(default)]
+    pub stall_warning_threshold: Option<i32>,
+    #[serde(default)]
+    pub last_activity: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegistrationExport {
+    pub discord_user_id: u64,
+    pub nation_id: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PinnedStatusExport {
+    pub channel_id: u64,
+    pub message_id: u64,
+}
+
+/// `None` means the player's global setting
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerGameSettingsExport {
+    pub discord_user_id: u64,
+    #[serde(default)]
+    pub turn_notifications: Option<bool>,
+    #[serde(default)]
+    pub reminder_thresholds_mins: Option<Vec<i32>>,
+    #[serde(default)]
+    pub detailed_notifications: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubstitutionExport {
+    pub nation_id: u32,
+    pub outgoing_discord_user_id: u64,
+    #[serde(default)]
+    pub incoming_discord_user_id: Option<u64>,
+    pub requested_turn: i32,
+    #[serde(default)]
+    pub taken_turn: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WaitlistExport {
+    pub discord_user_id: u64,
+    #[serde(default)]
+    pub nation_id: Option<u32>,
+    pub promoted: bool,
+    pub joined_at: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SentReminderExport {
+    pub discord_user_id: u64,
+    pub nation_id: u32,
+    pub turn: i32,
+    pub threshold_mins: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StallExport {
+    pub nation_id: u32,
+    #[serde(default)]
+    pub discord_user_id: Option<u64>,
+    pub turn: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnRecordExport {
+    pub turn: i32,
+    pub seen_at: String,
+    pub turn_timer: i32,
+    pub defeated_nation_ids: Vec<u32>,
+    pub ai_nation_ids: Vec<u32>,
+    pub possible_stall_nation_ids: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboxMessageExport {
+    pub turn: i32,
+    pub kind: String,
+    /// 0 for the game's own channel and webhook
+    pub recipient_discord_id: u64,
+    pub backend: String,
+    pub text: String,
+    pub attempts: i32,
+    pub next_attempt_at: String,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub delivered_at: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportMode {
+    /// Keep what's already in the database and add everything that doesn't clash with it
+    Merge,
+    /// Delete everything in the database first
+    Replace,
+}
+
+impl ImportMode {
+    pub fn from_string(string: &str) -> Option<ImportMode> {
+        match string.to_lowercase().as_ref() {
+            "merge" => Some(ImportMode::Merge),
+            "replace" => Some(ImportMode::Replace),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    pub games: usize,
+    pub players: usize,
+    pub registrations: usize,
+    /// Everything that was skipped, and why
+    pub conflicts: Vec<String>,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Imported {} games, {} players and {} registrations",
+            self.games, self.players, self.registrations,
+        )?;
+        if self.conflicts.is_empty() {
+            write!(f, " with no conflicts")
+        } else {
+            write!(f, " with {} conflicts:", self.conflicts.len())?;
+            for conflict in &self.conflicts {
+                write!(f, "\n- {}", conflict)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+impl DbConnection {
+    pub fn export(&self) -> Result<DbExport, Error> {
+        info!("db::export");
+        let players = {
+            let conn = &*self.0.clone().get()?;
+            let mut stmt = conn.prepare(include_str!("sql/select_players.sql"))?;
+            let foo = stmt.query_map(&[], player_export_from_row)?;
+            let rows = foo.collect::<Result<Vec<_>, _>>()?;
+            rows.into_iter().collect::<Result<Vec<_>, Error>>()?
+        };
+
+        let mut game_servers: Vec<(GameServer, Option<String>)> = self
+            .retrieve_all_servers()?
+            .into_iter()
+            .map(|game_server| (game_server, None))
+            .collect();
+        for (game_server, archived_at) in self.archived_games()? {
+            game_servers.push((game_server, Some(archived_at)));
+        }
+        game_servers.sort_by(|&(ref a, _), &(ref b, _)| a.alias.cmp(&b.alias));
+
+        let mut games = vec![];
+        for (game_server, archived_at) in game_servers {
+            games.push(self.export_game(game_server, archived_at)?);
+        }
+
+        Ok(DbExport {
+            version: DB_EXPORT_VERSION,
+            players,
+            games,
+        })
+    }
+
+    fn export_game(&self, game_server: GameServer, archived_at: Option<String>) -> Result<GameExport, Error> {
+        let alias = game_server.alias;
+        let mut registrations: Vec<RegistrationExport> = self
+            .players_with_nations_for_game_alias(&alias)?
+            .into_iter()
+            .map(|(player, nation_id)| RegistrationExport {
+                discord_user_id: player.discord_user_id.0,
+                nation_id: nation_id as u32,
+            })
+            .collect();
+        registrations.sort_by_key(|registration| registration.nation_id);
+
+        let (started, maybe_lobby_state) = match game_server.state {
+            GameServerState::Lobby(lobby_state) => (None, Some(lobby_state)),
+            GameServerState::StartedState(started_state, maybe_lobby_state) => (
+                Some(StartedExport {
+                    address: started_state.address,
+                    last_seen_turn: started_state.last_seen_turn,
+                }),
+                maybe_lobby_state,
+            ),
+        };
+
+        let substitutions = self
+            .substitutions_for_game_alias(&alias)?
+            .into_iter()
+            .map(|substitution| SubstitutionExport {
+                nation_id: substitution.nation_id as u32,
+                outgoing_discord_user_id: substitution.outgoing.0,
+                incoming_discord_user_id: substitution.incoming.map(|incoming| incoming.0),
+                requested_turn: substitution.requested_turn,
+                taken_turn: substitution.taken_turn,
+            })
+            .collect();
+        let stalls = self
+            .stalls_for_game_alias(&alias)?
+            .into_iter()
+            .map(|stall| StallExport {
+                nation_id: stall.nation_id as u32,
+                discord_user_id: stall.player.map(|player| player.0),
+                turn: stall.turn,
+            })
+            .collect();
+        let turn_history = self
+            .turn_history_for_game_alias(&alias)?
+            .into_iter()
+            .map(|turn_record| TurnRecordExport {
+                turn: turn_record.turn,
+                seen_at: turn_record.seen_at.format(SQLITE_DATETIME_FORMAT).to_string(),
+                turn_timer: turn_record.turn_timer,
+                defeated_nation_ids: nation_ids_to_export(&turn_record.defeated),
+                ai_nation_ids: nation_ids_to_export(&turn_record.ai),
+                possible_stall_nation_ids: nation_ids_to_export(&turn_record.possible_stalls),
+            })
+            .collect();
+
+        let stall_warning_threshold = self.stall_warning_threshold_for_game_alias(&alias)?;
+        let notification_channel_id = self
+            .notification_channel_for_game_alias(&alias)?
+            .map(|channel_id| channel_id.0);
+        let substitute_channel_id = self
+            .substitute_channel_for_game_alias(&alias)?
+            .map(|channel_id| channel_id.0);
+        let webhook = self.game_webhook(&alias)?.map(|webhook| WebhookExport {
+            url: webhook.url,
+            secret: webhook.secret,
+        });
+        let pinned_status = self
+            .pinned_status_for_game_alias(&alias)?
+            .map(|(channel_id, message_id)| PinnedStatusExport {
+                channel_id: channel_id.0,
+                message_id: message_id.0,
+            });
+
+        // the rest have no other use, so they're read straight from the tables
+        let (lobby, player_settings, waitlist, lobby_start_reminders_mins, sent_reminders, outbox) = {
+            let conn = &*self.0.clone().get()?;
+            let lobby = match maybe_lobby_state {
+                Some(lobby_state) => Some(LobbyExport {
+                    owner_discord_user_id: lobby_state.owner.0,
+                    era: lobby_state.era.to_string(),
+                    player_count: lobby_state.player_count,
+                    description: lobby_state.description,
+                    status: lobby_state.status.to_string(),
+                    scheduled_start: scheduled_start_to_column(lobby_state.scheduled_start),
+                    stall_warning_threshold,
+                    last_activity: query_for_alias(
+                        conn,
+                        include_str!("sql/export_lobby_last_activity.sql"),
+                        &alias,
+                        |row| Ok(row.get(0)),
+                    )?
+                    .into_iter()
+                    .next()
+                    .and_then(|last_activity: Option<String>| last_activity),
+                }),
+                None => None,
+            };
+
+            let player_settings = query_for_alias(
+                conn,
+                include_str!("sql/export_player_game_settings.sql"),
+                &alias,
+                |row| {
+                    let discord_user_id: i64 = row.get(0);
+                    let maybe_thresholds: Option<String> = row.get(2);
+                    Ok(PlayerGameSettingsExport {
+                        discord_user_id: discord_user_id as u64,
+                        turn_notifications: row.get(1),
+                        reminder_thresholds_mins: match maybe_thresholds {
+                            Some(thresholds) => Some(thresholds_from_column(&thresholds)?),
+                            None => None,
+                        },
+                        detailed_notifications: row.get(3),
+                    })
+                },
+            )?;
+            let waitlist = query_for_alias(conn, include_str!("sql/export_waitlist.sql"), &alias, |row| {
+                let discord_user_id: i64 = row.get(0);
+                Ok(WaitlistExport {
+                    discord_user_id: discord_user_id as u64,
+                    nation_id: row.get(1),
+                    promoted: row.get(2),
+                    joined_at: row.get(3),
+                })
+            })?;
+            let lobby_start_reminders_mins = query_for_alias(
+                conn,
+                include_str!("sql/export_lobby_start_reminders.sql"),
+                &alias,
+                |row| Ok(row.get(0)),
+            )?;
+            let sent_reminders = query_for_alias(conn, include_str!("sql/export_sent_reminders.sql"), &alias, |row| {
+                let discord_user_id: i64 = row.get(0);
+                Ok(SentReminderExport {
+                    discord_user_id: discord_user_id as u64,
+                    nation_id: row.get(1),
+                    turn: row.get(2),
+                    threshold_mins: row.get(3),
+                })
+            })?;
+            let outbox = query_for_alias(conn, include_str!("sql/export_outbox.sql"), &alias, |row| {
+                let recipient_discord_id: i64 = row.get(2);
+                Ok(OutboxMessageExport {
+                    turn: row.get(0),
+                    kind: row.get(1),
+                    recipient_discord_id: recipient_discord_id as u64,
+                    backend: row.get(3),
+                    text: row.get(4),
+                    attempts: row.get(5),
+                    next_attempt_at: row.get(6),
+                    last_error: row.get(7),
+                    delivered_at: row.get(8),
+                })
+            })?;
+            (lobby, player_settings, waitlist, lobby_start_reminders_mins, sent_reminders, outbox)
+        };
+
+        Ok(GameExport {
+            alias,
+            archived_at,
+            started,
+            lobby,
+            registrations,
+            notification_channel_id,
+            substitute_channel_id,
+            webhook,
+            pinned_status,
+            player_settings,
+            substitutions,
+            waitlist,
+            lobby_start_reminders_mins,
+            sent_reminders,
+            stalls,
+            turn_history,
+            outbox,
+        })
+    }
+
+    /// Everything happens in one transaction, so a failed import leaves the database untouched.
+    /// Games and players that clash with what's already there are skipped and reported.
+    pub fn import(&self, export: &DbExport, mode: ImportMode) -> Result<ImportReport, Error> {
+        info!("db::import {:?}", mode);
+        if export.version > DB_EXPORT_VERSION {
+            return Err(err_msg(format!(
+                "Export version {} is newer than the supported version {}",
+                export.version, DB_EXPORT_VERSION,
+            )));
+        }
+
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        if mode == ImportMode::Replace {
+            tx.execute_batch(include_str!("sql/delete_everything.sql"))?;
+        }
+
+        let mut existing_players: HashMap<u64, PlayerExport> = {
+            let mut stmt = tx.prepare(include_str!("sql/select_players.sql"))?;
+            let foo = stmt.query_map(&[], player_export_from_row)?;
+            let rows = foo.collect::<Result<Vec<_>, _>>()?;
+            let mut players = HashMap::new();
+            for player in rows {
+                let player = player?;
+                players.insert(player.discord_user_id, player);
+            }
+            players
+        };
+        let (mut existing_aliases, mut existing_addresses) = {
+            let mut stmt = tx.prepare(include_str!("sql/select_game_aliases_and_addresses.sql"))?;
+            let foo = stmt.query_map(&[], |ref row| {
+                let alias: String = row.get(0);
+                let maybe_address: Option<String> = row.get(1);
+                (alias, maybe_address)
+            })?;
+            let mut aliases = HashSet::new();
+            let mut addresses = HashSet::new();
+            for pair in foo {
+                let (alias, maybe_address) = pair?;
+                aliases.insert(alias.to_lowercase());
+                addresses.extend(maybe_address);
+            }
+            (aliases, addresses)
+        };
+
+        let mut report = ImportReport::default();
+
+        for player in &export.players {
+            if let Some(existing_player) = existing_players.get(&player.discord_user_id) {
+                if existing_player != player {
+                    report.conflicts.push(format!(
+                        "player {} already exists with different settings, kept the existing ones",
+                        player.discord_user_id,
+                    ));
+                }
+                continue;
+            }
+            let discord_user_id = player.discord_user_id as i64;
+            tx.execute(
+                include_str!("sql/insert_player.sql"),
+                &[&discord_user_id, &player.turn_notifications],
+            )?;
+            tx.execute(
+                include_str!("sql/update_reminder_thresholds.sql"),
+                &[
+                    &discord_user_id,
+                    &player
+                        .reminder_thresholds_mins
+                        .as_ref()
+                        .map(|thresholds| thresholds_to_column(thresholds)),
+                ],
+            )?;
+            tx.execute(
+                include_str!("sql/update_player_discord_dms.sql"),
+                &[&discord_user_id, &player.discord_dms],
+            )?;
+            tx.execute(
+                include_str!("sql/update_player_webhook.sql"),
+                &[
+                    &discord_user_id,
+                    &player.webhook.as_ref().map(|webhook| webhook.url.clone()),
+                    &player.webhook.as_ref().and_then(|webhook| webhook.secret.clone()),
+                ],
+            )?;
+            existing_players.insert(player.discord_user_id, player.clone());
+            report.players += 1;
+        }
+
+        for game in &export.games {
+            if existing_aliases.contains(&game.alias.to_lowercase()) {
+                report
+                    .conflicts
+                    .push(format!("game {} already exists, skipped it", game.alias));
+                continue;
+            }
+            if let Some(ref started) = game.started {
+                if existing_addresses.contains(&started.address) {
+                    report.conflicts.push(format!(
+                        "game {} uses the address {} of an existing game, skipped it",
+                        game.alias, started.address,
+                    ));
+                    continue;
+                }
+            }
+            let mut nation_ids = HashSet::new();
+            if let Some(registration) = game
+                .registrations
+                .iter()
+                .find(|registration| !nation_ids.insert(registration.nation_id))
+            {
+                report.conflicts.push(format!(
+                    "game {} has nation {} registered more than once, skipped it",
+                    game.alias, registration.nation_id,
+                ));
+                continue;
+            }
+            let game_server = match game_server_from_export(game) {
+                Ok(game_server) => game_server,
+                Err(e) => {
+                    report
+                        .conflicts
+                        .push(format!("game {} is invalid ({}), skipped it", game.alias, e));
+                    continue;
+                }
+            };
+
+            insert_game_server_with(&tx, &game_server)?;
+            import_game_details(&tx, game)?;
+            report.registrations += game.registrations.len();
+
+            existing_aliases.insert(game.alias.to_lowercase());
+            existing_addresses.extend(game.started.as_ref().map(|started| started.address.clone()));
+            report.games += 1;
+        }
+
+        tx.commit()?;
+        info!("db::import finished: {:?}", report);
+        Ok(report)
+    }
+}
+
+/// Everything about an already inserted game apart from its server and lobby
+fn import_game_details(conn: &Connection, game: &GameExport) -> Result<(), Error> {
+    let alias = &game.alias;
+    if let Some(ref archived_at) = game.archived_at {
+        conn.execute(include_str!("sql/update_game_archived_at.sql"), &[alias, archived_at])?;
+    }
+    conn.execute(
+        include_str!("sql/update_game_with_notification_channel.sql"),
+        &[alias, &game.notification_channel_id.map(|channel_id| channel_id as i64)],
+    )?;
+    conn.execute(
+        include_str!("sql/update_game_with_substitute_channel.sql"),
+        &[alias, &game.substitute_channel_id.map(|channel_id| channel_id as i64)],
+    )?;
+    conn.execute(
+        include_str!("sql/update_game_webhook.sql"),
+        &[
+            alias,
+            &game.webhook.as_ref().map(|webhook| webhook.url.clone()),
+            &game.webhook.as_ref().and_then(|webhook| webhook.secret.clone()),
+        ],
+    )?;
+    if let Some(ref lobby) = game.lobby {
+        conn.execute(
+            include_str!("sql/update_lobby_with_stall_warning_threshold.sql"),
+            &[alias, &lobby.stall_warning_threshold],
+        )?;
+        if let Some(ref last_activity) = lobby.last_activity {
+            conn.execute(include_str!("sql/import_lobby_last_activity.sql"), &[alias, last_activity])?;
+        }
+    }
+    if let Some(ref pinned_status) = game.pinned_status {
+        conn.execute(
+            include_str!("sql/insert_pinned_status.sql"),
+            &[alias, &(pinned_status.channel_id as i64), &(pinned_status.message_id as i64)],
+        )?;
+    }
+
+    for registration in &game.registrations {
+        insert_default_player(conn, registration.discord_user_id)?;
+        conn.execute(
+            include_str!("sql/insert_server_player.sql"),
+            &[&registration.nation_id, &(registration.discord_user_id as i64), alias],
+        )?;
+    }
+    for settings in &game.player_settings {
+        insert_default_player(conn, settings.discord_user_id)?;
+        conn.execute(
+            include_str!("sql/import_player_game_settings.sql"),
+            &[
+                alias,
+                &(settings.discord_user_id as i64),
+                &settings.turn_notifications,
+                &settings
+                    .reminder_thresholds_mins
+                    .as_ref()
+                    .map(|thresholds| thresholds_to_column(thresholds)),
+                &settings.detailed_notifications,
+            ],
+        )?;
+    }
+    for substitution in &game.substitutions {
+        insert_default_player(conn, substitution.outgoing_discord_user_id)?;
+        if let Some(incoming) = substitution.incoming_discord_user_id {
+            insert_default_player(conn, incoming)?;
+        }
+        conn.execute(
+            include_str!("sql/import_substitution.sql"),
+            &[
+                alias,
+                &substitution.nation_id,
+                &(substitution.outgoing_discord_user_id as i64),
+                &substitution.incoming_discord_user_id.map(|incoming| incoming as i64),
+                &substitution.requested_turn,
+                &substitution.taken_turn,
+            ],
+        )?;
+    }
+    for entry in &game.waitlist {
+        insert_default_player(conn, entry.discord_user_id)?;
+        conn.execute(
+            include_str!("sql/import_waitlist_entry.sql"),
+            &[
+                alias,
+                &(entry.discord_user_id as i64),
+                &entry.nation_id,
+                &entry.promoted,
+                &entry.joined_at,
+            ],
+        )?;
+    }
+    for threshold_mins in &game.lobby_start_reminders_mins {
+        conn.execute(include_str!("sql/insert_lobby_start_reminder.sql"), &[alias, threshold_mins])?;
+    }
+    for sent_reminder in &game.sent_reminders {
+        insert_default_player(conn, sent_reminder.discord_user_id)?;
+        conn.execute(
+            include_str!("sql/insert_sent_reminder.sql"),
+            &[
+                alias,
+                &(sent_reminder.discord_user_id as i64),
+                &sent_reminder.nation_id,
+                &sent_reminder.turn,
+                &sent_reminder.threshold_mins,
+            ],
+        )?;
+    }
+    for stall in &game.stalls {
+        if let Some(discord_user_id) = stall.discord_user_id {
+            insert_default_player(conn, discord_user_id)?;
+        }
+        conn.execute(
+            include_str!("sql/import_stall.sql"),
+            &[
+                alias,
+                &stall.nation_id,
+                &stall.discord_user_id.map(|discord_user_id| discord_user_id as i64),
+                &stall.turn,
+            ],
+        )?;
+    }
+    for turn_record in &game.turn_history {
+        conn.execute(
+            include_str!("sql/insert_turn_record.sql"),
+            &[
+                alias,
+                &turn_record.turn,
+                &turn_record.seen_at,
+                &turn_record.turn_timer,
+                &nation_ids_from_export(&turn_record.defeated_nation_ids),
+                &nation_ids_from_export(&turn_record.ai_nation_ids),
+                &nation_ids_from_export(&turn_record.possible_stall_nation_ids),
+            ],
+        )?;
+    }
+    for message in &game.outbox {
+        conn.execute(
+            include_str!("sql/import_outbox_message.sql"),
+            &[
+                alias,
+                &message.turn,
+                &message.kind,
+                &(message.recipient_discord_id as i64),
+                &message.backend,
+                &message.text,
+                &message.attempts,
+                &message.next_attempt_at,
+                &message.last_error,
+                &message.delivered_at,
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Players missing from the export get the same defaults as on `!register`
+fn insert_default_player(conn: &Connection, discord_user_id: u64) -> Result<(), Error> {
+    conn.execute(
+        include_str!("sql/insert_player.sql"),
+        &[&(discord_user_id as i64), &true],
+    )?;
+    Ok(())
+}
+
+fn player_export_from_row(row: &Row) -> Result<PlayerExport, Error> {
+    let discord_user_id: i64 = row.get(0);
+    let maybe_thresholds: Option<String> = row.get(2);
+    let maybe_webhook_url: Option<String> = row.get(4);
+    Ok(PlayerExport {
+        discord_user_id: discord_user_id as u64,
+        turn_notifications: row.get(1),
+        reminder_thresholds_mins: match maybe_thresholds {
+            Some(thresholds) => Some(thresholds_from_column(&thresholds)?),
+            None => None,
+        },
+        discord_dms: row.get(3),
+        webhook: maybe_webhook_url.map(|url| WebhookExport {
+            url,
+            secret: row.get(5),
+        }),
+    })
+}
+
+/// Runs a select that takes the game alias as its only parameter
+fn query_for_alias<T, F>(conn: &Connection, sql: &str, alias: &str, f: F) -> Result<Vec<T>, Error>
+where
+    F: FnMut(&Row) -> Result<T, Error>,
+{
+    let mut stmt = conn.prepare(sql)?;
+    let foo = stmt.query_map(&[&alias], f)?;
+    let rows = foo.collect::<Result<Vec<_>, _>>()?;
+    rows.into_iter().collect()
+}
+
+fn nation_ids_to_export(nation_ids: &[usize]) -> Vec<u32> {
+    nation_ids.iter().map(|&nation_id| nation_id as u32).collect()
+}
+
+fn nation_ids_from_export(nation_ids: &[u32]) -> String {
+    nation_ids_to_column(&nation_ids.iter().map(|&nation_id| nation_id as usize).collect::<Vec<_>>())
+}
+
+fn game_server_from_export(game: &GameExport) -> Result<GameServer, Error> {
+    let maybe_lobby_state = match game.lobby {
+        Some(ref lobby) => {
+            let scheduled_start = match lobby.scheduled_start {
+                Some(ref scheduled_start) => {
+                    let naive = NaiveDateTime::parse_from_str(scheduled_start, SQLITE_DATETIME_FORMAT)?;
+                    Some(DateTime::<Utc>::from_utc(naive, Utc))
+                }
+                None => None,
+            };
+            Some(LobbyState {
+                owner: UserId(lobby.owner_discord_user_id),
+                era: Era::from_string(&lobby.era)
+                    .ok_or_else(|| err_msg(format!("unknown era {}", lobby.era)))?,
+                player_count: lobby.player_count,
+                description: lobby.description.clone(),
+                status: LobbyStatus::from_string(&lobby.status)
+                    .ok_or_else(|| err_msg(format!("unknown lobby status {}", lobby.status)))?,
+                scheduled_start,
+            })
+        }
+        None => None,
+    };
+    let state = match (game.started.as_ref(), maybe_lobby_state) {
+        (Some(started), maybe_lobby_state) => GameServerState::StartedState(
+            StartedState {
+                address: started.address.clone(),
+                last_seen_turn: started.last_seen_turn,
+            },
+            maybe_lobby_state,
+        ),
+        (None, Some(lobby_state)) => GameServerState::Lobby(lobby_state),
+        (None, None) => return Err(err_msg("it has neither a lobby nor a started server")),
+    };
+    Ok(GameServer {
+        alias: game.alias.clone(),
+        state,
+    })
+}
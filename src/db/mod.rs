@@ -1,8 +1,7 @@
 use failure::{err_msg, Error};
 use r2d2_sqlite::SqliteConnectionManager;
 use r2d2::Pool;
-use serenity::model::id::UserId;
-use typemap::Key;
+use serenity::model::id::{ChannelId, MessageId, UserId};
 use num_traits::{FromPrimitive, ToPrimitive};
 use log::*;
 use lazy_static::lazy_static;
@@ -12,19 +11,23 @@ use crate::model::enums::*;
 use std::path::Path;
 
 use failure::SyncFailure;
+use rusqlite::types::ToSql;
+use rusqlite::{Connection, Row};
+use chrono::{DateTime, NaiveDateTime, Utc};
 
 use migrant_lib::{Settings, Config, Migrator, list, EmbeddedMigration, Migratable, migration::Statements};
 
 #[cfg(test)]
 pub mod test_helpers;
 
-pub struct DbConnectionKey;
-impl Key for DbConnectionKey {
-    type Value = DbConnection;
-}
+mod export;
+pub use self::export::*;
+
+mod backup;
+pub use self::backup::*;
 
 lazy_static! {
-    static ref MIGRATIONS: [Box<EmbeddedMigration>; 2] = [
+    static ref MIGRATIONS: [Box<EmbeddedMigration>; 14] = [
         Box::new(EmbeddedMigration {
             tag: "001-baseline".to_owned(),
             up: Some(Statements::StaticStr(include_str!("sql/migrations/001_baseline.sql"))),
@@ -35,10 +38,77 @@ lazy_static! {
             up: Some(Statements::StaticStr(include_str!("sql/migrations/002_lobby_description.sql"))),
             down: None,
         }),
+        Box::new(EmbeddedMigration {
+            tag: "003-stalls".to_owned(),
+            up: Some(Statements::StaticStr(include_str!("sql/migrations/003_stalls.sql"))),
+            down: None,
+        }),
+        Box::new(EmbeddedMigration {
+            tag: "004-notification-channel".to_owned(),
+            up: Some(Statements::StaticStr(include_str!("sql/migrations/004_notification_channel.sql"))),
+            down: None,
+        }),
+        Box::new(EmbeddedMigration {
+            tag: "005-reminders".to_owned(),
+            up: Some(Statements::StaticStr(include_str!("sql/migrations/005_reminders.sql"))),
+            down: None,
+        }),
+        Box::new(EmbeddedMigration {
+            tag: "006-player-game-settings".to_owned(),
+            up: Some(Statements::StaticStr(include_str!("sql/migrations/006_player_game_settings.sql"))),
+            down: None,
+        }),
+        Box::new(EmbeddedMigration {
+            tag: "007-pinned-statuses".to_owned(),
+            up: Some(Statements::StaticStr(include_str!("sql/migrations/007_pinned_statuses.sql"))),
+            down: None,
+        }),
+        Box::new(EmbeddedMigration {
+            tag: "008-substitutions".to_owned(),
+            up: Some(Statements::StaticStr(include_str!("sql/migrations/008_substitutions.sql"))),
+            down: None,
+        }),
+        Box::new(EmbeddedMigration {
+            tag: "009-lobby-waitlist".to_owned(),
+            up: Some(Statements::StaticStr(include_str!("sql/migrations/009_lobby_waitlist.sql"))),
+            down: None,
+        }),
+        Box::new(EmbeddedMigration {
+            tag: "010-lobby-lifecycle".to_owned(),
+            up: Some(Statements::StaticStr(include_str!("sql/migrations/010_lobby_lifecycle.sql"))),
+            down: None,
+        }),
+        Box::new(EmbeddedMigration {
+            tag: "011-archived-games".to_owned(),
+            up: Some(Statements::StaticStr(include_str!("sql/migrations/011_archived_games.sql"))),
+            down: None,
+        }),
+        Box::new(EmbeddedMigration {
+            tag: "012-turn-history".to_owned(),
+            up: Some(Statements::StaticStr(include_str!("sql/migrations/012_turn_history.sql"))),
+            down: None,
+        }),
+        Box::new(EmbeddedMigration {
+            tag: "013-notifiers".to_owned(),
+            up: Some(Statements::StaticStr(include_str!("sql/migrations/013_notifiers.sql"))),
+            down: None,
+        }),
+        Box::new(EmbeddedMigration {
+            tag: "014-notification-outbox".to_owned(),
+            up: Some(Statements::StaticStr(include_str!("sql/migrations/014_notification_outbox.sql"))),
+            down: None,
+        }),
     ];
 }
+/// Cheap to clone, all clones share the same pool
+#[derive(Clone)]
 pub struct DbConnection(Pool<SqliteConnectionManager>);
 impl DbConnection {
+    /// Every migration is applied whenever the database is opened
+    pub fn migration_tags() -> Vec<String> {
+        MIGRATIONS.iter().map(|migration| migration.tag.clone()).collect()
+    }
+
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
         let manager = SqliteConnectionManager::file(&path);
         let pool = Pool::new(manager)?;
@@ -99,92 +169,10 @@ impl DbConnection {
     pub fn insert_game_server(&self, game_server: &GameServer) -> Result<(), Error> {
         info!("db::insert_game_server: {:?}", game_server);
         let conn = &mut *self.0.clone().get()?;
-
-        match game_server.state {
-            GameServerState::Lobby(ref lobby_state) => {
-                let tx = conn.transaction()?;
-
-                tx.execute(
-                    include_str!("sql/insert_player.sql"),
-                    &[&(lobby_state.owner.0 as i64), &true],
-                )?;
-
-                tx.execute(
-                    include_str!("sql/insert_lobby.sql"),
-                    &[
-                        &lobby_state.era.to_i32(),
-                        &(lobby_state.owner.0 as i64),
-                        &lobby_state.player_count,
-                        &lobby_state.description,
-                    ],
-                )?;
-                tx.execute(
-                    include_str!("sql/insert_game_server_from_lobby.sql"),
-                    &[
-                        &game_server.alias,
-                        &lobby_state.era.to_i32(),
-                        &(lobby_state.owner.0 as i64),
-                        &lobby_state.player_count,
-                    ],
-                )?;
-                tx.commit()?;
-                Ok(())
-            }
-            GameServerState::StartedState(ref started_state, None) => {
-                let tx = conn.transaction()?;
-                tx.execute(
-                    include_str!("sql/insert_started_server.sql"),
-                    &[&started_state.address, &started_state.last_seen_turn],
-                )?;
-                tx.execute(
-                    include_str!("sql/insert_started_game_server.sql"),
-                    &[&game_server.alias, &started_state.address],
-                )?;
-                tx.commit()?;
-                Ok(())
-            }
-            GameServerState::StartedState(ref started_state, Some(ref lobby_state)) => {
-                let tx = conn.transaction()?;
-                tx.execute(
-                    include_str!("sql/insert_player.sql"),
-                    &[&(lobby_state.owner.0 as i64), &true],
-                )?;
-
-                tx.execute(
-                    include_str!("sql/insert_lobby.sql"),
-                    &[
-                        &lobby_state.era.to_i32(),
-                        &(lobby_state.owner.0 as i64),
-                        &lobby_state.player_count,
-                        &lobby_state.description,
-                    ],
-                )?;
-                tx.execute(
-                    include_str!("sql/insert_game_server_from_lobby.sql"),
-                    &[
-                        &game_server.alias,
-                        &lobby_state.era.to_i32(),
-                        &(lobby_state.owner.0 as i64),
-                        &lobby_state.player_count,
-                    ],
-                )?;
-                tx.execute(
-                    include_str!("sql/insert_started_state.sql"),
-                    &[&started_state.address, &started_state.last_seen_turn],
-                )?;
-
-                tx.execute(
-                    include_str!("sql/update_game_with_started_state.sql"),
-                    &[
-                        &started_state.address,
-                        &started_state.last_seen_turn,
-                        &game_server.alias,
-                    ],
-                )?;
-                tx.commit()?;
-                Ok(())
-            }
-        }
+        let tx = conn.transaction()?;
+        insert_game_server_with(&tx, game_server)?;
+        tx.commit()?;
+        Ok(())
     }
 
     pub fn insert_player(&self, player: &Player) -> Result<(), Error> {
@@ -208,18 +196,12 @@ impl DbConnection {
             let maybe_address: Option<String> = row.get(1);
             let maybe_last_seen_turn: Option<i32> = row.get(2);
             let alias: String = row.get(0);
-            let maybe_owner: Option<i64> = row.get(3);
-            let maybe_era: Option<i32> = row.get(4);
-            let maybe_player_count: Option<i32> = row.get(5);
-            let description: Option<String> = row.get(6);
+            let maybe_lobby_state = lobby_state_from_row(row, 3).unwrap();
             make_game_server(
                 alias,
                 maybe_address,
                 maybe_last_seen_turn,
-                maybe_owner,
-                maybe_era,
-                maybe_player_count,
-                description,
+                maybe_lobby_state,
             ).unwrap()
         })?;
         let vec = foo.collect::<Result<Vec<_>, _>>()?;
@@ -246,26 +228,42 @@ impl DbConnection {
         Ok(vec)
     }
 
+    /// Archived games aren't found, only `restore` and `purge` should see them
     pub fn game_for_alias(&self, game_alias: &str) -> Result<GameServer, Error> {
+        let (server, archived_at) = self.game_and_archived_at_for_alias(game_alias)?;
+        match archived_at {
+            Some(_) => Err(err_msg(format!(
+                "{} is archived, bring it back with !restore {}",
+                game_alias, game_alias
+            ))),
+            None => Ok(server),
+        }
+    }
+
+    pub fn game_for_alias_including_archived(&self, game_alias: &str) -> Result<GameServer, Error> {
+        let (server, _) = self.game_and_archived_at_for_alias(game_alias)?;
+        Ok(server)
+    }
+
+    fn game_and_archived_at_for_alias(
+        &self,
+        game_alias: &str,
+    ) -> Result<(GameServer, Option<String>), Error> {
         info!("db::game_for_alias");
         let conn = &*self.0.clone().get()?;
         let mut stmt = conn.prepare(include_str!("sql/select_game_server_for_alias.sql"))?;
         let foo = stmt.query_map(&[&game_alias], |ref row| {
             let maybe_address: Option<String> = row.get(0);
             let maybe_last_seen_turn: Option<i32> = row.get(1);
-            let maybe_owner: Option<i64> = row.get(2);
-            let maybe_era: Option<i32> = row.get(3);
-            let maybe_player_count: Option<i32> = row.get(4);
-            let description: Option<String> = row.get(5);
-            make_game_server(
+            let maybe_lobby_state = lobby_state_from_row(row, 2).unwrap();
+            let archived_at: Option<String> = row.get(8);
+            let server = make_game_server(
                 game_alias.to_owned(),
                 maybe_address,
                 maybe_last_seen_turn,
-                maybe_owner,
-                maybe_era,
-                maybe_player_count,
-                description,
-            ).unwrap()
+                maybe_lobby_state,
+            ).unwrap();
+            (server, archived_at)
         })?;
         let vec = foo.collect::<Result<Vec<_>, _>>()?;
         if vec.len() == 1 {
@@ -279,28 +277,125 @@ impl DbConnection {
         }
     }
 
+    /// The turn record and outbox messages are only inserted if the turn really is new, and in
+    /// the same transaction, so a turn can't be seen without its history and notifications
     pub fn update_game_with_possibly_new_turn(
         &self,
         game_alias: &str,
-        current_turn: i32,
+        turn_record: &TurnRecord,
+        outbox: &[OutboxMessage],
     ) -> Result<bool, Error> {
         info!("db::update_game_with_possibly_new_turn");
         let conn = &mut *self.0.clone().get()?;
-        let rows = conn.execute(
+        let tx = conn.transaction()?;
+        let rows = tx.execute(
             include_str!("sql/update_game_with_turn.sql"),
-            &[&current_turn, &game_alias],
+            &[&turn_record.turn, &game_alias],
         )?;
+        if rows > 0 {
+            insert_turn_record_with(&tx, game_alias, turn_record)?;
+            let now = turn_record.seen_at.format(SQLITE_DATETIME_FORMAT).to_string();
+            for message in outbox {
+                tx.execute(
+                    include_str!("sql/insert_outbox_message.sql"),
+                    &[
+                        &message.game,
+                        &message.turn,
+                        &message.kind,
+                        &message.recipient.map_or(0, |user_id| user_id.0 as i64),
+                        &message.backend,
+                        &message.text,
+                        &now,
+                    ],
+                )?;
+            }
+        }
+        tx.commit()?;
         info!("db::update_game_with_possibly_new_turn FINISHED");
         Ok(rows > 0)
     }
 
-    pub fn remove_player_from_game(&self, game_alias: &str, user: UserId) -> Result<(), Error> {
-        info!("db::remove_player_from_game");
+    /// Oldest first. Messages that have failed `max_attempts` times stay in the outbox
+    /// with their last error, but aren't tried again.
+    pub fn pending_outbox_messages(
+        &self,
+        now: DateTime<Utc>,
+        max_attempts: i32,
+    ) -> Result<Vec<PendingOutboxMessage>, Error> {
+        info!("db::pending_outbox_messages");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_pending_outbox_messages.sql"))?;
+        let foo = stmt.query_map(
+            &[&now.format(SQLITE_DATETIME_FORMAT).to_string(), &max_attempts],
+            |ref row| {
+                let recipient: i64 = row.get(5);
+                PendingOutboxMessage {
+                    id: row.get(0),
+                    attempts: row.get(1),
+                    message: OutboxMessage {
+                        game: row.get(2),
+                        turn: row.get(3),
+                        kind: row.get(4),
+                        recipient: if recipient == 0 { None } else { Some(UserId(recipient as u64)) },
+                        backend: row.get(6),
+                        text: row.get(7),
+                    },
+                }
+            },
+        )?;
+        let vec = foo.collect::<Result<Vec<_>, _>>()?;
+        Ok(vec)
+    }
+
+    pub fn mark_outbox_message_delivered(&self, id: i64, now: DateTime<Utc>) -> Result<(), Error> {
+        info!("db::mark_outbox_message_delivered");
+        let conn = &*self.0.clone().get()?;
+        conn.execute(
+            include_str!("sql/update_outbox_message_delivered.sql"),
+            &[&id, &now.format(SQLITE_DATETIME_FORMAT).to_string()],
+        )?;
+        Ok(())
+    }
+
+    pub fn mark_outbox_message_failed(
+        &self,
+        id: i64,
+        error: &str,
+        next_attempt_at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        info!("db::mark_outbox_message_failed");
         let conn = &*self.0.clone().get()?;
         conn.execute(
+            include_str!("sql/update_outbox_message_failed.sql"),
+            &[&id, &error, &next_attempt_at.format(SQLITE_DATETIME_FORMAT).to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Delivered messages are kept for a while so the same turn can't be queued twice
+    pub fn delete_delivered_outbox_messages(&self, before: DateTime<Utc>) -> Result<usize, Error> {
+        info!("db::delete_delivered_outbox_messages");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("sql/delete_delivered_outbox_messages.sql"),
+            &[&before.format(SQLITE_DATETIME_FORMAT).to_string()],
+        )?;
+        Ok(rows_modified as usize)
+    }
+
+    pub fn remove_player_from_game(&self, game_alias: &str, user: UserId) -> Result<(), Error> {
+        info!("db::remove_player_from_game");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        tx.execute(
             include_str!("sql/delete_player_from_game.sql"),
             &[&game_alias, &(user.0 as i64)],
         )?;
+        tx.execute(
+            include_str!("sql/delete_player_game_settings.sql"),
+            &[&game_alias, &(user.0 as i64)],
+        )?;
+        tx.commit()?;
         Ok(())
     }
 
@@ -312,6 +407,42 @@ impl DbConnection {
             include_str!("sql/delete_server_players.sql"),
             &[&game_alias],
         )?;
+        tx.execute(
+            include_str!("sql/delete_server_stalls.sql"),
+            &[&game_alias],
+        )?;
+        tx.execute(
+            include_str!("sql/delete_server_turn_history.sql"),
+            &[&game_alias],
+        )?;
+        tx.execute(
+            include_str!("sql/delete_server_outbox.sql"),
+            &[&game_alias],
+        )?;
+        tx.execute(
+            include_str!("sql/delete_server_sent_reminders.sql"),
+            &[&game_alias],
+        )?;
+        tx.execute(
+            include_str!("sql/delete_server_player_game_settings.sql"),
+            &[&game_alias],
+        )?;
+        tx.execute(
+            include_str!("sql/delete_server_pinned_status.sql"),
+            &[&game_alias],
+        )?;
+        tx.execute(
+            include_str!("sql/delete_server_substitutions.sql"),
+            &[&game_alias],
+        )?;
+        tx.execute(
+            include_str!("sql/delete_server_waitlist.sql"),
+            &[&game_alias],
+        )?;
+        tx.execute(
+            include_str!("sql/delete_server_lobby_start_reminders.sql"),
+            &[&game_alias],
+        )?;
         let rows_modified = tx.execute(include_str!("sql/delete_game_server.sql"), &[&game_alias])?;
         tx.execute(include_str!("sql/delete_started_server.sql"), &[])?;
         tx.execute(include_str!("sql/delete_lobby.sql"), &[])?;
@@ -323,6 +454,52 @@ impl DbConnection {
         }
     }
 
+    /// Archived games keep all their data but are left out of listings and turn checks
+    pub fn archive_game(&self, game_alias: &str) -> Result<(), Error> {
+        info!("db::archive_game");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(include_str!("sql/update_game_archived.sql"), &[&game_alias])?;
+        if rows_modified != 0 {
+            Ok(())
+        } else {
+            Err(err_msg(format!("Could not find an unarchived server with name {}", game_alias)))
+        }
+    }
+
+    pub fn restore_game(&self, game_alias: &str) -> Result<(), Error> {
+        info!("db::restore_game");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(include_str!("sql/update_game_restored.sql"), &[&game_alias])?;
+        if rows_modified != 0 {
+            Ok(())
+        } else {
+            Err(err_msg(format!("Could not find an archived server with name {}", game_alias)))
+        }
+    }
+
+    /// Returns each archived game with when it was archived, most recent first
+    pub fn archived_games(&self) -> Result<Vec<(GameServer, String)>, Error> {
+        info!("db::archived_games");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_archived_game_servers.sql"))?;
+        let foo = stmt.query_map(&[], |ref row| {
+            let alias: String = row.get(0);
+            let maybe_address: Option<String> = row.get(1);
+            let maybe_last_seen_turn: Option<i32> = row.get(2);
+            let maybe_lobby_state = lobby_state_from_row(row, 3).unwrap();
+            let archived_at: String = row.get(9);
+            let server = make_game_server(
+                alias,
+                maybe_address,
+                maybe_last_seen_turn,
+                maybe_lobby_state,
+            ).unwrap();
+            (server, archived_at)
+        })?;
+        let vec = foo.collect::<Result<Vec<_>, _>>()?;
+        Ok(vec)
+    }
+
     pub fn servers_for_player(&self, user_id: UserId) -> Result<Vec<(GameServer, i32)>, Error> {
         info!("servers_for_player");
         let conn = &*self.0.clone().get()?;
@@ -332,18 +509,12 @@ impl DbConnection {
             let alias: String = row.get(1);
             let maybe_address: Option<String> = row.get(0);
             let maybe_last_seen_turn: Option<i32> = row.get(2);
-            let maybe_owner: Option<i64> = row.get(4);
-            let maybe_era: Option<i32> = row.get(5);
-            let maybe_player_count: Option<i32> = row.get(6);
-            let description: Option<String> = row.get(7);
+            let maybe_lobby_state = lobby_state_from_row(row, 4).unwrap();
             let server = make_game_server(
                 alias,
                 maybe_address,
                 maybe_last_seen_turn,
-                maybe_owner,
-                maybe_era,
-                maybe_player_count,
-                description,
+                maybe_lobby_state,
             ).unwrap();
 
             let nation_id = row.get(3);
@@ -393,29 +564,29 @@ impl DbConnection {
                 &alias,
             ],
         )?;
+        // nobody is waiting for a lobby slot once the game has started
+        tx.execute(
+            include_str!("sql/delete_server_waitlist.sql"),
+            &[&alias],
+        )?;
         tx.commit()?;
         Ok(())
     }
 
-    pub fn select_lobbies(&self) -> Result<Vec<(GameServer, i32)>, Error> {
+    /// Cancelled and expired lobbies are only included if `include_inactive` is set
+    pub fn select_lobbies(&self, include_inactive: bool) -> Result<Vec<(GameServer, i32)>, Error> {
         info!("select_lobbies");
         let conn = &*self.0.clone().get()?;
         let mut stmt = conn.prepare(include_str!("sql/select_lobbies.sql"))?;
-        let foo = stmt.query_map(&[], |ref row| {
+        let foo = stmt.query_map(&[&include_inactive], |ref row| {
             let alias: String = row.get(0);
-            let maybe_owner: Option<i64> = row.get(1);
-            let maybe_era: Option<i32> = row.get(2);
-            let maybe_player_count: Option<i32> = row.get(3);
-            let registered_player_count: i32 = row.get(4);
-            let description: Option<String> = row.get(5);
+            let maybe_lobby_state = lobby_state_from_row(row, 1).unwrap();
+            let registered_player_count: i32 = row.get(7);
             let server = make_game_server(
                 alias,
                 None,
                 None,
-                maybe_owner,
-                maybe_era,
-                maybe_player_count,
-                description,
+                maybe_lobby_state,
             ).unwrap();
             (server, registered_player_count)
         })?;
@@ -436,59 +607,938 @@ impl DbConnection {
             Err(err_msg(format!("Could not find lobby with name {}", alias)))
         }
     }
-}
 
-fn make_game_server(
-    alias: String,
-    maybe_address: Option<String>,
-    maybe_last_seen_turn: Option<i32>,
-    maybe_owner: Option<i64>,
-    maybe_era: Option<i32>,
-    maybe_player_count: Option<i32>,
-    description: Option<String>,
-) -> Result<GameServer, Error> {
-    let state = match (
-        maybe_address,
-        maybe_last_seen_turn,
-        maybe_owner,
-        maybe_era,
-        maybe_player_count,
-    ) {
-        (Some(address), Some(last_seen_turn), None, None, None) => GameServerState::StartedState(
-            StartedState {
-                address,
-                last_seen_turn,
-            },
-            None,
-        ),
-        (Some(address), Some(last_seen_turn), Some(owner), Some(era), Some(player_count)) => {
-            GameServerState::StartedState(
-                StartedState {
-                    address,
-                    last_seen_turn,
-                },
-                Some(LobbyState {
-                    owner: UserId(owner as u64),
-                    era: Era::from_i32(era).ok_or(err_msg("unknown era"))?,
-                    player_count,
-                    description,
-                }),
-            )
-        }
-        (None, None, Some(owner), Some(era), Some(player_count)) => {
-            GameServerState::Lobby(LobbyState {
-                owner: UserId(owner as u64),
-                era: Era::from_i32(era).ok_or(err_msg("unknown era"))?,
-                player_count,
-                description,
+    pub fn insert_stall(&self, game_alias: &str, nation_id: usize, turn: i32) -> Result<(), Error> {
+        info!("db::insert_stall");
+        let conn = &*self.0.clone().get()?;
+        conn.execute(
+            include_str!("sql/insert_stall.sql"),
+            &[&game_alias, &(nation_id as u32), &turn],
+        )?;
+        Ok(())
+    }
+
+    pub fn stalls_for_game_alias(&self, game_alias: &str) -> Result<Vec<Stall>, Error> {
+        info!("db::stalls_for_game_alias");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_stalls.sql"))?;
+        let foo = stmt.query_map(&[&game_alias], |ref row| {
+            let nation_id: i32 = row.get(0);
+            let maybe_discord_user_id: Option<i64> = row.get(1);
+            Stall {
+                nation_id: nation_id as usize,
+                player: maybe_discord_user_id.map(|id| UserId(id as u64)),
+                turn: row.get(2),
+            }
+        })?;
+        let vec = foo.collect::<Result<Vec<_>, _>>()?;
+        Ok(vec)
+    }
+
+    /// Newest turn first
+    pub fn turn_history_for_game_alias(&self, game_alias: &str) -> Result<Vec<TurnRecord>, Error> {
+        info!("db::turn_history_for_game_alias");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_turn_history.sql"))?;
+        let foo = stmt.query_map(&[&game_alias], |ref row| {
+            let seen_at: String = row.get(1);
+            let defeated: String = row.get(3);
+            let ai: String = row.get(4);
+            let possible_stalls: String = row.get(5);
+            Ok(TurnRecord {
+                turn: row.get(0),
+                seen_at: DateTime::<Utc>::from_utc(
+                    NaiveDateTime::parse_from_str(&seen_at, SQLITE_DATETIME_FORMAT)?,
+                    Utc,
+                ),
+                turn_timer: row.get(2),
+                defeated: nation_ids_from_column(&defeated)?,
+                ai: nation_ids_from_column(&ai)?,
+                possible_stalls: nation_ids_from_column(&possible_stalls)?,
             })
+        })?;
+        let rows = foo.collect::<Result<Vec<_>, _>>()?;
+        let vec = rows.into_iter().collect::<Result<Vec<_>, Error>>()?;
+        Ok(vec)
+    }
+
+    pub fn set_stall_warning_threshold(&self, alias: &str, threshold: Option<i32>) -> Result<(), Error> {
+        info!("db::set_stall_warning_threshold");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("sql/update_lobby_with_stall_warning_threshold.sql"),
+            &[&alias, &threshold],
+        )?;
+        if rows_modified != 0 {
+            Ok(())
+        } else {
+            Err(err_msg(format!("Could not find lobby with name {}", alias)))
         }
-        _ => return Err(err_msg(format!("invalid db state for {}", alias))),
-    };
+    }
 
-    let server = GameServer {
-        alias,
-        state,
-    };
-    Ok(server)
+    pub fn stall_warning_threshold_for_game_alias(&self, alias: &str) -> Result<Option<i32>, Error> {
+        info!("db::stall_warning_threshold_for_game_alias");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_stall_warning_threshold.sql"))?;
+        let foo = stmt.query_map(&[&alias], |ref row| {
+            let threshold: Option<i32> = row.get(0);
+            threshold
+        })?;
+        let vec = foo.collect::<Result<Vec<_>, _>>()?;
+        Ok(vec.into_iter().next().and_then(|threshold| threshold))
+    }
+
+    pub fn set_notification_channel(&self, alias: &str, channel_id: Option<ChannelId>) -> Result<(), Error> {
+        info!("db::set_notification_channel");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("sql/update_game_with_notification_channel.sql"),
+            &[&alias, &channel_id.map(|channel_id| channel_id.0 as i64)],
+        )?;
+        if rows_modified != 0 {
+            Ok(())
+        } else {
+            Err(err_msg(format!("Could not find server with name {}", alias)))
+        }
+    }
+
+    pub fn notification_channel_for_game_alias(&self, alias: &str) -> Result<Option<ChannelId>, Error> {
+        info!("db::notification_channel_for_game_alias");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_notification_channel.sql"))?;
+        let foo = stmt.query_map(&[&alias], |ref row| {
+            let maybe_channel_id: Option<i64> = row.get(0);
+            maybe_channel_id.map(|channel_id| ChannelId(channel_id as u64))
+        })?;
+        let vec = foo.collect::<Result<Vec<_>, _>>()?;
+        Ok(vec.into_iter().next().and_then(|channel_id| channel_id))
+    }
+
+    pub fn set_game_webhook(&self, alias: &str, webhook: Option<&Webhook>) -> Result<(), Error> {
+        info!("db::set_game_webhook");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("sql/update_game_webhook.sql"),
+            &[
+                &alias,
+                &webhook.map(|webhook| webhook.url.clone()),
+                &webhook.and_then(|webhook| webhook.secret.clone()),
+            ],
+        )?;
+        if rows_modified != 0 {
+            Ok(())
+        } else {
+            Err(err_msg(format!("Could not find server with name {}", alias)))
+        }
+    }
+
+    pub fn game_webhook(&self, alias: &str) -> Result<Option<Webhook>, Error> {
+        info!("db::game_webhook");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_game_webhook.sql"))?;
+        let foo = stmt.query_map(&[&alias], |ref row| {
+            let maybe_url: Option<String> = row.get(0);
+            let secret: Option<String> = row.get(1);
+            maybe_url.map(|url| Webhook { url, secret })
+        })?;
+        let vec = foo.collect::<Result<Vec<_>, _>>()?;
+        Ok(vec.into_iter().next().and_then(|webhook| webhook))
+    }
+
+    /// The player has to exist already
+    pub fn set_player_discord_dms(&self, player: UserId, discord_dms: bool) -> Result<(), Error> {
+        info!("db::set_player_discord_dms");
+        let conn = &*self.0.clone().get()?;
+        conn.execute(
+            include_str!("sql/update_player_discord_dms.sql"),
+            &[&(player.0 as i64), &discord_dms],
+        )?;
+        Ok(())
+    }
+
+    /// The player has to exist already
+    pub fn set_player_webhook(&self, player: UserId, webhook: Option<&Webhook>) -> Result<(), Error> {
+        info!("db::set_player_webhook");
+        let conn = &*self.0.clone().get()?;
+        conn.execute(
+            include_str!("sql/update_player_webhook.sql"),
+            &[
+                &(player.0 as i64),
+                &webhook.map(|webhook| webhook.url.clone()),
+                &webhook.and_then(|webhook| webhook.secret.clone()),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Players the bot hasn't seen yet get DMs only
+    pub fn player_notifiers(&self, player: UserId) -> Result<PlayerNotifiers, Error> {
+        info!("db::player_notifiers");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_player_notifiers.sql"))?;
+        let foo = stmt.query_map(&[&(player.0 as i64)], |ref row| {
+            let maybe_url: Option<String> = row.get(1);
+            let secret: Option<String> = row.get(2);
+            PlayerNotifiers {
+                discord_dms: row.get(0),
+                webhook: maybe_url.map(|url| Webhook { url, secret }),
+            }
+        })?;
+        let vec = foo.collect::<Result<Vec<_>, _>>()?;
+        Ok(vec.into_iter().next().unwrap_or_default())
+    }
+
+    /// `None` means the player uses the default reminder thresholds
+    pub fn set_reminder_thresholds(
+        &self,
+        player: UserId,
+        thresholds_mins: Option<&[i32]>,
+    ) -> Result<(), Error> {
+        info!("db::set_reminder_thresholds");
+        let conn = &*self.0.clone().get()?;
+        conn.execute(
+            include_str!("sql/update_reminder_thresholds.sql"),
+            &[&(player.0 as i64), &thresholds_mins.map(thresholds_to_column)],
+        )?;
+        Ok(())
+    }
+
+    pub fn reminder_thresholds(&self, player: UserId) -> Result<Option<Vec<i32>>, Error> {
+        info!("db::reminder_thresholds");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_reminder_thresholds.sql"))?;
+        let foo = stmt.query_map(&[&(player.0 as i64)], |ref row| {
+            let thresholds: Option<String> = row.get(0);
+            thresholds
+        })?;
+        let vec = foo.collect::<Result<Vec<_>, _>>()?;
+        match vec.into_iter().next().and_then(|thresholds| thresholds) {
+            Some(thresholds) => Ok(Some(thresholds_from_column(&thresholds)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn players_with_nations_and_settings_for_game_alias(
+        &self,
+        game_alias: &str,
+    ) -> Result<Vec<(Player, usize, NotificationSettings)>, Error> {
+        info!("db::players_with_nations_and_settings_for_game_alias");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_players_nations_settings.sql"))?;
+        let foo = stmt.query_map(&[&game_alias], |ref row| {
+            let discord_user_id: i64 = row.get(0);
+            let player = Player {
+                discord_user_id: UserId(discord_user_id as u64),
+                turn_notifications: row.get(2),
+            };
+            let nation: i32 = row.get(1);
+            let turn_notifications: bool = row.get(3);
+            let reminder_thresholds: Option<String> = row.get(4);
+            let detailed: bool = row.get(5);
+            (player, nation as usize, turn_notifications, reminder_thresholds, detailed)
+        })?;
+        let mut ret = vec![];
+        for row in foo {
+            let (player, nation_id, turn_notifications, reminder_thresholds, detailed) = row?;
+            let reminder_thresholds_mins = match reminder_thresholds {
+                Some(thresholds) => Some(thresholds_from_column(&thresholds)?),
+                None => None,
+            };
+            let settings = NotificationSettings {
+                turn_notifications,
+                reminder_thresholds_mins,
+                detailed,
+            };
+            ret.push((player, nation_id, settings));
+        }
+        Ok(ret)
+    }
+
+    pub fn set_game_turn_notifications(
+        &self,
+        game_alias: &str,
+        player: UserId,
+        desired_turn_notifications: bool,
+    ) -> Result<(), Error> {
+        info!("db::set_game_turn_notifications");
+        self.update_player_game_setting(
+            game_alias,
+            player,
+            include_str!("sql/update_player_game_turn_notifications.sql"),
+            &desired_turn_notifications,
+        )
+    }
+
+    /// `None` means fall back to the player's global reminder thresholds
+    pub fn set_game_reminder_thresholds(
+        &self,
+        game_alias: &str,
+        player: UserId,
+        thresholds_mins: Option<&[i32]>,
+    ) -> Result<(), Error> {
+        info!("db::set_game_reminder_thresholds");
+        self.update_player_game_setting(
+            game_alias,
+            player,
+            include_str!("sql/update_player_game_reminder_thresholds.sql"),
+            &thresholds_mins.map(thresholds_to_column),
+        )
+    }
+
+    pub fn set_game_detailed_notifications(
+        &self,
+        game_alias: &str,
+        player: UserId,
+        detailed: bool,
+    ) -> Result<(), Error> {
+        info!("db::set_game_detailed_notifications");
+        self.update_player_game_setting(
+            game_alias,
+            player,
+            include_str!("sql/update_player_game_detailed_notifications.sql"),
+            &detailed,
+        )
+    }
+
+    pub fn set_pinned_status(
+        &self,
+        game_alias: &str,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> Result<(), Error> {
+        info!("db::set_pinned_status");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("sql/insert_pinned_status.sql"),
+            &[&game_alias, &(channel_id.0 as i64), &(message_id.0 as i64)],
+        )?;
+        if rows_modified != 0 {
+            Ok(())
+        } else {
+            Err(err_msg(format!("Could not find server with name {}", game_alias)))
+        }
+    }
+
+    pub fn remove_pinned_status(&self, game_alias: &str) -> Result<(), Error> {
+        info!("db::remove_pinned_status");
+        let conn = &*self.0.clone().get()?;
+        conn.execute(
+            include_str!("sql/delete_server_pinned_status.sql"),
+            &[&game_alias],
+        )?;
+        Ok(())
+    }
+
+    pub fn pinned_status_for_game_alias(
+        &self,
+        game_alias: &str,
+    ) -> Result<Option<(ChannelId, MessageId)>, Error> {
+        info!("db::pinned_status_for_game_alias");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_pinned_status.sql"))?;
+        let foo = stmt.query_map(&[&game_alias], |ref row| {
+            let channel_id: i64 = row.get(0);
+            let message_id: i64 = row.get(1);
+            (ChannelId(channel_id as u64), MessageId(message_id as u64))
+        })?;
+        let vec = foo.collect::<Result<Vec<_>, _>>()?;
+        Ok(vec.into_iter().next())
+    }
+
+    pub fn pinned_statuses(&self) -> Result<Vec<(String, ChannelId, MessageId)>, Error> {
+        info!("db::pinned_statuses");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_pinned_statuses.sql"))?;
+        let foo = stmt.query_map(&[], |ref row| {
+            let alias: String = row.get(0);
+            let channel_id: i64 = row.get(1);
+            let message_id: i64 = row.get(2);
+            (alias, ChannelId(channel_id as u64), MessageId(message_id as u64))
+        })?;
+        let vec = foo.collect::<Result<Vec<_>, _>>()?;
+        Ok(vec)
+    }
+
+    /// Asks for a substitute for each of the nations, or none of them if one is already asking
+    pub fn insert_substitution_requests(
+        &self,
+        game_alias: &str,
+        outgoing: UserId,
+        nation_ids: &[usize],
+        requested_turn: i32,
+    ) -> Result<(), Error> {
+        info!("db::insert_substitution_requests");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        for &nation_id in nation_ids {
+            let rows_modified = tx.execute(
+                include_str!("sql/insert_substitution.sql"),
+                &[&game_alias, &(outgoing.0 as i64), &(nation_id as u32), &requested_turn],
+            )?;
+            // dropping the transaction rolls back the requests for the other nations
+            if rows_modified == 0 {
+                return Err(err_msg(format!(
+                    "Nation {} in {} is already looking for a substitute",
+                    nation_id,
+                    game_alias
+                )));
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn substitutions_for_game_alias(&self, game_alias: &str) -> Result<Vec<Substitution>, Error> {
+        info!("db::substitutions_for_game_alias");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_substitutions.sql"))?;
+        let foo = stmt.query_map(&[&game_alias], |ref row| {
+            let nation_id: i32 = row.get(1);
+            let outgoing: i64 = row.get(2);
+            let maybe_incoming: Option<i64> = row.get(3);
+            Substitution {
+                id: row.get(0),
+                nation_id: nation_id as usize,
+                outgoing: UserId(outgoing as u64),
+                incoming: maybe_incoming.map(|incoming| UserId(incoming as u64)),
+                requested_turn: row.get(4),
+                taken_turn: row.get(5),
+            }
+        })?;
+        let vec = foo.collect::<Result<Vec<_>, _>>()?;
+        Ok(vec)
+    }
+
+    /// Moves the nation's registration over to the incoming player
+    pub fn take_substitution(
+        &self,
+        game_alias: &str,
+        substitution: &Substitution,
+        incoming: UserId,
+        taken_turn: i32,
+    ) -> Result<(), Error> {
+        info!("db::take_substitution");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            include_str!("sql/insert_player.sql"),
+            &[&(incoming.0 as i64), &true],
+        )?;
+        let rows_modified = tx.execute(
+            include_str!("sql/update_substitution_taken.sql"),
+            &[&substitution.id, &(incoming.0 as i64), &taken_turn],
+        )?;
+        if rows_modified == 0 {
+            return Err(err_msg("Somebody else has already taken that nation"));
+        }
+        let rows_modified = tx.execute(
+            include_str!("sql/update_server_player_for_substitution.sql"),
+            &[&game_alias, &(substitution.nation_id as u32), &(incoming.0 as i64)],
+        )?;
+        // dropping the transaction rolls back the substitution being taken
+        if rows_modified != 1 {
+            return Err(err_msg(format!(
+                "Nobody is registered as nation {} in {} any more",
+                substitution.nation_id, game_alias
+            )));
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn remove_open_substitution_requests(&self, game_alias: &str, outgoing: UserId) -> Result<usize, Error> {
+        info!("db::remove_open_substitution_requests");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("sql/delete_open_substitutions.sql"),
+            &[&game_alias, &(outgoing.0 as i64)],
+        )?;
+        Ok(rows_modified as usize)
+    }
+
+    pub fn set_substitute_channel(&self, alias: &str, channel_id: Option<ChannelId>) -> Result<(), Error> {
+        info!("db::set_substitute_channel");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("sql/update_game_with_substitute_channel.sql"),
+            &[&alias, &channel_id.map(|channel_id| channel_id.0 as i64)],
+        )?;
+        if rows_modified != 0 {
+            Ok(())
+        } else {
+            Err(err_msg(format!("Could not find server with name {}", alias)))
+        }
+    }
+
+    pub fn substitute_channel_for_game_alias(&self, alias: &str) -> Result<Option<ChannelId>, Error> {
+        info!("db::substitute_channel_for_game_alias");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_substitute_channel.sql"))?;
+        let foo = stmt.query_map(&[&alias], |ref row| {
+            let maybe_channel_id: Option<i64> = row.get(0);
+            maybe_channel_id.map(|channel_id| ChannelId(channel_id as u64))
+        })?;
+        let vec = foo.collect::<Result<Vec<_>, _>>()?;
+        Ok(vec.into_iter().next().and_then(|channel_id| channel_id))
+    }
+
+    /// Returns false if the player was already on the waitlist
+    pub fn insert_waitlist_entry(
+        &self,
+        game_alias: &str,
+        player: UserId,
+        preferred_nation_id: Option<u32>,
+    ) -> Result<bool, Error> {
+        info!("db::insert_waitlist_entry");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("sql/insert_waitlist_entry.sql"),
+            &[&game_alias, &(player.0 as i64), &preferred_nation_id],
+        )?;
+        Ok(rows_modified != 0)
+    }
+
+    pub fn waitlist_for_game_alias(&self, game_alias: &str) -> Result<Vec<WaitlistEntry>, Error> {
+        info!("db::waitlist_for_game_alias");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_waitlist.sql"))?;
+        let foo = stmt.query_map(&[&game_alias], |ref row| {
+            let player: i64 = row.get(0);
+            let preferred_nation_id: Option<i32> = row.get(1);
+            let promoted: i32 = row.get(2);
+            WaitlistEntry {
+                player: UserId(player as u64),
+                preferred_nation_id: preferred_nation_id.map(|nation_id| nation_id as u32),
+                promoted: promoted != 0,
+            }
+        })?;
+        let vec = foo.collect::<Result<Vec<_>, _>>()?;
+        Ok(vec)
+    }
+
+    pub fn promote_waitlist_entry(&self, game_alias: &str, player: UserId) -> Result<(), Error> {
+        info!("db::promote_waitlist_entry");
+        let conn = &*self.0.clone().get()?;
+        conn.execute(
+            include_str!("sql/update_waitlist_promoted.sql"),
+            &[&game_alias, &(player.0 as i64)],
+        )?;
+        Ok(())
+    }
+
+    /// Returns false if the player wasn't on the waitlist
+    pub fn remove_waitlist_entry(&self, game_alias: &str, player: UserId) -> Result<bool, Error> {
+        info!("db::remove_waitlist_entry");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("sql/delete_waitlist_entry.sql"),
+            &[&game_alias, &(player.0 as i64)],
+        )?;
+        Ok(rows_modified != 0)
+    }
+
+    /// Everything else refers to the game by id, so registrations and history carry over
+    pub fn rename_game(&self, old_alias: &str, new_alias: &str) -> Result<(), Error> {
+        info!("db::rename_game");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("sql/update_game_alias.sql"),
+            &[&old_alias, &new_alias],
+        )?;
+        if rows_modified != 0 {
+            Ok(())
+        } else {
+            Err(err_msg(format!("Could not find server with name {}", old_alias)))
+        }
+    }
+
+    pub fn update_started_server_address(&self, alias: &str, address: &str) -> Result<(), Error> {
+        info!("db::update_started_server_address");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("sql/update_started_server_address.sql"),
+            &[&alias, &address],
+        )?;
+        if rows_modified != 0 {
+            Ok(())
+        } else {
+            Err(err_msg(format!("Could not find started server with name {}", alias)))
+        }
+    }
+
+    pub fn set_lobby_owner(&self, alias: &str, owner: UserId) -> Result<(), Error> {
+        info!("db::set_lobby_owner");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            include_str!("sql/insert_player.sql"),
+            &[&(owner.0 as i64), &true],
+        )?;
+        let rows_modified = tx.execute(
+            include_str!("sql/update_lobby_owner.sql"),
+            &[&alias, &(owner.0 as i64)],
+        )?;
+        if rows_modified != 0 {
+            tx.commit()?;
+            Ok(())
+        } else {
+            Err(err_msg(format!("Could not find lobby with name {}", alias)))
+        }
+    }
+
+    pub fn set_lobby_status(&self, alias: &str, status: LobbyStatus) -> Result<(), Error> {
+        info!("db::set_lobby_status");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("sql/update_lobby_status.sql"),
+            &[&alias, &status.to_i32()],
+        )?;
+        if rows_modified != 0 {
+            Ok(())
+        } else {
+            Err(err_msg(format!("Could not find lobby with name {}", alias)))
+        }
+    }
+
+    /// Keeps the lobby from expiring
+    pub fn touch_lobby(&self, alias: &str) -> Result<(), Error> {
+        info!("db::touch_lobby");
+        let conn = &*self.0.clone().get()?;
+        conn.execute(include_str!("sql/update_lobby_last_activity.sql"), &[&alias])?;
+        Ok(())
+    }
+
+    /// Also forgets which start reminders were sent, so a new date gets its own reminders
+    pub fn set_lobby_scheduled_start(
+        &self,
+        alias: &str,
+        scheduled_start: Option<DateTime<Utc>>,
+    ) -> Result<(), Error> {
+        info!("db::set_lobby_scheduled_start");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        let rows_modified = tx.execute(
+            include_str!("sql/update_lobby_scheduled_start.sql"),
+            &[&alias, &scheduled_start_to_column(scheduled_start)],
+        )?;
+        tx.execute(
+            include_str!("sql/delete_server_lobby_start_reminders.sql"),
+            &[&alias],
+        )?;
+        if rows_modified != 0 {
+            tx.commit()?;
+            Ok(())
+        } else {
+            Err(err_msg(format!("Could not find lobby with name {}", alias)))
+        }
+    }
+
+    /// Marks open and locked lobbies without activity in the last `days` as expired.
+    /// Returns the alias and owner of each lobby that expired.
+    pub fn expire_inactive_lobbies(&self, days: u32) -> Result<Vec<(String, UserId)>, Error> {
+        info!("db::expire_inactive_lobbies");
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        let expired = {
+            let mut stmt = tx.prepare(include_str!("sql/select_inactive_lobbies.sql"))?;
+            let foo = stmt.query_map(&[&format!("-{} days", days)], |ref row| {
+                let alias: String = row.get(0);
+                let owner: i64 = row.get(1);
+                (alias, UserId(owner as u64))
+            })?;
+            foo.collect::<Result<Vec<_>, _>>()?
+        };
+        for &(ref alias, _) in &expired {
+            tx.execute(
+                include_str!("sql/update_lobby_status.sql"),
+                &[alias, &LobbyStatus::Expired.to_i32()],
+            )?;
+        }
+        tx.commit()?;
+        Ok(expired)
+    }
+
+    /// Returns false if this reminder was already sent
+    pub fn sent_lobby_start_reminder_thresholds(&self, alias: &str) -> Result<Vec<i32>, Error> {
+        info!("db::sent_lobby_start_reminder_thresholds");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_sent_lobby_start_reminder_thresholds.sql"))?;
+        let foo = stmt.query_map(&[&alias], |row| row.get(0))?;
+        let vec = foo.collect::<Result<Vec<_>, _>>()?;
+        Ok(vec)
+    }
+
+    pub fn insert_lobby_start_reminder(&self, alias: &str, threshold_mins: i32) -> Result<bool, Error> {
+        info!("db::insert_lobby_start_reminder");
+        let conn = &*self.0.clone().get()?;
+        let rows_modified = conn.execute(
+            include_str!("sql/insert_lobby_start_reminder.sql"),
+            &[&alias, &threshold_mins],
+        )?;
+        Ok(rows_modified != 0)
+    }
+
+    fn update_player_game_setting(
+        &self,
+        game_alias: &str,
+        player: UserId,
+        update_sql: &str,
+        value: &dyn ToSql,
+    ) -> Result<(), Error> {
+        let conn = &mut *self.0.clone().get()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            include_str!("sql/insert_player_game_settings.sql"),
+            &[&game_alias, &(player.0 as i64)],
+        )?;
+        let rows_modified = tx.execute(update_sql, &[&game_alias, &(player.0 as i64), value])?;
+        if rows_modified != 0 {
+            tx.commit()?;
+            Ok(())
+        } else {
+            Err(err_msg(format!("You are not registered in {}", game_alias)))
+        }
+    }
+
+    /// Returns false if this reminder has already been sent
+    pub fn insert_sent_reminder(
+        &self,
+        game_alias: &str,
+        player: UserId,
+        nation_id: usize,
+        turn: i32,
+        threshold_mins: i32,
+    ) -> Result<bool, Error> {
+        info!("db::insert_sent_reminder");
+        let conn = &*self.0.clone().get()?;
+        let rows = conn.execute(
+            include_str!("sql/insert_sent_reminder.sql"),
+            &[&game_alias, &(player.0 as i64), &(nation_id as u32), &turn, &threshold_mins],
+        )?;
+        Ok(rows > 0)
+    }
+
+    pub fn sent_reminder_thresholds(
+        &self,
+        game_alias: &str,
+        player: UserId,
+        nation_id: usize,
+        turn: i32,
+    ) -> Result<Vec<i32>, Error> {
+        info!("db::sent_reminder_thresholds");
+        let conn = &*self.0.clone().get()?;
+        let mut stmt = conn.prepare(include_str!("sql/select_sent_reminder_thresholds.sql"))?;
+        let foo = stmt.query_map(
+            &[&game_alias, &(player.0 as i64), &(nation_id as u32), &turn],
+            |row| row.get(0),
+        )?;
+        let vec = foo.collect::<Result<Vec<_>, _>>()?;
+        Ok(vec)
+    }
+
+    pub fn remove_old_sent_reminders(&self, game_alias: &str, current_turn: i32) -> Result<(), Error> {
+        info!("db::remove_old_sent_reminders");
+        let conn = &*self.0.clone().get()?;
+        conn.execute(
+            include_str!("sql/delete_old_sent_reminders.sql"),
+            &[&game_alias, &current_turn],
+        )?;
+        Ok(())
+    }
+}
+
+fn thresholds_to_column(thresholds_mins: &[i32]) -> String {
+    thresholds_mins
+        .iter()
+        .map(|threshold| threshold.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn thresholds_from_column(column: &str) -> Result<Vec<i32>, Error> {
+    column
+        .split(',')
+        .filter(|threshold| !threshold.is_empty())
+        .map(|threshold| threshold.parse::<i32>()
+            .map_err(|_| err_msg(format!("invalid reminder threshold {}", threshold))))
+        .collect()
+}
+
+fn nation_ids_to_column(nation_ids: &[usize]) -> String {
+    nation_ids
+        .iter()
+        .map(|nation_id| nation_id.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn nation_ids_from_column(column: &str) -> Result<Vec<usize>, Error> {
+    column
+        .split(',')
+        .filter(|nation_id| !nation_id.is_empty())
+        .map(|nation_id| nation_id.parse::<usize>()
+            .map_err(|_| err_msg(format!("invalid nation id {}", nation_id))))
+        .collect()
+}
+
+const SQLITE_DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+fn scheduled_start_to_column(scheduled_start: Option<DateTime<Utc>>) -> Option<String> {
+    scheduled_start.map(|scheduled_start| scheduled_start.format(SQLITE_DATETIME_FORMAT).to_string())
+}
+
+/// Reads the owner, era, player count, description, status and scheduled start columns
+/// starting at `first_column`. All of them are null for servers that didn't come from a lobby.
+fn lobby_state_from_row(row: &Row, first_column: i32) -> Result<Option<LobbyState>, Error> {
+    let maybe_owner: Option<i64> = row.get(first_column);
+    let maybe_era: Option<i32> = row.get(first_column + 1);
+    let maybe_player_count: Option<i32> = row.get(first_column + 2);
+    let description: Option<String> = row.get(first_column + 3);
+    let maybe_status: Option<i32> = row.get(first_column + 4);
+    let maybe_scheduled_start: Option<String> = row.get(first_column + 5);
+
+    match (maybe_owner, maybe_era, maybe_player_count) {
+        (Some(owner), Some(era), Some(player_count)) => {
+            let scheduled_start = match maybe_scheduled_start {
+                Some(scheduled_start) => {
+                    let naive = NaiveDateTime::parse_from_str(&scheduled_start, SQLITE_DATETIME_FORMAT)?;
+                    Some(DateTime::<Utc>::from_utc(naive, Utc))
+                }
+                None => None,
+            };
+            Ok(Some(LobbyState {
+                owner: UserId(owner as u64),
+                era: Era::from_i32(era).ok_or(err_msg("unknown era"))?,
+                player_count,
+                description,
+                status: LobbyStatus::from_i32(maybe_status.unwrap_or(0))
+                    .ok_or(err_msg("unknown lobby status"))?,
+                scheduled_start,
+            }))
+        }
+        (None, None, None) => Ok(None),
+        _ => Err(err_msg("invalid db state for lobby")),
+    }
+}
+
+fn make_game_server(
+    alias: String,
+    maybe_address: Option<String>,
+    maybe_last_seen_turn: Option<i32>,
+    maybe_lobby_state: Option<LobbyState>,
+) -> Result<GameServer, Error> {
+    let state = match (maybe_address, maybe_last_seen_turn, maybe_lobby_state) {
+        (Some(address), Some(last_seen_turn), maybe_lobby_state) => GameServerState::StartedState(
+            StartedState {
+                address,
+                last_seen_turn,
+            },
+            maybe_lobby_state,
+        ),
+        (None, None, Some(lobby_state)) => GameServerState::Lobby(lobby_state),
+        _ => return Err(err_msg(format!("invalid db state for {}", alias))),
+    };
+
+    let server = GameServer {
+        alias,
+        state,
+    };
+    Ok(server)
+}
+
+/// Does nothing if the turn was already recorded
+fn insert_turn_record_with(conn: &Connection, game_alias: &str, turn_record: &TurnRecord) -> Result<(), Error> {
+    conn.execute(
+        include_str!("sql/insert_turn_record.sql"),
+        &[
+            &game_alias,
+            &turn_record.turn,
+            &turn_record.seen_at.format(SQLITE_DATETIME_FORMAT).to_string(),
+            &turn_record.turn_timer,
+            &nation_ids_to_column(&turn_record.defeated),
+            &nation_ids_to_column(&turn_record.ai),
+            &nation_ids_to_column(&turn_record.possible_stalls),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Inserts the game server and its lobby and started server, leaving the transaction to the caller
+fn insert_game_server_with(conn: &Connection, game_server: &GameServer) -> Result<(), Error> {
+    match game_server.state {
+        GameServerState::Lobby(ref lobby_state) => {
+            conn.execute(
+                include_str!("sql/insert_player.sql"),
+                &[&(lobby_state.owner.0 as i64), &true],
+            )?;
+
+            conn.execute(
+                include_str!("sql/insert_lobby.sql"),
+                &[
+                    &lobby_state.era.to_i32(),
+                    &(lobby_state.owner.0 as i64),
+                    &lobby_state.player_count,
+                    &lobby_state.description,
+                    &lobby_state.status.to_i32(),
+                    &scheduled_start_to_column(lobby_state.scheduled_start),
+                ],
+            )?;
+            // the lobby we just inserted, rather than any lobby with the same owner and settings
+            let lobby_id = conn.last_insert_rowid();
+            conn.execute(
+                include_str!("sql/insert_game_server_from_lobby.sql"),
+                &[&game_server.alias, &lobby_id],
+            )?;
+            Ok(())
+        }
+        GameServerState::StartedState(ref started_state, None) => {
+            conn.execute(
+                include_str!("sql/insert_started_server.sql"),
+                &[&started_state.address, &started_state.last_seen_turn],
+            )?;
+            conn.execute(
+                include_str!("sql/insert_started_game_server.sql"),
+                &[&game_server.alias, &started_state.address],
+            )?;
+            Ok(())
+        }
+        GameServerState::StartedState(ref started_state, Some(ref lobby_state)) => {
+            conn.execute(
+                include_str!("sql/insert_player.sql"),
+                &[&(lobby_state.owner.0 as i64), &true],
+            )?;
+
+            conn.execute(
+                include_str!("sql/insert_lobby.sql"),
+                &[
+                    &lobby_state.era.to_i32(),
+                    &(lobby_state.owner.0 as i64),
+                    &lobby_state.player_count,
+                    &lobby_state.description,
+                    &lobby_state.status.to_i32(),
+                    &scheduled_start_to_column(lobby_state.scheduled_start),
+                ],
+            )?;
+            // the lobby we just inserted, rather than any lobby with the same owner and settings
+            let lobby_id = conn.last_insert_rowid();
+            conn.execute(
+                include_str!("sql/insert_game_server_from_lobby.sql"),
+                &[&game_server.alias, &lobby_id],
+            )?;
+            conn.execute(
+                include_str!("sql/insert_started_state.sql"),
+                &[&started_state.address, &started_state.last_seen_turn],
+            )?;
+
+            conn.execute(
+                include_str!("sql/update_game_with_started_state.sql"),
+                &[
+                    &started_state.address,
+                    &started_state.last_seen_turn,
+                    &game_server.alias,
+                ],
+            )?;
+            Ok(())
+        }
+    }
 }
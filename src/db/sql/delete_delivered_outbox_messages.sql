@@ -0,0 +1,3 @@
+DELETE FROM notification_outbox
+WHERE delivered_at IS NOT NULL
+AND delivered_at < ?1;
@@ -0,0 +1,14 @@
+DELETE FROM lobby_start_reminders;
+DELETE FROM lobby_waitlist;
+DELETE FROM substitutions;
+DELETE FROM pinned_statuses;
+DELETE FROM player_game_settings;
+DELETE FROM sent_reminders;
+DELETE FROM notification_outbox;
+DELETE FROM turn_history;
+DELETE FROM stalls;
+DELETE FROM server_players;
+DELETE FROM game_servers;
+DELETE FROM started_servers;
+DELETE FROM lobbies;
+DELETE FROM players;
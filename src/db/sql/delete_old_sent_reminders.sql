@@ -0,0 +1,4 @@
+DELETE FROM sent_reminders
+WHERE server_id IN
+    (SELECT id from game_servers WHERE alias = ?1)
+AND turn < ?2;
@@ -0,0 +1,6 @@
+DELETE FROM substitutions
+WHERE server_id IN
+    (SELECT id from game_servers WHERE alias = ?1)
+AND outgoing_player_id IN
+    (SELECT id from players WHERE discord_user_id = ?2)
+AND incoming_player_id IS NULL;
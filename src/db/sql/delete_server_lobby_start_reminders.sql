@@ -0,0 +1,3 @@
+DELETE FROM lobby_start_reminders
+WHERE server_id IN
+    (SELECT id from game_servers WHERE alias = ?1);
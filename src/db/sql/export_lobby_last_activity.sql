@@ -0,0 +1,4 @@
+SELECT l.last_activity
+FROM game_servers g
+JOIN lobbies l ON l.id = g.lobby_id
+WHERE g.alias = ?1;
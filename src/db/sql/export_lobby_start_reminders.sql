@@ -0,0 +1,5 @@
+SELECT r.threshold_mins
+FROM game_servers g
+JOIN lobby_start_reminders r ON r.server_id = g.id
+WHERE g.alias = ?1
+ORDER BY r.threshold_mins DESC;
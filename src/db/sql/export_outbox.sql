@@ -0,0 +1,5 @@
+SELECT o.turn, o.kind, o.recipient_discord_id, o.backend, o.text, o.attempts, o.next_attempt_at, o.last_error, o.delivered_at
+FROM game_servers g
+JOIN notification_outbox o ON o.server_id = g.id
+WHERE g.alias = ?1
+ORDER BY o.id;
@@ -0,0 +1,6 @@
+SELECT p.discord_user_id, s.turn_notifications, s.reminder_thresholds, s.detailed_notifications
+FROM game_servers g
+JOIN player_game_settings s ON s.server_id = g.id
+JOIN players p ON p.id = s.player_id
+WHERE g.alias = ?1
+ORDER BY p.discord_user_id;
@@ -0,0 +1,6 @@
+SELECT p.discord_user_id, r.nation_id, r.turn, r.threshold_mins
+FROM game_servers g
+JOIN sent_reminders r ON r.server_id = g.id
+JOIN players p ON p.id = r.player_id
+WHERE g.alias = ?1
+ORDER BY p.discord_user_id, r.nation_id, r.turn, r.threshold_mins DESC;
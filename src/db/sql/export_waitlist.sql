@@ -0,0 +1,6 @@
+SELECT p.discord_user_id, w.nation_id, w.promoted, w.joined_at
+FROM game_servers g
+JOIN lobby_waitlist w ON w.server_id = g.id
+JOIN players p ON p.id = w.player_id
+WHERE g.alias = ?1
+ORDER BY w.joined_at, w.id;
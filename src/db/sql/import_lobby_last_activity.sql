@@ -0,0 +1,4 @@
+UPDATE lobbies
+SET last_activity = ?2
+WHERE id IN
+    (SELECT lobby_id from game_servers WHERE alias = ?1);
@@ -0,0 +1,5 @@
+INSERT INTO notification_outbox
+    (server_id, turn, kind, recipient_discord_id, backend, text, attempts, next_attempt_at, last_error, delivered_at)
+SELECT g.id, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10
+FROM game_servers g
+WHERE g.alias = ?1;
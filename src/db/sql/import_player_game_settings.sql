@@ -0,0 +1,6 @@
+INSERT INTO player_game_settings
+    (server_id, player_id, turn_notifications, reminder_thresholds, detailed_notifications)
+SELECT g.id, p.id, ?3, ?4, ?5
+FROM game_servers g
+JOIN players p ON p.discord_user_id = ?2
+WHERE g.alias = ?1;
@@ -0,0 +1,6 @@
+INSERT INTO stalls (server_id, nation_id, player_id, turn)
+SELECT g.id, ?2,
+    (SELECT p.id FROM players p WHERE p.discord_user_id = ?3),
+    ?4
+FROM game_servers g
+WHERE g.alias = ?1;
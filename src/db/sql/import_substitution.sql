@@ -0,0 +1,8 @@
+INSERT INTO substitutions
+    (server_id, nation_id, outgoing_player_id, incoming_player_id, requested_turn, taken_turn)
+SELECT g.id, ?2, outgoing.id,
+    (SELECT incoming.id FROM players incoming WHERE incoming.discord_user_id = ?4),
+    ?5, ?6
+FROM game_servers g
+JOIN players outgoing ON outgoing.discord_user_id = ?3
+WHERE g.alias = ?1;
@@ -0,0 +1,5 @@
+INSERT INTO lobby_waitlist (server_id, player_id, nation_id, promoted, joined_at)
+SELECT g.id, p.id, ?3, ?4, ?5
+FROM game_servers g
+JOIN players p ON p.discord_user_id = ?2
+WHERE g.alias = ?1;
@@ -1,4 +1,4 @@
-INSERT INTO lobbies (owner_id, era, player_count, description)
-SELECT id, ?1, ?3, ?4
+INSERT INTO lobbies (owner_id, era, player_count, description, status, scheduled_start, last_activity)
+SELECT id, ?1, ?3, ?4, ?5, ?6, CURRENT_TIMESTAMP
 FROM players
 WHERE discord_user_id = ?2;
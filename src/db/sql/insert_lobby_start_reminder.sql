@@ -0,0 +1,4 @@
+INSERT OR IGNORE INTO lobby_start_reminders (server_id, threshold_mins)
+SELECT id, ?2
+FROM game_servers
+WHERE alias = ?1;
@@ -0,0 +1,5 @@
+INSERT OR IGNORE INTO notification_outbox
+    (server_id, turn, kind, recipient_discord_id, backend, text, next_attempt_at)
+SELECT g.id, ?2, ?3, ?4, ?5, ?6, ?7
+FROM game_servers g
+WHERE g.alias = ?1;
@@ -0,0 +1,4 @@
+INSERT OR REPLACE INTO pinned_statuses (server_id, channel_id, message_id)
+SELECT g.id, ?2, ?3
+FROM game_servers g
+WHERE g.alias = ?1;
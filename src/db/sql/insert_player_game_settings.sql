@@ -0,0 +1,7 @@
+INSERT OR IGNORE INTO player_game_settings (server_id, player_id)
+SELECT DISTINCT g.id, p.id
+FROM game_servers g
+JOIN server_players sp ON sp.server_id = g.id
+JOIN players p ON p.id = sp.player_id
+WHERE g.alias = ?1
+AND p.discord_user_id = ?2;
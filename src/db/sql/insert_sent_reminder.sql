@@ -0,0 +1,5 @@
+INSERT OR IGNORE INTO sent_reminders (server_id, player_id, nation_id, turn, threshold_mins)
+SELECT g.id, p.id, ?3, ?4, ?5
+FROM game_servers g
+JOIN players p ON p.discord_user_id = ?2
+WHERE g.alias = ?1;
@@ -0,0 +1,6 @@
+INSERT OR IGNORE INTO stalls (server_id, nation_id, player_id, turn)
+SELECT g.id, ?2,
+    (SELECT sp.player_id FROM server_players sp WHERE sp.server_id = g.id AND sp.nation_id = ?2),
+    ?3
+FROM game_servers g
+WHERE g.alias = ?1;
@@ -0,0 +1,9 @@
+INSERT INTO substitutions (server_id, nation_id, outgoing_player_id, requested_turn)
+SELECT g.id, ?3, p.id, ?4
+FROM game_servers g
+JOIN players p ON p.discord_user_id = ?2
+WHERE g.alias = ?1
+AND NOT EXISTS (
+    SELECT 1 FROM substitutions s
+    WHERE s.server_id = g.id AND s.nation_id = ?3 AND s.incoming_player_id IS NULL
+);
@@ -0,0 +1,5 @@
+INSERT OR IGNORE INTO turn_history
+    (server_id, turn, seen_at, turn_timer, defeated_nation_ids, ai_nation_ids, possible_stall_nation_ids)
+SELECT g.id, ?2, ?3, ?4, ?5, ?6, ?7
+FROM game_servers g
+WHERE g.alias = ?1;
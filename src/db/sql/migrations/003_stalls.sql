@@ -0,0 +1,11 @@
+create table if not exists stalls (
+    id INTEGER NOT NULL PRIMARY KEY,
+    server_id int NOT NULL REFERENCES game_servers(id),
+    nation_id int NOT NULL,
+    player_id int REFERENCES players(id),
+    turn int NOT NULL,
+
+    CONSTRAINT server_nation_turn_unique UNIQUE (server_id, nation_id, turn)
+);
+
+ALTER TABLE lobbies ADD COLUMN stall_warning_threshold int;
@@ -0,0 +1 @@
+ALTER TABLE game_servers ADD COLUMN notification_channel_id int;
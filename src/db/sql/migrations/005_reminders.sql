@@ -0,0 +1,11 @@
+ALTER TABLE players ADD COLUMN reminder_thresholds TEXT;
+
+create table if not exists sent_reminders (
+    server_id int NOT NULL REFERENCES game_servers(id),
+    player_id int NOT NULL REFERENCES players(id),
+    nation_id int NOT NULL,
+    turn int NOT NULL,
+    threshold_mins int NOT NULL,
+
+    CONSTRAINT sent_reminder_unique UNIQUE (server_id, player_id, nation_id, turn, threshold_mins)
+);
@@ -0,0 +1,10 @@
+-- NULL means use the player's global setting
+create table if not exists player_game_settings (
+    server_id int NOT NULL REFERENCES game_servers(id),
+    player_id int NOT NULL REFERENCES players(id),
+    turn_notifications BOOLEAN,
+    reminder_thresholds TEXT,
+    detailed_notifications BOOLEAN,
+
+    CONSTRAINT player_game_settings_unique UNIQUE (server_id, player_id)
+);
@@ -0,0 +1,7 @@
+create table if not exists pinned_statuses (
+    server_id int NOT NULL REFERENCES game_servers(id),
+    channel_id int NOT NULL,
+    message_id int NOT NULL,
+
+    CONSTRAINT pinned_status_server_unique UNIQUE (server_id)
+);
@@ -0,0 +1,11 @@
+create table if not exists substitutions (
+    id INTEGER NOT NULL PRIMARY KEY,
+    server_id int NOT NULL REFERENCES game_servers(id),
+    nation_id int NOT NULL,
+    outgoing_player_id int NOT NULL REFERENCES players(id),
+    incoming_player_id int REFERENCES players(id),
+    requested_turn int NOT NULL,
+    taken_turn int
+);
+
+ALTER TABLE game_servers ADD COLUMN substitute_channel_id int;
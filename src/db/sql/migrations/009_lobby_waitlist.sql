@@ -0,0 +1,10 @@
+create table if not exists lobby_waitlist (
+    id INTEGER NOT NULL PRIMARY KEY,
+    server_id int NOT NULL REFERENCES game_servers(id),
+    player_id int NOT NULL REFERENCES players(id),
+    nation_id int,
+    promoted int NOT NULL DEFAULT 0,
+    joined_at datetime NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT lobby_waitlist_player_unique UNIQUE (server_id, player_id)
+);
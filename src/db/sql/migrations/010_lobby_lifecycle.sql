@@ -0,0 +1,12 @@
+ALTER TABLE lobbies ADD COLUMN status int NOT NULL DEFAULT 0;
+ALTER TABLE lobbies ADD COLUMN last_activity datetime;
+ALTER TABLE lobbies ADD COLUMN scheduled_start datetime;
+
+UPDATE lobbies SET last_activity = CURRENT_TIMESTAMP;
+
+create table if not exists lobby_start_reminders (
+    server_id int NOT NULL REFERENCES game_servers(id),
+    threshold_mins int NOT NULL,
+
+    CONSTRAINT lobby_start_reminder_unique UNIQUE (server_id, threshold_mins)
+);
@@ -0,0 +1,12 @@
+create table if not exists turn_history (
+    id INTEGER NOT NULL PRIMARY KEY,
+    server_id int NOT NULL REFERENCES game_servers(id),
+    turn int NOT NULL,
+    seen_at datetime NOT NULL,
+    turn_timer int NOT NULL,
+    defeated_nation_ids text NOT NULL,
+    ai_nation_ids text NOT NULL,
+    possible_stall_nation_ids text NOT NULL,
+
+    CONSTRAINT server_turn_unique UNIQUE (server_id, turn)
+);
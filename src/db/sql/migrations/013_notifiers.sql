@@ -0,0 +1,6 @@
+ALTER TABLE players ADD COLUMN discord_dms BOOLEAN NOT NULL DEFAULT 1;
+ALTER TABLE players ADD COLUMN webhook_url VARCHAR(255);
+ALTER TABLE players ADD COLUMN webhook_secret VARCHAR(255);
+
+ALTER TABLE game_servers ADD COLUMN webhook_url VARCHAR(255);
+ALTER TABLE game_servers ADD COLUMN webhook_secret VARCHAR(255);
@@ -0,0 +1,16 @@
+create table if not exists notification_outbox (
+    id INTEGER NOT NULL PRIMARY KEY,
+    server_id int NOT NULL REFERENCES game_servers(id),
+    turn int NOT NULL,
+    kind VARCHAR(255) NOT NULL,
+    -- 0 when it's for the game's own channel and webhook rather than a player
+    recipient_discord_id int NOT NULL,
+    backend VARCHAR(255) NOT NULL,
+    text TEXT NOT NULL,
+    attempts int NOT NULL DEFAULT 0,
+    next_attempt_at datetime NOT NULL,
+    last_error TEXT,
+    delivered_at datetime,
+
+    CONSTRAINT outbox_unique UNIQUE (server_id, turn, kind, recipient_discord_id, backend)
+);
@@ -0,0 +1,3 @@
+SELECT g.alias, s.address
+FROM game_servers g
+LEFT JOIN started_servers s ON s.id = g.started_server_id;
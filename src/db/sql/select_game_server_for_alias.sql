@@ -1,4 +1,4 @@
-SELECT s.address, s.last_seen_turn, p.discord_user_id, l.era, l.player_count, l.description
+SELECT s.address, s.last_seen_turn, p.discord_user_id, l.era, l.player_count, l.description, l.status, l.scheduled_start, g.archived_at
 FROM game_servers g
 LEFT JOIN started_servers s ON s.id = g.started_server_id
 LEFT JOIN lobbies l ON l.id = g.lobby_id
@@ -1,4 +1,6 @@
-SELECT g.alias, s.address, s.last_seen_turn, l.owner_id, l.era, l.player_count, l.description
+SELECT g.alias, s.address, s.last_seen_turn, p.discord_user_id, l.era, l.player_count, l.description, l.status, l.scheduled_start
 FROM game_servers g
 LEFT JOIN started_servers s ON s.id = g.started_server_id
-LEFT JOIN lobbies l ON l.id = g.lobby_id;
+LEFT JOIN lobbies l ON l.id = g.lobby_id
+LEFT JOIN players p ON p.id = l.owner_id
+WHERE g.archived_at IS NULL;
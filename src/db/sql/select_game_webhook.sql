@@ -0,0 +1,3 @@
+SELECT webhook_url, webhook_secret
+FROM game_servers
+WHERE alias = ?1;
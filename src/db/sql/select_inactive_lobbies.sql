@@ -0,0 +1,8 @@
+SELECT g.alias, p.discord_user_id
+FROM game_servers g
+JOIN lobbies l ON l.id = g.lobby_id
+JOIN players p ON p.id = l.owner_id
+WHERE g.started_server_id IS NULL
+AND g.archived_at IS NULL
+AND l.status IN (0, 1)
+AND COALESCE(l.last_activity, '1970-01-01 00:00:00') < datetime('now', ?1);
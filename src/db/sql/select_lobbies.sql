@@ -1,6 +1,9 @@
-SELECT g.alias, l.owner_id, l.era, l.player_count, count(sp.player_id), l.description
+SELECT g.alias, p.discord_user_id, l.era, l.player_count, l.description, l.status, l.scheduled_start, count(sp.player_id)
 FROM game_servers g
 JOIN lobbies l ON l.id = g.lobby_id
+JOIN players p ON p.id = l.owner_id
 LEFT JOIN server_players sp on sp.server_id = g.id
 WHERE g.started_server_id IS NULL
-GROUP BY g.id
\ No newline at end of file
+AND g.archived_at IS NULL
+AND (?1 OR l.status IN (0, 1))
+GROUP BY g.id
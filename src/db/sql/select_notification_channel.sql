@@ -0,0 +1,3 @@
+SELECT notification_channel_id
+FROM game_servers
+WHERE alias = ?1;
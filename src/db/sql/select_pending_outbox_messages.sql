@@ -0,0 +1,7 @@
+SELECT o.id, o.attempts, g.alias, o.turn, o.kind, o.recipient_discord_id, o.backend, o.text
+FROM notification_outbox o
+JOIN game_servers g ON g.id = o.server_id
+WHERE o.delivered_at IS NULL
+AND o.next_attempt_at <= ?1
+AND o.attempts < ?2
+ORDER BY o.id;
@@ -0,0 +1,4 @@
+SELECT ps.channel_id, ps.message_id
+FROM pinned_statuses ps
+JOIN game_servers g ON g.id = ps.server_id
+WHERE g.alias = ?1;
@@ -0,0 +1,3 @@
+SELECT discord_dms, webhook_url, webhook_secret
+FROM players
+WHERE discord_user_id = ?1;
@@ -0,0 +1,3 @@
+SELECT discord_user_id, turn_notifications, reminder_thresholds, discord_dms, webhook_url, webhook_secret
+FROM players
+ORDER BY id;
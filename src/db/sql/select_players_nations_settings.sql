@@ -0,0 +1,9 @@
+SELECT p.discord_user_id, sp.nation_id, p.turn_notifications,
+    COALESCE(s.turn_notifications, p.turn_notifications),
+    COALESCE(s.reminder_thresholds, p.reminder_thresholds),
+    COALESCE(s.detailed_notifications, 1)
+FROM game_servers g
+JOIN server_players sp ON sp.server_id = g.id
+JOIN players p ON p.id = sp.player_id
+LEFT JOIN player_game_settings s ON s.server_id = g.id AND s.player_id = p.id
+WHERE g.alias = ?1;
@@ -0,0 +1,3 @@
+SELECT reminder_thresholds
+FROM players
+WHERE discord_user_id = ?1;
@@ -0,0 +1,4 @@
+SELECT r.threshold_mins
+FROM lobby_start_reminders r
+JOIN game_servers g ON g.id = r.server_id
+WHERE g.alias = ?1;
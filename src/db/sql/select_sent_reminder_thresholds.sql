@@ -0,0 +1,8 @@
+SELECT r.threshold_mins
+FROM sent_reminders r
+JOIN game_servers g ON g.id = r.server_id
+JOIN players p ON p.id = r.player_id
+WHERE g.alias = ?1
+AND p.discord_user_id = ?2
+AND r.nation_id = ?3
+AND r.turn = ?4;
@@ -1,7 +1,9 @@
- SELECT s.address, g.alias, s.last_seen_turn, sp.nation_id, l.owner_id, l.era, l.player_count, l.description
+ SELECT s.address, g.alias, s.last_seen_turn, sp.nation_id, owner.discord_user_id, l.era, l.player_count, l.description, l.status, l.scheduled_start
 FROM players p
 JOIN server_players sp on sp.player_id = p.id
 JOIN game_servers g on g.id = sp.server_id
 LEFT JOIN lobbies l on l.id = g.lobby_id
+LEFT JOIN players owner on owner.id = l.owner_id
 LEFT JOIN started_servers s on s.id = g.started_server_id
-WHERE p.discord_user_id = ?1;
+WHERE p.discord_user_id = ?1
+AND g.archived_at IS NULL;
@@ -0,0 +1,4 @@
+SELECT l.stall_warning_threshold
+FROM game_servers g
+JOIN lobbies l ON l.id = g.lobby_id
+WHERE g.alias = ?1;
@@ -0,0 +1,6 @@
+SELECT st.nation_id, p.discord_user_id, st.turn
+FROM game_servers g
+JOIN stalls st ON st.server_id = g.id
+LEFT JOIN players p ON p.id = st.player_id
+WHERE g.alias = ?1
+ORDER BY st.turn DESC, st.nation_id;
@@ -0,0 +1,3 @@
+SELECT substitute_channel_id
+FROM game_servers
+WHERE alias = ?1;
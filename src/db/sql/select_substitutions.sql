@@ -0,0 +1,7 @@
+SELECT s.id, s.nation_id, outgoing.discord_user_id, incoming.discord_user_id, s.requested_turn, s.taken_turn
+FROM game_servers g
+JOIN substitutions s ON s.server_id = g.id
+JOIN players outgoing ON outgoing.id = s.outgoing_player_id
+LEFT JOIN players incoming ON incoming.id = s.incoming_player_id
+WHERE g.alias = ?1
+ORDER BY s.id;
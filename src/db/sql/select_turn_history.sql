@@ -0,0 +1,5 @@
+SELECT t.turn, t.seen_at, t.turn_timer, t.defeated_nation_ids, t.ai_nation_ids, t.possible_stall_nation_ids
+FROM game_servers g
+JOIN turn_history t ON t.server_id = g.id
+WHERE g.alias = ?1
+ORDER BY t.turn DESC;
@@ -0,0 +1,4 @@
+UPDATE game_servers
+SET archived_at = CURRENT_TIMESTAMP
+WHERE alias = ?1
+AND archived_at IS NULL;
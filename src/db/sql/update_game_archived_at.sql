@@ -0,0 +1,3 @@
+UPDATE game_servers
+SET archived_at = ?2
+WHERE alias = ?1;
@@ -0,0 +1,3 @@
+UPDATE game_servers
+SET webhook_url = ?2, webhook_secret = ?3
+WHERE alias = ?1;
@@ -0,0 +1,3 @@
+UPDATE game_servers
+SET notification_channel_id = ?2
+WHERE alias = ?1;
@@ -0,0 +1,5 @@
+UPDATE lobbies
+SET owner_id = (SELECT id FROM players WHERE discord_user_id = ?2),
+    last_activity = CURRENT_TIMESTAMP
+WHERE id IN
+    (SELECT lobby_id from game_servers WHERE alias = ?1);
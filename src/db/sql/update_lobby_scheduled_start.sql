@@ -0,0 +1,4 @@
+UPDATE lobbies
+SET scheduled_start = ?2, last_activity = CURRENT_TIMESTAMP
+WHERE id IN
+    (SELECT lobby_id from game_servers WHERE alias = ?1);
@@ -0,0 +1,4 @@
+UPDATE lobbies
+SET status = ?2, last_activity = CURRENT_TIMESTAMP
+WHERE id IN
+    (SELECT lobby_id from game_servers WHERE alias = ?1);
@@ -1,2 +1,2 @@
-UPDATE lobbies SET description = ?2
+UPDATE lobbies SET description = ?2, last_activity = CURRENT_TIMESTAMP
 WHERE id = (SELECT lobby_id FROM game_servers WHERE alias = ?1);
\ No newline at end of file
@@ -0,0 +1,2 @@
+UPDATE lobbies SET stall_warning_threshold = ?2
+WHERE id = (SELECT lobby_id FROM game_servers WHERE alias = ?1);
@@ -0,0 +1,3 @@
+UPDATE notification_outbox
+SET delivered_at = ?2
+WHERE id = ?1;
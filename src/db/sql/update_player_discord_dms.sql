@@ -0,0 +1,3 @@
+UPDATE players
+SET discord_dms = ?2
+WHERE discord_user_id = ?1;
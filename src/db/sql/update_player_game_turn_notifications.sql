@@ -0,0 +1,4 @@
+UPDATE player_game_settings
+SET turn_notifications = ?3
+WHERE server_id = (SELECT id FROM game_servers WHERE alias = ?1)
+AND player_id = (SELECT id FROM players WHERE discord_user_id = ?2);
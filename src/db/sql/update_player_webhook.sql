@@ -0,0 +1,3 @@
+UPDATE players
+SET webhook_url = ?2, webhook_secret = ?3
+WHERE discord_user_id = ?1;
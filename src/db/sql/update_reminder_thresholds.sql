@@ -0,0 +1,3 @@
+UPDATE players
+SET reminder_thresholds = ?2
+WHERE discord_user_id = ?1;
@@ -0,0 +1,4 @@
+UPDATE server_players
+SET player_id = (SELECT id FROM players WHERE discord_user_id = ?3)
+WHERE server_id = (SELECT id FROM game_servers WHERE alias = ?1)
+AND nation_id = ?2;
@@ -0,0 +1,4 @@
+UPDATE started_servers
+SET address = ?2
+WHERE id IN
+    (SELECT started_server_id from game_servers WHERE alias = ?1);
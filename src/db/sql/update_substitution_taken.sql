@@ -0,0 +1,5 @@
+UPDATE substitutions
+SET incoming_player_id = (SELECT id FROM players WHERE discord_user_id = ?2),
+    taken_turn = ?3
+WHERE id = ?1
+AND incoming_player_id IS NULL;
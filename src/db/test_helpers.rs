@@ -73,6 +73,87 @@ impl DbConnection {
         conn.query_row("SELECT COUNT(*) FROM lobbies", &[], |r| r.get(0))
             .unwrap()
     }
+
+    /// An open early era lobby
+    pub fn insert_test_lobby(&self, alias: &str, owner: UserId, player_count: i32) -> LobbyState {
+        let lobby_state = test_lobby_state(owner, player_count);
+        self.insert_game_server(&GameServer {
+            alias: alias.to_owned(),
+            state: GameServerState::Lobby(lobby_state.clone()),
+        })
+        .unwrap();
+        lobby_state
+    }
+
+    /// A game added by its address rather than started from a lobby, so it has no owner
+    pub fn insert_test_game(&self, alias: &str, address: &str, last_seen_turn: i32) {
+        self.insert_game_server(&GameServer {
+            alias: alias.to_owned(),
+            state: GameServerState::StartedState(
+                StartedState {
+                    address: address.to_owned(),
+                    last_seen_turn,
+                },
+                None,
+            ),
+        })
+        .unwrap();
+    }
+
+    /// A game started from a four player lobby, which keeps the lobby's owner
+    pub fn insert_test_started_lobby(&self, alias: &str, owner: UserId, address: &str, last_seen_turn: i32) {
+        self.insert_game_server(&GameServer {
+            alias: alias.to_owned(),
+            state: GameServerState::StartedState(
+                StartedState {
+                    address: address.to_owned(),
+                    last_seen_turn,
+                },
+                Some(test_lobby_state(owner, 4)),
+            ),
+        })
+        .unwrap();
+    }
+
+    /// With turn notifications on. Does nothing if they already exist.
+    pub fn insert_test_player(&self, user_id: UserId) {
+        self.insert_player(&Player {
+            discord_user_id: user_id,
+            turn_notifications: true,
+        })
+        .unwrap();
+    }
+
+    /// Creates the player first if they're new
+    pub fn register_test_player(&self, alias: &str, user_id: UserId, nation_id: u32) {
+        self.insert_test_player(user_id);
+        self.insert_server_player(alias, user_id, nation_id).unwrap();
+    }
+
+    /// Does nothing if the turn was already recorded
+    pub fn insert_turn_record(&self, game_alias: &str, turn_record: &TurnRecord) -> Result<(), Error> {
+        let conn = &*self.0.clone().get()?;
+        insert_turn_record_with(conn, game_alias, turn_record)
+    }
+
+    pub fn set_lobby_last_activity(&self, alias: &str, last_activity: &str) {
+        let conn = &*self.0.clone().get().unwrap();
+        conn.execute(
+            "UPDATE lobbies SET last_activity = ?2 WHERE id = (SELECT lobby_id FROM game_servers WHERE alias = ?1)",
+            &[&alias, &last_activity],
+        ).unwrap();
+    }
+}
+
+fn test_lobby_state(owner: UserId, player_count: i32) -> LobbyState {
+    LobbyState {
+        owner,
+        era: Era::Early,
+        player_count,
+        description: None,
+        status: LobbyStatus::Open,
+        scheduled_start: None,
+    }
 }
 
 /*
@@ -0,0 +1,132 @@
+use failure::{err_msg, Error};
+use serenity::model::id::{ChannelId, MessageId, UserId};
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::{ChannelMessages, DirectMessages, PinnedMessages, UserLookup};
+use crate::commands::core::Embed;
+
+/// Something the bot did on discord
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sent {
+    Dm(UserId, String),
+    Message(ChannelId, String),
+    Embed(ChannelId, Embed),
+    Pinned(ChannelId, MessageId, Embed),
+    Edited(ChannelId, MessageId, Embed),
+    Deleted(ChannelId, MessageId),
+}
+
+/// Only knows the users it's given, and remembers everything it's asked to send.
+/// Like discord, it won't look up or DM anyone it doesn't know, or edit a message it didn't pin.
+#[derive(Debug, Default)]
+pub struct FakeDiscord {
+    users: HashMap<UserId, String>,
+    sent: Mutex<Vec<Sent>>,
+    /// The channel each pinned message is in
+    pinned: Mutex<HashMap<MessageId, ChannelId>>,
+}
+
+impl FakeDiscord {
+    pub fn new() -> FakeDiscord {
+        FakeDiscord::default()
+    }
+
+    pub fn with_user(mut self, user_id: UserId, name: &str) -> FakeDiscord {
+        self.users.insert(user_id, name.to_owned());
+        self
+    }
+
+    /// In the order it was sent
+    pub fn sent(&self) -> Vec<Sent> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    pub fn dms_to(&self, user_id: UserId) -> Vec<String> {
+        self.sent()
+            .into_iter()
+            .filter_map(|sent| match sent {
+                Sent::Dm(to, text) if to == user_id => Some(text),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// As if somebody deleted a message the bot pinned
+    pub fn delete_message(&self, message_id: MessageId) {
+        self.pinned.lock().unwrap().remove(&message_id);
+    }
+
+    fn check_user(&self, user_id: UserId) -> Result<&str, Error> {
+        self.users
+            .get(&user_id)
+            .map(|name| name.as_str())
+            .ok_or_else(|| err_msg(format!("Unknown User {}", user_id.0)))
+    }
+
+    fn send(&self, sent: Sent) {
+        self.sent.lock().unwrap().push(sent);
+    }
+}
+
+impl UserLookup for FakeDiscord {
+    fn user_name(&self, user_id: UserId) -> Result<String, Error> {
+        self.check_user(user_id).map(|name| format!("@{}", name))
+    }
+
+    fn cached_user_name(&self, user_id: UserId) -> Option<String> {
+        self.check_user(user_id).ok().map(|name| name.to_owned())
+    }
+}
+
+impl DirectMessages for FakeDiscord {
+    fn send_dm(&self, user_id: UserId, text: &str) -> Result<(), Error> {
+        self.check_user(user_id)?;
+        self.send(Sent::Dm(user_id, text.to_owned()));
+        Ok(())
+    }
+}
+
+impl ChannelMessages for FakeDiscord {
+    fn say(&self, channel_id: ChannelId, text: &str) -> Result<(), Error> {
+        self.send(Sent::Message(channel_id, text.to_owned()));
+        Ok(())
+    }
+
+    fn send_embed(&self, channel_id: ChannelId, embed: &Embed) -> Result<(), Error> {
+        self.send(Sent::Embed(channel_id, embed.clone()));
+        Ok(())
+    }
+
+    fn delete(&self, channel_id: ChannelId, message_id: MessageId) -> Result<(), Error> {
+        self.send(Sent::Deleted(channel_id, message_id));
+        Ok(())
+    }
+}
+
+impl PinnedMessages for FakeDiscord {
+    fn pin_embed(&self, channel_id: ChannelId, embed: &Embed) -> Result<MessageId, Error> {
+        let message_id = MessageId(self.sent.lock().unwrap().len() as u64 + 1);
+        self.pinned.lock().unwrap().insert(message_id, channel_id);
+        self.send(Sent::Pinned(channel_id, message_id, embed.clone()));
+        Ok(message_id)
+    }
+
+    fn edit_embed(&self, channel_id: ChannelId, message_id: MessageId, embed: &Embed) -> Result<(), Error> {
+        if self.pinned.lock().unwrap().get(&message_id) != Some(&channel_id) {
+            return Err(err_msg("Unknown Message"));
+        }
+        self.send(Sent::Edited(channel_id, message_id, embed.clone()));
+        Ok(())
+    }
+
+    fn unpin(&self, _channel_id: ChannelId, message_id: MessageId) -> Result<(), Error> {
+        self.pinned.lock().unwrap().remove(&message_id);
+        Ok(())
+    }
+
+    fn was_deleted(&self, channel_id: ChannelId, message_id: MessageId) -> bool {
+        self.pinned.lock().unwrap().get(&message_id) != Some(&channel_id)
+    }
+}
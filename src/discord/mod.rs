@@ -0,0 +1,123 @@
+//! Everything the bot looks up or says on discord outside of a command's reply goes
+//! through these traits, so tests can swap in `FakeDiscord` and run offline
+
+use failure::{err_msg, Error};
+use serenity::model::id::{ChannelId, MessageId, UserId};
+use std::error::Error as TraitError;
+
+use crate::commands::core::Embed;
+
+#[cfg(test)]
+mod fake;
+#[cfg(test)]
+pub use self::fake::*;
+
+pub trait UserLookup {
+    /// How the user is shown in messages
+    fn user_name(&self, user_id: UserId) -> Result<String, Error>;
+    /// The user's plain name, for showing outside discord. Only what's already known, so
+    /// it never costs a request to discord. `None` if they aren't known yet.
+    fn cached_user_name(&self, user_id: UserId) -> Option<String>;
+}
+
+pub trait DirectMessages {
+    fn send_dm(&self, user_id: UserId, text: &str) -> Result<(), Error>;
+}
+
+pub trait ChannelMessages {
+    fn say(&self, channel_id: ChannelId, text: &str) -> Result<(), Error>;
+    fn send_embed(&self, channel_id: ChannelId, embed: &Embed) -> Result<(), Error>;
+    /// Somebody else's message, like one with a secret in it
+    fn delete(&self, channel_id: ChannelId, message_id: MessageId) -> Result<(), Error>;
+}
+
+/// Embeds that are kept up to date after they're sent, like `!pin-status`
+pub trait PinnedMessages {
+    /// Sends the embed and pins it
+    fn pin_embed(&self, channel_id: ChannelId, embed: &Embed) -> Result<MessageId, Error>;
+    fn edit_embed(&self, channel_id: ChannelId, message_id: MessageId, embed: &Embed) -> Result<(), Error>;
+    fn unpin(&self, channel_id: ChannelId, message_id: MessageId) -> Result<(), Error>;
+    /// Only true when the channel can still be seen but the message can't, so that an outage
+    /// doesn't look like somebody deleted it
+    fn was_deleted(&self, channel_id: ChannelId, message_id: MessageId) -> bool;
+}
+
+pub trait Discord: UserLookup + DirectMessages + ChannelMessages + PinnedMessages {}
+
+impl<T: UserLookup + DirectMessages + ChannelMessages + PinnedMessages> Discord for T {}
+
+/// The real thing, through serenity's http client
+pub struct SerenityDiscord;
+
+fn serenity_err(e: serenity::Error) -> Error {
+    err_msg(e.description().to_owned())
+}
+
+impl UserLookup for SerenityDiscord {
+    /// A mention, which discord shows as the user's name
+    fn user_name(&self, user_id: UserId) -> Result<String, Error> {
+        let user = user_id.to_user().map_err(serenity_err)?;
+        Ok(user.to_string())
+    }
+
+    /// From serenity's cache, which fills up with the users the bot sees
+    fn cached_user_name(&self, user_id: UserId) -> Option<String> {
+        let user = serenity::CACHE.read().user(user_id)?;
+        let name = user.read().name.clone();
+        Some(name)
+    }
+}
+
+impl DirectMessages for SerenityDiscord {
+    fn send_dm(&self, user_id: UserId, text: &str) -> Result<(), Error> {
+        let private_channel = user_id.create_dm_channel().map_err(serenity_err)?;
+        private_channel.say(text).map_err(serenity_err)?;
+        Ok(())
+    }
+}
+
+impl ChannelMessages for SerenityDiscord {
+    fn say(&self, channel_id: ChannelId, text: &str) -> Result<(), Error> {
+        channel_id.say(text).map_err(serenity_err)?;
+        Ok(())
+    }
+
+    fn send_embed(&self, channel_id: ChannelId, embed: &Embed) -> Result<(), Error> {
+        let embed = embed.to_discord();
+        channel_id
+            .send_message(|m| m.embed(|_| embed))
+            .map_err(serenity_err)?;
+        Ok(())
+    }
+
+    fn delete(&self, channel_id: ChannelId, message_id: MessageId) -> Result<(), Error> {
+        channel_id.delete_message(message_id).map_err(serenity_err)
+    }
+}
+
+impl PinnedMessages for SerenityDiscord {
+    fn pin_embed(&self, channel_id: ChannelId, embed: &Embed) -> Result<MessageId, Error> {
+        let embed = embed.to_discord();
+        let sent = channel_id
+            .send_message(|m| m.embed(|_| embed))
+            .map_err(serenity_err)?;
+        channel_id.pin(sent.id).map_err(serenity_err)?;
+        Ok(sent.id)
+    }
+
+    fn edit_embed(&self, channel_id: ChannelId, message_id: MessageId, embed: &Embed) -> Result<(), Error> {
+        let embed = embed.to_discord();
+        channel_id
+            .edit_message(message_id, |m| m.embed(|_| embed))
+            .map_err(serenity_err)?;
+        Ok(())
+    }
+
+    fn unpin(&self, channel_id: ChannelId, message_id: MessageId) -> Result<(), Error> {
+        channel_id.unpin(message_id).map_err(serenity_err)
+    }
+
+    fn was_deleted(&self, channel_id: ChannelId, message_id: MessageId) -> bool {
+        channel_id.message(message_id).is_err() && channel_id.to_channel().is_ok()
+    }
+}
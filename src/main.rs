@@ -1,7 +1,13 @@
 #[cfg_attr(test, macro_use)]
 mod db;
+mod cli;
+mod api;
+mod config;
 mod commands;
+mod discord;
+mod metrics;
 mod model;
+mod notifier;
 mod server;
 
 #[cfg(test)]
@@ -9,16 +15,16 @@ pub mod it;
 
 use serenity::framework::standard::StandardFramework;
 use serenity::prelude::*;
-use simplelog::{Config, LogLevelFilter, SimpleLogger};
+use simplelog::{Config as LogConfig, SimpleLogger};
 
 use std::thread;
+use std::time;
 use failure::*;
-use std::fs::File;
-use std::io::Read;
 use std::env;
 use log::*;
 
 
+use crate::config::Config;
 use crate::db::*;
 use crate::server::RealServerConnection;
 
@@ -27,58 +33,94 @@ impl EventHandler for Handler {}
 
 fn main() {
     if let Err(e) = do_main() {
+        // the logger might not be set up yet if the config was the problem
+        eprintln!("{}", e);
         info!("server crashed with error {:?}", e)
     }
 }
 
 fn do_main() -> Result<(), Error> {
-    SimpleLogger::init(LogLevelFilter::Debug, Config::default())?;
+    let config = Config::load()?;
+    SimpleLogger::init(config.log_level_filter(), LogConfig::default())?;
     info!("Logger initialised");
+    server::set_timeouts(config.connect_timeout(), config.read_timeout());
 
-    let mut discord_client = create_discord_client().context("Creating discord client")?;
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        let db_conn = open_db(&config)?;
+        let admins = commands::admin::Admins(config.admins());
+        return cli::run::<RealServerConnection>(&args, &config, &db_conn, &admins);
+    }
+
+    let mut discord_client = create_discord_client(&config).context("Creating discord client")?;
     if let Err(why) = discord_client.start() {
         error!("Client error: {:?}", why);
     }
     Ok(())
 }
 
-fn read_token() -> Result<String, Error> {
-    let mut token_file = File::open("resources/token").context("Opening file 'resources/token'")?;
-    let mut temp_token = String::new();
-    token_file.read_to_string(&mut temp_token).context("Reading contents of file")?;
-    info!("Read discord bot token");
-    Ok(temp_token)
+fn open_db(config: &Config) -> Result<DbConnection, Error> {
+    let path = env::current_dir()?.join(&config.db_path);
+    let db_conn = DbConnection::new(&path).context(format!("Opening database '{}'", path.display()))?;
+    info!("Opened database connection");
+    Ok(db_conn)
 }
 
-fn create_discord_client() -> Result<Client, Error> {
-    let token = read_token().context("Reading token file")?;
+fn create_discord_client(config: &Config) -> Result<Client, Error> {
+    let token = config.read_token()?;
+    info!("Read discord bot token");
 
-    let path = env::current_dir()?;
-    let path = path.join("resources/dom5bot.db");
-    let db_conn = DbConnection::new(&path).context(format!("Opening database '{}'", path.display()))?;
-    info!("Opened database connection");
+    let db_conn = open_db(config)?;
+    if let Some(ref metrics_address) = config.metrics_address {
+        metrics::serve(metrics_address)?;
+    }
+    if let Some(ref api_address) = config.api_address {
+        api::serve::<RealServerConnection>(api_address, db_conn.clone())?;
+    }
+    let backups = config.backups()?;
+    // nothing is being backed up yet, so anything half-written is from before a restart
+    if let Err(err) = backups.remove_partials() {
+        error!("error deleting partial backups in {}: {}", backups.dir.display(), err);
+    }
+    // an interval of 0 hours turns scheduled backups off, `!backup` still works
+    let backup_interval = time::Duration::from_secs(config.backup.interval_hours * 60 * 60);
+    let backup_db_conn = db_conn.clone();
+    let scheduled_backups = backups.clone();
+    thread::spawn(move || {
+        db::back_up_every(&backup_db_conn, &scheduled_backups, backup_interval);
+    });
+    let outbox_db_conn = db_conn.clone();
+    thread::spawn(move || {
+        notifier::deliver_outbox_every(
+            time::Duration::from_secs(notifier::OUTBOX_INTERVAL_SECS),
+            &outbox_db_conn,
+        );
+    });
 
     let mut discord_client = Client::new(&token, Handler).map_err(SyncFailure::new)?;
     info!("Created discord client");
-    {
-        let mut data = discord_client.data.lock();
-        data.insert::<DbConnectionKey>(db_conn);
-    }
 
     use crate::commands::WithSearchCommands;
-    use crate::commands::servers::WithServersCommands;
+    let inspector = config.inspector()?;
+    use crate::commands::admin::{Admins, WithAdminCommands};
+    // needs the token, which `Client::new` gave to serenity's http client
+    let admins = Admins(config.admins()).with_bot_owner()?;
+    info!("Looked up the bot's owner");
+    use crate::commands::servers::{Feeds, StatusPages, WithServersCommands};
     discord_client.with_framework(
         StandardFramework::new()
-            .configure(|c| c.prefix("!"))
+            .configure(|c| c.prefix(&config.prefix))
             .simple_bucket("simple", 1)
-            .with_search_commands("simple")
-            .with_servers_commands::<RealServerConnection>("simple")
+            .with_search_commands("simple", &inspector)
+            .with_servers_commands::<RealServerConnection>("simple", &db_conn, &admins)
+            .with_admin_commands("simple", &db_conn, &backups, &admins)
             .help(|_, msg, _, _, _| commands::help(msg))
             .before(|_, msg, _| {
                 info!("received message {:?}", msg);
                 true
             })
-            .after(|_ctx, msg, _cmd_name, result| {
+            .after(|_ctx, msg, cmd_name, result| {
+                metrics::record_command(cmd_name, result.is_ok());
                 if let Err(err) = result {
                     print!("command error: ");
                     let text = format!("ERROR: {}", err.0);
@@ -89,12 +131,30 @@ fn create_discord_client() -> Result<Client, Error> {
     );
     info!("Configured discord client");
 
-     let data_clone = discord_client.data.clone();
-     thread::spawn(move || {
-        commands::servers::check_for_new_turns_every_1_min::<RealServerConnection>(
-            data_clone.as_ref(),
+    let poll_interval = config.poll_interval();
+    let lobby_expiry_days = config.lobby_expiry_days;
+    let status_pages = match config.status_page_dir {
+        Some(ref dir) => Some(StatusPages {
+            dir: env::current_dir()?.join(dir),
+        }),
+        None => None,
+    };
+    let feeds = match config.feed_dir {
+        Some(ref dir) => Some(Feeds {
+            dir: env::current_dir()?.join(dir),
+        }),
+        None => None,
+    };
+    thread::spawn(move || {
+        commands::servers::check_for_new_turns_every::<RealServerConnection>(
+            poll_interval,
+            &db_conn,
+            &discord::SerenityDiscord,
+            status_pages.as_ref(),
+            feeds.as_ref(),
+            lobby_expiry_days,
         );
-     });
+    });
     // start listening for events by starting a single shard
     Ok(discord_client)
 }
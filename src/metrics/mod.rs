@@ -0,0 +1,202 @@
+use failure::{err_msg, Error};
+use lazy_static::lazy_static;
+use log::*;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts,
+    TextEncoder,
+};
+use tiny_http::{Header, Method, Response, Server};
+
+use std::net::SocketAddr;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests;
+
+/// Game servers usually answer in well under a second, a poll cycle covers every game
+const FETCH_BUCKETS_SECS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+const POLL_CYCLE_BUCKETS_SECS: &[f64] = &[0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0];
+
+struct Metrics {
+    poll_cycle_duration: Histogram,
+    game_data_fetch_duration: HistogramVec,
+    game_data_failures: IntCounterVec,
+    game_data_cache_lookups: IntCounter,
+    game_data_cache_misses: IntCounter,
+    notifications_sent: IntCounterVec,
+    notifications_failed: IntCounterVec,
+    commands: IntCounterVec,
+    command_errors: IntCounterVec,
+    tracked_games: IntGauge,
+    tracked_lobbies: IntGauge,
+}
+
+lazy_static! {
+    // Everything is registered together so a scrape straight after startup already lists
+    // every metric that doesn't have labels
+    static ref METRICS: Metrics = Metrics::register().expect("Registering metrics");
+}
+
+impl Metrics {
+    fn register() -> Result<Metrics, prometheus::Error> {
+        let metrics = Metrics {
+            poll_cycle_duration: Histogram::with_opts(
+                HistogramOpts::new(
+                    "dom5status_poll_cycle_duration_seconds",
+                    "How long each check for new turns took",
+                ).buckets(POLL_CYCLE_BUCKETS_SECS.to_vec()),
+            )?,
+            game_data_fetch_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "dom5status_game_data_fetch_duration_seconds",
+                    "How long each game server took to answer, cached answers aren't counted",
+                ).buckets(FETCH_BUCKETS_SECS.to_vec()),
+                &["address"],
+            )?,
+            game_data_failures: IntCounterVec::new(
+                Opts::new(
+                    "dom5status_game_data_failures_total",
+                    "Requests to a game server that failed or timed out",
+                ),
+                &["address"],
+            )?,
+            game_data_cache_lookups: IntCounter::new(
+                "dom5status_game_data_cache_lookups_total",
+                "Requests for game data, whether or not they were answered from the cache",
+            )?,
+            game_data_cache_misses: IntCounter::new(
+                "dom5status_game_data_cache_misses_total",
+                "Requests for game data that had to go to the game server",
+            )?,
+            notifications_sent: IntCounterVec::new(
+                Opts::new(
+                    "dom5status_notifications_sent_total",
+                    "Messages sent by the turn checker, per backend",
+                ),
+                &["kind", "backend"],
+            )?,
+            notifications_failed: IntCounterVec::new(
+                Opts::new(
+                    "dom5status_notifications_failed_total",
+                    "Messages the turn checker could not send, per backend",
+                ),
+                &["kind", "backend"],
+            )?,
+            commands: IntCounterVec::new(
+                Opts::new("dom5status_commands_total", "Commands run"),
+                &["command"],
+            )?,
+            command_errors: IntCounterVec::new(
+                Opts::new("dom5status_command_errors_total", "Commands that replied with an error"),
+                &["command"],
+            )?,
+            tracked_games: IntGauge::new(
+                "dom5status_tracked_games",
+                "Started games as of the last turn check",
+            )?,
+            tracked_lobbies: IntGauge::new(
+                "dom5status_tracked_lobbies",
+                "Lobbies as of the last turn check",
+            )?,
+        };
+        prometheus::register(Box::new(metrics.poll_cycle_duration.clone()))?;
+        prometheus::register(Box::new(metrics.game_data_fetch_duration.clone()))?;
+        prometheus::register(Box::new(metrics.game_data_failures.clone()))?;
+        prometheus::register(Box::new(metrics.game_data_cache_lookups.clone()))?;
+        prometheus::register(Box::new(metrics.game_data_cache_misses.clone()))?;
+        prometheus::register(Box::new(metrics.notifications_sent.clone()))?;
+        prometheus::register(Box::new(metrics.notifications_failed.clone()))?;
+        prometheus::register(Box::new(metrics.commands.clone()))?;
+        prometheus::register(Box::new(metrics.command_errors.clone()))?;
+        prometheus::register(Box::new(metrics.tracked_games.clone()))?;
+        prometheus::register(Box::new(metrics.tracked_lobbies.clone()))?;
+        Ok(metrics)
+    }
+}
+
+fn as_secs_f64(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1_000_000_000.0
+}
+
+pub fn observe_poll_cycle(duration: Duration) {
+    METRICS.poll_cycle_duration.observe(as_secs_f64(duration));
+}
+
+/// Only for requests that actually went to the game server
+pub fn observe_game_data_fetch(address: &str, duration: Duration, succeeded: bool) {
+    METRICS.game_data_cache_misses.inc();
+    METRICS
+        .game_data_fetch_duration
+        .with_label_values(&[address])
+        .observe(as_secs_f64(duration));
+    if !succeeded {
+        METRICS.game_data_failures.with_label_values(&[address]).inc();
+    }
+}
+
+/// Every request for game data, the misses are counted by `observe_game_data_fetch`
+pub fn record_game_data_lookup() {
+    METRICS.game_data_cache_lookups.inc();
+}
+
+pub fn record_notification<T, E>(kind: &str, backend: &str, result: &Result<T, E>) {
+    match result {
+        Ok(_) => METRICS.notifications_sent.with_label_values(&[kind, backend]).inc(),
+        Err(_) => METRICS.notifications_failed.with_label_values(&[kind, backend]).inc(),
+    }
+}
+
+pub fn record_command(command: &str, succeeded: bool) {
+    METRICS.commands.with_label_values(&[command]).inc();
+    if !succeeded {
+        METRICS.command_errors.with_label_values(&[command]).inc();
+    }
+}
+
+pub fn set_tracked(games: usize, lobbies: usize) {
+    METRICS.tracked_games.set(games as i64);
+    METRICS.tracked_lobbies.set(lobbies as i64);
+}
+
+/// Everything in the Prometheus text format
+pub fn encode() -> Result<String, Error> {
+    lazy_static::initialize(&METRICS);
+    let mut buffer = vec![];
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
+
+/// Starts answering `GET /metrics` on a background thread. Returns the address actually
+/// listened on, which only differs from `address` when it asks for port 0.
+pub fn serve(address: &str) -> Result<SocketAddr, Error> {
+    let server = Server::http(address)
+        .map_err(|e| err_msg(format!("Listening for metrics requests on {}: {}", address, e)))?;
+    let local_address = server
+        .server_addr()
+        .to_ip()
+        .ok_or_else(|| err_msg(format!("Listening for metrics requests on {}: not an IP address", address)))?;
+    info!("serving metrics on http://{}/metrics", local_address);
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = if *request.method() == Method::Get && request.url() == "/metrics" {
+                match encode() {
+                    Ok(text) => Response::from_string(text).with_header(
+                        Header::from_bytes(&b"Content-Type"[..], &b"text/plain; version=0.0.4"[..])
+                            .expect("Static header is valid"),
+                    ),
+                    Err(err) => {
+                        error!("encoding metrics failed with: {}", err);
+                        Response::from_string(err.to_string()).with_status_code(500)
+                    }
+                }
+            } else {
+                Response::from_string("Not found").with_status_code(404)
+            };
+            if let Err(err) = request.respond(response) {
+                debug!("could not answer metrics request: {}", err);
+            }
+        }
+    });
+    Ok(local_address)
+}
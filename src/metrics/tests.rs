@@ -0,0 +1,79 @@
+use super::*;
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+// The registry is shared by every test, so each test uses its own label values
+
+fn get(address: SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "GET {} HTTP/1.0\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn should_list_unlabelled_metrics_before_anything_happens() {
+    let text = encode().unwrap();
+    assert!(text.contains("dom5status_poll_cycle_duration_seconds_count"));
+    assert!(text.contains("dom5status_game_data_cache_lookups_total"));
+    assert!(text.contains("dom5status_tracked_games"));
+    assert!(text.contains("dom5status_tracked_lobbies"));
+}
+
+#[test]
+fn should_count_commands_and_their_errors() {
+    record_command("metrics_test_command", true);
+    record_command("metrics_test_command", false);
+    record_command("metrics_test_command", true);
+    let text = encode().unwrap();
+    assert!(text.contains("dom5status_commands_total{command=\"metrics_test_command\"} 3"));
+    assert!(text.contains("dom5status_command_errors_total{command=\"metrics_test_command\"} 1"));
+}
+
+#[test]
+fn should_count_sent_and_failed_notifications() {
+    record_notification::<(), ()>("metrics_test_kind", "webhook", &Ok(()));
+    record_notification::<(), ()>("metrics_test_kind", "webhook", &Ok(()));
+    record_notification::<(), ()>("metrics_test_kind", "webhook", &Err(()));
+    record_notification::<(), ()>("metrics_test_kind", "discord_dm", &Ok(()));
+    let text = encode().unwrap();
+    assert!(text.contains(
+        "dom5status_notifications_sent_total{backend=\"webhook\",kind=\"metrics_test_kind\"} 2"
+    ));
+    assert!(text.contains(
+        "dom5status_notifications_failed_total{backend=\"webhook\",kind=\"metrics_test_kind\"} 1"
+    ));
+    assert!(text.contains(
+        "dom5status_notifications_sent_total{backend=\"discord_dm\",kind=\"metrics_test_kind\"} 1"
+    ));
+}
+
+#[test]
+fn should_time_game_data_fetches_per_address() {
+    observe_game_data_fetch("metrics.test:1", Duration::from_millis(200), true);
+    observe_game_data_fetch("metrics.test:1", Duration::from_secs(20), false);
+    let text = encode().unwrap();
+    assert!(text.contains(
+        "dom5status_game_data_fetch_duration_seconds_count{address=\"metrics.test:1\"} 2"
+    ));
+    assert!(text.contains(
+        "dom5status_game_data_fetch_duration_seconds_bucket{address=\"metrics.test:1\",le=\"0.25\"} 1"
+    ));
+    assert!(text.contains("dom5status_game_data_failures_total{address=\"metrics.test:1\"} 1"));
+}
+
+#[test]
+fn should_serve_metrics_over_http() {
+    record_command("metrics_test_http", true);
+    let address = serve("127.0.0.1:0").unwrap();
+
+    let response = get(address, "/metrics");
+    assert!(response.starts_with("HTTP/1.0 200") || response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("text/plain; version=0.0.4"));
+    assert!(response.contains("dom5status_commands_total{command=\"metrics_test_http\"} 1"));
+
+    let response = get(address, "/");
+    assert!(response.starts_with("HTTP/1.0 404") || response.starts_with("HTTP/1.1 404"));
+}
@@ -0,0 +1,42 @@
+use std::fmt;
+use enum_primitive_derive::Primitive;
+
+#[derive(Clone, Copy, PartialEq, Debug, Primitive)]
+pub enum LobbyStatus {
+    Open = 0,
+    Locked = 1,
+    Cancelled = 2,
+    Expired = 3,
+}
+
+impl LobbyStatus {
+    pub fn from_string(string: &str) -> Option<LobbyStatus> {
+        match string.to_lowercase().as_ref() {
+            "open" => Some(LobbyStatus::Open),
+            "locked" => Some(LobbyStatus::Locked),
+            "cancelled" => Some(LobbyStatus::Cancelled),
+            "expired" => Some(LobbyStatus::Expired),
+            _ => None,
+        }
+    }
+
+    /// Cancelled and expired lobbies are kept around but no longer listed
+    pub fn is_active(self) -> bool {
+        match self {
+            LobbyStatus::Open | LobbyStatus::Locked => true,
+            LobbyStatus::Cancelled | LobbyStatus::Expired => false,
+        }
+    }
+}
+
+impl fmt::Display for LobbyStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match *self {
+            LobbyStatus::Open => "open",
+            LobbyStatus::Locked => "locked",
+            LobbyStatus::Cancelled => "cancelled",
+            LobbyStatus::Expired => "expired",
+        };
+        f.write_str(text)
+    }
+}
@@ -6,3 +6,5 @@ mod submission_status;
 pub use self::submission_status::*;
 mod era;
 pub use self::era::*;
+mod lobby_status;
+pub use self::lobby_status::*;
@@ -1,5 +1,6 @@
-use crate::model::enums::Era;
+use crate::model::enums::{Era, LobbyStatus};
 use serenity::model::id::UserId;
+use chrono::{DateTime, Utc};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct GameServer {
@@ -25,4 +26,6 @@ pub struct LobbyState {
     pub era: Era,
     pub player_count: i32,
     pub description: Option<String>,
+    pub status: LobbyStatus,
+    pub scheduled_start: Option<DateTime<Utc>>,
 }
@@ -14,3 +14,24 @@ pub use self::player::*;
 
 mod game_server;
 pub use self::game_server::*;
+
+mod stall;
+pub use self::stall::*;
+
+mod notification_settings;
+pub use self::notification_settings::*;
+
+mod substitution;
+pub use self::substitution::*;
+
+mod waitlist_entry;
+pub use self::waitlist_entry::*;
+
+mod turn_record;
+pub use self::turn_record::*;
+
+mod notifiers;
+pub use self::notifiers::*;
+
+mod outbox;
+pub use self::outbox::*;
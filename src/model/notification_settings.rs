@@ -0,0 +1,8 @@
+/// A player's notification settings for one game, after falling back to their global settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationSettings {
+    pub turn_notifications: bool,
+    /// `None` means use the default thresholds
+    pub reminder_thresholds_mins: Option<Vec<i32>>,
+    pub detailed: bool,
+}
@@ -0,0 +1,23 @@
+/// Somewhere to POST notifications to as JSON
+#[derive(Debug, Clone, PartialEq)]
+pub struct Webhook {
+    pub url: String,
+    /// Sent in a header so the receiver can tell the bot apart from anyone else
+    pub secret: Option<String>,
+}
+
+/// Where a player wants to hear from the bot
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerNotifiers {
+    pub discord_dms: bool,
+    pub webhook: Option<Webhook>,
+}
+
+impl Default for PlayerNotifiers {
+    fn default() -> Self {
+        PlayerNotifiers {
+            discord_dms: true,
+            webhook: None,
+        }
+    }
+}
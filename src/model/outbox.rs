@@ -0,0 +1,22 @@
+use serenity::model::id::UserId;
+
+/// A notification for one backend of one recipient, queued in the same transaction
+/// as the new turn it's about
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxMessage {
+    pub game: String,
+    pub turn: i32,
+    pub kind: String,
+    /// Nobody when it's for the game's own channel and webhook
+    pub recipient: Option<UserId>,
+    pub backend: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingOutboxMessage {
+    pub id: i64,
+    /// Failed attempts so far
+    pub attempts: i32,
+    pub message: OutboxMessage,
+}
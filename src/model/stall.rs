@@ -0,0 +1,8 @@
+use serenity::model::id::UserId;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stall {
+    pub nation_id: usize,
+    pub player: Option<UserId>,
+    pub turn: i32,
+}
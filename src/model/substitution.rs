@@ -0,0 +1,12 @@
+use serenity::model::id::UserId;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Substitution {
+    pub id: i64,
+    pub nation_id: usize,
+    pub outgoing: UserId,
+    /// `None` while the nation is still looking for a substitute
+    pub incoming: Option<UserId>,
+    pub requested_turn: i32,
+    pub taken_turn: Option<i32>,
+}
@@ -0,0 +1,13 @@
+use chrono::{DateTime, Utc};
+
+/// A new turn as the turn checker saw it, along with what happened in the turn that just ended
+#[derive(Debug, Clone, PartialEq)]
+pub struct TurnRecord {
+    pub turn: i32,
+    pub seen_at: DateTime<Utc>,
+    /// Milliseconds left when the turn was first seen, 0 or less when there's no timer
+    pub turn_timer: i32,
+    pub defeated: Vec<usize>,
+    pub ai: Vec<usize>,
+    pub possible_stalls: Vec<usize>,
+}
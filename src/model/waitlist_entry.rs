@@ -0,0 +1,9 @@
+use serenity::model::id::UserId;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaitlistEntry {
+    pub player: UserId,
+    pub preferred_nation_id: Option<u32>,
+    /// A promoted player holds a free lobby slot until they register a nation
+    pub promoted: bool,
+}
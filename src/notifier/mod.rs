@@ -0,0 +1,329 @@
+use failure::{err_msg, Error};
+use hyper::Client;
+use hyper::header::{ContentType, Headers, UserAgent};
+use hyper::net::HttpsConnector;
+use hyper_native_tls::NativeTlsClient;
+use serde::{Serialize, Serializer};
+use serenity::model::id::{ChannelId, UserId};
+use log::*;
+use chrono::{DateTime, Utc};
+
+use std::{cmp, thread};
+use std::time::Duration;
+
+use crate::db::DbConnection;
+use crate::discord::{Discord, SerenityDiscord};
+use crate::metrics;
+use crate::model::{OutboxMessage, PlayerNotifiers, Webhook};
+
+#[cfg(test)]
+mod tests;
+
+/// Carries the webhook's secret, if it has one
+pub const WEBHOOK_SECRET_HEADER: &str = "X-Dom5status-Secret";
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+
+/// How often the outbox worker looks for messages that are due
+pub const OUTBOX_INTERVAL_SECS: u64 = 10;
+/// With the backoff below that's about six hours before giving up on a message
+pub const MAX_OUTBOX_ATTEMPTS: i32 = 12;
+const FIRST_RETRY_DELAY_SECS: i64 = 30;
+const MAX_RETRY_DELAY_SECS: i64 = 60 * 60;
+const KEEP_DELIVERED_DAYS: i64 = 30;
+
+/// Something the bot tells a player or a whole game without being asked. This is also
+/// the JSON body that webhooks receive.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Notification {
+    pub game: String,
+    /// Lobbies don't have turns yet
+    pub turn: Option<i32>,
+    /// One of new_turn, reminder, stall_warning, start_reminder, lobby_expired, game_started,
+    /// waitlist_promotion, kicked, owner_transfer or substitute
+    pub kind: String,
+    pub text: String,
+    /// Who it's for, or nothing when it's for everyone in the game
+    #[serde(serialize_with = "user_id_as_string")]
+    pub discord_user_id: Option<UserId>,
+}
+
+/// Discord ids are too big for a javascript number
+fn user_id_as_string<S: Serializer>(user_id: &Option<UserId>, serializer: S) -> Result<S::Ok, S::Error> {
+    match *user_id {
+        Some(user_id) => serializer.serialize_some(&user_id.0.to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+impl Notification {
+    pub fn for_game(game: &str, turn: Option<i32>, kind: &str, text: String) -> Notification {
+        Notification {
+            game: game.to_owned(),
+            turn,
+            kind: kind.to_owned(),
+            text,
+            discord_user_id: None,
+        }
+    }
+
+    pub fn for_player(
+        user_id: UserId,
+        game: &str,
+        turn: Option<i32>,
+        kind: &str,
+        text: String,
+    ) -> Notification {
+        Notification {
+            discord_user_id: Some(user_id),
+            ..Notification::for_game(game, turn, kind, text)
+        }
+    }
+}
+
+impl<'a> From<&'a OutboxMessage> for Notification {
+    fn from(message: &'a OutboxMessage) -> Notification {
+        Notification {
+            game: message.game.clone(),
+            turn: Some(message.turn),
+            kind: message.kind.clone(),
+            text: message.text.clone(),
+            discord_user_id: message.recipient,
+        }
+    }
+}
+
+pub trait Notifier {
+    /// For logs and metrics
+    fn backend(&self) -> &'static str;
+    fn notify(&self, notification: &Notification) -> Result<(), Error>;
+}
+
+pub struct DiscordDm<'a>(pub &'a dyn Discord, pub UserId);
+
+impl<'a> Notifier for DiscordDm<'a> {
+    fn backend(&self) -> &'static str {
+        "discord_dm"
+    }
+
+    fn notify(&self, notification: &Notification) -> Result<(), Error> {
+        self.0.send_dm(self.1, &notification.text)
+    }
+}
+
+pub struct DiscordChannel<'a>(pub &'a dyn Discord, pub ChannelId);
+
+impl<'a> Notifier for DiscordChannel<'a> {
+    fn backend(&self) -> &'static str {
+        "discord_channel"
+    }
+
+    fn notify(&self, notification: &Notification) -> Result<(), Error> {
+        self.0.say(self.1, &notification.text)
+    }
+}
+
+impl Notifier for Webhook {
+    fn backend(&self) -> &'static str {
+        "webhook"
+    }
+
+    /// Any 2xx answer counts as delivered
+    fn notify(&self, notification: &Notification) -> Result<(), Error> {
+        let body = serde_json::to_string(notification)?;
+        let tls = NativeTlsClient::new()?;
+        let mut client = Client::with_connector(HttpsConnector::new(tls));
+        client.set_read_timeout(Some(Duration::from_secs(WEBHOOK_TIMEOUT_SECS)));
+        client.set_write_timeout(Some(Duration::from_secs(WEBHOOK_TIMEOUT_SECS)));
+
+        let mut headers = Headers::new();
+        headers.set(ContentType::json());
+        headers.set(UserAgent("dom5status".to_owned()));
+        if let Some(ref secret) = self.secret {
+            headers.set_raw(WEBHOOK_SECRET_HEADER, vec![secret.clone().into_bytes()]);
+        }
+        let response = client.post(&self.url).headers(headers).body(&body[..]).send()?;
+        if response.status.is_success() {
+            Ok(())
+        } else {
+            Err(err_msg(format!("webhook answered {}", response.status)))
+        }
+    }
+}
+
+pub fn player_notifiers<'a>(
+    discord: &'a dyn Discord,
+    user_id: UserId,
+    settings: &PlayerNotifiers,
+) -> Vec<Box<dyn Notifier + 'a>> {
+    let mut notifiers: Vec<Box<dyn Notifier + 'a>> = vec![];
+    if settings.discord_dms {
+        notifiers.push(Box::new(DiscordDm(discord, user_id)));
+    }
+    if let Some(ref webhook) = settings.webhook {
+        notifiers.push(Box::new(webhook.clone()));
+    }
+    notifiers
+}
+
+pub fn game_notifiers<'a>(
+    discord: &'a dyn Discord,
+    channel_id: Option<ChannelId>,
+    webhook: Option<Webhook>,
+) -> Vec<Box<dyn Notifier + 'a>> {
+    let mut notifiers: Vec<Box<dyn Notifier + 'a>> = vec![];
+    if let Some(channel_id) = channel_id {
+        notifiers.push(Box::new(DiscordChannel(discord, channel_id)));
+    }
+    if let Some(webhook) = webhook {
+        notifiers.push(Box::new(webhook));
+    }
+    notifiers
+}
+
+/// One backend failing doesn't stop the others. Returns the first failure.
+pub fn notify_all(notifiers: &[Box<dyn Notifier + '_>], notification: &Notification) -> Result<(), Error> {
+    let mut first_err = None;
+    for notifier in notifiers {
+        let result = notifier.notify(notification);
+        metrics::record_notification(&notification.kind, notifier.backend(), &result);
+        if let Err(err) = result {
+            error!(
+                "could not send {} for {} by {}: {}",
+                notification.kind,
+                notification.game,
+                notifier.backend(),
+                err
+            );
+            first_err.get_or_insert(err);
+        }
+    }
+    match first_err {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Through whichever backends the player chose
+pub fn notify_player(
+    db_conn: &DbConnection,
+    discord: &dyn Discord,
+    user_id: UserId,
+    notification: &Notification,
+) -> Result<(), Error> {
+    let settings = db_conn.player_notifiers(user_id)?;
+    notify_all(&player_notifiers(discord, user_id, &settings), notification)
+}
+
+/// A player's backends, or the game's notification channel and webhook when there's
+/// no recipient
+fn current_notifiers<'a>(
+    db_conn: &DbConnection,
+    discord: &'a dyn Discord,
+    game: &str,
+    recipient: Option<UserId>,
+) -> Result<Vec<Box<dyn Notifier + 'a>>, Error> {
+    match recipient {
+        Some(user_id) => Ok(player_notifiers(discord, user_id, &db_conn.player_notifiers(user_id)?)),
+        None => Ok(game_notifiers(
+            discord,
+            db_conn.notification_channel_for_game_alias(game)?,
+            db_conn.game_webhook(game)?,
+        )),
+    }
+}
+
+/// One outbox message for each backend the notification's recipient has right now.
+/// Only notifications about a turn can go through the outbox.
+pub fn outbox_messages(db_conn: &DbConnection, notification: &Notification) -> Result<Vec<OutboxMessage>, Error> {
+    let turn = notification
+        .turn
+        .ok_or_else(|| err_msg(format!("{} for {} isn't about a turn", notification.kind, notification.game)))?;
+    // only the backends' names are needed here, nothing is sent
+    let notifiers = current_notifiers(db_conn, &SerenityDiscord, &notification.game, notification.discord_user_id)?;
+    Ok(notifiers
+        .iter()
+        .map(|notifier| OutboxMessage {
+            game: notification.game.clone(),
+            turn,
+            kind: notification.kind.clone(),
+            recipient: notification.discord_user_id,
+            backend: notifier.backend().to_owned(),
+            text: notification.text.clone(),
+        })
+        .collect())
+}
+
+/// The message's backend as its recipient has it set up now, or nothing if they've
+/// turned it off since the message was queued
+pub fn outbox_notifier<'a>(
+    db_conn: &DbConnection,
+    discord: &'a dyn Discord,
+    message: &OutboxMessage,
+) -> Result<Option<Box<dyn Notifier + 'a>>, Error> {
+    let notifiers = current_notifiers(db_conn, discord, &message.game, message.recipient)?;
+    Ok(notifiers.into_iter().find(|notifier| notifier.backend() == message.backend))
+}
+
+/// How long to wait after the `attempts`th failure: 30s, 1m, 2m... up to an hour
+pub fn retry_delay(attempts: i32) -> chrono::Duration {
+    // 30s << 7 is already past an hour
+    let doublings = cmp::min(cmp::max(attempts - 1, 0), 7) as u32;
+    chrono::Duration::seconds(cmp::min(FIRST_RETRY_DELAY_SECS << doublings, MAX_RETRY_DELAY_SECS))
+}
+
+/// Tries every message that's due once. A failure is recorded against its message
+/// and doesn't hold up the rest.
+pub fn deliver_outbox<'a, F>(db_conn: &DbConnection, now: DateTime<Utc>, notifier_for: F) -> Result<(), Error>
+where
+    F: Fn(&DbConnection, &OutboxMessage) -> Result<Option<Box<dyn Notifier + 'a>>, Error>,
+{
+    for pending in db_conn.pending_outbox_messages(now, MAX_OUTBOX_ATTEMPTS)? {
+        let message = &pending.message;
+        let notifier = match notifier_for(db_conn, message)? {
+            Some(notifier) => notifier,
+            None => {
+                info!(
+                    "dropping {} for {} by {}, it's been turned off",
+                    message.kind, message.game, message.backend
+                );
+                db_conn.mark_outbox_message_delivered(pending.id, now)?;
+                continue;
+            }
+        };
+        let result = notifier.notify(&Notification::from(message));
+        metrics::record_notification(&message.kind, notifier.backend(), &result);
+        match result {
+            Ok(()) => db_conn.mark_outbox_message_delivered(pending.id, now)?,
+            Err(err) => {
+                let attempts = pending.attempts + 1;
+                if attempts >= MAX_OUTBOX_ATTEMPTS {
+                    error!(
+                        "giving up on {} for {} by {} after {} attempts: {}",
+                        message.kind, message.game, message.backend, attempts, err
+                    );
+                } else {
+                    warn!(
+                        "could not send {} for {} by {}, will retry: {}",
+                        message.kind, message.game, message.backend, err
+                    );
+                }
+                db_conn.mark_outbox_message_failed(pending.id, &err.to_string(), now + retry_delay(attempts))?;
+            }
+        }
+    }
+    db_conn.delete_delivered_outbox_messages(now - chrono::Duration::days(KEEP_DELIVERED_DAYS))?;
+    Ok(())
+}
+
+/// The only thing that sends outbox messages, so nothing is sent twice at once
+pub fn deliver_outbox_every(interval: Duration, db_conn: &DbConnection) {
+    loop {
+        thread::sleep(interval);
+        let notifier_for = |db_conn: &DbConnection, message: &OutboxMessage| {
+            outbox_notifier(db_conn, &SerenityDiscord, message)
+        };
+        deliver_outbox(db_conn, Utc::now(), notifier_for).unwrap_or_else(|e| {
+            error!("Delivering the notification outbox failed with: {}", e);
+        });
+    }
+}
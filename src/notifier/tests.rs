@@ -0,0 +1,395 @@
+use super::*;
+
+use crate::discord::{FakeDiscord, Sent};
+use crate::model::{Player, TurnRecord};
+use chrono::TimeZone;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::thread;
+use tiny_http::{Response, Server};
+
+struct Received {
+    path: String,
+    secret: Option<String>,
+    body: serde_json::Value,
+}
+
+/// Answers a single request with `status` and hands back what it received
+fn webhook_receiver(status: u16) -> (String, mpsc::Receiver<Received>) {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", server.server_addr().to_ip().unwrap());
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut request = server.recv().unwrap();
+        let mut body = String::new();
+        request.as_reader().read_to_string(&mut body).unwrap();
+        let secret = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv(WEBHOOK_SECRET_HEADER))
+            .map(|header| header.value.as_str().to_owned());
+        let received = Received {
+            path: request.url().to_owned(),
+            secret,
+            body: serde_json::from_str(&body).unwrap(),
+        };
+        request.respond(Response::empty(status)).unwrap();
+        sender.send(received).unwrap();
+    });
+    (url, receiver)
+}
+
+struct CountingNotifier {
+    calls: Rc<Cell<usize>>,
+    fails: bool,
+}
+
+impl Notifier for CountingNotifier {
+    fn backend(&self) -> &'static str {
+        "notifier_test"
+    }
+
+    fn notify(&self, _: &Notification) -> Result<(), Error> {
+        self.calls.set(self.calls.get() + 1);
+        if self.fails {
+            Err(err_msg("nope"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn backends(notifiers: &[Box<dyn Notifier + '_>]) -> Vec<&'static str> {
+    notifiers.iter().map(|notifier| notifier.backend()).collect()
+}
+
+#[test]
+fn should_post_the_notification_to_a_webhook_with_its_secret() {
+    let (url, receiver) = webhook_receiver(200);
+    let webhook = Webhook {
+        url,
+        secret: Some("hunter2".to_owned()),
+    };
+    let notification = Notification::for_player(
+        UserId(1234),
+        "foo",
+        Some(3),
+        "new_turn",
+        "your turn!".to_owned(),
+    );
+
+    webhook.notify(&notification).unwrap();
+
+    let received = receiver.recv().unwrap();
+    assert_eq!(received.path, "/hook");
+    assert_eq!(received.secret, Some("hunter2".to_owned()));
+    assert_eq!(
+        received.body,
+        serde_json::json!({
+            "game": "foo",
+            "turn": 3,
+            "kind": "new_turn",
+            "text": "your turn!",
+            "discord_user_id": "1234",
+        })
+    );
+}
+
+#[test]
+fn should_leave_out_the_secret_header_and_the_user_when_there_are_none() {
+    let (url, receiver) = webhook_receiver(204);
+    let webhook = Webhook { url, secret: None };
+    let notification = Notification::for_game("foo", None, "lobby_expired", "gone".to_owned());
+
+    webhook.notify(&notification).unwrap();
+
+    let received = receiver.recv().unwrap();
+    assert_eq!(received.secret, None);
+    assert_eq!(received.body["turn"], serde_json::Value::Null);
+    assert_eq!(received.body["discord_user_id"], serde_json::Value::Null);
+}
+
+#[test]
+fn should_fail_when_the_webhook_does_not_answer_2xx() {
+    let (url, receiver) = webhook_receiver(500);
+    let webhook = Webhook { url, secret: None };
+    let notification = Notification::for_game("foo", Some(1), "new_turn", "turn 1".to_owned());
+
+    assert!(webhook.notify(&notification).is_err());
+    receiver.recv().unwrap();
+}
+
+#[test]
+fn should_pick_player_backends_from_their_settings() {
+    let webhook = Webhook {
+        url: "http://example.com".to_owned(),
+        secret: None,
+    };
+    let discord = FakeDiscord::new();
+
+    let default = player_notifiers(&discord, UserId(1), &PlayerNotifiers::default());
+    assert_eq!(backends(&default), vec!["discord_dm"]);
+
+    let both = player_notifiers(&discord, UserId(1), &PlayerNotifiers {
+        discord_dms: true,
+        webhook: Some(webhook.clone()),
+    });
+    assert_eq!(backends(&both), vec!["discord_dm", "webhook"]);
+
+    let webhook_only = player_notifiers(&discord, UserId(1), &PlayerNotifiers {
+        discord_dms: false,
+        webhook: Some(webhook),
+    });
+    assert_eq!(backends(&webhook_only), vec!["webhook"]);
+
+    let nothing = player_notifiers(&discord, UserId(1), &PlayerNotifiers {
+        discord_dms: false,
+        webhook: None,
+    });
+    assert!(nothing.is_empty());
+}
+
+#[test]
+fn should_pick_game_backends_from_the_channel_and_webhook() {
+    let webhook = Webhook {
+        url: "http://example.com".to_owned(),
+        secret: None,
+    };
+    let discord = FakeDiscord::new();
+
+    assert!(game_notifiers(&discord, None, None).is_empty());
+    assert_eq!(backends(&game_notifiers(&discord, Some(ChannelId(1)), None)), vec!["discord_channel"]);
+    assert_eq!(backends(&game_notifiers(&discord, None, Some(webhook.clone()))), vec!["webhook"]);
+    assert_eq!(
+        backends(&game_notifiers(&discord, Some(ChannelId(1)), Some(webhook))),
+        vec!["discord_channel", "webhook"]
+    );
+}
+
+#[test]
+fn should_keep_notifying_after_a_backend_fails() {
+    let calls = Rc::new(Cell::new(0));
+    let notifiers: Vec<Box<dyn Notifier>> = vec![
+        Box::new(CountingNotifier { calls: calls.clone(), fails: true }),
+        Box::new(CountingNotifier { calls: calls.clone(), fails: false }),
+        Box::new(CountingNotifier { calls: calls.clone(), fails: true }),
+    ];
+    let notification = Notification::for_game("foo", Some(1), "new_turn", "turn 1".to_owned());
+
+    assert!(notify_all(&notifiers, &notification).is_err());
+    assert_eq!(calls.get(), 3);
+}
+
+#[test]
+fn should_succeed_when_every_backend_does() {
+    let calls = Rc::new(Cell::new(0));
+    let notifiers: Vec<Box<dyn Notifier>> = vec![
+        Box::new(CountingNotifier { calls: calls.clone(), fails: false }),
+        Box::new(CountingNotifier { calls: calls.clone(), fails: false }),
+    ];
+    let notification = Notification::for_game("foo", Some(1), "new_turn", "turn 1".to_owned());
+
+    notify_all(&notifiers, &notification).unwrap();
+    assert_eq!(calls.get(), 2);
+}
+
+fn outbox_message(game: &str, turn: i32, recipient: Option<UserId>, backend: &str) -> OutboxMessage {
+    OutboxMessage {
+        game: game.to_owned(),
+        turn,
+        kind: "new_turn".to_owned(),
+        recipient,
+        backend: backend.to_owned(),
+        text: format!("turn {}", turn),
+    }
+}
+
+fn turn_record(turn: i32, seen_at: DateTime<Utc>) -> TurnRecord {
+    TurnRecord {
+        turn,
+        seen_at,
+        turn_timer: 60 * 60 * 1000,
+        defeated: vec![],
+        ai: vec![],
+        possible_stalls: vec![],
+    }
+}
+
+/// Hands out counting notifiers, failing for the recipients in `failing`
+fn counting_notifiers(
+    calls: Rc<Cell<usize>>,
+    failing: Vec<Option<UserId>>,
+) -> impl Fn(&DbConnection, &OutboxMessage) -> Result<Option<Box<dyn Notifier>>, Error> {
+    move |_, message| {
+        Ok(Some(Box::new(CountingNotifier {
+            calls: calls.clone(),
+            fails: failing.contains(&message.recipient),
+        }) as Box<dyn Notifier>))
+    }
+}
+
+#[test]
+fn should_queue_messages_with_a_new_turn_only_once() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.outbox:3000", 4);
+    let now = Utc.ymd(2019, 6, 1).and_hms(18, 0, 0);
+    let outbox = vec![
+        outbox_message("foo", 5, None, "discord_channel"),
+        outbox_message("foo", 5, Some(UserId(1)), "discord_dm"),
+        outbox_message("foo", 5, Some(UserId(1)), "webhook"),
+        // the same game, turn, recipient and backend again
+        outbox_message("foo", 5, Some(UserId(1)), "discord_dm"),
+    ];
+
+    assert!(db_conn.update_game_with_possibly_new_turn("foo", &turn_record(5, now), &outbox).unwrap());
+    // a turn that isn't new doesn't queue anything
+    assert!(!db_conn.update_game_with_possibly_new_turn("foo", &turn_record(5, now), &outbox).unwrap());
+    assert_eq!(db_conn.turn_history_for_game_alias("foo").unwrap().len(), 1);
+
+    let pending = db_conn.pending_outbox_messages(now, MAX_OUTBOX_ATTEMPTS).unwrap();
+    let messages = pending.into_iter().map(|pending| pending.message).collect::<Vec<_>>();
+    assert_eq!(messages, outbox[..3].to_vec());
+}
+
+#[test]
+fn should_deliver_the_rest_of_the_outbox_when_one_message_fails() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.outbox:3000", 4);
+    let now = Utc.ymd(2019, 6, 1).and_hms(18, 0, 0);
+    let outbox = vec![
+        outbox_message("foo", 5, Some(UserId(1)), "discord_dm"),
+        outbox_message("foo", 5, Some(UserId(2)), "discord_dm"),
+        outbox_message("foo", 5, Some(UserId(3)), "discord_dm"),
+    ];
+    db_conn.update_game_with_possibly_new_turn("foo", &turn_record(5, now), &outbox).unwrap();
+    let calls = Rc::new(Cell::new(0));
+
+    deliver_outbox(db_conn, now, counting_notifiers(calls.clone(), vec![Some(UserId(1))])).unwrap();
+
+    assert_eq!(calls.get(), 3);
+    let later = now + retry_delay(1);
+    let pending = db_conn.pending_outbox_messages(later, MAX_OUTBOX_ATTEMPTS).unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].attempts, 1);
+    assert_eq!(pending[0].message.recipient, Some(UserId(1)));
+}
+
+#[test]
+fn should_back_off_before_retrying_and_stop_once_delivered() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.outbox:3000", 4);
+    let now = Utc.ymd(2019, 6, 1).and_hms(18, 0, 0);
+    let outbox = vec![outbox_message("foo", 5, Some(UserId(1)), "webhook")];
+    db_conn.update_game_with_possibly_new_turn("foo", &turn_record(5, now), &outbox).unwrap();
+    let calls = Rc::new(Cell::new(0));
+
+    deliver_outbox(db_conn, now, counting_notifiers(calls.clone(), vec![Some(UserId(1))])).unwrap();
+    // not due yet
+    deliver_outbox(db_conn, now + chrono::Duration::seconds(10), counting_notifiers(calls.clone(), vec![])).unwrap();
+    assert_eq!(calls.get(), 1);
+
+    let retry_at = now + retry_delay(1);
+    deliver_outbox(db_conn, retry_at, counting_notifiers(calls.clone(), vec![])).unwrap();
+    assert_eq!(calls.get(), 2);
+    deliver_outbox(db_conn, retry_at + chrono::Duration::hours(2), counting_notifiers(calls.clone(), vec![])).unwrap();
+    assert_eq!(calls.get(), 2);
+}
+
+#[test]
+fn should_give_up_after_too_many_attempts() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.outbox:3000", 4);
+    let mut now = Utc.ymd(2019, 6, 1).and_hms(18, 0, 0);
+    let outbox = vec![outbox_message("foo", 5, None, "discord_channel")];
+    db_conn.update_game_with_possibly_new_turn("foo", &turn_record(5, now), &outbox).unwrap();
+    let calls = Rc::new(Cell::new(0));
+
+    for _ in 0..MAX_OUTBOX_ATTEMPTS + 2 {
+        deliver_outbox(db_conn, now, counting_notifiers(calls.clone(), vec![None])).unwrap();
+        now = now + chrono::Duration::hours(2);
+    }
+
+    assert_eq!(calls.get(), MAX_OUTBOX_ATTEMPTS as usize);
+    assert!(db_conn.pending_outbox_messages(now, MAX_OUTBOX_ATTEMPTS).unwrap().is_empty());
+}
+
+#[test]
+fn should_drop_messages_for_backends_that_were_turned_off() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.outbox:3000", 4);
+    let now = Utc.ymd(2019, 6, 1).and_hms(18, 0, 0);
+    let outbox = vec![outbox_message("foo", 5, Some(UserId(1)), "webhook")];
+    db_conn.update_game_with_possibly_new_turn("foo", &turn_record(5, now), &outbox).unwrap();
+
+    // the player has no webhook any more
+    let discord = FakeDiscord::new().with_user(UserId(1), "one");
+    deliver_outbox(db_conn, now, |db_conn, message| outbox_notifier(db_conn, &discord, message)).unwrap();
+
+    assert!(db_conn.pending_outbox_messages(now, MAX_OUTBOX_ATTEMPTS).unwrap().is_empty());
+}
+
+#[test]
+fn should_deliver_outbox_messages_through_discord() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.outbox:3000", 4);
+    db_conn.set_notification_channel("foo", Some(ChannelId(9))).unwrap();
+    let now = Utc.ymd(2019, 6, 1).and_hms(18, 0, 0);
+    let outbox = vec![
+        outbox_message("foo", 5, None, "discord_channel"),
+        outbox_message("foo", 5, Some(UserId(1)), "discord_dm"),
+        // discord doesn't know them, so this one is retried
+        outbox_message("foo", 5, Some(UserId(2)), "discord_dm"),
+    ];
+    db_conn.update_game_with_possibly_new_turn("foo", &turn_record(5, now), &outbox).unwrap();
+    let discord = FakeDiscord::new().with_user(UserId(1), "one");
+
+    deliver_outbox(db_conn, now, |db_conn, message| outbox_notifier(db_conn, &discord, message)).unwrap();
+
+    assert_eq!(
+        discord.sent(),
+        vec![
+            Sent::Message(ChannelId(9), "turn 5".to_owned()),
+            Sent::Dm(UserId(1), "turn 5".to_owned()),
+        ]
+    );
+    let pending = db_conn
+        .pending_outbox_messages(now + retry_delay(1), MAX_OUTBOX_ATTEMPTS)
+        .unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].message.recipient, Some(UserId(2)));
+}
+
+#[test]
+fn should_double_the_retry_delay_up_to_an_hour() {
+    assert_eq!(retry_delay(1), chrono::Duration::seconds(30));
+    assert_eq!(retry_delay(2), chrono::Duration::seconds(60));
+    assert_eq!(retry_delay(4), chrono::Duration::seconds(240));
+    assert_eq!(retry_delay(8), chrono::Duration::hours(1));
+    assert_eq!(retry_delay(100), chrono::Duration::hours(1));
+}
+
+#[test]
+fn should_queue_a_message_for_each_of_the_recipients_backends() {
+    let db_conn = &DbConnection::test();
+    db_conn.insert_test_game("foo", "foo.outbox:3000", 4);
+    db_conn.insert_player(&Player { discord_user_id: UserId(1), turn_notifications: true }).unwrap();
+    db_conn
+        .set_player_webhook(UserId(1), Some(&Webhook { url: "http://example.com".to_owned(), secret: None }))
+        .unwrap();
+
+    let player = Notification::for_player(UserId(1), "foo", Some(5), "new_turn", "turn 5".to_owned());
+    let backends = outbox_messages(db_conn, &player)
+        .unwrap()
+        .into_iter()
+        .map(|message| message.backend)
+        .collect::<Vec<_>>();
+    assert_eq!(backends, vec!["discord_dm", "webhook"]);
+
+    // no notification channel or webhook for the game
+    let game = Notification::for_game("foo", Some(5), "new_turn", "turn 5".to_owned());
+    assert!(outbox_messages(db_conn, &game).unwrap().is_empty());
+
+    let lobby = Notification::for_player(UserId(1), "foo", None, "lobby_expired", "gone".to_owned());
+    assert!(outbox_messages(db_conn, &lobby).is_err());
+}
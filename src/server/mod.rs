@@ -1,10 +1,16 @@
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use cached::{Cached, TimedCache, cached_key_result};
+use cached::{TimedCache, cached_key_result};
 use hex_slice::AsHex;
 use flate2::read::ZlibDecoder;
 use std::io::{Cursor, Read, Write};
 use std::io;
-use std::net;
+use std::net::{self, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use std::collections::HashMap;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use lazy_static::lazy_static;
+use crate::metrics;
 use crate::model::{GameData, Nation, RawGameData};
 use crate::model::enums::{NationStatus, Nations, SubmissionStatus};
 use log::*;
@@ -22,13 +28,47 @@ cached_key_result! {
     }
 }
 
-pub fn cache_get(k: &String) -> Option<GameData> { // FIXME possible timing issues
-    let mut cache = ONE_MIN_GAME_DATA.lock().unwrap();
-    cache.cache_get(k).cloned()
+lazy_static! {
+    // The game data seen by the previous turn check, keyed by server address. Unlike the
+    // one minute cache above this doesn't expire between polls.
+    static ref LAST_POLLED_GAME_DATA: RwLock<HashMap<String, GameData>> = RwLock::new(HashMap::new());
 }
 
+// Every access is a single map operation, so a panic elsewhere can't leave the map half
+// updated and there's no reason to stop the turn checker over a poisoned lock.
+fn read_snapshots() -> RwLockReadGuard<'static, HashMap<String, GameData>> {
+    LAST_POLLED_GAME_DATA.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn write_snapshots() -> RwLockWriteGuard<'static, HashMap<String, GameData>> {
+    LAST_POLLED_GAME_DATA.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Store the latest polled game data for the server, returning what was seen last time
+pub fn snapshot_replace(server_address: &str, game_data: GameData) -> Option<GameData> {
+    let mut snapshots = write_snapshots();
+    snapshots.insert(server_address.to_owned(), game_data)
+}
+
+pub fn snapshot_get(server_address: &str) -> Option<GameData> {
+    let snapshots = read_snapshots();
+    snapshots.get(server_address).cloned()
+}
+
+/// Keep the previous snapshot when a game moves, so the next turn check can still diff against it
+pub fn snapshot_move(old_server_address: &str, new_server_address: &str) {
+    let mut snapshots = write_snapshots();
+    if let Some(game_data) = snapshots.remove(old_server_address) {
+        snapshots.insert(new_server_address.to_owned(), game_data);
+    }
+}
+
+// Only runs on a cache miss
 fn get_game_data_cache(server_address: &str) -> io::Result<GameData> {
-    let raw_data = get_raw_game_data(server_address)?;
+    let started = Instant::now();
+    let raw_data = get_raw_game_data(server_address);
+    metrics::observe_game_data_fetch(server_address, started.elapsed(), raw_data.is_ok());
+    let raw_data = raw_data?;
     let mut game_data = GameData {
         game_name: raw_data.game_name,
         nations: vec![],
@@ -61,10 +101,31 @@ pub struct RealServerConnection;
 
 impl ServerConnection for RealServerConnection {
     fn get_game_data(server_address: &str) -> io::Result<GameData> {
+        metrics::record_game_data_lookup();
         get_game_data_fn(server_address)
     }
 }
 
+/// Answers for every address without connecting to anything, so the chat commands can be
+/// tried out offline. Every server runs an empty game on turn 1, named after the first part
+/// of its host so that a game can be moved to another host with the same name.
+pub struct OfflineServerConnection;
+
+impl ServerConnection for OfflineServerConnection {
+    fn get_game_data(server_address: &str) -> io::Result<GameData> {
+        let game_name = server_address
+            .split(&['.', ':'][..])
+            .next()
+            .unwrap_or(server_address);
+        Ok(GameData {
+            game_name: game_name.to_owned(),
+            nations: vec![],
+            turn: 1,
+            turn_timer: 48 * 60 * 60 * 1000,
+        })
+    }
+}
+
 fn get_raw_game_data(server_address: &str) -> io::Result<RawGameData> {
     let buffer = call_server_for_info(server_address)?;
     let decompressed = decompress_server_info(&buffer)?;
@@ -72,9 +133,38 @@ fn get_raw_game_data(server_address: &str) -> io::Result<RawGameData> {
     Ok(game_data)
 }
 
+static CONNECT_TIMEOUT_SECS: AtomicUsize = AtomicUsize::new(10);
+static READ_TIMEOUT_SECS: AtomicUsize = AtomicUsize::new(30);
+
+/// Called once on startup with the configured timeouts. A server that's down or hung
+/// shouldn't hold up the turn check for every other game.
+pub fn set_timeouts(connect_timeout: Duration, read_timeout: Duration) {
+    CONNECT_TIMEOUT_SECS.store(connect_timeout.as_secs() as usize, Ordering::Relaxed);
+    READ_TIMEOUT_SECS.store(read_timeout.as_secs() as usize, Ordering::Relaxed);
+}
+
+fn connect_with_timeouts(server_address: &str) -> io::Result<net::TcpStream> {
+    let connect_timeout = Duration::from_secs(CONNECT_TIMEOUT_SECS.load(Ordering::Relaxed) as u64);
+    let read_timeout = Duration::from_secs(READ_TIMEOUT_SECS.load(Ordering::Relaxed) as u64);
+    let mut last_error = None;
+    for socket_address in server_address.to_socket_addrs()? {
+        match net::TcpStream::connect_timeout(&socket_address, connect_timeout) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(read_timeout))?;
+                stream.set_write_timeout(Some(read_timeout))?;
+                return Ok(stream);
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("could not resolve {}", server_address))
+    }))
+}
+
 fn call_server_for_info(server_address: &str) -> io::Result<Vec<u8>> {
     info!("starting to connect to {}", server_address);
-    let mut stream = net::TcpStream::connect(server_address)?;
+    let mut stream = connect_with_timeouts(server_address)?;
     info!("connected");
     let mut wtr = vec![];
     wtr.write_u8(b'f')?;